@@ -27,6 +27,7 @@ struct ColorVertex {
     @location(2) uv: vec2<f32>,
     @location(3) color: i32,
     @location(4) ao: f32,
+    @location(5) light: vec2<f32>,
 }
 
 struct ColorVertexOutput {
@@ -36,6 +37,7 @@ struct ColorVertexOutput {
     @location(2) uv: vec2<f32>,
     @location(3) @interpolate(flat) color: i32,
     @location(4) ao: f32,
+    @location(5) light: vec2<f32>,
 }
 
 @vertex
@@ -57,6 +59,7 @@ fn vertex(vertex: ColorVertex) -> ColorVertexOutput {
     out.uv = vertex.uv;
     out.color = vertex.color;
     out.ao = vertex.ao;
+    out.light = vertex.light;
     return out;
 }
 
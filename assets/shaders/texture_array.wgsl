@@ -27,6 +27,7 @@ struct ChunkVertex {
     @location(2) uv: vec2<f32>,
     @location(3) layer: i32,
     @location(4) ao: f32,
+    @location(5) light: vec2<f32>,
 }
 
 struct ChunkVertexOutput {
@@ -36,6 +37,7 @@ struct ChunkVertexOutput {
     @location(2) uv: vec2<f32>,
     @location(3) @interpolate(flat) layer: i32,
     @location(4) ao: f32,
+    @location(5) light: vec2<f32>,
 }
 
 @vertex
@@ -57,6 +59,7 @@ fn vertex(vertex: ChunkVertex) -> ChunkVertexOutput {
     out.uv = vertex.uv;
     out.layer = vertex.layer;
     out.ao = vertex.ao;
+    out.light = vertex.light;
     return out;
 }
 
@@ -80,7 +83,13 @@ fn fragment(
 
     // alpha discard
     pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
-    pbr_input.material.base_color = vec4<f32>(pbr_input.material.base_color.xyz * in.ao, pbr_input.material.base_color.w);
+    // voxel light: each level is 80% as bright as the one above it, with a small floor so caves aren't pitch black
+    let sky_light = max(pow(0.8, (1.0 - in.light.x) * 15.0), 0.05);
+    let block_light = pow(0.8, (1.0 - in.light.y) * 15.0) * step(0.001, in.light.y);
+    let unlit_color = pbr_input.material.base_color.xyz;
+    pbr_input.material.base_color = vec4<f32>(unlit_color * in.ao * max(sky_light, block_light), pbr_input.material.base_color.w);
+    // block light shows up at night too, so it's added as emission that isn't scaled by camera exposure (alpha = 0)
+    pbr_input.material.emissive = vec4<f32>(unlit_color * in.ao * block_light * 0.5, 0.0);
 #ifdef PREPASS_PIPELINE
     // in deferred mode we can't modify anything after that, as lighting is run in a separate fullscreen shader.
     let out = deferred_output(in, pbr_input);
@@ -12,6 +12,7 @@ use engine::{
     },
     controllers::{ControllableBundle, JumpBundle},
     physics::{collision::Aabb, movement::Velocity, PhysicsBundle, GRAVITY},
+    serialization::SaveWithChunk,
     world::LevelLoadState,
 };
 use util::{physics::aim_projectile_straight_fallback, plugin::SmoothLookTo, SendEventCommand};
@@ -91,6 +92,7 @@ pub fn spawn_skeleton_pirate(
             SceneRoot(skele_res.scene.clone_weak()),
             spawn.location,
             Name::new("SkeletonPirate"),
+            (ActorName::core("skeleton_pirate"), SaveWithChunk),
             (
                 CombatantBundle::<EnemyTeam> {
                     combatant: Combatant::new(10.0, 0.0),
@@ -2,6 +2,7 @@ use bevy::prelude::*;
 
 use engine::{
     physics::{collision::Aabb, movement::GravityMult, PhysicsBundle},
+    serialization::SaveWithChunk,
     world::LevelLoadState,
 };
 
@@ -78,6 +79,7 @@ fn spawn_wisp(
             MeshMaterial3d(res.material.clone()),
             spawn.location,
             Name::new("wisp"),
+            (ActorName::core("wisp"), SaveWithChunk),
             CombatantBundle::<PlayerTeam> {
                 combatant: Combatant::new(10., 0.),
                 ..default()
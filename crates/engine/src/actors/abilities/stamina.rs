@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::world::atmosphere::Calendar;
 
@@ -20,11 +21,14 @@ pub struct StaminaUpdatedEvent {
     pub change_max: f32,
 }
 
-#[derive(Component, Clone, Copy, Debug)]
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Stamina {
     pub max: f32,
     pub current: f32,
+    //not saved, so a loaded stamina always sends an update
+    #[serde(skip)]
     old_max: f32,
+    #[serde(skip)]
     old_current: f32,
 }
 
@@ -7,7 +7,7 @@ use crate::{
     physics::{
         collision::{Aabb, CollidingDirections},
         movement::Velocity,
-        PhysicsBundle,
+        query, PhysicsBundle,
     },
     world::{
         events::ChunkUpdatedEvent, BlockCoord, BlockId, BlockMesh, BlockPhysics, BlockType, Level,
@@ -101,7 +101,8 @@ fn falling_block_placer(
     for (entity, hit, falling_block, v, tf) in block_query.iter() {
         if hit.0.intersects(falling_block.impact_direcitons) {
             //we had a collision on one of the allowed axes
-            if let Some(placing_coord) = level.blockcast(
+            if let Some(placing_coord) = query::blockcast(
+                &level,
                 tf.translation,
                 -v.0.normalize_or_zero() * BACKTRACK_DIST,
                 |opt_b| opt_b.map(|b| matches!(b, BlockType::Empty)).unwrap_or(true),
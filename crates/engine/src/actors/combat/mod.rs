@@ -1,6 +1,7 @@
 use std::time::Duration;
 
 use bevy::{ecs::entity::EntityHashMap, prelude::*};
+use serde::{Deserialize, Serialize};
 use team::*;
 
 pub mod damage;
@@ -9,7 +10,10 @@ pub mod projectile;
 #[macro_use]
 pub mod team;
 
-use crate::{physics::collision::Aabb, world::LevelSystemSet};
+use crate::{
+    physics::{collision::Aabb, spatial::SpatialIndex},
+    world::LevelSystemSet,
+};
 
 use super::Player;
 
@@ -39,7 +43,7 @@ impl Plugin for CombatPlugin {
 }
 
 #[derive(Resource)]
-pub struct LevelEntity(Entity);
+pub struct LevelEntity(pub Entity);
 
 #[derive(Bundle, Clone)]
 pub struct CombatantBundle<T: team::Team> {
@@ -224,7 +228,7 @@ impl<'a> GetRootMut<'a> for Mut<'a, Combatant> {
     }
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub struct Health {
     pub current: f32,
     pub max: f32,
@@ -555,20 +559,21 @@ fn create_level_entity(mut commands: Commands) {
 fn do_contact_damage<T: Team>(
     attacker_query: Query<(Entity, &ContactDamage, &GlobalTransform, &Aabb), With<T>>,
     target_query: Query<(Entity, &GlobalTransform, &Aabb), T::Targets>,
+    index: Res<SpatialIndex>,
     mut attack_writer: EventWriter<AttackEvent>,
 ) {
     const AABB_SCALE: Vec3 = Vec3::splat(1.1);
     for (entity, cd, gtf, aabb) in attacker_query.iter() {
-        for (target_entity, target_gtf, _) in
-            target_query
-                .iter()
-                .filter(move |(_, target_gtf, target_aabb)| {
-                    target_aabb.intersects_aabb(
-                        target_gtf.translation(),
-                        aabb.scale(AABB_SCALE),
-                        gtf.translation(),
-                    )
-                })
+        let scaled = aabb.scale(AABB_SCALE);
+        let candidates = index.query_box(
+            scaled.world_min(gtf.translation()),
+            scaled.world_max(gtf.translation()),
+        );
+        for (target_entity, target_gtf, _) in candidates
+            .filter_map(|candidate| target_query.get(candidate).ok())
+            .filter(|(_, target_gtf, target_aabb)| {
+                target_aabb.intersects_aabb(target_gtf.translation(), scaled, gtf.translation())
+            })
         {
             //they intersect
             attack_writer.send(AttackEvent {
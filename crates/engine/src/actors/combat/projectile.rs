@@ -6,6 +6,7 @@ use crate::{
         collision::{Aabb, CollidingBlocks},
         movement::Velocity,
         query::test_box,
+        spatial::SpatialIndex,
     },
     world::{
         events::{BlockDamageSetEvent, ChunkUpdatedEvent},
@@ -116,12 +117,19 @@ fn test_projectile_hit<T: Team>(
     mut commands: Commands,
     level: Res<Level>,
     object_query: Query<(Entity, &GlobalTransform, &Aabb), T::Targets>,
+    spatial_index: Res<SpatialIndex>,
     id_query: Query<&BlockId>,
     mut damage_writer: EventWriter<BlockDamageSetEvent>,
     mut update_writer: EventWriter<ChunkUpdatedEvent>,
 ) {
     for (proj_entity, tf, proj, v, opt_in_entity, colliding_blocks, aabb) in query.iter() {
-        let opt_hit_entity = test_box::<T>(tf.translation(), *aabb, &object_query, &[proj_entity]);
+        let opt_hit_entity = test_box::<T>(
+            tf.translation(),
+            *aabb,
+            &object_query,
+            &spatial_index,
+            &[proj_entity],
+        );
         if opt_hit_entity.is_some() || !colliding_blocks.is_empty() {
             let hit_blocks = colliding_blocks.iter().map(|&(coord, _, _)| coord);
             proj_hit(
@@ -1,6 +1,6 @@
 use bevy::{ecs::query::QueryFilter, prelude::*};
 
-use crate::physics::collision::Aabb;
+use crate::physics::{collision::Aabb, spatial::SpatialIndex};
 
 use super::Combatant;
 
@@ -75,54 +75,61 @@ pub trait AddTeam {
     fn append_ally<T>(self, t: T) -> Self::AllyResultType;
 }
 
+//these look up candidates in the spatial index, so they only check combatants near `origin` instead of every one in the world
 pub fn get_targets_in_range<'a, T: Team>(
     query: &'a Query<'a, 'a, (Entity, &'a Combatant, &'a GlobalTransform), T::Targets>,
+    index: &'a SpatialIndex,
     origin: Vec3,
     range: f32,
 ) -> impl Iterator<Item = (Entity, &'a Combatant, &'a GlobalTransform)> {
     let sqr_dist = range * range;
-    query
-        .iter()
+    index
+        .query_range(origin, range)
+        .filter_map(move |entity| query.get(entity).ok())
         .filter(move |(_, _, gtf)| gtf.translation().distance_squared(origin) <= sqr_dist)
 }
 
 pub fn get_colliding_targets<'a, 'b: 'a, T: Team>(
     query: &'b Query<'a, 'a, (Entity, &'a Combatant, &'a GlobalTransform, &'a Aabb), T::Targets>,
+    index: &'b SpatialIndex,
     origin: Vec3,
     aabb: Aabb,
     my_aabb_scale: f32,
 ) -> impl Iterator<Item = (Entity, &'b Combatant, &'b GlobalTransform, &'b Aabb)> {
-    query.iter().filter(move |(_, _, gtf, target_aabb)| {
-        target_aabb.intersects_aabb(
-            gtf.translation(),
-            aabb.scale(Vec3::ONE * my_aabb_scale),
-            origin,
-        )
-    })
+    let scaled = aabb.scale(Vec3::ONE * my_aabb_scale);
+    index
+        .query_box(scaled.world_min(origin), scaled.world_max(origin))
+        .filter_map(move |entity| query.get(entity).ok())
+        .filter(move |(_, _, gtf, target_aabb)| {
+            target_aabb.intersects_aabb(gtf.translation(), scaled, origin)
+        })
 }
 
 pub fn get_allies_in_range<'a, T: Team>(
     query: &'a Query<'a, 'a, (Entity, &'a Combatant, &'a GlobalTransform), T::Allies>,
+    index: &'a SpatialIndex,
     origin: Vec3,
     range: f32,
 ) -> impl Iterator<Item = (Entity, &'a Combatant, &'a GlobalTransform)> {
     let sqr_dist = range * range;
-    query
-        .iter()
+    index
+        .query_range(origin, range)
+        .filter_map(move |entity| query.get(entity).ok())
         .filter(move |(_, _, gtf)| gtf.translation().distance_squared(origin) <= sqr_dist)
 }
 
 pub fn get_colliding_allies<'a, T: Team>(
     query: &'a Query<'a, 'a, (Entity, &'a Combatant, &'a GlobalTransform, &'a Aabb), T::Allies>,
+    index: &'a SpatialIndex,
     origin: Vec3,
     aabb: Aabb,
     my_aabb_scale: f32,
 ) -> impl Iterator<Item = (Entity, &'a Combatant, &'a GlobalTransform, &'a Aabb)> {
-    query.iter().filter(move |(_, _, gtf, target_aabb)| {
-        target_aabb.intersects_aabb(
-            gtf.translation(),
-            aabb.scale(Vec3::ONE * my_aabb_scale),
-            origin,
-        )
-    })
+    let scaled = aabb.scale(Vec3::ONE * my_aabb_scale);
+    index
+        .query_box(scaled.world_min(origin), scaled.world_max(origin))
+        .filter_map(move |entity| query.get(entity).ok())
+        .filter(move |(_, _, gtf, target_aabb)| {
+            target_aabb.intersects_aabb(gtf.translation(), scaled, origin)
+        })
 }
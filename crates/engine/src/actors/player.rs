@@ -19,9 +19,10 @@ use crate::{
     net::{
         client::ClientState,
         server::{SyncPosition, SyncVelocity},
-        ClientMessage, NetworkType, PlayerList, RemoteClient,
+        ClientMessage, NetworkType, PlayerList, RemoteClient, HOST_USERNAME,
     },
     physics::{movement::*, *},
+    serialization::{db::LevelDB, players::load_player, SavedToLoadedIdMap},
     world::{settings::Settings, *},
 };
 
@@ -48,7 +49,10 @@ pub struct LocalPlayer;
 pub struct LocalPlayerSpawnedEvent(pub Entity);
 
 #[derive(Event)]
-pub struct SpawnLocalPlayerEvent;
+pub struct SpawnLocalPlayerEvent {
+    //false when respawning after death, so the player starts fresh instead of where they were saved
+    pub restore_saved: bool,
+}
 
 #[derive(Resource)]
 pub struct RespawningPlayer(pub Option<Duration>);
@@ -128,7 +132,9 @@ fn spawn_remote_player(
 }
 
 fn trigger_local_player_spawn(mut writer: EventWriter<SpawnLocalPlayerEvent>) {
-    writer.send(SpawnLocalPlayerEvent);
+    writer.send(SpawnLocalPlayerEvent {
+        restore_saved: true,
+    });
 }
 
 //todo - update when I update mulitplayer
@@ -156,7 +162,9 @@ fn respawn_players(
         .unwrap_or(false)
     {
         info!("Respawning player!");
-        writer.send(SpawnLocalPlayerEvent);
+        writer.send(SpawnLocalPlayerEvent {
+            restore_saved: false,
+        });
         respawning.0 = None;
     }
 }
@@ -168,26 +176,46 @@ pub fn spawn_local_player(
     level: Res<Level>,
     mut pickup_item: EventWriter<PickupItemEvent>,
     resources: Res<ItemResources>,
+    block_resources: Res<BlockResources>,
     item_query: Query<&MaxStackSize>,
+    creator_query: Query<&CreatorItem>,
     ghost_resources: Res<GhostResources>,
     held_item_resouces: Res<HeldItemResources>,
     player_query: Query<(), With<LocalPlayer>>,
     camera: Res<MainCamera>,
+    mut db: Option<ResMut<LevelDB>>,
+    item_id_map: Option<Res<SavedToLoadedIdMap<ItemId>>>,
 ) {
-    for _ in spawn_reader.read() {
+    for SpawnLocalPlayerEvent { restore_saved } in spawn_reader.read() {
         if !player_query.is_empty() {
             info!("trying to spawn local player when there's already one!");
         }
-        //adjust for ghost height
-        let spawn_point = level.get_spawn_point() + Vec3::new(0., 1.5, 0.);
+        let saved = match db.as_mut() {
+            Some(db) if *restore_saved => load_player(db, HOST_USERNAME).unwrap_or_else(|e| {
+                error!("Error loading saved player: {:?}", e);
+                None
+            }),
+            _ => None,
+        };
+        let spawn_point = match &saved {
+            Some(saved) if saved.level == level.id => saved.transform.translation,
+            //adjust for ghost height
+            _ => level.get_spawn_point() + Vec3::new(0., 1.5, 0.),
+        };
         info!("Spawning local player at {:?}", spawn_point);
+        let mut combatant = Combatant::new(10.0, 0.0);
+        if let (Some(saved_health), Combatant::Root { health, .. }) =
+            (saved.as_ref().and_then(|saved| saved.health), &mut combatant)
+        {
+            *health = saved_health;
+        }
         let player_id = commands
             .spawn((
                 StateScoped(LevelLoadState::Loaded),
                 Name::new("local player"),
                 LocalPlayer {},
                 CombatantBundle::<PlayerTeam> {
-                    combatant: Combatant::new(10.0, 0.0),
+                    combatant,
                     death_info: DeathInfo {
                         death_type: crate::actors::DeathType::LocalPlayer,
                     },
@@ -214,96 +242,28 @@ pub fn spawn_local_player(
             &held_item_resouces,
             &mut commands,
         );
+        if let Some(stamina) = saved.as_ref().and_then(|saved| saved.stamina) {
+            commands.entity(player_id).insert(stamina);
+        }
         let mut inventory = Inventory::new(player_id, 40);
-
-        inventory.pickup_item(
-            ItemStack::new(
-                resources
-                    .registry
-                    .get_basic(&ItemName::core("ruby_pickaxe"))
-                    .unwrap(),
-                1,
-            ),
-            &item_query,
-            &mut pickup_item,
-        );
-        inventory.pickup_item(
-            ItemStack::new(
-                resources
-                    .registry
-                    .get_basic(&ItemName::core("ruby_shovel"))
-                    .unwrap(),
-                1,
-            ),
-            &item_query,
-            &mut pickup_item,
-        );
-        inventory.pickup_item(
-            ItemStack::new(
-                resources
-                    .registry
-                    .get_basic(&ItemName::core("ruby_axe"))
-                    .unwrap(),
-                1,
-            ),
-            &item_query,
-            &mut pickup_item,
-        );
-        inventory.pickup_item(
-            ItemStack::new(
-                resources
-                    .registry
-                    .get_basic(&ItemName::core("moon"))
-                    .unwrap(),
-                1,
-            ),
-            &item_query,
-            &mut pickup_item,
-        );
-        inventory.pickup_item(
-            ItemStack::new(
-                resources
-                    .registry
-                    .get_basic(&ItemName::core("dagger"))
-                    .unwrap(),
-                100,
-            ),
-            &item_query,
-            &mut pickup_item,
-        );
-        inventory.pickup_item(
-            ItemStack::new(
-                resources
-                    .registry
-                    .get_basic(&ItemName::core("spike_ball_launcher"))
-                    .unwrap(),
-                100,
-            ),
-            &item_query,
-            &mut pickup_item,
-        );
-        inventory.pickup_item(
-            ItemStack::new(
-                resources
-                    .registry
-                    .get_basic(&ItemName::core("grapple"))
-                    .unwrap(),
-                1,
+        match (&saved, &item_id_map) {
+            (Some(saved), Some(id_map)) => saved.restore_inventory(
+                &mut inventory,
+                &resources.registry,
+                &block_resources.registry,
+                &creator_query,
+                id_map,
+                &mut commands,
             ),
-            &item_query,
-            &mut pickup_item,
-        );
-        inventory.pickup_item(
-            ItemStack::new(
-                resources
-                    .registry
-                    .get_basic(&ItemName::core("suicide_pill"))
-                    .unwrap(),
-                1,
+            _ => give_starting_items(
+                &mut inventory,
+                &resources,
+                &block_resources,
+                &item_query,
+                &creator_query,
+                &mut pickup_item,
             ),
-            &item_query,
-            &mut pickup_item,
-        );
+        }
 
         commands.entity(player_id).insert(inventory);
         //makes sure that player is actually spawned before this occurs, since events fire at a different time than commands
@@ -311,6 +271,123 @@ pub fn spawn_local_player(
     }
 }
 
+fn give_starting_items(
+    inventory: &mut Inventory,
+    resources: &ItemResources,
+    block_resources: &BlockResources,
+    item_query: &Query<&MaxStackSize>,
+    creator_query: &Query<&CreatorItem>,
+    pickup_item: &mut EventWriter<PickupItemEvent>,
+) {
+    inventory.pickup_item(
+        ItemStack::new(
+            resources
+                .registry
+                .get_basic(&ItemName::core("ruby_pickaxe"))
+                .unwrap(),
+            1,
+        ),
+        item_query,
+        pickup_item,
+    );
+    inventory.pickup_item(
+        ItemStack::new(
+            resources
+                .registry
+                .get_basic(&ItemName::core("ruby_shovel"))
+                .unwrap(),
+            1,
+        ),
+        item_query,
+        pickup_item,
+    );
+    inventory.pickup_item(
+        ItemStack::new(
+            resources
+                .registry
+                .get_basic(&ItemName::core("ruby_axe"))
+                .unwrap(),
+            1,
+        ),
+        item_query,
+        pickup_item,
+    );
+    inventory.pickup_item(
+        ItemStack::new(
+            resources
+                .registry
+                .get_basic(&ItemName::core("moon"))
+                .unwrap(),
+            1,
+        ),
+        item_query,
+        pickup_item,
+    );
+    inventory.pickup_item(
+        ItemStack::new(
+            resources
+                .registry
+                .get_basic(&ItemName::core("dagger"))
+                .unwrap(),
+            100,
+        ),
+        item_query,
+        pickup_item,
+    );
+    inventory.pickup_item(
+        ItemStack::new(
+            resources
+                .registry
+                .get_basic(&ItemName::core("spike_ball_launcher"))
+                .unwrap(),
+            100,
+        ),
+        item_query,
+        pickup_item,
+    );
+    inventory.pickup_item(
+        ItemStack::new(
+            resources
+                .registry
+                .get_basic(&ItemName::core("grapple"))
+                .unwrap(),
+            1,
+        ),
+        item_query,
+        pickup_item,
+    );
+    inventory.pickup_item(
+        ItemStack::new(
+            resources
+                .registry
+                .get_basic(&ItemName::core("suicide_pill"))
+                .unwrap(),
+            1,
+        ),
+        item_query,
+        pickup_item,
+    );
+    //these are blocks, so we give the item that places them
+    for (name, count) in [
+        ("glowjelly_lamp", 16),
+        ("signal_wire", 64),
+        ("signal_source", 8),
+        ("break_sensor", 8),
+        ("gate", 8),
+        ("alarm_bell", 4),
+    ] {
+        if let Some(block) = block_resources.registry.get_basic(&BlockName::core(name))
+            && let Ok(CreatorItem(block_item)) = creator_query.get(block)
+        {
+            inventory.pickup_item(
+                ItemStack::new(*block_item, count),
+                item_query,
+                pickup_item,
+            );
+        }
+    }
+}
+
 fn populate_player_entity(
     entity: Entity,
     camera: Entity,
@@ -3,12 +3,17 @@ use core::f32;
 use crate::{
     chunk_loading::ChunkLoader,
     physics::{collision::Aabb, movement::Mass, PhysicsBundle},
+    serialization::{db::LevelDB, SaveWorldStateSet},
     util::SendEventCommand,
-    world::{settings::Settings, Level, LevelLoadState},
+    world::{levels::LevelId, settings::Settings, Level, LevelLoadState},
 };
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use super::{team::PlayerTeam, ActorName, ActorResources, Combatant, CombatantBundle};
+use super::{team::PlayerTeam, ActorName, ActorResources, Combatant, CombatantBundle, Health};
+
+//world_info key the anchor is saved under
+const WORLD_ANCHOR_KEY: &str = "world_anchor";
 
 #[derive(Resource)]
 pub struct WorldAnchorResources {
@@ -31,6 +36,17 @@ pub struct WorldAnchorScene;
 #[derive(Event)]
 pub struct SpawnWorldAnchorEvent {
     pub location: Transform,
+    //spawns with full health if None
+    pub health: Option<Health>,
+}
+
+//saved as Some while the anchor is alive, and None once it's been destroyed so it doesn't come back when the level is loaded
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct SavedWorldAnchor {
+    //the level the anchor was in. the translation is only used if the anchor is spawned in the same level
+    pub level: LevelId,
+    pub translation: Vec3,
+    pub health: Health,
 }
 
 pub struct WorldAnchorPlugin;
@@ -41,6 +57,7 @@ impl Plugin for WorldAnchorPlugin {
             .add_systems(Update, spawn_world_anchor)
             .add_systems(OnEnter(LevelLoadState::Loaded), trigger_spawning)
             .add_systems(OnExit(LevelLoadState::Loaded), cleanup)
+            .add_systems(Last, save_world_anchor.in_set(SaveWorldStateSet))
             .add_observer(on_world_anchor_destroyed)
             .add_event::<SpawnWorldAnchorEvent>();
     }
@@ -50,7 +67,10 @@ fn add_to_registry(mut res: ResMut<ActorResources>) {
     res.registry.add_dynamic(
         ActorName::core("world_anchor"),
         Box::new(|commands, tf| {
-            commands.queue(SendEventCommand(SpawnWorldAnchorEvent { location: tf }))
+            commands.queue(SendEventCommand(SpawnWorldAnchorEvent {
+                location: tf,
+                health: None,
+            }))
         }),
     );
 }
@@ -61,10 +81,71 @@ pub fn load_resources(mut commands: Commands, assets: Res<AssetServer>) {
     });
 }
 
-fn trigger_spawning(mut writer: EventWriter<SpawnWorldAnchorEvent>, level: Res<Level>) {
-    writer.send(SpawnWorldAnchorEvent {
-        location: Transform::from_translation(level.get_spawn_point()),
-    });
+fn trigger_spawning(
+    mut writer: EventWriter<SpawnWorldAnchorEvent>,
+    level: Res<Level>,
+    db: Option<ResMut<LevelDB>>,
+    mut commands: Commands,
+) {
+    let saved =
+        match db.map(|mut db| db.load_world_info::<Option<SavedWorldAnchor>>(WORLD_ANCHOR_KEY)) {
+            Some(Ok(saved)) => saved,
+            Some(Err(e)) => {
+                error!("Error loading world anchor: {:?}", e);
+                None
+            }
+            None => None,
+        };
+    match saved {
+        //the anchor was destroyed before the level was closed
+        Some(None) => {
+            info!("world anchor was destroyed, not spawning it");
+            commands.insert_resource(WorldAnchorHasSpawned);
+        }
+        Some(Some(anchor)) => {
+            let translation = if anchor.level == level.id {
+                anchor.translation
+            } else {
+                level.get_spawn_point()
+            };
+            writer.send(SpawnWorldAnchorEvent {
+                location: Transform::from_translation(translation),
+                health: Some(anchor.health),
+            });
+        }
+        None => {
+            writer.send(SpawnWorldAnchorEvent {
+                location: Transform::from_translation(level.get_spawn_point()),
+                health: None,
+            });
+        }
+    }
+}
+
+fn save_world_anchor(
+    active: Option<Res<ActiveWorldAnchor>>,
+    has_spawned: Option<Res<WorldAnchorHasSpawned>>,
+    query: Query<(&Transform, &Combatant), With<WorldAnchor>>,
+    level: Option<Res<Level>>,
+    mut db: ResMut<LevelDB>,
+) {
+    let saved = match (active, level) {
+        (Some(active), Some(level)) => {
+            let Ok((tf, Combatant::Root { health, .. })) = query.get(active.0) else {
+                return;
+            };
+            Some(SavedWorldAnchor {
+                level: level.id,
+                translation: tf.translation,
+                health: *health,
+            })
+        }
+        //spawned and then destroyed
+        (None, _) if has_spawned.is_some() => None,
+        //the level isn't loaded yet
+        _ => return,
+    };
+    db.save_world_info(WORLD_ANCHOR_KEY, bincode::serialize(&saved).unwrap());
 }
 
 fn cleanup(mut commands: Commands) {
@@ -81,6 +162,11 @@ pub fn spawn_world_anchor(
     _children_query: Query<&Children>,
 ) {
     for spawn in spawn_requests.read() {
+        let mut combatant = Combatant::new(10., 0.);
+        if let (Some(saved_health), Combatant::Root { health, .. }) = (spawn.health, &mut combatant)
+        {
+            *health = saved_health;
+        }
         let anchor = commands
             .spawn((
                 StateScoped(LevelLoadState::Loaded),
@@ -88,7 +174,7 @@ pub fn spawn_world_anchor(
                 spawn.location.with_scale(Vec3::new(2.0, 2.0, 2.0)),
                 Name::new("world anchor"),
                 CombatantBundle::<PlayerTeam> {
-                    combatant: Combatant::new(10., 0.),
+                    combatant,
                     ..default()
                 },
                 PhysicsBundle {
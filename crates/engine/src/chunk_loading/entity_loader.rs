@@ -5,6 +5,7 @@ use crate::{
     serialization::NeedsSaving,
     world::{
         chunk::{ChunkCoord, ChunkType, LODChunk, LODChunkType},
+        levels::LevelMember,
         Level,
     },
 };
@@ -56,7 +57,7 @@ pub fn do_loading(
     mut commands: Commands,
     level: Res<Level>,
     mut despawn_writer: EventWriter<DespawnChunkEvent>,
-    loader_query: Query<(&GlobalTransform, &ChunkLoader)>,
+    loader_query: Query<(&GlobalTransform, &ChunkLoader, Option<&LevelMember>)>,
     mut timer: ResMut<ChunkLoadingTimer>,
     time: Res<Time>,
     save_query: Query<&NeedsSaving>,
@@ -70,7 +71,11 @@ pub fn do_loading(
     //load all in range
     let mut loaded_chunks = HashMap::new();
     let mut loaded_lods = Vec::new();
-    for (transform, loader) in loader_query.iter() {
+    //loaders in other levels don't keep chunks in this one loaded
+    for (transform, loader, _) in loader_query
+        .iter()
+        .filter(|(_, _, member)| member.is_none_or(|m| m.0 == level.id))
+    {
         let base_coord = ChunkCoord::from(transform.translation());
         loader.for_each_chunk(|coord| {
             let test_coord = coord + base_coord;
@@ -14,6 +14,7 @@ use crate::{
         grapple::Grappled,
         movement::{GravityMult, Velocity},
         query::{self, Raycast, RaycastHit},
+        spatial::SpatialIndex,
     },
     world::{
         events::{BlockHitEvent, BlockUsedEvent},
@@ -224,6 +225,7 @@ pub fn player_punch(
     combat_query: Query<&Combatant>,
     block_physics_query: Query<&BlockPhysics>,
     object_query: Query<(Entity, &GlobalTransform, &Aabb)>,
+    spatial_index: Res<SpatialIndex>,
     mut attack_punch_writer: EventWriter<AttackEvent>,
     mut block_hit_writer: EventWriter<BlockHitEvent>,
     focused: Res<CursorLocked>,
@@ -249,6 +251,7 @@ pub fn player_punch(
                         &level,
                         &block_physics_query,
                         &object_query,
+                        &spatial_index,
                         &[player_entity],
                     ) {
                         Some(RaycastHit::Block(hit_pos, _)) => {
@@ -283,6 +286,7 @@ pub fn player_use(
     level: Res<Level>,
     block_physics_query: Query<&BlockPhysics>,
     object_query: Query<(Entity, &GlobalTransform, &Aabb)>,
+    spatial_index: Res<SpatialIndex>,
     usable_block_query: Query<&UsableBlock>,
     mut block_use_writer: EventWriter<BlockUsedEvent>,
     action: Res<ActionState<Action>>,
@@ -298,6 +302,7 @@ pub fn player_use(
                 &level,
                 &block_physics_query,
                 &object_query,
+                &spatial_index,
                 &[entity],
             ) {
                 if level.use_block(
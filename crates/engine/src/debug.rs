@@ -6,6 +6,7 @@ use crate::{
     actors::LocalPlayer,
     controllers::Action,
     physics::{collision::Aabb, PhysicsSystemSet},
+    serialization::QuarantinedChunks,
     world::{chunk::ChunkCoord, BlockCoord, BlockPhysics},
     worldgen::UsedShaperResources,
     GameState,
@@ -29,6 +30,7 @@ impl Plugin for DebugUIPlugin {
                     update_coords,
                     update_chunk_coords,
                     update_noises,
+                    update_quarantined_chunks,
                     update_gizmos,
                     toggle_gizmo_depth,
                 )
@@ -53,6 +55,8 @@ struct DebugChunkCoordinates;
 struct DebugCoordinates;
 #[derive(Component)]
 struct DebugTerrainNoises;
+#[derive(Component)]
+struct DebugQuarantinedChunks;
 
 #[derive(Component, Default)]
 pub struct DebugDrawTransform;
@@ -155,6 +159,7 @@ fn spawn_debug(mut commands: Commands, query: Query<&DebugUI>, resources: Res<De
                     resources.0.clone(),
                     DebugTerrainNoises,
                 ));
+                children.spawn((Text::default(), resources.0.clone(), DebugQuarantinedChunks));
             });
     } else {
         warn!("Tried to spawn debug ui when one already exists!");
@@ -220,6 +225,25 @@ fn update_noises(
     }
 }
 
+//only shown once something has been quarantined
+fn update_quarantined_chunks(
+    mut ui_query: Query<&mut Text, With<DebugQuarantinedChunks>>,
+    quarantined: Res<QuarantinedChunks>,
+) {
+    for mut text in ui_query.iter_mut() {
+        text.0 = match quarantined.0.last() {
+            Some(last) => format!(
+                "quarantined chunks: {}\nlast: {:?} at {:?} ({})",
+                quarantined.0.len(),
+                last.table,
+                last.coord,
+                last.reason
+            ),
+            None => String::new(),
+        };
+    }
+}
+
 fn clear_fixed_update_gizmos(mut fixed_update_blocks: ResMut<FixedUpdateBlockGizmos>) {
     fixed_update_blocks.blocks.clear();
 }
@@ -4,8 +4,11 @@ use crate::{
     physics::{
         collision::Aabb,
         query::{self, Raycast, RaycastHit},
+        spatial::SpatialIndex,
+    },
+    world::{
+        events::ChunkUpdatedEvent, BlockId, BlockPhysics, BlockStateProperties, BlockType, Level,
     },
-    world::{events::ChunkUpdatedEvent, BlockId, BlockPhysics, BlockType, Level},
 };
 
 use super::{HitResult, UseEndEvent, UseItemEvent};
@@ -21,7 +24,9 @@ pub fn use_block_entity_item(
     mut update_writer: EventWriter<ChunkUpdatedEvent>,
     block_physics_query: Query<&BlockPhysics>,
     object_query: Query<(Entity, &GlobalTransform, &Aabb)>,
+    spatial_index: Res<SpatialIndex>,
     id_query: Query<&BlockId>,
+    state_query: Query<&BlockStateProperties>,
     mut commands: Commands,
 ) {
     for UseItemEvent {
@@ -37,16 +42,25 @@ pub fn use_block_entity_item(
                 &level,
                 &block_physics_query,
                 &object_query,
+                &spatial_index,
                 &[*user],
             ) {
-                let normal = crate::util::max_component_norm(hit.hit_pos - coord.center()).into();
+                let offset = hit.hit_pos - coord.center();
+                let place_pos = coord.offset(hit.normal);
                 level.set_block_entity(
-                    coord + normal,
+                    place_pos,
                     BlockType::Filled(block_item.0),
                     &id_query,
                     &mut update_writer,
                     &mut commands,
                 );
+                if let Ok(properties) = state_query.get(block_item.0) {
+                    //set_block_entity already queued the remesh
+                    level.set_block_state_noupdate(
+                        place_pos,
+                        properties.placement_state(hit.normal, *tf.forward(), offset.y),
+                    );
+                }
                 hit_writer.send(UseEndEvent {
                     user: *user,
                     inventory_slot: *inventory_slot,
@@ -3,11 +3,41 @@ use bevy::prelude::*;
 use rand::prelude::*;
 use rand_distr::Uniform;
 
+use crate::{
+    actors::{ActorName, ActorResources, Player},
+    mesher::item_mesher::{HeldItemResources, ItemMesh, ItemMeshMaterial},
+    physics::{collision::Aabb, movement::Velocity, PhysicsBundle},
+    serialization::SaveWithChunk,
+    world::{BlockName, BlockRegistry, BlockResources, LevelLoadState, LevelSystemSet},
+};
+
+use super::{
+    inventory::Inventory, CreatorItem, ItemName, ItemRegistry, ItemResources, ItemStack,
+    ItemSystemSet, MaxStackSize, PickupItemEvent,
+};
+
 pub struct LootPlugin;
 
 impl Plugin for LootPlugin {
-    fn build(&self, _app: &mut App) {
-        
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnDroppedItemEvent>()
+            .add_systems(Startup, add_to_registry)
+            .add_systems(
+                Update,
+                spawn_dropped_items.in_set(ItemSystemSet::DropPickupProcessing),
+            )
+            .add_systems(Update, add_dropped_item_meshes)
+            .add_systems(
+                Update,
+                pickup_dropped_items.in_set(LevelSystemSet::Despawn),
+            )
+            .register_type::<DroppedItem>()
+            .register_type::<BlockLoot>()
+            .register_type::<LootDrop>()
+            .register_type::<Vec<LootDrop>>()
+            .register_type::<ToolKind>()
+            .register_type::<ToolRequirement>()
+            .register_type::<Option<ToolRequirement>>();
     }
 }
 
@@ -59,4 +89,246 @@ impl<T: LootTableDroppable> Default for LootTable<T> {
     fn default() -> Self {
         Self { drops: Default::default(), drop_chance: 1.0, drop_count: Uniform::new_inclusive(1,1) }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum ToolKind {
+    Axe,
+    Pickaxe,
+    Shovel,
+}
+
+//a drop with this only happens if the block was broken with at least `level` of `kind`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub struct ToolRequirement {
+    pub kind: ToolKind,
+    pub level: u32,
+}
+
+//one entry in a block's drop table. every entry is rolled on its own
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct LootDrop {
+    pub item: ItemName,
+    //0..1
+    pub chance: f32,
+    //inclusive
+    pub min_count: u32,
+    pub max_count: u32,
+    pub tool: Option<ToolRequirement>,
+}
+
+impl LootDrop {
+    //returns how many of the item drop, which may be 0
+    //`tool_level` gives the breaking tool's level for each kind of tool
+    pub fn roll(&self, rng: &mut impl Rng, tool_level: impl Fn(ToolKind) -> u32) -> u32 {
+        if self
+            .tool
+            .is_some_and(|required| tool_level(required.kind) < required.level)
+        {
+            return 0;
+        }
+        if self.chance < 1.0 && rng.gen::<f32>() >= self.chance {
+            return 0;
+        }
+        rng.gen_range(self.min_count..=self.max_count.max(self.min_count))
+    }
+}
+
+//what a block drops when it's broken, declared in its scene file
+//blocks without this drop their own item, using the LootTable the block registry gives them
+#[derive(Component, Clone, Reflect, Default, Debug)]
+#[reflect(Component, FromWorld)]
+pub struct BlockLoot {
+    pub drops: Vec<LootDrop>,
+}
+
+impl BlockLoot {
+    //returns the items that dropped and how many of each
+    pub fn roll(
+        &self,
+        rng: &mut impl Rng,
+        tool_level: impl Fn(ToolKind) -> u32,
+    ) -> Vec<(&ItemName, u32)> {
+        self.drops
+            .iter()
+            .map(|drop| (&drop.item, drop.roll(rng, &tool_level)))
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
+}
+
+//finds the item entity for `name`. block items aren't in the item registry, so they're found through the block with the same name
+pub fn resolve_item(
+    name: &ItemName,
+    items: &ItemRegistry,
+    blocks: &BlockRegistry,
+    creator_query: &Query<&CreatorItem>,
+) -> Option<Entity> {
+    items.get_basic(name).or_else(|| {
+        blocks
+            .get_basic(&BlockName::new(name.namespace.clone(), name.name.clone()))
+            .and_then(|block| creator_query.get(block).ok())
+            .map(|CreatorItem(item)| *item)
+    })
+}
+
+//how long after spawning before a dropped item can be picked up
+const PICKUP_DELAY: f32 = 0.5;
+const PICKUP_RADIUS: f32 = 1.5;
+//dropped items that aren't picked up in this many seconds are despawned
+const DROPPED_ITEM_LIFETIME: f32 = 300.0;
+
+//an item stack lying in the world
+//stored by name so it can be saved with its chunk, see SaveWithChunk
+#[derive(Component, Clone, Reflect, Default, Debug)]
+#[reflect(Component, FromWorld)]
+pub struct DroppedItem {
+    pub item: ItemName,
+    pub size: u32,
+    pub age: f32,
+}
+
+#[derive(Event)]
+pub struct SpawnDroppedItemEvent {
+    pub stack: ItemStack,
+    pub position: Vec3,
+    pub velocity: Vec3,
+}
+
+const DROPPED_ITEM_SIZE: f32 = 0.25;
+
+//everything but the DroppedItem, which is restored when the chunk is loaded
+fn dropped_item_bundle(tf: Transform, velocity: Vec3) -> impl Bundle {
+    (
+        StateScoped(LevelLoadState::Loaded),
+        PhysicsBundle {
+            velocity: Velocity(velocity),
+            collider: Aabb::centered(Vec3::splat(DROPPED_ITEM_SIZE)),
+            ..default()
+        },
+        tf,
+        ActorName::core("dropped_item"),
+    )
+}
+
+fn add_to_registry(mut res: ResMut<ActorResources>) {
+    res.registry.add_dynamic(
+        ActorName::core("dropped_item"),
+        Box::new(|commands, tf| {
+            commands.spawn(dropped_item_bundle(tf, Vec3::ZERO));
+        }),
+    );
+}
+
+fn spawn_dropped_items(
+    mut reader: EventReader<SpawnDroppedItemEvent>,
+    name_query: Query<&ItemName>,
+    mut commands: Commands,
+) {
+    for SpawnDroppedItemEvent {
+        stack,
+        position,
+        velocity,
+    } in reader.read()
+    {
+        let Ok(name) = name_query.get(stack.id) else {
+            warn!("Dropped item {:?} has no ItemName", stack.id);
+            continue;
+        };
+        commands.spawn((
+            dropped_item_bundle(
+                Transform::from_translation(*position).with_scale(Vec3::splat(DROPPED_ITEM_SIZE)),
+                *velocity,
+            ),
+            DroppedItem {
+                item: name.clone(),
+                size: stack.size,
+                age: 0.0,
+            },
+            SaveWithChunk,
+        ));
+    }
+}
+
+//covers dropped items that were spawned and ones restored from a saved chunk
+fn add_dropped_item_meshes(
+    dropped_query: Query<(Entity, &DroppedItem), Added<DroppedItem>>,
+    mesh_query: Query<&ItemMesh>,
+    creator_query: Query<&CreatorItem>,
+    held_item_resources: Option<Res<HeldItemResources>>,
+    item_resources: Res<ItemResources>,
+    block_resources: Res<BlockResources>,
+    mut commands: Commands,
+) {
+    let Some(res) = held_item_resources else {
+        return;
+    };
+    for (entity, dropped) in dropped_query.iter() {
+        let Some(item_mesh) = resolve_item(
+            &dropped.item,
+            &item_resources.registry,
+            &block_resources.registry,
+            &creator_query,
+        )
+        .and_then(|item| mesh_query.get(item).ok()) else {
+            continue;
+        };
+        let mut ec = commands.entity(entity);
+        ec.insert(Mesh3d(item_mesh.mesh.clone()));
+        match item_mesh.material {
+            ItemMeshMaterial::ColorArray => ec.insert(MeshMaterial3d(res.color_material.clone())),
+            ItemMeshMaterial::TextureArray => {
+                ec.insert(MeshMaterial3d(res.texture_material.clone()))
+            }
+        };
+    }
+}
+
+fn pickup_dropped_items(
+    mut dropped_query: Query<(Entity, &GlobalTransform, &mut DroppedItem)>,
+    mut player_query: Query<(&GlobalTransform, &mut Inventory), With<Player>>,
+    data_query: Query<&MaxStackSize>,
+    creator_query: Query<&CreatorItem>,
+    item_resources: Res<ItemResources>,
+    block_resources: Res<BlockResources>,
+    mut pickup_writer: EventWriter<PickupItemEvent>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (entity, item_tf, mut dropped) in dropped_query.iter_mut() {
+        dropped.age += time.delta_secs();
+        if dropped.age > DROPPED_ITEM_LIFETIME {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        if dropped.age < PICKUP_DELAY {
+            continue;
+        }
+        for (player_tf, mut inv) in player_query.iter_mut() {
+            if player_tf.translation().distance_squared(item_tf.translation())
+                > PICKUP_RADIUS * PICKUP_RADIUS
+            {
+                continue;
+            }
+            let Some(item) = resolve_item(
+                &dropped.item,
+                &item_resources.registry,
+                &block_resources.registry,
+                &creator_query,
+            ) else {
+                break;
+            };
+            match inv.pickup_item(
+                ItemStack::new(item, dropped.size),
+                &data_query,
+                &mut pickup_writer,
+            ) {
+                Some(leftover) => dropped.size = leftover.size,
+                None => {
+                    commands.entity(entity).despawn_recursive();
+                    break;
+                }
+            }
+        }
+    }
+}
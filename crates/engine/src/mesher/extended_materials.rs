@@ -4,7 +4,7 @@ use bevy::{
     render::render_resource::{AsBindGroup, ShaderRef},
 };
 
-use super::materials::{ATTRIBUTE_AO, ATTRIBUTE_TEXLAYER};
+use super::materials::{ATTRIBUTE_AO, ATTRIBUTE_LIGHT, ATTRIBUTE_TEXLAYER};
 
 #[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
 pub struct TextureArrayExtension {
@@ -41,6 +41,7 @@ impl MaterialExtension for TextureArrayExtension {
             //my addition
             ATTRIBUTE_TEXLAYER.at_shader_location(3),
             ATTRIBUTE_AO.at_shader_location(4),
+            ATTRIBUTE_LIGHT.at_shader_location(5),
         ])?;
         descriptor.vertex.buffers = vec![vertex_layout];
         Ok(())
@@ -79,6 +80,7 @@ impl MaterialExtension for ColorArrayExtension {
             //texlayer is rgba32 color, uses little endian with r as least significant
             ATTRIBUTE_TEXLAYER.at_shader_location(3),
             ATTRIBUTE_AO.at_shader_location(4),
+            ATTRIBUTE_LIGHT.at_shader_location(5),
         ])?;
         descriptor.vertex.buffers = vec![vertex_layout];
         Ok(())
@@ -7,6 +7,7 @@ use std::time::Instant;
 use ::util::direction::{Direction, *};
 
 use crate::world::chunk::*;
+use crate::world::light::FatChunkLight;
 use crate::world::{util::*, Level, *};
 use crate::worldgen::GeneratedChunk;
 use bevy::{
@@ -17,7 +18,7 @@ use bevy::{
 
 use super::extended_materials::TextureArrayExtension;
 use super::is_chunk_ready_for_meshing;
-use super::materials::ATTRIBUTE_AO;
+use super::materials::{ATTRIBUTE_AO, ATTRIBUTE_LIGHT};
 use super::{materials::ATTRIBUTE_TEXLAYER, ChunkMaterial, SPAWN_MESH_TIME_BUDGET_COUNT};
 
 #[derive(Component, Default)]
@@ -54,6 +55,7 @@ pub struct MeshData {
     pub uvs: Vec<Vec2>,
     pub layer_idx: Vec<i32>,
     pub ao_level: Vec<f32>,
+    pub light_level: Vec<Vec2>,
 }
 
 impl MeshData {
@@ -71,6 +73,7 @@ impl MeshData {
         mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
         mesh.insert_attribute(ATTRIBUTE_TEXLAYER, self.layer_idx);
         mesh.insert_attribute(ATTRIBUTE_AO, self.ao_level);
+        mesh.insert_attribute(ATTRIBUTE_LIGHT, self.light_level);
 
         mesh.insert_indices(mesh::Indices::U32(self.tris));
         meshes.add(mesh)
@@ -175,15 +178,19 @@ pub fn queue_meshing(
                         //don't mesh if all neighbors aren't ready yet
                         return;
                     }
-                    let meshing = chunk.with_storage(Box::new(chunk.blocks.create_fat_palette(
+                    let mut fat_palette = chunk.blocks.create_fat_palette(
                         &mesh_query,
                         face_neighbors,
                         edge_neighbors,
                         corner_neighbors,
-                    )));
+                    );
+                    apply_fluid_heights(&mut fat_palette, &level, *coord);
+                    apply_block_states(&mut fat_palette, &chunk.states);
+                    let meshing = chunk.with_storage(Box::new(fat_palette));
+                    let light = level.get_fat_light(*coord);
                     let task = pool.spawn(async move {
                         let mut data = ChunkMesh::new(1.0);
-                        mesh_chunk(&meshing, &mut data);
+                        mesh_chunk(&meshing, &light, &mut data);
                         data
                     });
                     commands.command_scope(|mut commands| {
@@ -278,6 +285,7 @@ pub fn spawn_mesh<T: Bundle>(
 
 pub fn mesh_chunk<T: ChunkStorage<BlockMesh>>(
     fat_chunk: &Chunk<T, BlockMesh>,
+    light: &FatChunkLight,
     data: &mut ChunkMesh,
 ) {
     let _my_span = info_span!("mesh_chunk", name = "mesh_chunk").entered();
@@ -287,6 +295,7 @@ pub fn mesh_chunk<T: ChunkStorage<BlockMesh>>(
                 let coord = FatChunkIdx::new(x, y, z);
                 mesh_block(
                     fat_chunk,
+                    light,
                     &fat_chunk[Into::<usize>::into(coord)],
                     coord,
                     Into::<ChunkIdx>::into(coord).to_vec3() * data.scale,
@@ -296,6 +305,55 @@ pub fn mesh_chunk<T: ChunkStorage<BlockMesh>>(
         }
     }
 }
+//rotates the faces of blocks with a facing or axis
+//neighbors in the border aren't rotated since only their occlusion matters, not their textures
+fn apply_block_states(
+    fat_palette: &mut BlockPalette<BlockMesh, BLOCKS_PER_FAT_CHUNK>,
+    states: &BlockStates,
+) {
+    for (idx, state) in states.iter() {
+        let fat_idx: usize = FatChunkIdx::from(ChunkIdx::from_usize(idx)).into();
+        let BlockMeshShape::MultiTexture(tex) = fat_palette[fat_idx].shape else {
+            continue;
+        };
+        let mut mesh = fat_palette[fat_idx].clone();
+        mesh.shape = BlockMeshShape::MultiTexture(state.rotate_faces(tex));
+        fat_palette.set_block(fat_idx, mesh);
+    }
+}
+
+//fluid heights depend on the fluid level, which isn't part of the block mesh
+//fluid with the same fluid above it fills the whole block so there's no gap in falling fluid
+fn apply_fluid_heights(
+    fat_palette: &mut BlockPalette<BlockMesh, BLOCKS_PER_FAT_CHUNK>,
+    level: &Level,
+    coord: ChunkCoord,
+) {
+    let origin = BlockCoord::from(coord);
+    for x in -1..CHUNK_SIZE_I8 + 1 {
+        for y in -1..CHUNK_SIZE_I8 + 1 {
+            for z in -1..CHUNK_SIZE_I8 + 1 {
+                let idx: usize = FatChunkIdx::new(x, y, z).into();
+                let BlockMeshShape::Fluid(_, tex) = fat_palette[idx].shape else {
+                    continue;
+                };
+                let pos = origin + BlockCoord::new(x as i32, y as i32, z as i32);
+                let fluid_above = y < CHUNK_SIZE_I8
+                    && matches!(fat_palette[FatChunkIdx::new(x, y + 1, z).into()].shape,
+                        BlockMeshShape::Fluid(_, above_tex) if above_tex == tex);
+                let height = if fluid_above {
+                    1.0
+                } else {
+                    blocks::fluid::fluid_height(level.get_fluid_level(pos))
+                };
+                let mut mesh = fat_palette[idx].clone();
+                mesh.shape = BlockMeshShape::Fluid(height, tex);
+                fat_palette.set_block(idx, mesh);
+            }
+        }
+    }
+}
+
 pub fn should_mesh_face(block: &BlockMesh, block_face: Direction, neighbor: &BlockMesh) -> bool {
     has_face(block, block_face) && face_showing(block, block_face, neighbor)
 }
@@ -305,7 +363,8 @@ pub fn has_face(block: &BlockMesh, block_face: Direction) -> bool {
         BlockMeshShape::Empty => false,
         BlockMeshShape::Uniform(_)
         | BlockMeshShape::MultiTexture(_)
-        | BlockMeshShape::BottomSlab(_, _) => true,
+        | BlockMeshShape::BottomSlab(_, _)
+        | BlockMeshShape::Fluid(_, _) => true,
         BlockMeshShape::Cross(_) => !matches!(block_face, Direction::PosY | Direction::NegY),
     }
 }
@@ -322,6 +381,15 @@ pub fn face_showing(block: &BlockMesh, block_face: Direction, neighbor: &BlockMe
             block_face == Direction::PosY
                 || block != neighbor && neighbor.shape.is_transparent(block_face.opposite())
         }
+        //neighboring blocks of the same fluid always join up, even if their heights differ
+        BlockMeshShape::Fluid(_, tex) => {
+            if let BlockMeshShape::Fluid(_, neighbor_tex) = neighbor.shape
+                && neighbor_tex == tex
+            {
+                return false;
+            }
+            block_face == Direction::PosY || neighbor.shape.is_transparent(block_face.opposite())
+        }
         BlockMeshShape::Cross(_) => true,
         BlockMeshShape::Empty => false,
     }
@@ -336,31 +404,38 @@ pub fn mesh_single_block(b: &BlockMesh, meshes: &mut ResMut<Assets<Mesh>>) -> Op
     if has_face(b, Direction::PosZ) {
         mesh_pos_z(&b.shape, Vec3::ZERO, Vec3::ONE, &mut mesh);
         mesh.ao_level.extend([1.0; 4]);
+        mesh.light_level.extend([Vec2::ONE; 4]);
     }
     if has_face(b, Direction::NegZ) {
         mesh_neg_z(&b.shape, Vec3::ZERO, Vec3::ONE, &mut mesh);
         mesh.ao_level.extend([1.0; 4]);
+        mesh.light_level.extend([Vec2::ONE; 4]);
     }
     if has_face(b, Direction::PosX) {
         mesh_pos_x(&b.shape, Vec3::ZERO, Vec3::ONE, &mut mesh);
         mesh.ao_level.extend([1.0; 4]);
+        mesh.light_level.extend([Vec2::ONE; 4]);
     }
     if has_face(b, Direction::NegX) {
         mesh_neg_x(&b.shape, Vec3::ZERO, Vec3::ONE, &mut mesh);
         mesh.ao_level.extend([1.0; 4]);
+        mesh.light_level.extend([Vec2::ONE; 4]);
     }
     if has_face(b, Direction::PosY) {
         mesh_pos_y(&b.shape, Vec3::ZERO, Vec3::ONE, &mut mesh);
         mesh.ao_level.extend([1.0; 4]);
+        mesh.light_level.extend([Vec2::ONE; 4]);
     }
     if has_face(b, Direction::NegY) {
         mesh_neg_y(&b.shape, Vec3::ZERO, Vec3::ONE, &mut mesh);
         mesh.ao_level.extend([1.0; 4]);
+        mesh.light_level.extend([Vec2::ONE; 4]);
     }
     Some(mesh.create_mesh(meshes))
 }
 fn mesh_block<T: ChunkStorage<BlockMesh>>(
     fat_chunk: &Chunk<T, BlockMesh>,
+    light: &FatChunkLight,
     b: &BlockMesh,
     coord: FatChunkIdx,
     origin: Vec3,
@@ -385,7 +460,14 @@ fn mesh_block<T: ChunkStorage<BlockMesh>>(
             Vec3::new(data.scale, data.scale, data.scale),
             selected_data,
         );
-        add_ao_pos_z(&b.shape, fat_chunk, coord, selected_data)
+        add_ao_pos_z(&b.shape, fat_chunk, coord, selected_data);
+        add_face_light(
+            &b.shape,
+            light,
+            coord,
+            FatChunkIdx::new(coord.x, coord.y, coord.z + 1),
+            selected_data,
+        );
     }
     //negative z face
     if should_mesh_face(
@@ -399,7 +481,14 @@ fn mesh_block<T: ChunkStorage<BlockMesh>>(
             Vec3::new(data.scale, data.scale, data.scale),
             selected_data,
         );
-        add_ao_neg_z(&b.shape, fat_chunk, coord, selected_data)
+        add_ao_neg_z(&b.shape, fat_chunk, coord, selected_data);
+        add_face_light(
+            &b.shape,
+            light,
+            coord,
+            FatChunkIdx::new(coord.x, coord.y, coord.z - 1),
+            selected_data,
+        );
     }
     //positive y face
     if should_mesh_face(
@@ -413,7 +502,14 @@ fn mesh_block<T: ChunkStorage<BlockMesh>>(
             Vec3::new(data.scale, data.scale, data.scale),
             selected_data,
         );
-        add_ao_pos_y(&b.shape, fat_chunk, coord, selected_data)
+        add_ao_pos_y(&b.shape, fat_chunk, coord, selected_data);
+        add_face_light(
+            &b.shape,
+            light,
+            coord,
+            FatChunkIdx::new(coord.x, coord.y + 1, coord.z),
+            selected_data,
+        );
     }
     //negative y face
     if should_mesh_face(
@@ -427,7 +523,14 @@ fn mesh_block<T: ChunkStorage<BlockMesh>>(
             Vec3::new(data.scale, data.scale, data.scale),
             selected_data,
         );
-        add_ao_neg_y(&b.shape, fat_chunk, coord, selected_data)
+        add_ao_neg_y(&b.shape, fat_chunk, coord, selected_data);
+        add_face_light(
+            &b.shape,
+            light,
+            coord,
+            FatChunkIdx::new(coord.x, coord.y - 1, coord.z),
+            selected_data,
+        );
     }
     //positive x face
     if should_mesh_face(
@@ -441,7 +544,14 @@ fn mesh_block<T: ChunkStorage<BlockMesh>>(
             Vec3::new(data.scale, data.scale, data.scale),
             selected_data,
         );
-        add_ao_pos_x(&b.shape, fat_chunk, coord, selected_data)
+        add_ao_pos_x(&b.shape, fat_chunk, coord, selected_data);
+        add_face_light(
+            &b.shape,
+            light,
+            coord,
+            FatChunkIdx::new(coord.x + 1, coord.y, coord.z),
+            selected_data,
+        );
     }
     //negative x face
     if should_mesh_face(
@@ -455,7 +565,14 @@ fn mesh_block<T: ChunkStorage<BlockMesh>>(
             Vec3::new(data.scale, data.scale, data.scale),
             selected_data,
         );
-        add_ao_neg_x(&b.shape, fat_chunk, coord, selected_data)
+        add_ao_neg_x(&b.shape, fat_chunk, coord, selected_data);
+        add_face_light(
+            &b.shape,
+            light,
+            coord,
+            FatChunkIdx::new(coord.x - 1, coord.y, coord.z),
+            selected_data,
+        );
     }
 }
 
@@ -513,7 +630,7 @@ pub fn mesh_neg_z(b: &BlockMeshShape, origin: Vec3, scale: Vec3, data: &mut Mesh
 
             tex[Direction::NegZ.to_idx()] as i32
         }
-        BlockMeshShape::BottomSlab(height, tex) => {
+        BlockMeshShape::BottomSlab(height, tex) | BlockMeshShape::Fluid(height, tex) => {
             //TODO: ao strength should be reduced based on height
             data.verts.push(origin + Vec3::new(0., 0., 0.));
             data.verts
@@ -617,7 +734,7 @@ pub fn mesh_pos_z(b: &BlockMeshShape, origin: Vec3, scale: Vec3, data: &mut Mesh
 
             tex[Direction::PosZ.to_idx()] as i32
         }
-        BlockMeshShape::BottomSlab(height, tex) => {
+        BlockMeshShape::BottomSlab(height, tex) | BlockMeshShape::Fluid(height, tex) => {
             data.verts.push(origin + Vec3::new(0., 0., scale.z));
             data.verts.push(origin + Vec3::new(scale.x, 0., scale.z));
             data.verts
@@ -717,7 +834,7 @@ pub fn mesh_neg_x(b: &BlockMeshShape, origin: Vec3, scale: Vec3, data: &mut Mesh
 
             tex[Direction::NegX.to_idx()] as i32
         }
-        BlockMeshShape::BottomSlab(height, tex) => {
+        BlockMeshShape::BottomSlab(height, tex) | BlockMeshShape::Fluid(height, tex) => {
             data.verts.push(origin + Vec3::new(0., 0., scale.z));
             data.verts
                 .push(origin + Vec3::new(0., scale.y * height, scale.z));
@@ -819,7 +936,7 @@ pub fn mesh_pos_x(b: &BlockMeshShape, origin: Vec3, scale: Vec3, data: &mut Mesh
 
             tex[Direction::PosX.to_idx()] as i32
         }
-        BlockMeshShape::BottomSlab(height, tex) => {
+        BlockMeshShape::BottomSlab(height, tex) | BlockMeshShape::Fluid(height, tex) => {
             data.verts
                 .push(origin + Vec3::new(scale.x, scale.y * height, scale.z));
             data.verts.push(origin + Vec3::new(scale.x, 0., scale.z));
@@ -915,7 +1032,7 @@ pub fn mesh_pos_y(b: &BlockMeshShape, origin: Vec3, scale: Vec3, data: &mut Mesh
 
             tex[Direction::PosY.to_idx()] as i32
         }
-        BlockMeshShape::BottomSlab(height, tex) => {
+        BlockMeshShape::BottomSlab(height, tex) | BlockMeshShape::Fluid(height, tex) => {
             data.verts
                 .push(origin + Vec3::new(0., scale.y * height, 0.));
             data.verts
@@ -993,7 +1110,7 @@ pub fn mesh_neg_y(b: &BlockMeshShape, origin: Vec3, scale: Vec3, data: &mut Mesh
 
             tex[Direction::NegY.to_idx()] as i32
         }
-        BlockMeshShape::BottomSlab(_, tex) => {
+        BlockMeshShape::BottomSlab(_, tex) | BlockMeshShape::Fluid(_, tex) => {
             data.verts.push(origin + Vec3::new(0., 0., 0.));
             data.verts.push(origin + Vec3::new(scale.x, 0., 0.));
             data.verts.push(origin + Vec3::new(scale.x, 0., scale.z));
@@ -1021,6 +1138,21 @@ pub fn mesh_neg_y(b: &BlockMeshShape, origin: Vec3, scale: Vec3, data: &mut Mesh
     data.layer_idx.push(texture);
 }
 
+//faces take the light of the block they face, except for shapes that don't fill their block
+fn add_face_light(
+    b: &BlockMeshShape,
+    light: &FatChunkLight,
+    coord: FatChunkIdx,
+    facing: FatChunkIdx,
+    data: &mut MeshData,
+) {
+    let level = match b {
+        BlockMeshShape::Cross(_) => light[Into::<usize>::into(coord)],
+        _ => light[Into::<usize>::into(facing)],
+    };
+    data.light_level.extend([level.to_vec2(); 4]);
+}
+
 fn add_tris(tris: &mut Vec<u32>, first_vert_idx: u32) {
     tris.push(first_vert_idx);
     tris.push(first_vert_idx + 1);
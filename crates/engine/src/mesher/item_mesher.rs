@@ -6,6 +6,7 @@ use crate::{
     items::{inventory::Inventory, ItemIcon, ItemName},
     world::{
         chunk::{Chunk, ChunkCoord, FatChunkIdx, BLOCKS_PER_FAT_CHUNK},
+        light::LightLevel,
         util::BlockPalette,
         BlockMesh, BlockMeshShape,
     },
@@ -235,7 +236,11 @@ pub fn generate_item_meshes(
                     }
                 }
 
-                mesh_chunk(&fat_chunk, &mut chunk_mesh);
+                mesh_chunk(
+                    &fat_chunk,
+                    &[LightLevel::FULL; BLOCKS_PER_FAT_CHUNK],
+                    &mut chunk_mesh,
+                );
                 for vert in chunk_mesh.transparent.verts.iter_mut() {
                     *vert /= 16.0;
                 }
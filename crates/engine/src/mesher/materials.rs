@@ -22,6 +22,9 @@ pub const ATTRIBUTE_TEXLAYER: MeshVertexAttribute =
     MeshVertexAttribute::new("TexLayer", 970540917, VertexFormat::Sint32);
 pub const ATTRIBUTE_AO: MeshVertexAttribute =
     MeshVertexAttribute::new("AOLevel", 970540918, VertexFormat::Float32);
+//(sky light, block light), each 0-1
+pub const ATTRIBUTE_LIGHT: MeshVertexAttribute =
+    MeshVertexAttribute::new("LightLevel", 970540919, VertexFormat::Float32x2);
 
 #[derive(Resource)]
 pub struct ChunkMaterial {
@@ -59,6 +59,10 @@ impl Plugin for MesherPlugin {
 pub struct TerrainTexture(pub Vec<Handle<Image>>);
 
 pub fn is_chunk_ready_for_meshing(coord: ChunkCoord, level: &Level) -> bool {
+    if !level.has_chunk_light(coord) {
+        //wait for the lighting pass so we don't mesh twice
+        return false;
+    }
     //i wish i could extrac this if let Some() shit into a function
     //but that makes the borrow checker angry
     for dx in -1..2 {
@@ -58,6 +58,9 @@ pub struct RemoteClient(pub Option<ClientId>);
 #[derive(Component)]
 pub struct DisconnectedClient(pub Option<ClientId>);
 
+//username of the server's own player, which is also the player in singleplayer
+pub const HOST_USERNAME: &str = "host";
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PlayerInfo {
     pub username: String,
@@ -12,11 +12,20 @@ use bevy_quinnet::{
 };
 
 use crate::{
-    actors::LocalPlayer,
-    items::{inventory::Inventory, ItemRegistry, ItemResources, SwingItemEvent, UseItemEvent},
-    net::{DisconnectedClient, PlayerInfo, PlayerList, RemoteClient, ORDERED_RELIABLE},
+    actors::{Combatant, LocalPlayer},
+    items::{
+        inventory::Inventory, CreatorItem, ItemId, ItemRegistry, ItemResources, SwingItemEvent,
+        UseItemEvent,
+    },
+    net::{
+        DisconnectedClient, PlayerInfo, PlayerList, RemoteClient, HOST_USERNAME, ORDERED_RELIABLE,
+    },
     physics::movement::Velocity,
-    serialization::ChunkSaveFormat,
+    serialization::{
+        db::LevelDB,
+        players::{load_player, SavedPlayer},
+        ChunkSaveFormat, SavedToLoadedIdMap,
+    },
     util::LocalRepeatingTimer,
     world::{
         chunk::{ChunkCoord, ChunkType},
@@ -109,9 +118,12 @@ fn handle_client_messages(
     mut use_item_writer: EventWriter<UseItemEvent>,
     mut swing_item_writer: EventWriter<SwingItemEvent>,
     inventory_query: Query<&Inventory>,
+    creator_query: Query<&CreatorItem>,
     block_resources: Res<BlockResources>,
     item_resources: Res<ItemResources>,
+    item_id_map: Option<Res<SavedToLoadedIdMap<ItemId>>>,
     level: Res<Level>,
+    mut db: Option<ResMut<LevelDB>>,
 ) {
     let endpoint = server.endpoint_mut();
     for client_id in endpoint.clients() {
@@ -119,16 +131,33 @@ fn handle_client_messages(
         {
             match message {
                 ClientMessage::Join { name } => {
+                    //players that were here before start where they were saved, with what they had
+                    let saved = db.as_mut().and_then(|db| {
+                        load_player(db, &name).unwrap_or_else(|e| {
+                            error!("Error loading saved player {}: {:?}", name, e);
+                            None
+                        })
+                    });
+                    let spawn_point = saved
+                        .as_ref()
+                        .filter(|saved| saved.level == level.id)
+                        .map_or_else(
+                            || level.get_spawn_point(),
+                            |saved| saved.transform.translation,
+                        );
                     handle_join(
                         client_id,
                         name,
-                        level.get_spawn_point(),
+                        spawn_point,
+                        saved.as_ref(),
                         &mut users,
                         server_player.as_ref().map(|s| s.0.clone()),
                         endpoint,
                         &mut commands,
                         &block_resources.registry,
                         &item_resources.registry,
+                        &creator_query,
+                        item_id_map.as_deref(),
                     );
                 }
                 ClientMessage::Disconnect {} => {
@@ -214,12 +243,15 @@ fn handle_join(
     client_id: ClientId,
     username: String,
     spawn_point: Vec3,
+    saved: Option<&SavedPlayer>,
     users: &mut PlayerList,
     server_player: Option<PlayerInfo>,
     endpoint: &mut Endpoint,
     commands: &mut Commands,
     block_registry: &BlockRegistry,
     item_registry: &ItemRegistry,
+    creator_query: &Query<&CreatorItem>,
+    item_id_map: Option<&SavedToLoadedIdMap<ItemId>>,
 ) {
     if users.infos.contains_key(&client_id) {
         warn!(
@@ -232,6 +264,30 @@ fn handle_join(
         let player_entity = commands
             .spawn((StateScoped(GameState::Game), RemoteClient(Some(client_id))))
             .id();
+        //the rest of the player is added by spawn_remote_player. what's saved for them is put back here, so it's saved again
+        let mut combatant = Combatant::new(10.0, 0.0);
+        if let (Some(saved_health), Combatant::Root { health, .. }) =
+            (saved.and_then(|saved| saved.health), &mut combatant)
+        {
+            *health = saved_health;
+        }
+        let mut inventory = Inventory::new(player_entity, 40);
+        if let (Some(saved), Some(id_map)) = (saved, item_id_map) {
+            saved.restore_inventory(
+                &mut inventory,
+                item_registry,
+                block_registry,
+                creator_query,
+                id_map,
+                commands,
+            );
+        }
+        commands
+            .entity(player_entity)
+            .insert((combatant, inventory));
+        if let Some(stamina) = saved.and_then(|saved| saved.stamina) {
+            commands.entity(player_entity).insert(stamina);
+        }
         let info = PlayerInfo {
             username,
             entity: player_entity,
@@ -384,7 +440,7 @@ fn assign_server_player(
 ) {
     if let Ok(entity) = local_player.get_single() {
         let info = PlayerInfo {
-            username: "host".into(),
+            username: HOST_USERNAME.into(),
             entity,
         };
         commands.insert_resource(ServerPlayer(info.clone()));
@@ -407,7 +463,9 @@ fn send_chunk(
                     chunk: ChunkSaveFormat::palette_ids_only_no_map(
                         (c.position, &c.blocks),
                         id_query,
-                    ),
+                    )
+                    .with_states(&c.states)
+                    .with_fluid_levels(level.get_saved_fluid_levels(coord)),
                 },
             ) {
                 error!("{}", e);
@@ -230,6 +230,60 @@ impl Aabb {
         }
     }
 
+    //slab test against a ray. `ray_direction` should be normalized, so the returned time is a distance
+    //returns (distance, normal of the face the ray entered through), or a normal of None if the ray starts inside
+    pub fn ray_intersection(
+        self,
+        my_pos: Vec3,
+        ray_origin: Vec3,
+        ray_direction: Vec3,
+        max_dist: f32,
+    ) -> Option<(f32, Option<Direction>)> {
+        let min = self.world_min(my_pos);
+        let max = self.world_max(my_pos);
+        let mut t_enter = f32::NEG_INFINITY;
+        let mut t_exit = f32::INFINITY;
+        let mut enter_face = None;
+        for axis in 0..3 {
+            let origin = ray_origin[axis];
+            let dir = ray_direction[axis];
+            if dir == 0.0 {
+                //parallel to this slab, so it has to already be between the planes
+                if origin < min[axis] || origin > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t1 = (min[axis] - origin) / dir;
+            let t2 = (max[axis] - origin) / dir;
+            let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+            if near > t_enter {
+                t_enter = near;
+                //moving in the positive direction enters through the negative face
+                enter_face = Some(match (axis, dir > 0.0) {
+                    (0, true) => Direction::NegX,
+                    (0, false) => Direction::PosX,
+                    (1, true) => Direction::NegY,
+                    (1, false) => Direction::PosY,
+                    (_, true) => Direction::NegZ,
+                    (_, false) => Direction::PosZ,
+                });
+            }
+            t_exit = t_exit.min(far);
+        }
+        if t_exit < t_enter.max(0.0) {
+            return None;
+        }
+        if t_enter < 0.0 {
+            //ray starts inside
+            return Some((0.0, None));
+        }
+        if t_enter > max_dist {
+            return None;
+        }
+        Some((t_enter, enter_face))
+    }
+
     //I had a lot of issues getting swept collision working, expect a lot of comments
     //returns (time, hit point, normal)
     pub fn sweep_ray(
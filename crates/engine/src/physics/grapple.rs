@@ -10,6 +10,7 @@ use crate::{
 use super::{
     movement::{Acceleration, Mass, Velocity},
     query::{raycast, Raycast},
+    spatial::SpatialIndex,
     PhysicsLevelSet,
 };
 
@@ -103,6 +104,7 @@ fn shoot_grapple(
     hand_query: Query<&UseHand>,
     physics_query: Query<&crate::world::BlockPhysics>,
     object_query: Query<(Entity, &GlobalTransform, &super::collision::Aabb)>,
+    spatial_index: Res<SpatialIndex>,
     mut commands: Commands,
 ) {
     for ShootGrappleEvent {
@@ -113,7 +115,14 @@ fn shoot_grapple(
         max_speed,
     } in event_reader.read()
     {
-        let Some(hit) = raycast(*ray, &level, &physics_query, &object_query, &[*owner]) else {
+        let Some(hit) = raycast(
+            *ray,
+            &level,
+            &physics_query,
+            &object_query,
+            &spatial_index,
+            &[*owner],
+        ) else {
             continue;
         };
         let visual = GrappleVisual {
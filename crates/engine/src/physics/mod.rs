@@ -11,6 +11,7 @@ pub mod grapple;
 pub mod interpolation;
 pub mod movement;
 pub mod query;
+pub mod spatial;
 pub mod spring;
 mod test;
 
@@ -22,6 +23,7 @@ pub const TPS: f64 = 64.0;
 //run in fixed update
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub enum PhysicsSystemSet {
+    UpdateSpatialIndex,
     Main, //all user code should run here
     ProcessRaycasts,
     UpdatePosition,
@@ -44,12 +46,14 @@ impl Plugin for PhysicsPlugin {
             grapple::GrapplePlugin,
             spring::SpringPlugin,
             interpolation::InterpolationPlugin,
+            spatial::SpatialIndexPlugin,
         ))
         .insert_resource(Time::<Fixed>::from_hz(TPS))
         .configure_sets(
             FixedUpdate,
             (
-                PhysicsSystemSet::Main.after(UtilSystemSet),
+                PhysicsSystemSet::UpdateSpatialIndex.after(UtilSystemSet),
+                PhysicsSystemSet::Main,
                 PhysicsSystemSet::ProcessRaycasts,
                 PhysicsSystemSet::UpdatePosition,
                 PhysicsSystemSet::UpdateDerivatives,
@@ -1,8 +1,10 @@
 use bevy::prelude::*;
 
+use util::direction::Direction;
+
 use crate::{
-    physics::collision::Aabb,
-    world::{BlockCoord, BlockPhysics, Level},
+    physics::{collision::Aabb, spatial::SpatialIndex},
+    world::{BlockCoord, BlockPhysics, BlockType, Level, LevelData},
 };
 
 #[derive(Copy, Clone)]
@@ -29,79 +31,228 @@ pub enum RaycastHit {
 
 pub struct RayCastHitEntity {
     pub hit_pos: Vec3,
-    pub normal: util::direction::Direction,
+    //the face the ray entered through. if the ray started inside, it's the face opposite the ray's direction
+    pub normal: Direction,
     pub entity: Entity,
+    //from the ray's origin to hit_pos
+    pub distance: f32,
+}
+
+#[derive(Debug)]
+pub struct BlockcastHit {
+    pub hit_pos: Vec3,
+    pub block_pos: BlockCoord,
+    pub block: Option<BlockType>,
+    //the face of block_pos the line entered through, or zero if the line started in it
+    pub normal: BlockCoord,
+}
+
+//a block visited by VoxelTraversal
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoxelStep {
+    pub coord: BlockCoord,
+    //distance along the ray where it enters the block
+    pub distance: f32,
+    //the face the ray entered through, or None for the block the ray starts in
+    pub normal: Option<Direction>,
+}
+
+//visits every block a ray passes through, in order, without skipping corners (Amanatides & Woo)
+pub struct VoxelTraversal {
+    coord: IVec3,
+    step: IVec3,
+    //distance along the ray to the next block boundary on each axis
+    t_max: Vec3,
+    //distance along the ray to cross a whole block on each axis
+    t_delta: Vec3,
+    max_dist: f32,
+    next: Option<VoxelStep>,
+}
+
+impl VoxelTraversal {
+    //`direction` should be normalized, so distances are in blocks
+    pub fn new(origin: Vec3, direction: Vec3, max_dist: f32) -> Self {
+        let coord = origin.floor().as_ivec3();
+        let mut step = IVec3::ZERO;
+        let mut t_max = Vec3::INFINITY;
+        let mut t_delta = Vec3::INFINITY;
+        for axis in 0..3 {
+            if direction[axis] == 0.0 {
+                continue;
+            }
+            step[axis] = if direction[axis] > 0.0 { 1 } else { -1 };
+            let boundary = if step[axis] > 0 {
+                coord[axis] as f32 + 1.0
+            } else {
+                coord[axis] as f32
+            };
+            t_max[axis] = (boundary - origin[axis]) / direction[axis];
+            t_delta[axis] = 1.0 / direction[axis].abs();
+        }
+        Self {
+            coord,
+            step,
+            t_max,
+            t_delta,
+            max_dist,
+            next: Some(VoxelStep {
+                coord: coord.into(),
+                distance: 0.0,
+                normal: None,
+            }),
+        }
+    }
+}
+
+impl Iterator for VoxelTraversal {
+    type Item = VoxelStep;
+
+    fn next(&mut self) -> Option<VoxelStep> {
+        let current = self.next.take()?;
+        let axis = if self.t_max.x < self.t_max.y && self.t_max.x < self.t_max.z {
+            0
+        } else if self.t_max.y < self.t_max.z {
+            1
+        } else {
+            2
+        };
+        let distance = self.t_max[axis];
+        if distance <= self.max_dist {
+            self.coord[axis] += self.step[axis];
+            self.t_max[axis] += self.t_delta[axis];
+            //moving in the positive direction enters through the negative face
+            let normal = match (axis, self.step[axis] > 0) {
+                (0, true) => Direction::NegX,
+                (0, false) => Direction::PosX,
+                (1, true) => Direction::NegY,
+                (1, false) => Direction::PosY,
+                (_, true) => Direction::NegZ,
+                (_, false) => Direction::PosZ,
+            };
+            self.next = Some(VoxelStep {
+                coord: self.coord.into(),
+                distance,
+                normal: Some(normal),
+            });
+        }
+        Some(current)
+    }
 }
 
-//todo improve this: blockcast for blocks (breseham's -> sweeping), only query entities store in chunks along the line and sweep
-//todo: normal -- update use_block_entity_item after
+//returns the closest block or entity the ray hits. blocks are hit using their BlockPhysics collider
 pub fn raycast(
     ray: Raycast,
     level: &Level,
     physics_query: &Query<&BlockPhysics>,
     object_query: &Query<(Entity, &GlobalTransform, &Aabb)>,
+    index: &SpatialIndex,
     exclude: &[Entity],
 ) -> Option<RaycastHit> {
-    const STEP_SIZE: f32 = 1. / 32.;
-    let mut dist = 0.0;
-    while dist <= ray.length {
-        //test intersection
-        let test_point = ray.origin + ray.direction * dist;
-        //test block
-        let test_block_coord = BlockCoord::from(test_point);
-        if let Some(block_entity) = level.get_block_entity(test_block_coord) {
-            if !exclude.contains(&block_entity) {
-                if let Some(collider) = physics_query
-                    .get(block_entity)
-                    .ok()
-                    .and_then(Aabb::from_block)
-                {
-                    if collider.intersects_point(test_block_coord.to_vec3(), test_point) {
-                        //our point intersects the block
-                        return Some(RaycastHit::Block(
-                            test_block_coord,
-                            RayCastHitEntity {
-                                hit_pos: test_point,
-                                entity: block_entity,
-                                normal: util::direction::Direction::PosY,
-                            },
-                        ));
-                    }
-                }
-            }
+    let direction = *ray.direction;
+    //normal to use when the ray starts inside something
+    let inside_normal = Direction::from(-direction);
+    let mut closest = None;
+    let mut max_dist = ray.length;
+    for step in VoxelTraversal::new(ray.origin, direction, ray.length) {
+        let Some(block_entity) = level.get_block_entity(step.coord) else {
+            continue;
+        };
+        if exclude.contains(&block_entity) {
+            continue;
         }
-        //test entity
-        for (entity, tf, col) in object_query.iter() {
-            if exclude.contains(&entity) {
-                continue;
-            }
-            if col.intersects_point(tf.translation(), test_point) {
-                //our point intersects an entity
-                return Some(RaycastHit::Object(RayCastHitEntity {
-                    hit_pos: test_point,
-                    normal: util::direction::Direction::PosY,
+        let Some(collider) = physics_query
+            .get(block_entity)
+            .ok()
+            .and_then(Aabb::from_block)
+        else {
+            continue;
+        };
+        //colliders don't stick out of their block, so the first one that's hit is the closest
+        if let Some((distance, normal)) =
+            collider.ray_intersection(step.coord.to_vec3(), ray.origin, direction, ray.length)
+        {
+            max_dist = distance;
+            closest = Some(RaycastHit::Block(
+                step.coord,
+                RayCastHitEntity {
+                    hit_pos: ray.origin + direction * distance,
+                    normal: normal.unwrap_or(inside_normal),
+                    entity: block_entity,
+                    distance,
+                },
+            ));
+            break;
+        }
+    }
+    for entity in index.query_ray(ray.origin, direction, max_dist) {
+        if exclude.contains(&entity) {
+            continue;
+        }
+        let Ok((entity, tf, col)) = object_query.get(entity) else {
+            continue;
+        };
+        if let Some((distance, normal)) =
+            col.ray_intersection(tf.translation(), ray.origin, direction, max_dist)
+        {
+            //entities win ties, since they're usually in front of the block they're standing on
+            if distance <= max_dist {
+                max_dist = distance;
+                closest = Some(RaycastHit::Object(RayCastHitEntity {
+                    hit_pos: ray.origin + direction * distance,
+                    normal: normal.unwrap_or(inside_normal),
                     entity,
+                    distance,
                 }));
             }
         }
-        dist += STEP_SIZE;
+    }
+    closest
+}
+
+//checks the blocks on the line from `origin` to `origin + end_offset` in order, and returns the first one `checker` accepts
+//unloaded blocks are passed to `checker` as None
+pub fn blockcast(
+    level: &LevelData,
+    origin: Vec3,
+    end_offset: Vec3,
+    mut checker: impl FnMut(Option<BlockType>) -> bool,
+) -> Option<BlockcastHit> {
+    let _my_span = info_span!("blockcast", name = "blockcast").entered();
+    let length = end_offset.length();
+    let direction = end_offset.normalize_or_zero();
+    for step in VoxelTraversal::new(origin, direction, length) {
+        let block = level.get_block(step.coord);
+        if checker(block) {
+            return Some(BlockcastHit {
+                hit_pos: origin + direction * step.distance,
+                block_pos: step.coord,
+                block,
+                normal: step
+                    .normal
+                    .map_or(BlockCoord::new(0, 0, 0), BlockCoord::from),
+            });
+        }
     }
     None
 }
 
-//todo improve this
+//returns an entity or block that contains `point`. entities are checked first
 pub fn test_point<T: crate::actors::team::Team>(
     point: Vec3,
     level: &Level,
     physics_query: &Query<&BlockPhysics>,
     object_query: &Query<(Entity, &GlobalTransform, &Aabb), T::Targets>,
+    index: &SpatialIndex,
     exclude: &[Entity],
 ) -> Option<Entity> {
     //test entity
-    for (entity, tf, col) in object_query.iter() {
+    for entity in index.query_box(point, point) {
         if exclude.contains(&entity) {
             continue;
         }
+        let Ok((entity, tf, col)) = object_query.get(entity) else {
+            continue;
+        };
         if col.intersects_point(tf.translation(), point) {
             //our point intersects an entity
             return Some(entity);
@@ -126,18 +277,22 @@ pub fn test_point<T: crate::actors::team::Team>(
     None
 }
 
-//todo improve this
+//returns an entity that overlaps `aabb` at `point`
 pub fn test_box<T: crate::actors::team::Team>(
     point: Vec3,
     aabb: Aabb,
     object_query: &Query<(Entity, &GlobalTransform, &Aabb), T::Targets>,
+    index: &SpatialIndex,
     exclude: &[Entity],
 ) -> Option<Entity> {
     //test entity
-    for (entity, tf, col) in object_query.iter() {
+    for entity in index.query_box(aabb.world_min(point), aabb.world_max(point)) {
         if exclude.contains(&entity) {
             continue;
         }
+        let Ok((entity, tf, col)) = object_query.get(entity) else {
+            continue;
+        };
         if aabb.intersects_aabb(point, *col, tf.translation()) {
             //our point intersects an entity
             return Some(entity);
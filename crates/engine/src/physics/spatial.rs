@@ -0,0 +1,155 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use super::{collision::Aabb, query::VoxelTraversal, PhysicsSystemSet};
+
+pub struct SpatialIndexPlugin;
+
+impl Plugin for SpatialIndexPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialIndex>()
+            .add_systems(
+                FixedUpdate,
+                update_spatial_index.in_set(PhysicsSystemSet::UpdateSpatialIndex),
+            )
+            //frames can go by without a physics tick, so entities spawned since the last one are added before Update
+            .add_systems(PreUpdate, update_spatial_index.run_if(aabb_added));
+    }
+}
+
+//side length of a cell in blocks. most actors fit in one cell, and range queries are usually a few cells across
+pub const SPATIAL_CELL_SIZE: f32 = 4.0;
+
+#[derive(Clone, Copy, Debug)]
+struct SpatialEntry {
+    entity: Entity,
+    position: Vec3,
+    //world space bounds of the collider, grown to include `position` if the collider is offset away from it
+    min: Vec3,
+    max: Vec3,
+}
+
+//buckets every entity with an Aabb by the cells it overlaps, so queries only look at entities near them
+//rebuilt at the start of every physics tick, and before Update if anything with an Aabb was spawned since.
+//systems in Update see the positions from the last rebuild
+//results are candidates: callers still filter them with their own query (for teams, etc.)
+#[derive(Resource, Default)]
+pub struct SpatialIndex {
+    entries: Vec<SpatialEntry>,
+    cells: HashMap<IVec3, Vec<usize>>,
+}
+
+impl SpatialIndex {
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        //keep the cells that were used last time so their allocations get reused, and drop the rest
+        self.cells.retain(|_, bucket| {
+            let used = !bucket.is_empty();
+            bucket.clear();
+            used
+        });
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec3, aabb: Aabb) {
+        let min = aabb.world_min(position).min(position);
+        let max = aabb.world_max(position).max(position);
+        let idx = self.entries.len();
+        self.entries.push(SpatialEntry {
+            entity,
+            position,
+            min,
+            max,
+        });
+        let (min_cell, max_cell) = (Self::cell(min), Self::cell(max));
+        for x in min_cell.x..=max_cell.x {
+            for y in min_cell.y..=max_cell.y {
+                for z in min_cell.z..=max_cell.z {
+                    self.cells.entry(IVec3::new(x, y, z)).or_default().push(idx);
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn cell(point: Vec3) -> IVec3 {
+        (point / SPATIAL_CELL_SIZE).floor().as_ivec3()
+    }
+
+    //entities whose bounds overlap the box from `min` to `max`. each entity is returned once
+    pub fn query_box(&self, min: Vec3, max: Vec3) -> impl Iterator<Item = Entity> + '_ {
+        self.box_entries(min, max).map(|entry| entry.entity)
+    }
+
+    fn box_entries(&self, min: Vec3, max: Vec3) -> impl Iterator<Item = &SpatialEntry> + '_ {
+        let (min_cell, max_cell) = (Self::cell(min), Self::cell(max));
+        (min_cell.z..=max_cell.z)
+            .flat_map(move |z| {
+                (min_cell.y..=max_cell.y)
+                    .flat_map(move |y| (min_cell.x..=max_cell.x).map(move |x| IVec3::new(x, y, z)))
+            })
+            .filter_map(|cell| self.cells.get(&cell).map(|bucket| (cell, bucket)))
+            .flat_map(move |(cell, bucket)| {
+                bucket.iter().filter_map(move |idx| {
+                    let entry = &self.entries[*idx];
+                    let overlaps = entry.min.cmple(max).all() && entry.max.cmpge(min).all();
+                    //an entity can be in several cells, so only return it from the cell holding the min corner of the overlap
+                    (overlaps && Self::cell(entry.min.max(min)) == cell).then_some(entry)
+                })
+            })
+    }
+
+    //entities whose position is within `range` of `origin`
+    pub fn query_range(&self, origin: Vec3, range: f32) -> impl Iterator<Item = Entity> + '_ {
+        let sqr_dist = range * range;
+        let half = Vec3::splat(range);
+        self.box_entries(origin - half, origin + half)
+            .filter(move |entry| entry.position.distance_squared(origin) <= sqr_dist)
+            .map(|entry| entry.entity)
+    }
+
+    //entities in the cells a ray passes through, roughly in order of distance. each entity is returned once
+    //`direction` should be normalized
+    pub fn query_ray(&self, origin: Vec3, direction: Vec3, max_dist: f32) -> Vec<Entity> {
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        //a traversal in cell space visits the same cells as one in world space
+        for step in VoxelTraversal::new(
+            origin / SPATIAL_CELL_SIZE,
+            direction,
+            max_dist / SPATIAL_CELL_SIZE,
+        ) {
+            let Some(bucket) = self.cells.get(&IVec3::from(step.coord)) else {
+                continue;
+            };
+            for idx in bucket {
+                if seen.insert(*idx) {
+                    result.push(self.entries[*idx].entity);
+                }
+            }
+        }
+        result
+    }
+}
+
+fn update_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    query: Query<(Entity, &GlobalTransform, &Aabb)>,
+) {
+    index.clear();
+    for (entity, tf, aabb) in query.iter() {
+        index.insert(entity, tf.translation(), *aabb);
+    }
+}
+
+fn aabb_added(query: Query<(), Added<Aabb>>) -> bool {
+    !query.is_empty()
+}
@@ -1 +1,3 @@
-mod collision;
\ No newline at end of file
+mod collision;
+mod query;
+mod spatial;
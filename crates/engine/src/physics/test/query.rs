@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+use util::direction::Direction;
+
+use crate::physics::{collision::Aabb, query::VoxelTraversal};
+use crate::world::BlockCoord;
+
+#[test]
+fn test_voxel_traversal_axis() {
+    let steps: Vec<_> = VoxelTraversal::new(Vec3::new(0.5, 0.5, 0.5), Vec3::X, 3.0).collect();
+    let coords: Vec<_> = steps.iter().map(|s| s.coord).collect();
+    assert_eq!(
+        coords,
+        vec![
+            BlockCoord::new(0, 0, 0),
+            BlockCoord::new(1, 0, 0),
+            BlockCoord::new(2, 0, 0),
+            BlockCoord::new(3, 0, 0),
+        ]
+    );
+    assert_eq!(steps[0].normal, None);
+    assert_eq!(steps[1].normal, Some(Direction::NegX));
+    assert_eq!(steps[1].distance, 0.5);
+    assert_eq!(steps[3].distance, 2.5);
+}
+
+#[test]
+fn test_voxel_traversal_negative_diagonal() {
+    let direction = Vec3::new(-1.0, -2.0, 0.0).normalize();
+    let steps: Vec<_> = VoxelTraversal::new(Vec3::new(0.25, 0.25, 0.5), direction, 5.0).collect();
+    //every block is next to the previous one, so the ray never skips a corner
+    for pair in steps.windows(2) {
+        let diff = IVec3::from(pair[1].coord) - IVec3::from(pair[0].coord);
+        assert_eq!(diff.abs().element_sum(), 1);
+        assert!(pair[1].distance >= pair[0].distance);
+        //the normal points back towards the previous block
+        assert_eq!(
+            BlockCoord::from(pair[1].normal.unwrap()),
+            BlockCoord::from(-diff)
+        );
+    }
+    //crosses y = 0 before x = 0
+    assert_eq!(steps[1].coord, BlockCoord::new(0, -1, 0));
+    assert_eq!(steps[1].normal, Some(Direction::PosY));
+    assert!(steps.last().unwrap().distance <= 5.0);
+}
+
+#[test]
+fn test_ray_aabb() {
+    let aabb = Aabb::new(Vec3::ONE, Vec3::ZERO);
+    let pos = Vec3::new(2.0, 0.0, 0.0);
+    //hits the near face
+    let (dist, normal) = aabb
+        .ray_intersection(pos, Vec3::new(0.0, 0.5, 0.5), Vec3::X, 10.0)
+        .unwrap();
+    assert_eq!(dist, 2.0);
+    assert_eq!(normal, Some(Direction::NegX));
+    //too short
+    assert!(aabb
+        .ray_intersection(pos, Vec3::new(0.0, 0.5, 0.5), Vec3::X, 1.5)
+        .is_none());
+    //misses
+    assert!(aabb
+        .ray_intersection(pos, Vec3::new(0.0, 1.5, 0.5), Vec3::X, 10.0)
+        .is_none());
+    //pointing away
+    assert!(aabb
+        .ray_intersection(pos, Vec3::new(0.0, 0.5, 0.5), Vec3::NEG_X, 10.0)
+        .is_none());
+    //from above
+    let (dist, normal) = aabb
+        .ray_intersection(pos, Vec3::new(2.5, 3.0, 0.5), Vec3::NEG_Y, 10.0)
+        .unwrap();
+    assert_eq!(dist, 2.0);
+    assert_eq!(normal, Some(Direction::PosY));
+    //starting inside
+    assert_eq!(
+        aabb.ray_intersection(pos, Vec3::new(2.5, 0.5, 0.5), Vec3::Y, 10.0),
+        Some((0.0, None))
+    );
+}
@@ -0,0 +1,202 @@
+use std::time::Instant;
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::physics::{
+    collision::Aabb,
+    spatial::{SpatialIndex, SpatialIndexPlugin},
+};
+
+fn random_actors(rng: &mut StdRng, count: usize, area: Vec3) -> Vec<(Entity, Vec3, Aabb)> {
+    (0..count)
+        .map(|i| {
+            let position = Vec3::new(
+                rng.gen_range(-area.x..area.x),
+                rng.gen_range(-area.y..area.y),
+                rng.gen_range(-area.z..area.z),
+            );
+            let size = Vec3::new(
+                rng.gen_range(0.5..2.0),
+                rng.gen_range(0.5..3.0),
+                rng.gen_range(0.5..2.0),
+            );
+            (Entity::from_raw(i as u32), position, Aabb::centered(size))
+        })
+        .collect()
+}
+
+fn build_index(actors: &[(Entity, Vec3, Aabb)]) -> SpatialIndex {
+    let mut index = SpatialIndex::default();
+    for (entity, position, aabb) in actors {
+        index.insert(*entity, *position, *aabb);
+    }
+    index
+}
+
+fn sorted(mut entities: Vec<Entity>) -> Vec<Entity> {
+    entities.sort();
+    entities
+}
+
+#[test]
+fn test_spatial_index_matches_brute_force() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let actors = random_actors(&mut rng, 500, Vec3::new(30.0, 10.0, 30.0));
+    let index = build_index(&actors);
+    assert_eq!(index.len(), actors.len());
+    for _ in 0..200 {
+        let center = Vec3::new(
+            rng.gen_range(-35.0..35.0),
+            rng.gen_range(-12.0..12.0),
+            rng.gen_range(-35.0..35.0),
+        );
+        let query_aabb = Aabb::centered(Vec3::splat(rng.gen_range(0.1..12.0)));
+        //box queries return exactly the overlapping entities, once each
+        let found = sorted(
+            index
+                .query_box(query_aabb.world_min(center), query_aabb.world_max(center))
+                .filter(|e| {
+                    let (_, pos, aabb) = actors[e.index() as usize];
+                    aabb.intersects_aabb(pos, query_aabb, center)
+                })
+                .collect(),
+        );
+        let expected = sorted(
+            actors
+                .iter()
+                .filter(|(_, pos, aabb)| aabb.intersects_aabb(*pos, query_aabb, center))
+                .map(|(e, _, _)| *e)
+                .collect(),
+        );
+        assert_eq!(found, expected);
+        let mut deduped = index
+            .query_box(query_aabb.world_min(center), query_aabb.world_max(center))
+            .collect::<Vec<_>>();
+        let len = deduped.len();
+        deduped.sort();
+        deduped.dedup();
+        assert_eq!(deduped.len(), len);
+        //range queries use the entity's position
+        let range = rng.gen_range(0.0..15.0);
+        let found = sorted(index.query_range(center, range).collect());
+        let expected = sorted(
+            actors
+                .iter()
+                .filter(|(_, pos, _)| pos.distance(center) <= range)
+                .map(|(e, _, _)| *e)
+                .collect(),
+        );
+        assert_eq!(found, expected);
+        //ray queries return every entity the ray hits, and possibly others near it
+        let direction = Vec3::new(
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+        )
+        .normalize_or(Vec3::X);
+        let candidates = index.query_ray(center, direction, 40.0);
+        for (entity, pos, aabb) in actors.iter() {
+            if aabb
+                .ray_intersection(*pos, center, direction, 40.0)
+                .is_some()
+            {
+                assert!(candidates.contains(entity));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_spatial_index_reuse() {
+    let mut index = SpatialIndex::default();
+    index.insert(Entity::from_raw(0), Vec3::ZERO, Aabb::centered(Vec3::ONE));
+    index.clear();
+    assert!(index.is_empty());
+    assert_eq!(index.query_range(Vec3::ZERO, 10.0).count(), 0);
+    index.insert(
+        Entity::from_raw(1),
+        Vec3::splat(100.0),
+        Aabb::centered(Vec3::ONE),
+    );
+    assert_eq!(
+        index
+            .query_range(Vec3::splat(100.0), 1.0)
+            .collect::<Vec<_>>(),
+        vec![Entity::from_raw(1)]
+    );
+}
+
+#[test]
+fn test_spawned_entities_indexed_without_physics_tick() {
+    //no time plugin, so FixedUpdate never runs
+    let mut app = App::new();
+    app.add_plugins(SpatialIndexPlugin);
+    let entity = app
+        .world_mut()
+        .spawn((
+            GlobalTransform::from_xyz(1.0, 2.0, 3.0),
+            Aabb::centered(Vec3::ONE),
+        ))
+        .id();
+    app.update();
+    let index = app.world().resource::<SpatialIndex>();
+    assert_eq!(
+        index
+            .query_range(Vec3::new(1.0, 2.0, 3.0), 1.0)
+            .collect::<Vec<_>>(),
+        vec![entity]
+    );
+}
+
+//headless benchmark of one tick of contact damage checks during a large wave
+//run with `cargo test --release -p engine spatial_index_benchmark -- --ignored --nocapture`
+#[test]
+#[ignore]
+fn spatial_index_benchmark() {
+    const ACTORS: usize = 4000;
+    const TICKS: u32 = 20;
+    let mut rng = StdRng::seed_from_u64(0);
+    let actors = random_actors(&mut rng, ACTORS, Vec3::new(100.0, 20.0, 100.0));
+    let contact_scale = Vec3::splat(1.1);
+
+    let start = Instant::now();
+    let mut brute_hits = 0;
+    for _ in 0..TICKS {
+        for (_, pos, aabb) in actors.iter() {
+            let scaled = aabb.scale(contact_scale);
+            brute_hits += actors
+                .iter()
+                .filter(|(_, other_pos, other)| other.intersects_aabb(*other_pos, scaled, *pos))
+                .count();
+        }
+    }
+    let brute_time = start.elapsed() / TICKS;
+
+    let start = Instant::now();
+    let mut index = SpatialIndex::default();
+    let mut index_hits = 0;
+    for _ in 0..TICKS {
+        index.clear();
+        for (entity, pos, aabb) in actors.iter() {
+            index.insert(*entity, *pos, *aabb);
+        }
+        for (_, pos, aabb) in actors.iter() {
+            let scaled = aabb.scale(contact_scale);
+            index_hits += index
+                .query_box(scaled.world_min(*pos), scaled.world_max(*pos))
+                .filter(|e| {
+                    let (_, other_pos, other) = actors[e.index() as usize];
+                    other.intersects_aabb(other_pos, scaled, *pos)
+                })
+                .count();
+        }
+    }
+    let index_time = start.elapsed() / TICKS;
+
+    assert_eq!(brute_hits, index_hits);
+    println!(
+        "{ACTORS} actors: brute force {:?}/tick, spatial index {:?}/tick (including rebuild)",
+        brute_time, index_time
+    );
+}
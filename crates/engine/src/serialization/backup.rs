@@ -0,0 +1,213 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+    utils::HashMap,
+};
+use futures_lite::future;
+use rusqlite::{backup::Progress, Connection, DatabaseName};
+
+use crate::world::settings::Settings;
+
+use super::db::{LevelDB, LevelDBErr};
+
+//next to the level files, so it isn't listed with the levels
+pub const BACKUP_FOLDER: &str = "backups";
+const BACKUP_EXTENSION: &str = "db";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BackupReason {
+    //taken every Settings::backup_interval while the level is open
+    Scheduled,
+    //taken before the save is upgraded
+    Migration,
+    //taken before a backup is restored over the level, so restoring can be undone
+    Restore,
+    //taken before the level is changed from outside the game, like by worldtool
+    Maintenance,
+}
+
+impl BackupReason {
+    fn tag(self) -> &'static str {
+        match self {
+            BackupReason::Scheduled => "scheduled",
+            BackupReason::Migration => "migration",
+            BackupReason::Restore => "restore",
+            BackupReason::Maintenance => "maintenance",
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "scheduled" => Some(BackupReason::Scheduled),
+            "migration" => Some(BackupReason::Migration),
+            "restore" => Some(BackupReason::Restore),
+            "maintenance" => Some(BackupReason::Maintenance),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for BackupReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.tag())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    pub time: SystemTime,
+    pub reason: BackupReason,
+}
+
+//while a level is open, counts down to the next scheduled backup
+#[derive(Resource, Default)]
+pub struct ScheduledBackups {
+    elapsed: Duration,
+    task: Option<Task<Result<PathBuf, LevelDBErr>>>,
+}
+
+//backups of the level saved at `level_path` go in backups/<level name>/ next to it
+pub fn backup_dir(level_path: &Path) -> PathBuf {
+    let name = level_path.file_stem().unwrap_or_default();
+    level_path
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(BACKUP_FOLDER)
+        .join(name)
+}
+
+//backups are named <unix seconds>.<milliseconds>-<reason>.db, with -<n> after the reason if there was already one with that name
+fn parse_backup(path: PathBuf) -> Option<BackupInfo> {
+    if path.extension()? != BACKUP_EXTENSION {
+        return None;
+    }
+    let mut parts = path.file_stem()?.to_str()?.split('-');
+    let (secs, millis) = parts.next()?.split_once('.')?;
+    let time = SystemTime::UNIX_EPOCH
+        + Duration::from_secs(secs.parse().ok()?)
+        + Duration::from_millis(millis.parse().ok()?);
+    Some(BackupInfo {
+        time,
+        reason: BackupReason::from_tag(parts.next()?)?,
+        path,
+    })
+}
+
+//the backups in `dir`, newest first
+pub fn list_backups(dir: &Path) -> Vec<BackupInfo> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups = entries
+        .filter_map(|entry| parse_backup(entry.ok()?.path()))
+        .collect::<Vec<_>>();
+    backups.sort_by(|a, b| b.time.cmp(&a.time));
+    backups
+}
+
+//copies the db into `dir` with sqlite's online backup, which is safe to do while the db is being written to
+pub fn snapshot(
+    conn: &Connection,
+    dir: &Path,
+    reason: BackupReason,
+) -> Result<PathBuf, LevelDBErr> {
+    std::fs::create_dir_all(dir).map_err(LevelDBErr::Io)?;
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let name = format!(
+        "{}.{:03}-{}",
+        now.as_secs(),
+        now.subsec_millis(),
+        reason.tag()
+    );
+    let mut path = dir.join(format!("{}.{}", name, BACKUP_EXTENSION));
+    let mut n = 1;
+    while path.exists() {
+        n += 1;
+        path = dir.join(format!("{}-{}.{}", name, n, BACKUP_EXTENSION));
+    }
+    //written under another name first so a half finished backup is never listed
+    let partial = path.with_extension("partial");
+    conn.backup(DatabaseName::Main, &partial, None::<fn(Progress)>)
+        .map_err(LevelDBErr::Sqlite)?;
+    std::fs::rename(&partial, &path).map_err(LevelDBErr::Io)?;
+    Ok(path)
+}
+
+//deletes the oldest backups in `dir` past the newest `keep` of each reason
+//reasons are counted apart, so scheduled backups don't rotate out the one taken before a migration or restore
+pub fn rotate_backups(dir: &Path, keep: usize) {
+    let mut seen: HashMap<BackupReason, usize> = HashMap::new();
+    for backup in list_backups(dir) {
+        let count = seen.entry(backup.reason).or_default();
+        *count += 1;
+        if *count <= keep {
+            continue;
+        }
+        if let Err(e) = std::fs::remove_file(&backup.path) {
+            warn!(
+                "Couldn't delete old backup {}: {:?}",
+                backup.path.display(),
+                e
+            );
+        }
+    }
+}
+
+//replaces the level saved at `level_path` with `backup`. the level must not be open
+//the level is backed up first, so this can be undone by restoring that backup
+pub fn restore_backup(level_path: &Path, backup: &Path, keep: usize) -> Result<(), LevelDBErr> {
+    let dir = backup_dir(level_path);
+    let mut conn = Connection::open(level_path).map_err(LevelDBErr::Sqlite)?;
+    snapshot(&conn, &dir, BackupReason::Restore)?;
+    conn.restore(DatabaseName::Main, backup, None::<fn(Progress)>)
+        .map_err(LevelDBErr::Sqlite)?;
+    //after restoring, so the backup being restored isn't the one that's deleted
+    rotate_backups(&dir, keep);
+    Ok(())
+}
+
+//takes a backup of the open level every Settings::backup_interval. the copy happens in the background
+pub fn scheduled_backup(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    db: Res<LevelDB>,
+    mut backups: ResMut<ScheduledBackups>,
+) {
+    if let Some(task) = &mut backups.task {
+        if let Some(result) = future::block_on(future::poll_once(task)) {
+            match result {
+                Ok(path) => info!("Backed up level to {}", path.display()),
+                Err(e) => error!("Error backing up level: {:?}", e),
+            }
+            backups.task = None;
+        }
+        return;
+    }
+    backups.elapsed += time.delta();
+    if backups.elapsed < settings.backup_interval {
+        return;
+    }
+    backups.elapsed = Duration::ZERO;
+    match db.connection() {
+        Ok(conn) => {
+            let dir = backup_dir(db.path());
+            let keep = settings.backups_kept;
+            backups.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+                let path = snapshot(&conn, &dir, BackupReason::Scheduled)?;
+                rotate_backups(&dir, keep);
+                Ok(path)
+            }));
+        }
+        //only levels saved in sqlite can be backed up
+        Err(LevelDBErr::Unsupported(_)) => {}
+        Err(e) => error!("Error connecting to level for backup: {:?}", e),
+    }
+}
@@ -0,0 +1,64 @@
+use super::db::LevelDBErr;
+
+//blobs bigger than this are treated as corrupt instead of being decompressed. chunks are nowhere near it
+const MAX_DECODED_LEN: usize = 16 * 1024 * 1024;
+
+//how a chunk row's blob is stored, between the bincode in SaveCommand and the bytes in the db
+//every row is saved with the id of the codec it was written with, so changing the codec doesn't break old saves
+//ids are saved, so they must never change. add new codecs with new ids
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum ChunkCodec {
+    //the bincode, as is. rows saved before there were codecs have this
+    Bincode = 0,
+    //the bincode compressed with lz4, with its length in front
+    #[default]
+    Lz4 = 1,
+}
+
+impl ChunkCodec {
+    pub const ALL: [ChunkCodec; 2] = [ChunkCodec::Bincode, ChunkCodec::Lz4];
+
+    pub fn id(self) -> i64 {
+        self as i64
+    }
+
+    pub fn from_id(id: i64) -> Option<Self> {
+        Self::ALL.into_iter().find(|codec| codec.id() == id)
+    }
+
+    //these are run in the db's task, off the main thread
+    pub fn encode(self, data: Vec<u8>) -> Vec<u8> {
+        match self {
+            ChunkCodec::Bincode => data,
+            ChunkCodec::Lz4 => lz4_flex::compress_prepend_size(&data),
+        }
+    }
+
+    pub fn decode(self, data: &[u8]) -> Result<Vec<u8>, LevelDBErr> {
+        match self {
+            ChunkCodec::Bincode => Ok(data.to_vec()),
+            ChunkCodec::Lz4 => {
+                //the length is checked first, so a bad one can't make us allocate gigabytes
+                let Some((len, compressed)) = data.split_first_chunk::<4>() else {
+                    return Err(LevelDBErr::Codec(self, "missing length".to_string()));
+                };
+                let len = u32::from_le_bytes(*len) as usize;
+                if len > MAX_DECODED_LEN {
+                    return Err(LevelDBErr::Codec(
+                        self,
+                        format!("length {} is too big", len),
+                    ));
+                }
+                let decoded = lz4_flex::decompress(compressed, len)
+                    .map_err(|e| LevelDBErr::Codec(self, e.to_string()))?;
+                if decoded.len() != len {
+                    return Err(LevelDBErr::Codec(
+                        self,
+                        format!("expected {} bytes, got {}", len, decoded.len()),
+                    ));
+                }
+                Ok(decoded)
+            }
+        }
+    }
+}
@@ -1,46 +1,156 @@
-use std::{collections::VecDeque, path::Path};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use bevy::{
     prelude::*,
     tasks::{AsyncComputeTaskPool, Task},
+    utils::HashMap,
 };
 use bincode::ErrorKind;
 use futures_lite::future;
-use r2d2::{Pool, PooledConnection};
+use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::*;
+use serde::{de::DeserializeOwned, Serialize};
 
-use super::queries::*;
-use crate::world::chunk::*;
+use super::{
+    codec::ChunkCodec,
+    storage::{ChunkWrite, LevelStorage, SharedStorage, SqliteStorage, StoredRow},
+};
+use crate::{
+    util::{checksum::crc32, string::Version},
+    world::{chunk::*, levels::LevelId},
+};
 
 #[derive(Resource)]
 pub struct LevelDB {
-    pool: Pool<SqliteConnectionManager>,
+    storage: SharedStorage,
+    path: PathBuf,
     current_task: Option<Task<Result<LevelDBResult, LevelDBErr>>>,
     //FIFO queues, we always save before loading
-    save_queue: VecDeque<Vec<SaveCommand>>,
+    save_queue: VecDeque<SaveBatch>,
     load_queue: VecDeque<Vec<LoadCommand>>,
+    //world_info rows to write, keyed by name. only the latest value for each key is kept
+    world_info_queue: HashMap<String, Vec<u8>>,
+    //corrupt rows to move out of the chunk tables. done before anything else
+    quarantine_queue: Vec<QuarantineCommand>,
+    //rows to rewrite from what's saved in them. done after the saves queued before them
+    update_queue: VecDeque<UpdateCommand>,
+    //what new rows are written with. rows are read with the codec they were saved with
+    codec: ChunkCodec,
+}
+
+//rows to save, made in the db task so slow ones (like terrain diffs) don't hold up the main thread
+type SaveBatch = Box<dyn FnOnce() -> Vec<SaveCommand> + Send>;
+
+//a command with no data deletes the row instead, bincode never serializes anything to nothing
+pub struct SaveCommand(pub LevelId, pub ChunkTable, pub ChunkCoord, pub Vec<u8>);
+
+impl SaveCommand {
+    //serializes `value` into the row for `coord` in `table`
+    pub fn encode<T: Serialize>(
+        level: LevelId,
+        table: ChunkTable,
+        coord: ChunkCoord,
+        value: &T,
+    ) -> Result<Self, LevelDBErr> {
+        bincode::serialize(value)
+            .map(|blob| Self(level, table, coord, blob))
+            .map_err(LevelDBErr::Bincode)
+    }
 }
 
-pub struct SaveCommand(pub ChunkTable, pub ChunkCoord, pub Vec<u8>);
 //will load all entries in to_load for chunk at position, then delete the specified entries
 pub struct LoadCommand {
+    pub level: LevelId,
     pub position: ChunkCoord,
     pub to_load: Vec<ChunkTable>,
 }
 
+//rewrites a row from its saved contents, for adding to chunks that aren't loaded
+//`update` gets the decoded row, or an empty blob if there isn't one, and returns what to save instead
+pub struct UpdateCommand {
+    pub level: LevelId,
+    pub table: ChunkTable,
+    pub coord: ChunkCoord,
+    pub update: Box<dyn FnOnce(Vec<u8>) -> Result<Vec<u8>, LevelDBErr> + Send>,
+}
+
+struct QuarantineCommand {
+    chunk: ChunkQuarantinedEvent,
+    data: Vec<u8>,
+}
+
 enum LevelDBResult {
     Save(usize),
-    Load(Vec<DataFromDBEvent>),
+    Load(Vec<DataFromDBEvent>, Vec<ChunkQuarantinedEvent>),
+    Quarantine(Vec<ChunkQuarantinedEvent>),
+    Update(usize, Vec<ChunkQuarantinedEvent>),
 }
 
 #[derive(Event)]
-pub struct DataFromDBEvent(pub ChunkCoord, pub Vec<(ChunkTable, Vec<u8>)>);
+pub struct DataFromDBEvent(pub LevelId, pub ChunkCoord, pub Vec<(ChunkTable, Vec<u8>)>);
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+//a row that couldn't be read was moved to the quarantine table. the chunk is loaded as if the row was never saved,
+// so corrupt terrain is generated again from the seed
+#[derive(Event, Clone, Debug)]
+pub struct ChunkQuarantinedEvent {
+    pub level: LevelId,
+    pub table: ChunkTable,
+    pub coord: ChunkCoord,
+    pub reason: String,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum ChunkTable {
     Terrain = 0,
     Buffers = 1,
+    //pending scheduled block ticks
+    BlockTicks = 2,
+    //entities with SaveWithChunk that were in the chunk when it was unloaded
+    Entities = 3,
+    //terrain saved as the blocks that differ from the generator, see Settings::save_terrain_diffs
+    TerrainDiff = 4,
+}
+
+impl ChunkTable {
+    pub const ALL: [ChunkTable; 5] = [
+        ChunkTable::Terrain,
+        ChunkTable::Buffers,
+        ChunkTable::BlockTicks,
+        ChunkTable::Entities,
+        ChunkTable::TerrainDiff,
+    ];
+
+    //each level gets its own range of tids, and the surface keeps the ones from before there were levels
+    pub fn tid(self, level: LevelId) -> i32 {
+        level.0 as i32 * 256 + self as i32
+    }
+
+    //every tid used by `level`'s chunks, whatever table they're in
+    pub fn level_tids(level: LevelId) -> std::ops::Range<i32> {
+        Self::Terrain.tid(level)..Self::Terrain.tid(LevelId(level.0 + 1))
+    }
+
+    //the level and table a tid was made from, if it's one we know about
+    pub fn from_tid(tid: i32) -> Option<(LevelId, ChunkTable)> {
+        let level = u32::try_from(tid / 256).ok()?;
+        let table = Self::ALL
+            .into_iter()
+            .find(|table| *table as i32 == tid % 256)?;
+        Some((LevelId(level), table))
+    }
+
+    //a chunk's terrain is saved whole or as a diff, never both. saving one deletes the other
+    pub fn other_terrain(self) -> Option<ChunkTable> {
+        match self {
+            ChunkTable::Terrain => Some(ChunkTable::TerrainDiff),
+            ChunkTable::TerrainDiff => Some(ChunkTable::Terrain),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -50,57 +160,147 @@ pub enum LevelDBErr {
     Bincode(Box<ErrorKind>),
     NewWorldVersion,
     InvalidWorldVersion,
+    Io(std::io::Error),
+    //the save is too old to open and no migration upgrades it from this version
+    MissingMigration(Version),
+    //the named migration step failed. the save was rolled back
+    MigrationFailed(&'static str, Box<LevelDBErr>),
+    //a chunk row couldn't be decoded with the codec it was saved with
+    Codec(ChunkCodec, String),
+    //the level's storage can't do this, like sql on a level that isn't saved in sqlite
+    Unsupported(&'static str),
+    //the level's terrain diffs were made against this generator version, not the current one
+    GeneratorChanged(u32),
 }
 
+impl std::fmt::Display for LevelDBErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelDBErr::R2D2(e) => write!(f, "Couldn't connect to the save: {}", e),
+            LevelDBErr::Sqlite(e) => write!(f, "Database error: {}", e),
+            LevelDBErr::Bincode(e) => write!(f, "Corrupt save data: {}", e),
+            LevelDBErr::NewWorldVersion => {
+                write!(f, "This world was saved by a newer version of the game")
+            }
+            LevelDBErr::InvalidWorldVersion => write!(f, "This world has an invalid version"),
+            LevelDBErr::Io(e) => write!(f, "File error: {}", e),
+            LevelDBErr::MissingMigration(version) => write!(
+                f,
+                "This world was saved by version {} and can't be upgraded",
+                version
+            ),
+            LevelDBErr::MigrationFailed(step, e) => {
+                write!(f, "Upgrading this world failed at {}: {}", step, e)
+            }
+            LevelDBErr::Codec(codec, e) => write!(f, "Corrupt {:?} chunk data: {}", codec, e),
+            LevelDBErr::Unsupported(what) => {
+                write!(f, "This world's save format doesn't support {}", what)
+            }
+            LevelDBErr::GeneratorChanged(version) => write!(
+                f,
+                "This world's terrain was saved against world generator {} and can't be rebuilt",
+                version
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LevelDBErr {}
+
 impl LevelDB {
-    pub fn new(path: &Path) -> Result<LevelDB, r2d2::Error> {
-        let manager = SqliteConnectionManager::file(path).with_init(|conn| {
-            conn.execute_batch(
-                "PRAGMA journal_mode=WAL;
-             PRAGMA synchronous=NORMAL;",
-            )
-        });
-        let pool = Pool::new(manager)?;
-        Ok(Self {
-            pool,
+    //opens the sqlite level at `path`, making it if it doesn't exist
+    pub fn new(path: &Path) -> Result<LevelDB, LevelDBErr> {
+        Ok(Self::with_storage(
+            path,
+            Arc::new(SqliteStorage::open(path)?),
+        ))
+    }
+    //`path` is where the level is saved, it's what backups are named after
+    pub fn with_storage(path: &Path, storage: SharedStorage) -> Self {
+        Self {
+            storage,
+            path: path.to_path_buf(),
             current_task: None,
             save_queue: VecDeque::new(),
             load_queue: VecDeque::new(),
-        })
-    }
-    pub fn execute_command_sync(
-        &mut self,
-        f: impl FnOnce(PooledConnection<SqliteConnectionManager>) -> Result<usize, rusqlite::Error>
-            + Send,
-    ) -> Option<LevelDBErr> {
-        match self.pool.get() {
-            Ok(conn) => f(conn).map_err(LevelDBErr::Sqlite).err(),
-            Err(e) => Some(LevelDBErr::R2D2(e)),
+            world_info_queue: HashMap::new(),
+            quarantine_queue: Vec::new(),
+            update_queue: VecDeque::new(),
+            codec: ChunkCodec::default(),
         }
     }
-    pub fn execute_query_sync<T>(
-        &mut self,
-        sql: &str,
-        p: impl Params,
-        f: impl FnOnce(&Row) -> Result<T>,
-    ) -> Result<T, LevelDBErr> {
-        match self.pool.get() {
-            Ok(conn) => conn.query_row(sql, p, f).map_err(LevelDBErr::Sqlite),
-            Err(e) => Err(LevelDBErr::R2D2(e)),
-        }
+    //the file the level is saved in
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn set_codec(&mut self, codec: ChunkCodec) {
+        self.codec = codec;
+    }
+    //for reading and writing right away, instead of queueing it up for the db's task
+    pub fn storage(&self) -> &(dyn LevelStorage + Send + Sync) {
+        self.storage.as_ref()
+    }
+    //a connection for work that doesn't fit in a single command, like migrations. don't hold on to it
+    //only levels saved in sqlite have one
+    pub fn connection(&self) -> Result<PooledConnection<SqliteConnectionManager>, LevelDBErr> {
+        self.storage.connection()
     }
     //adds chunks to the buffer to be saved
     pub fn save_chunk_data(&mut self, data: Vec<SaveCommand>) {
         if !data.is_empty() {
-            self.save_queue.push_back(data);
+            self.save_queue.push_back(Box::new(move || data));
         }
     }
+    //like save_chunk_data, but the rows are made by `f` in the db task, in order with the other saves
+    pub fn save_chunk_data_with(&mut self, f: impl FnOnce() -> Vec<SaveCommand> + Send + 'static) {
+        self.save_queue.push_back(Box::new(f));
+    }
+    //queues a row to be rewritten once the saves before it are done
+    pub fn update_chunk_row(&mut self, command: UpdateCommand) {
+        self.update_queue.push_back(command);
+    }
     //adds chunks to the queue to be loaded, will write to DataFromDBEvent when loaded
     pub fn load_chunk_data(&mut self, data: Vec<LoadCommand>) {
         if !data.is_empty() {
             self.load_queue.push_back(data);
         }
     }
+    //moves a row that was loaded but couldn't be decoded to the quarantine table, unless it's been saved over since
+    //`data` is the blob that was loaded
+    pub fn quarantine_chunk(
+        &mut self,
+        level: LevelId,
+        table: ChunkTable,
+        coord: ChunkCoord,
+        data: Vec<u8>,
+        reason: impl Into<String>,
+    ) {
+        self.quarantine_queue.push(QuarantineCommand {
+            chunk: ChunkQuarantinedEvent {
+                level,
+                table,
+                coord,
+                reason: reason.into(),
+            },
+            data,
+        });
+    }
+    //queues a world_info row to be written along with the chunks
+    pub fn save_world_info(&mut self, key: impl Into<String>, value: Vec<u8>) {
+        self.world_info_queue.insert(key.into(), value);
+    }
+    //reads a world_info row right away. returns None if the row doesn't exist
+    pub fn load_world_info<T: DeserializeOwned>(
+        &mut self,
+        key: &str,
+    ) -> Result<Option<T>, LevelDBErr> {
+        match self.storage.load_world_info(key)? {
+            Some(data) => bincode::deserialize(&data)
+                .map(Some)
+                .map_err(LevelDBErr::Bincode),
+            None => Ok(None),
+        }
+    }
 
     fn flush_saves(&mut self) {
         info!("flush_saves");
@@ -108,16 +308,36 @@ impl LevelDB {
             //finish current task
             let _ = future::block_on(task);
         }
+        if !self.quarantine_queue.is_empty() {
+            let commands = std::mem::take(&mut self.quarantine_queue);
+            if let Err(e) = do_quarantining(self.storage.as_ref(), commands) {
+                error!("Error quarantining chunks: {:?}", e);
+            }
+        }
         let mut saved = 0;
         //run all saving tasks before closing
-        while let Some(command) = self.save_queue.pop_front() {
-            if let Ok(conn) = self.pool.get() {
-                saved += command.len();
-                if let Err(e) = do_saving(conn, command) {
-                    error!("Error saving chunks: {:?}", e);
-                }
+        while let Some(batch) = self.save_queue.pop_front() {
+            let command = batch();
+            saved += command.len();
+            if let Err(e) = do_saving(self.storage.as_ref(), command, self.codec) {
+                error!("Error saving chunks: {:?}", e);
             }
         }
+        if !self.update_queue.is_empty() {
+            let commands = self.update_queue.drain(..).collect();
+            if let Err(e) = do_updating(self.storage.as_ref(), commands, self.codec) {
+                error!("Error updating chunks: {:?}", e);
+            }
+        }
+        if !self.world_info_queue.is_empty() {
+            let rows = std::mem::take(&mut self.world_info_queue);
+            if let Err(e) = do_world_info_saving(self.storage.as_ref(), rows) {
+                error!("Error saving world info: {:?}", e);
+            }
+        }
+        if let Err(e) = self.storage.flush() {
+            error!("Error flushing level: {:?}", e);
+        }
         info!(
             "Finished saving! Saved {} chunks after last command.",
             saved
@@ -131,60 +351,200 @@ impl Drop for LevelDB {
     }
 }
 
-//contacts the db, should be done in a single thread
+//blobs are encoded here so compression happens off the main thread
 fn do_saving(
-    conn: PooledConnection<SqliteConnectionManager>,
+    storage: &dyn LevelStorage,
     data: Vec<SaveCommand>,
+    codec: ChunkCodec,
 ) -> Result<LevelDBResult, LevelDBErr> {
-    match conn.prepare_cached(SAVE_CHUNK_DATA) {
-        Ok(mut stmt) => {
-            let len = data.len();
-            for SaveCommand(tid, coord, blob) in data {
-                if let Err(e) = stmt.execute(params![tid as i32, coord.x, coord.y, coord.z, blob]) {
-                    return Err(LevelDBErr::Sqlite(e));
-                }
+    let len = data.len();
+    let mut writes = Vec::with_capacity(len);
+    for SaveCommand(level, table, coord, blob) in data {
+        if blob.is_empty() {
+            writes.push(ChunkWrite {
+                level,
+                table,
+                coord,
+                row: None,
+            });
+            continue;
+        }
+        let blob = codec.encode(blob);
+        //the checksum is of the stored bytes, so it's checked before decoding
+        let checksum = crc32(&blob);
+        writes.push(ChunkWrite {
+            level,
+            table,
+            coord,
+            row: Some(StoredRow {
+                data: blob,
+                codec: codec.id(),
+                checksum: Some(checksum),
+            }),
+        });
+        if let Some(stale) = table.other_terrain() {
+            writes.push(ChunkWrite {
+                level,
+                table: stale,
+                coord,
+                row: None,
+            });
+        }
+    }
+    storage.write_chunks(writes)?;
+    Ok(LevelDBResult::Save(len))
+}
+
+fn do_updating(
+    storage: &dyn LevelStorage,
+    commands: Vec<UpdateCommand>,
+    codec: ChunkCodec,
+) -> Result<LevelDBResult, LevelDBErr> {
+    let mut count = 0;
+    let mut quarantined = Vec::new();
+    for UpdateCommand {
+        level,
+        table,
+        coord,
+        update,
+    } in commands
+    {
+        //a row that can't be read is moved out of the way and replaced
+        let data = match load_chunk_row(storage, level, table, coord)? {
+            LoadedRow::Found(data) => data,
+            LoadedRow::Missing => Vec::new(),
+            LoadedRow::Quarantined(chunk) => {
+                quarantined.push(chunk);
+                Vec::new()
+            }
+        };
+        match update(data) {
+            //written right away, since the next update could be for the same row
+            Ok(blob) => {
+                do_saving(storage, vec![SaveCommand(level, table, coord, blob)], codec)?;
+                count += 1;
             }
-            Ok(LevelDBResult::Save(len))
+            Err(e) => error!(
+                "error updating {:?} row for chunk {:?} in level {:?}: {:?}",
+                table, coord, level, e
+            ),
         }
-        Err(e) => Err(LevelDBErr::Sqlite(e)),
     }
+    Ok(LevelDBResult::Update(count, quarantined))
+}
+
+fn do_world_info_saving(
+    storage: &dyn LevelStorage,
+    rows: HashMap<String, Vec<u8>>,
+) -> Result<LevelDBResult, LevelDBErr> {
+    let len = rows.len();
+    storage.save_world_info(rows.into_iter().collect())?;
+    Ok(LevelDBResult::Save(len))
+}
+
+fn do_quarantining(
+    storage: &dyn LevelStorage,
+    commands: Vec<QuarantineCommand>,
+) -> Result<LevelDBResult, LevelDBErr> {
+    let mut quarantined = Vec::new();
+    for QuarantineCommand { chunk, data } in commands {
+        //`data` was decoded, so the row is only moved if it still decodes to the same thing
+        let stored = storage
+            .read_chunk(chunk.level, chunk.table, chunk.coord)?
+            .filter(|row| {
+                ChunkCodec::from_id(row.codec)
+                    .and_then(|codec| codec.decode(&row.data).ok())
+                    .is_some_and(|decoded| decoded == data)
+            });
+        //if it was saved over, all that's left to keep is what was loaded
+        let row = stored.unwrap_or(StoredRow {
+            data,
+            codec: ChunkCodec::Bincode.id(),
+            checksum: None,
+        });
+        storage.quarantine_chunk(&chunk, &row)?;
+        quarantined.push(chunk);
+    }
+    Ok(LevelDBResult::Quarantine(quarantined))
 }
 
-//contacts the db, should be done in a single thread
 fn do_loading(
-    conn: PooledConnection<SqliteConnectionManager>,
+    storage: &dyn LevelStorage,
     data: Vec<LoadCommand>,
 ) -> Result<LevelDBResult, LevelDBErr> {
     let mut results = Vec::new();
-    match conn.prepare_cached(LOAD_CHUNK_DATA) {
-        Ok(mut load_stmt) => {
-            for LoadCommand { position, to_load } in data {
-                let mut coord_result = Vec::new();
-                //loading
-                for tid in to_load {
-                    let result = load_stmt.query_row(
-                        params![tid as i32, position.x, position.y, position.z],
-                        |row| row.get(0),
-                    );
-                    match result {
-                        Ok(data) => coord_result.push((tid, data)),
-                        Err(rusqlite::Error::QueryReturnedNoRows) => {
-                            coord_result.push((tid, Vec::new()))
-                        }
-                        Err(e) => return Err(LevelDBErr::Sqlite(e)),
-                    }
+    let mut quarantined = Vec::new();
+    for LoadCommand {
+        level,
+        position,
+        to_load,
+    } in data
+    {
+        let mut coord_result = Vec::new();
+        for table in to_load {
+            //missing and quarantined rows are passed on as empty, like the chunk was never saved
+            let data = match load_chunk_row(storage, level, table, position)? {
+                LoadedRow::Found(data) => data,
+                LoadedRow::Missing => Vec::new(),
+                LoadedRow::Quarantined(chunk) => {
+                    quarantined.push(chunk);
+                    Vec::new()
                 }
-                results.push(DataFromDBEvent(position, coord_result));
-            }
-            Ok(LevelDBResult::Load(results))
+            };
+            coord_result.push((table, data));
         }
-        Err(e) => Err(LevelDBErr::Sqlite(e)),
+        results.push(DataFromDBEvent(level, position, coord_result));
     }
+    Ok(LevelDBResult::Load(results, quarantined))
+}
+
+pub(super) enum LoadedRow {
+    Found(Vec<u8>),
+    Missing,
+    Quarantined(ChunkQuarantinedEvent),
+}
+
+//reads a row, checks it against the checksum it was saved with, and decodes it with its codec. rows that fail are quarantined
+//rows saved before there were checksums aren't checked
+pub(super) fn load_chunk_row(
+    storage: &(impl LevelStorage + ?Sized),
+    level: LevelId,
+    table: ChunkTable,
+    coord: ChunkCoord,
+) -> Result<LoadedRow, LevelDBErr> {
+    let Some(row) = storage.read_chunk(level, table, coord)? else {
+        return Ok(LoadedRow::Missing);
+    };
+    let reason = match (ChunkCodec::from_id(row.codec), row.checksum) {
+        (None, _) => format!("unknown codec {}", row.codec),
+        (Some(_), Some(expected)) if expected != crc32(&row.data) => format!(
+            "checksum mismatch ({} bytes, saved {:08x}, read {:08x})",
+            row.data.len(),
+            expected,
+            crc32(&row.data)
+        ),
+        (Some(chunk_codec), _) => match chunk_codec.decode(&row.data) {
+            Ok(decoded) => return Ok(LoadedRow::Found(decoded)),
+            Err(e) => e.to_string(),
+        },
+    };
+    let chunk = ChunkQuarantinedEvent {
+        level,
+        table,
+        coord,
+        reason,
+    };
+    storage.quarantine_chunk(&chunk, &row)?;
+    Ok(LoadedRow::Quarantined(chunk))
 }
 
 //checks if the db's current_task is finished, and if so, will send an event depending on the task.
 //if there is no current task or it's finished, it will start a new task from the db's command queue
-pub fn tick_db(mut db: ResMut<LevelDB>, mut load_writer: EventWriter<DataFromDBEvent>) {
+pub fn tick_db(
+    mut db: ResMut<LevelDB>,
+    mut load_writer: EventWriter<DataFromDBEvent>,
+    mut quarantine_writer: EventWriter<ChunkQuarantinedEvent>,
+) {
     let mut finished = false;
     if let Some(ref mut task) = &mut db.current_task {
         if let Some(data) = future::block_on(future::poll_once(task)) {
@@ -193,9 +553,17 @@ pub fn tick_db(mut db: ResMut<LevelDB>, mut load_writer: EventWriter<DataFromDBE
             match data {
                 Ok(result) => match result {
                     LevelDBResult::Save(count) => info!("Saved {} chunks.", count),
-                    LevelDBResult::Load(events) => {
+                    LevelDBResult::Load(events, quarantined) => {
                         info!("Loaded {} chunks.", events.len());
                         load_writer.send_batch(events);
+                        quarantine_writer.send_batch(quarantined);
+                    }
+                    LevelDBResult::Quarantine(quarantined) => {
+                        quarantine_writer.send_batch(quarantined)
+                    }
+                    LevelDBResult::Update(count, quarantined) => {
+                        info!("Updated {} chunks.", count);
+                        quarantine_writer.send_batch(quarantined);
                     }
                 },
                 Err(e) => error!("DB Error: {:?}", e),
@@ -207,29 +575,33 @@ pub fn tick_db(mut db: ResMut<LevelDB>, mut load_writer: EventWriter<DataFromDBE
     //start next task if needed
     if finished || db.current_task.is_none() {
         //do saves loads, important for chunk buffers
-        if let Some(save_command) = db.save_queue.pop_front() {
-            assign_db_work(db.pool.get(), &mut db, move |conn| {
-                do_saving(conn, save_command)
+        //corrupt rows are moved out first, so they aren't loaded again
+        if !db.quarantine_queue.is_empty() {
+            let commands = std::mem::take(&mut db.quarantine_queue);
+            assign_db_work(&mut db, move |storage| do_quarantining(storage, commands));
+        } else if let Some(batch) = db.save_queue.pop_front() {
+            let codec = db.codec;
+            assign_db_work(&mut db, move |storage| do_saving(storage, batch(), codec));
+        } else if !db.update_queue.is_empty() {
+            let commands = db.update_queue.drain(..).collect();
+            let codec = db.codec;
+            assign_db_work(&mut db, move |storage| {
+                do_updating(storage, commands, codec)
             });
+        } else if !db.world_info_queue.is_empty() {
+            let rows = std::mem::take(&mut db.world_info_queue);
+            assign_db_work(&mut db, move |storage| do_world_info_saving(storage, rows));
         } else if let Some(load_command) = db.load_queue.pop_front() {
-            assign_db_work(db.pool.get(), &mut db, move |conn| {
-                do_loading(conn, load_command)
-            });
+            assign_db_work(&mut db, move |storage| do_loading(storage, load_command));
         }
     }
 }
 
 fn assign_db_work(
-    conn_result: Result<PooledConnection<SqliteConnectionManager>, r2d2::Error>,
-    db: &mut ResMut<'_, LevelDB>,
-    f: impl FnOnce(PooledConnection<SqliteConnectionManager>) -> Result<LevelDBResult, LevelDBErr>
-        + Send
-        + 'static,
+    db: &mut LevelDB,
+    f: impl FnOnce(&dyn LevelStorage) -> Result<LevelDBResult, LevelDBErr> + Send + 'static,
 ) {
-    //work in background
-    let pool = AsyncComputeTaskPool::get();
-    match conn_result {
-        Ok(conn) => db.current_task = Some(pool.spawn(async { f(conn) })),
-        Err(e) => error!("Error establishing DB connection: {:?}", e),
-    }
+    //work in background, with the task's own handle to the storage
+    let storage = db.storage.clone();
+    db.current_task = Some(AsyncComputeTaskPool::get().spawn(async move { f(storage.as_ref()) }));
 }
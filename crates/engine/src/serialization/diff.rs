@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    world::{
+        chunk::{ChunkCoord, GeneratingChunk, BLOCKS_PER_CHUNK},
+        util::BlockPalette,
+        BlockId, BlockState, BlockStates, Id,
+    },
+    worldgen::{
+        generate_chunk, DecorationResources, DecorationSettings, UsedShaperResources,
+        UsedShaperSettings,
+    },
+};
+
+use super::{ChunkSaveFormat, LoadedToSavedIdMap, SavedToLoadedIdMap};
+
+//the blocks in a chunk that differ from what the generator makes there, saved in ChunkTable::TerrainDiff
+//a diff only means anything with the seed and generator it was made against,
+// so changes to worldgen need a migration that turns these rows back into whole chunks
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TerrainDiff {
+    pub position: ChunkCoord,
+    //(start index, run length, block) for each run of changed blocks
+    pub changes: Vec<(u16, u16, BlockId)>,
+    //the generator doesn't make block states, so these are all of them
+    pub states: Vec<(u16, BlockState)>,
+    //same for fluid levels
+    pub fluid_levels: Vec<(u16, u8)>,
+}
+
+impl TerrainDiff {
+    //`blocks` has loaded ids, the diff is made with saved ids
+    pub fn new(
+        generated: &GeneratingChunk,
+        blocks: &BlockPalette<BlockId, BLOCKS_PER_CHUNK>,
+        states: &BlockStates,
+        map: &LoadedToSavedIdMap<BlockId>,
+    ) -> Self {
+        let mut changes: Vec<(u16, u16, BlockId)> = Vec::new();
+        for idx in 0..BLOCKS_PER_CHUNK {
+            let block = blocks[idx];
+            if block == generated[idx] {
+                continue;
+            }
+            match changes.last_mut() {
+                Some((start, run, last))
+                    if *start as usize + *run as usize == idx && *last == block =>
+                {
+                    *run += 1
+                }
+                _ => changes.push((idx as u16, 1, block)),
+            }
+        }
+        for (_, _, block) in changes.iter_mut() {
+            *block = map.get(block).unwrap();
+        }
+        Self {
+            position: generated.position,
+            changes,
+            states: states
+                .iter()
+                .map(|(idx, state)| (idx as u16, state))
+                .collect(),
+            fluid_levels: Vec::new(),
+        }
+    }
+
+    pub fn with_fluid_levels(mut self, fluid_levels: Vec<(u16, u8)>) -> Self {
+        self.fluid_levels = fluid_levels;
+        self
+    }
+
+    pub fn map_to_loaded(&mut self, map: &SavedToLoadedIdMap<BlockId>) {
+        for (_, _, id) in self.changes.iter_mut() {
+            match map.get(id) {
+                Some(loaded_id) => *id = loaded_id,
+                None => {
+                    error!("Couldn't map saved block id {:?} to loaded id", id);
+                    *id = BlockId(Id::Empty);
+                }
+            }
+        }
+    }
+
+    //the whole chunk, as if it had been saved in full. `generated` must be at the same position
+    pub fn apply(self, generated: &GeneratingChunk) -> ChunkSaveFormat {
+        let mut blocks = generated.blocks.iter().copied().collect::<Vec<_>>();
+        for (start, run, id) in self.changes {
+            for block in blocks.iter_mut().skip(start as usize).take(run as usize) {
+                *block = id;
+            }
+        }
+        ChunkSaveFormat {
+            position: self.position,
+            data: blocks
+                .iter()
+                .dedup_with_count()
+                .map(|(run, block)| (*block, run as u16))
+                .collect(),
+            states: self.states,
+            fluid_levels: self.fluid_levels,
+        }
+    }
+}
+
+//generates chunks from the active level's seed to make and apply diffs against. cheap to clone into tasks
+#[derive(Clone)]
+pub struct TerrainBaseline {
+    shaper: Arc<UsedShaperSettings>,
+    decoration: Arc<DecorationSettings>,
+    seed: u64,
+}
+
+impl TerrainBaseline {
+    pub fn new(shaper: &UsedShaperResources, decoration: &DecorationResources, seed: u64) -> Self {
+        Self {
+            shaper: shaper.0.clone(),
+            decoration: decoration.0.clone(),
+            seed,
+        }
+    }
+
+    pub fn generate(&self, coord: ChunkCoord) -> GeneratingChunk {
+        generate_chunk(coord, self.shaper.clone(), &self.decoration, self.seed)
+    }
+}
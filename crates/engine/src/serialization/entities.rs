@@ -0,0 +1,355 @@
+use bevy::{
+    ecs::{
+        component::Components,
+        reflect::{ReflectCommandExt, ReflectMapEntities},
+    },
+    prelude::*,
+    reflect::{
+        serde::{ReflectDeserializer, ReflectSerializer},
+        TypeRegistry,
+    },
+    utils::{HashMap, HashSet},
+};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actors::{ActorName, ActorResources},
+    world::{
+        chunk::{ChunkCoord, CHUNK_SIZE_F32},
+        levels::{LevelId, LevelMember, Levels},
+        Level, LevelData,
+    },
+};
+
+use super::{db::*, SaveWithChunk};
+
+//an entity saved in a chunk's ChunkTable::Entities row
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedEntity {
+    pub actor: ActorName,
+    pub transform: Transform,
+    //each one is a component written with ReflectSerializer
+    pub components: Vec<Vec<u8>>,
+}
+
+impl SavedEntity {
+    //saves the reflected components the game registered. bevy's own components (transforms, hierarchy, rendering, asset handles)
+    // are left to the actor's spawner, as are components that point at other entities, since those get new ids when respawned
+    pub fn new(
+        entity: EntityRef,
+        actor: &ActorName,
+        transform: Transform,
+        components: &Components,
+        registry: &TypeRegistry,
+    ) -> Self {
+        let components = entity
+            .archetype()
+            .components()
+            .filter_map(|id| components.get_info(id)?.type_id())
+            .filter_map(|type_id| registry.get(type_id))
+            .filter(|registration| {
+                !registration.type_info().type_path().starts_with("bevy_")
+                    && registration.data::<ReflectMapEntities>().is_none()
+            })
+            .filter_map(|registration| registration.data::<ReflectComponent>()?.reflect(entity))
+            .filter_map(|value| {
+                bincode_options()
+                    .serialize(&ReflectSerializer::new(
+                        value.as_partial_reflect(),
+                        registry,
+                    ))
+                    .ok()
+            })
+            .collect();
+        Self {
+            actor: actor.clone(),
+            transform,
+            components,
+        }
+    }
+
+    pub fn restore(self, entity: Entity, registry: &TypeRegistry, commands: &mut Commands) {
+        for bytes in self.components {
+            match bincode_options().deserialize_seed(ReflectDeserializer::new(registry), &bytes) {
+                Ok(component) => {
+                    commands.entity(entity).insert_reflect(component);
+                }
+                Err(e) => warn!(
+                    "couldn't restore a component of saved {:?}: {:?}",
+                    self.actor, e
+                ),
+            }
+        }
+    }
+}
+
+//the same options have to be used to read and write components
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+//tracks saved entities in the active level
+#[derive(Resource, Default)]
+pub struct ChunkEntities {
+    //the last loaded chunk each entity was in. it's saved with this chunk
+    homes: HashMap<Entity, ChunkCoord>,
+    //entities that left the loaded area while their home chunk is still loaded. written when it unloads
+    parked: HashMap<ChunkCoord, Vec<SavedEntity>>,
+    //loaded chunks that had entities when they were loaded. their rows are rewritten when they unload, even if it's empty,
+    // so the entities aren't spawned twice
+    spawned_rows: HashSet<ChunkCoord>,
+}
+
+//saved entities whose actor is being spawned, by the id they were given when they were loaded
+#[derive(Resource, Default)]
+pub struct PendingRestores {
+    entities: HashMap<u64, SavedEntity>,
+    next_id: u64,
+    //set while the actor's generator's commands are applied, so the entity it spawns is the one restored
+    spawning: Option<u64>,
+}
+
+impl PendingRestores {
+    pub fn clear(&mut self) {
+        self.entities.clear();
+        self.spawning = None;
+    }
+}
+
+//spawns the saved entity's actor, and restores its components onto the entity the generator spawns
+//generators that spawn through an event don't spawn while their commands are applied, so their components aren't restored
+pub(super) fn spawn_saved(
+    saved: SavedEntity,
+    resources: &ActorResources,
+    pending: &mut PendingRestores,
+    commands: &mut Commands,
+) {
+    let id = pending.next_id;
+    pending.next_id += 1;
+    let (actor, transform) = (saved.actor.clone(), saved.transform);
+    pending.entities.insert(id, saved);
+    commands.queue(move |world: &mut World| {
+        world.resource_mut::<PendingRestores>().spawning = Some(id);
+    });
+    resources.registry.spawn(&actor, commands, transform);
+    commands.queue(move |world: &mut World| {
+        let mut pending = world.resource_mut::<PendingRestores>();
+        pending.spawning = None;
+        if let Some(saved) = pending.entities.remove(&id) {
+            warn!(
+                "{:?} didn't spawn right away, so its saved components weren't restored",
+                saved.actor
+            );
+        }
+    });
+}
+
+//spawns the entities saved with chunks that were just loaded
+pub fn load_chunk_entities(
+    mut events: EventReader<DataFromDBEvent>,
+    mut db: ResMut<LevelDB>,
+    level: Res<Level>,
+    resources: Res<ActorResources>,
+    mut tracked: ResMut<ChunkEntities>,
+    mut pending: ResMut<PendingRestores>,
+    mut commands: Commands,
+) {
+    for DataFromDBEvent(level_id, coord, data) in events.read() {
+        //rows for chunks that unloaded before their data came back stay in the db
+        if *level_id != level.id || !level.contains_chunk(*coord) {
+            continue;
+        }
+        let Some((_, bytes)) = data
+            .iter()
+            .find(|(table, _)| *table == ChunkTable::Entities)
+        else {
+            continue;
+        };
+        if bytes.is_empty() {
+            continue;
+        }
+        match bincode::deserialize::<Vec<SavedEntity>>(bytes) {
+            Ok(entities) => {
+                if entities.is_empty() {
+                    continue;
+                }
+                tracked.spawned_rows.insert(*coord);
+                for saved in entities {
+                    if resources.registry.get_id(&saved.actor).is_none() {
+                        warn!("no actor named {:?} to load at {:?}", saved.actor, coord);
+                        continue;
+                    }
+                    spawn_saved(saved, &resources, &mut pending, &mut commands);
+                }
+            }
+            Err(e) => {
+                error!("error deserializing chunk entities at {:?}: {:?}", coord, e);
+                db.quarantine_chunk(
+                    *level_id,
+                    ChunkTable::Entities,
+                    *coord,
+                    bytes.clone(),
+                    e.to_string(),
+                );
+            }
+        }
+    }
+}
+
+//the first actor spawned while a restore's generator runs is the one it spawned
+pub fn restore_saved_components(
+    trigger: Trigger<OnAdd, ActorName>,
+    mut pending: ResMut<PendingRestores>,
+    registry: Res<AppTypeRegistry>,
+    mut commands: Commands,
+) {
+    let Some(id) = pending.spawning.take() else {
+        return;
+    };
+    if let Some(saved) = pending.entities.remove(&id) {
+        saved.restore(trigger.entity(), &registry.read(), &mut commands);
+    }
+}
+
+//saves and despawns entities that left the loaded area, and writes the rows of chunks that were unloaded
+pub fn save_unloaded_entities(
+    query: Query<(EntityRef, &ActorName, &Transform), With<SaveWithChunk>>,
+    member_query: Query<&LevelMember>,
+    levels: Res<Levels>,
+    level: Res<Level>,
+    mut db: ResMut<LevelDB>,
+    mut tracked: ResMut<ChunkEntities>,
+    registry: Res<AppTypeRegistry>,
+    components: &Components,
+    mut commands: Commands,
+) {
+    let registry = registry.read();
+    let mut rows: HashMap<ChunkCoord, Vec<SavedEntity>> = HashMap::new();
+    for (entity, actor, tf) in query.iter() {
+        //entities moved to another level are saved there by despawn_outside_active_level
+        if member_query
+            .get(entity.id())
+            .is_ok_and(|member| !levels.keeps_entities(member.0))
+        {
+            continue;
+        }
+        let coord = ChunkCoord::from(tf.translation);
+        if level.contains_chunk(coord) {
+            tracked.homes.insert(entity.id(), coord);
+            continue;
+        }
+        //entities that were never in a loaded chunk are waiting for the area around them to load
+        let Some(home) = tracked.homes.remove(&entity.id()) else {
+            continue;
+        };
+        //keep it in its home chunk so it's in the loaded area when it's respawned. this can put it inside a block
+        let mut transform = *tf;
+        let min = home.to_vec3();
+        transform.translation = transform
+            .translation
+            .clamp(min, min + Vec3::splat(CHUNK_SIZE_F32 - 0.01));
+        let saved = SavedEntity::new(entity, actor, transform, components, &registry);
+        commands.entity(entity.id()).despawn_recursive();
+        if level.contains_chunk(home) {
+            tracked.parked.entry(home).or_default().push(saved);
+        } else {
+            rows.entry(home).or_default().push(saved);
+        }
+    }
+    let unloaded = tracked
+        .parked
+        .keys()
+        .chain(tracked.spawned_rows.iter())
+        .filter(|coord| !level.contains_chunk(**coord))
+        .copied()
+        .collect::<Vec<_>>();
+    for coord in unloaded {
+        tracked.spawned_rows.remove(&coord);
+        let parked = tracked.parked.remove(&coord).unwrap_or_default();
+        rows.entry(coord).or_default().extend(parked);
+    }
+    //forget entities that were despawned some other way
+    tracked.homes.retain(|entity, _| query.contains(*entity));
+    db.save_chunk_data(row_commands(level.id, rows));
+}
+
+//saves every entity and writes the rows of every tracked chunk. used when the level is closed, since its chunks aren't unloaded
+//returns the saved entities so they can be despawned
+pub(super) fn save_all_entities(
+    level: &LevelData,
+    query: &Query<(EntityRef, &ActorName, &Transform), With<SaveWithChunk>>,
+    tracked: &mut ChunkEntities,
+    components: &Components,
+    registry: &TypeRegistry,
+) -> (Vec<SaveCommand>, Vec<Entity>) {
+    let mut rows = std::mem::take(&mut tracked.parked);
+    for coord in tracked.spawned_rows.drain() {
+        rows.entry(coord).or_default();
+    }
+    let mut saved = Vec::new();
+    for (entity, actor, tf) in query.iter() {
+        let coord = ChunkCoord::from(tf.translation);
+        let home = if level.contains_chunk(coord) {
+            coord
+        } else if let Some(home) = tracked.homes.get(&entity.id()) {
+            *home
+        } else {
+            continue;
+        };
+        rows.entry(home)
+            .or_default()
+            .push(SavedEntity::new(entity, actor, *tf, components, registry));
+        saved.push(entity.id());
+    }
+    tracked.homes.clear();
+    (row_commands(level.id, rows), saved)
+}
+
+pub fn save_entities_on_exit(
+    query: Query<(EntityRef, &ActorName, &Transform), With<SaveWithChunk>>,
+    level: Res<Level>,
+    mut db: ResMut<LevelDB>,
+    mut tracked: ResMut<ChunkEntities>,
+    registry: Res<AppTypeRegistry>,
+    components: &Components,
+) {
+    let (save_data, saved) =
+        save_all_entities(&level, &query, &mut tracked, components, &registry.read());
+    info!("Saving {} entities before exiting.", saved.len());
+    db.save_chunk_data(save_data);
+}
+
+//adds entities to a chunk's row in a level that isn't loaded, keeping the ones already saved there
+//they're spawned when the chunk is next loaded in that level
+pub fn save_into_level(
+    db: &mut LevelDB,
+    level: LevelId,
+    coord: ChunkCoord,
+    entities: Vec<SavedEntity>,
+) {
+    db.update_chunk_row(UpdateCommand {
+        level,
+        table: ChunkTable::Entities,
+        coord,
+        update: Box::new(move |data| {
+            let mut saved = if data.is_empty() {
+                Vec::new()
+            } else {
+                bincode::deserialize::<Vec<SavedEntity>>(&data).map_err(LevelDBErr::Bincode)?
+            };
+            saved.extend(entities);
+            bincode::serialize(&saved).map_err(LevelDBErr::Bincode)
+        }),
+    });
+}
+
+fn row_commands(level: LevelId, rows: HashMap<ChunkCoord, Vec<SavedEntity>>) -> Vec<SaveCommand> {
+    rows.into_iter()
+        .filter_map(|(coord, entities)| {
+            SaveCommand::encode(level, ChunkTable::Entities, coord, &entities)
+                .map_err(|e| error!("error serializing entities at {:?}: {:?}", coord, e))
+                .ok()
+        })
+        .collect()
+}
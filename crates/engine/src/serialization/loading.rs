@@ -1,23 +1,36 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, tasks::{AsyncComputeTaskPool, Task}};
+use futures_lite::future;
 
 use crate::{
     world::{
-        chunk::{ChunkCoord, ChunkType},
+        chunk::{ArrayChunk, ChunkCoord, ChunkType},
+        levels::LevelId,
         Level, BlockResources, BlockId, LevelData, events::ChunkUpdatedEvent,
     },
-    worldgen::{ChunkNeedsGenerated, GeneratedChunk},
+    util::string::Version,
+    worldgen::{ChunkNeedsGenerated, DecorationResources, GeneratedChunk, UsedShaperResources},
+    GameState,
 };
 
-use super::{ChunkSaveFormat, NeedsLoading, SaveTimer, SavedToLoadedIdMap};
+use super::{ChunkSaveFormat, LevelOpenError, LevelSaveVersion, NeedsLoading, SaveTimer, SavedToLoadedIdMap, SAVE_VERSION};
 use super::db::*;
+use super::diff::{TerrainBaseline, TerrainDiff};
 
 const LOADING_ENABLED: bool = true;
 
+//a chunk saved as a diff, being generated again so the diff can be applied to it
+#[derive(Component)]
+pub struct RegeneratingTask {
+    task: Task<ChunkSaveFormat>,
+    ticks: Vec<(u16, u32)>,
+}
+
 pub fn queue_terrain_loading(
     mut commands: Commands,
     mut db: ResMut<LevelDB>,
     query: Query<(Entity, &ChunkCoord), With<NeedsLoading>>,
     timer: Res<SaveTimer>,
+    level: Res<Level>,
 ) {
     //timer gets updating in saving system, so loading will happen after
     if !timer.0.finished() {
@@ -29,8 +42,15 @@ pub fn queue_terrain_loading(
             .map(move |(entity, coord)| {
                 commands.entity(entity).remove::<NeedsLoading>();
                 LoadCommand {
+                    level: level.id,
                     position: *coord,
-                    to_load: vec![ChunkTable::Terrain, ChunkTable::Buffers],
+                    to_load: vec![
+                        ChunkTable::Terrain,
+                        ChunkTable::Buffers,
+                        ChunkTable::BlockTicks,
+                        ChunkTable::Entities,
+                        ChunkTable::TerrainDiff,
+                    ],
                 }
             })
             .collect(),
@@ -39,52 +59,94 @@ pub fn queue_terrain_loading(
 
 pub fn load_chunk_terrain(
     mut commands: Commands,
+    mut db: ResMut<LevelDB>,
     mut events: EventReader<DataFromDBEvent>,
     mut tf_query: Query<&mut Transform>,
     level: Res<Level>,
     resources: Res<BlockResources>,
     map: Res<SavedToLoadedIdMap<BlockId>>,
+    shaper: Option<Res<UsedShaperResources>>,
+    decoration: Option<Res<DecorationResources>>,
     mut update_writer: EventWriter<ChunkUpdatedEvent>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    save_version: Option<Res<LevelSaveVersion>>,
 ) {
     let mut loaded = 0;
-    for DataFromDBEvent(coord, data_vec) in events.read().filter(|DataFromDBEvent(_, data)| {
+    let newer = save_version.is_some_and(|version| version.0 > Version::from(SAVE_VERSION));
+    //rows from a newer version that couldn't be decoded
+    let mut unreadable = Vec::new();
+    //diffs are loaded whether or not the level is set to save them
+    let baseline = shaper.as_deref().zip(decoration.as_deref()).map(|(shaper, decoration)| TerrainBaseline::new(shaper, decoration, level.seed));
+    for DataFromDBEvent(level_id, coord, data_vec) in events.read().filter(|DataFromDBEvent(level_id, _, data)| {
         //even if there is no terrain/buffer, we will still have entries (just with an empty data vec)
-        data.len() == 2 && data[0].0 == ChunkTable::Terrain && data[1].0 == ChunkTable::Buffers
+        //loads for a level that was switched away from are dropped, its chunks get loaded again if it's switched back to
+        *level_id == level.id
+            && data.len() == 5
+            && data[0].0 == ChunkTable::Terrain
+            && data[1].0 == ChunkTable::Buffers
+            && data[2].0 == ChunkTable::BlockTicks
+            && data[3].0 == ChunkTable::Entities
+            && data[4].0 == ChunkTable::TerrainDiff
     }) {
         let terrain_data = &data_vec[0].1;
         let buff_data = &data_vec[1].1;
+        let ticks_data = &data_vec[2].1;
+        let diff_data = &data_vec[4].1;
         //do buffers before loading terrain, that way if there's both, we only generate the terrain mesh once.
         //first copy over the buffer so that it is applied when the chunk is added right after the terrain loads.
         if LOADING_ENABLED && !buff_data.is_empty() {
-            match bincode::deserialize::<ChunkSaveFormat>(buff_data.as_slice()) {
+            match ChunkSaveFormat::decode(buff_data) {
                 Ok(mut fmt) => {
                     fmt.map_to_loaded(&map);
                     level.add_rle_buffer(*coord, &fmt.into_buffer(&resources.registry, &mut commands), &mut commands, &mut update_writer)
                 },
-                Err(e) => error!("error deserializing chunk buffer at {:?}: {:?}", coord, e),
+                Err(e) => {
+                    unreadable_row(&mut db, newer, &mut unreadable, *level_id, ChunkTable::Buffers, *coord, buff_data, e);
+                },
             }
         }
         //load terrain or mark as needing generation
         if let Some(entity) = level.get_chunk_entity(*coord) {
-            if !LOADING_ENABLED || terrain_data.is_empty() {
-                commands.entity(entity).insert(ChunkNeedsGenerated::Full);
-            } else {
-                match bincode::deserialize::<ChunkSaveFormat>(terrain_data.as_slice()) {
+            if LOADING_ENABLED && !terrain_data.is_empty() {
+                match ChunkSaveFormat::decode(terrain_data) {
                     Ok(mut parsed) => {
                         parsed.map_to_loaded(&map);
+                        let fluid_levels = std::mem::take(&mut parsed.fluid_levels);
                         let chunk = parsed.into_chunk(entity, &resources.registry, &mut commands);
-                        let pos = chunk.position;
-                        if let Ok(mut tf) = tf_query.get_mut(entity) {
-                            tf.translation = chunk.position.to_vec3();
+                        let ticks = load_ticks(&mut db, *level_id, *coord, ticks_data);
+                        add_loaded_chunk(chunk, ticks, fluid_levels, &level, &mut tf_query, &mut commands, &mut update_writer);
+                        loaded += 1;
+                    },
+                    Err(e) => {
+                        if unreadable_row(&mut db, newer, &mut unreadable, *level_id, ChunkTable::Terrain, *coord, terrain_data, e) {
+                            commands.entity(entity).insert(ChunkNeedsGenerated::Full);
                         }
-                        level.add_chunk(chunk.position, ChunkType::Full(chunk));
-                        LevelData::update_chunk_only::<false>(entity, pos,&mut commands, &mut update_writer);
-                        commands.entity(entity).insert(GeneratedChunk);
+                    },
+                }
+            } else if LOADING_ENABLED && !diff_data.is_empty() {
+                match (bincode::deserialize::<TerrainDiff>(diff_data.as_slice()), &baseline) {
+                    (Ok(mut diff), Some(baseline)) => {
+                        diff.map_to_loaded(&map);
+                        let baseline = baseline.clone();
+                        let pos = *coord;
+                        commands.entity(entity).insert(RegeneratingTask {
+                            task: AsyncComputeTaskPool::get().spawn(async move { diff.apply(&baseline.generate(pos)) }),
+                            ticks: load_ticks(&mut db, *level_id, *coord, ticks_data),
+                        });
                         loaded += 1;
                     },
-                    Err(e) => error!("error deserializing chunk terrain at {:?}: {:?}", coord, e),
+                    (Ok(_), None) => {
+                        error!("couldn't load terrain diff at {:?} before the generator was set up", coord);
+                        commands.entity(entity).insert(ChunkNeedsGenerated::Full);
+                    },
+                    (Err(e), _) => {
+                        if unreadable_row(&mut db, newer, &mut unreadable, *level_id, ChunkTable::TerrainDiff, *coord, diff_data, e) {
+                            commands.entity(entity).insert(ChunkNeedsGenerated::Full);
+                        }
+                    },
                 }
-                
+            } else {
+                commands.entity(entity).insert(ChunkNeedsGenerated::Full);
             }
         }
     }
@@ -92,4 +154,93 @@ pub fn load_chunk_terrain(
     if loaded > 0 {
         info!("Loaded terrain for {} chunks.", loaded);
     }
+    //rows that failed their checksum were already quarantined by the db and come through empty, so the chunk is generated again.
+    //ones from a newer version are lost if the chunk is generated over them, so the level is closed instead
+    if let Some(message) = unreadable.first() {
+        error!("Closing the level, {} chunk rows couldn't be read", unreadable.len());
+        commands.insert_resource(LevelOpenError {
+            level: level.name,
+            message: message.clone(),
+        });
+        next_game_state.set(GameState::Menu);
+    }
+}
+
+//a row that can't be decoded is corrupt, so it's quarantined like rows that fail their checksum. returns whether it was
+//in a level saved by a newer version the row may be in a layout this version doesn't know, and generating the chunk
+// would save over it, so it's kept and the level is closed instead
+fn unreadable_row(
+    db: &mut LevelDB,
+    newer: bool,
+    unreadable: &mut Vec<String>,
+    level: LevelId,
+    table: ChunkTable,
+    coord: ChunkCoord,
+    data: &[u8],
+    error: bincode::Error,
+) -> bool {
+    error!("error deserializing {:?} at {:?}: {:?}", table, coord, error);
+    if newer {
+        unreadable.push(format!("the {:?} row of chunk {:?} couldn't be read: {}", table, coord, error));
+        return false;
+    }
+    db.quarantine_chunk(level, table, coord, data.to_vec(), error.to_string());
+    true
+}
+
+//adds the terrain of a diff to the level once the generator has made the rest of the chunk
+pub fn poll_regenerating_chunks(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut RegeneratingTask)>,
+    mut tf_query: Query<&mut Transform>,
+    level: Res<Level>,
+    resources: Res<BlockResources>,
+    mut update_writer: EventWriter<ChunkUpdatedEvent>,
+) {
+    for (entity, mut regenerating) in query.iter_mut() {
+        if let Some(mut parsed) = future::block_on(future::poll_once(&mut regenerating.task)) {
+            commands.entity(entity).remove::<RegeneratingTask>();
+            let fluid_levels = std::mem::take(&mut parsed.fluid_levels);
+            let chunk = parsed.into_chunk(entity, &resources.registry, &mut commands);
+            let ticks = std::mem::take(&mut regenerating.ticks);
+            add_loaded_chunk(chunk, ticks, fluid_levels, &level, &mut tf_query, &mut commands, &mut update_writer);
+        }
+    }
+}
+
+//loading doesn't mark the chunk as needing to be saved
+fn add_loaded_chunk(
+    chunk: ArrayChunk,
+    ticks: Vec<(u16, u32)>,
+    fluid_levels: Vec<(u16, u8)>,
+    level: &LevelData,
+    tf_query: &mut Query<&mut Transform>,
+    commands: &mut Commands,
+    update_writer: &mut EventWriter<ChunkUpdatedEvent>,
+) {
+    let entity = chunk.entity;
+    let pos = chunk.position;
+    if let Ok(mut tf) = tf_query.get_mut(entity) {
+        tf.translation = pos.to_vec3();
+    }
+    level.add_chunk(pos, ChunkType::Full(chunk));
+    level.load_saved_block_ticks(pos, ticks);
+    level.load_saved_fluid_levels(pos, fluid_levels);
+    LevelData::update_chunk_only::<false>(entity, pos, commands, update_writer);
+    commands.entity(entity).insert(GeneratedChunk);
+}
+
+//a row that can't be read is quarantined, and the chunk loads without its ticks
+fn load_ticks(db: &mut LevelDB, level: LevelId, coord: ChunkCoord, ticks_data: &[u8]) -> Vec<(u16, u32)> {
+    if ticks_data.is_empty() {
+        return Vec::new();
+    }
+    match bincode::deserialize(ticks_data) {
+        Ok(ticks) => ticks,
+        Err(e) => {
+            error!("error deserializing block ticks at {:?}: {:?}", coord, e);
+            db.quarantine_chunk(level, ChunkTable::BlockTicks, coord, ticks_data.to_vec(), e.to_string());
+            Vec::new()
+        },
+    }
 }
@@ -0,0 +1,181 @@
+use std::ops::Range;
+
+use bevy::prelude::*;
+use rusqlite::{params, Connection};
+
+use crate::util::{checksum::crc32, string::Version};
+
+use super::{
+    codec::ChunkCodec,
+    db::{ChunkTable, LevelDBErr},
+    queries::INSERT_WORLD_INFO,
+    ChunkSaveFormat,
+};
+
+//upgrades saves from one SAVE_VERSION to a later one
+pub struct Migration {
+    //shown in the error if the step fails
+    pub name: &'static str,
+    //saves with a version in this range are upgraded to `versions.end`
+    pub versions: Range<Version>,
+    //runs in the same transaction as the other steps, so a failed migration leaves the save as it was
+    pub run: fn(&Connection) -> Result<(), LevelDBErr>,
+}
+
+//every migration step the game knows about. plugins add their own steps while the app is being built
+#[derive(Resource, Default)]
+pub struct MigrationRegistry {
+    steps: Vec<Migration>,
+}
+
+impl MigrationRegistry {
+    //the steps for the game's own changes to the save format
+    pub fn with_game_steps() -> Self {
+        let mut registry = Self::default();
+        registry.add(Migration {
+            name: "block states",
+            versions: Version::from("0.1.0")..Version::from("0.1.1"),
+            run: add_block_states,
+        });
+        registry
+    }
+
+    pub fn add(&mut self, migration: Migration) -> &mut Self {
+        self.steps.push(migration);
+        self
+    }
+
+    //the steps that take a save at `saved` to a version that `target` can open, in the order they run
+    //saves that are already compatible with `target` may still have steps, if there was a breaking change in a patch version
+    pub fn plan(&self, saved: &Version, target: &Version) -> Result<Vec<&Migration>, LevelDBErr> {
+        let mut steps = Vec::new();
+        let mut current = saved.clone();
+        //each step moves `current` forward, so this can't loop forever
+        while let Some(step) = self
+            .steps
+            .iter()
+            .find(|step| step.versions.contains(&current) && step.versions.end <= *target)
+        {
+            steps.push(step);
+            current = step.versions.end.clone();
+        }
+        if !current.game_compatible(target) && current < *target {
+            return Err(LevelDBErr::MissingMigration(current));
+        }
+        Ok(steps)
+    }
+}
+
+//runs `steps` in one transaction and updates the saved version to the last step's version
+pub fn run_migrations(conn: &mut Connection, steps: &[&Migration]) -> Result<(), LevelDBErr> {
+    let Some(last) = steps.last() else {
+        return Ok(());
+    };
+    let tx = conn.transaction().map_err(LevelDBErr::Sqlite)?;
+    for step in steps {
+        info!(
+            "Migrating save from {} to {} ({})",
+            step.versions.start, step.versions.end, step.name
+        );
+        (step.run)(&tx).map_err(|e| LevelDBErr::MigrationFailed(step.name, Box::new(e)))?;
+    }
+    tx.execute(
+        INSERT_WORLD_INFO,
+        params![
+            "version",
+            bincode::serialize(&last.versions.end.to_string()).unwrap()
+        ],
+    )
+    .map_err(LevelDBErr::Sqlite)?;
+    tx.commit().map_err(LevelDBErr::Sqlite)
+}
+
+//terrain saved before chunks had block states and fluid levels is written with them empty
+//rows already in the current layout, and ones that can't be read, are written back as they were
+fn add_block_states(conn: &Connection) -> Result<(), LevelDBErr> {
+    for table in [ChunkTable::Terrain, ChunkTable::Buffers] {
+        rewrite_chunks(conn, table, |data| match ChunkSaveFormat::decode(&data) {
+            Ok(chunk) => bincode::serialize(&chunk).map_err(LevelDBErr::Bincode),
+            Err(_) => Ok(data),
+        })?;
+    }
+    Ok(())
+}
+
+//helpers for migration steps
+
+//rewrites the world_info row at `key`, if there is one. the row is deleted if `f` returns None
+pub fn rewrite_world_info(
+    conn: &Connection,
+    key: &str,
+    f: impl FnOnce(Vec<u8>) -> Result<Option<Vec<u8>>, LevelDBErr>,
+) -> Result<(), LevelDBErr> {
+    let value = match conn.query_row(
+        "SELECT value FROM world_info WHERE key = ?1",
+        params![key],
+        |row| row.get::<_, Vec<u8>>(0),
+    ) {
+        Ok(value) => value,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(()),
+        Err(e) => return Err(LevelDBErr::Sqlite(e)),
+    };
+    match f(value)? {
+        Some(value) => conn.execute(INSERT_WORLD_INFO, params![key, value]),
+        None => conn.execute("DELETE FROM world_info WHERE key = ?1", params![key]),
+    }
+    .map(|_| ())
+    .map_err(LevelDBErr::Sqlite)
+}
+
+//moves the world_info row at `from` to `to`, replacing anything that was there
+pub fn rename_world_info(conn: &Connection, from: &str, to: &str) -> Result<(), LevelDBErr> {
+    conn.execute(
+        "INSERT OR REPLACE INTO world_info (key,value) SELECT ?2, value FROM world_info WHERE key = ?1",
+        params![from, to],
+    )
+    .and_then(|_| conn.execute("DELETE FROM world_info WHERE key = ?1", params![from]))
+    .map(|_| ())
+    .map_err(LevelDBErr::Sqlite)
+}
+
+//rewrites every blob saved in `table`, in every level. `f` gets the bincode, the rows keep their codec and get a new checksum
+//rows with a codec we don't know are left alone, they're quarantined when they're loaded
+pub fn rewrite_chunks(
+    conn: &Connection,
+    table: ChunkTable,
+    mut f: impl FnMut(Vec<u8>) -> Result<Vec<u8>, LevelDBErr>,
+) -> Result<(), LevelDBErr> {
+    let mut select = conn
+        .prepare("SELECT tid, x, y, z, data, codec FROM data WHERE tid % 256 = ?1")
+        .map_err(LevelDBErr::Sqlite)?;
+    let rows = select
+        .query_map(params![table as i32], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, i32>(1)?,
+                row.get::<_, i32>(2)?,
+                row.get::<_, i32>(3)?,
+                row.get::<_, Vec<u8>>(4)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })
+        .map_err(LevelDBErr::Sqlite)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(LevelDBErr::Sqlite)?;
+    let mut update = conn
+        .prepare(
+            "UPDATE data SET data = ?5, checksum = ?6 WHERE tid = ?1 AND x = ?2 AND y = ?3 AND z = ?4",
+        )
+        .map_err(LevelDBErr::Sqlite)?;
+    for (tid, x, y, z, data, codec) in rows {
+        let Some(codec) = ChunkCodec::from_id(codec) else {
+            continue;
+        };
+        let data = codec.encode(f(codec.decode(&data)?)?);
+        let checksum = crc32(&data);
+        update
+            .execute(params![tid, x, y, z, data, checksum])
+            .map_err(LevelDBErr::Sqlite)?;
+    }
+    Ok(())
+}
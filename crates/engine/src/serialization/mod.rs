@@ -1,27 +1,47 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
+use bincode::Options;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-use bevy::{prelude::*, utils::HashMap};
+use bevy::{app::AppExit, prelude::*, time::common_conditions::on_timer, utils::HashMap};
 
 use crate::{
     net::NetworkType,
+    util::string::Version,
     world::{
         chunk::{ArrayChunk, ChunkCoord, ChunkTrait, BLOCKS_PER_CHUNK},
+        levels::Levels,
         util::BlockPalette,
-        BlockId, BlockRegistry, BlockType, Id, LevelSystemSet,
+        BlockId, BlockRegistry, BlockState, BlockStates, BlockType, Id, Level, LevelSystemSet,
     },
+    GameState,
 };
 
 pub struct SerializationPlugin;
 
+pub mod backup;
+pub mod codec;
 pub mod db;
+pub mod diff;
+pub mod entities;
 mod loading;
+pub mod migrations;
+pub mod players;
 pub mod queries;
 mod save;
+pub mod schematic;
 mod setup;
 pub mod state;
+pub mod storage;
+mod test;
+
+//the version of the save format. levels are stamped with it when they're opened, and migrations upgrade older levels to it
+//it isn't tied to the crates' versions, so bump it when the way levels are saved changes
+pub const SAVE_VERSION: &str = "0.1.1";
+
+//players are also saved when the game is closed or left for the menu
+const PLAYER_SAVE_INTERVAL: Duration = Duration::from_secs(10);
 
 impl Plugin for SerializationPlugin {
     fn build(&self, app: &mut App) {
@@ -32,21 +52,106 @@ impl Plugin for SerializationPlugin {
                 Update,
                 (
                     loading::load_chunk_terrain,
+                    loading::poll_regenerating_chunks,
+                    entities::load_chunk_entities.after(loading::load_chunk_terrain),
                     loading::queue_terrain_loading,
                     db::tick_db,
+                    record_quarantined_chunks.after(db::tick_db),
                     save::do_saving,
                     save::save_all,
+                    players::save_players.run_if(on_timer(PLAYER_SAVE_INTERVAL)),
+                    backup::scheduled_backup,
+                    setup::save_levels.run_if(resource_exists_and_changed::<Levels>),
                 )
                     .in_set(LevelSystemSet::AfterLoadingAndMain)
                     .run_if(not(in_state(NetworkType::Client))),
             )
+            .add_systems(
+                Update,
+                (
+                    entities::save_unloaded_entities
+                        .before(save::switch_active_level)
+                        .run_if(resource_exists::<Level>.and(resource_exists::<db::LevelDB>))
+                        .run_if(resource_exists::<Levels>),
+                    save::switch_active_level.run_if(resource_exists::<Levels>),
+                )
+                    .in_set(LevelSystemSet::Despawn)
+                    .run_if(not(in_state(NetworkType::Client))),
+            )
+            .add_systems(
+                Last,
+                (entities::save_entities_on_exit, players::save_players)
+                    .run_if(on_event::<AppExit>)
+                    .run_if(resource_exists::<Level>)
+                    .run_if(resource_exists::<db::LevelDB>)
+                    .run_if(not(in_state(NetworkType::Client))),
+            )
+            //closing the db writes anything that's still queued, and lets the level be restored from the menu
+            .add_systems(
+                OnExit(GameState::Game),
+                (
+                    (entities::save_entities_on_exit, players::save_players)
+                        .run_if(resource_exists::<Level>)
+                        .run_if(resource_exists::<db::LevelDB>)
+                        .run_if(not(in_state(NetworkType::Client))),
+                    close_level_db,
+                )
+                    .chain(),
+            )
+            .add_observer(entities::restore_saved_components)
+            .configure_sets(
+                Last,
+                SaveWorldStateSet
+                    .run_if(save_timer_finished.or(on_event::<AppExit>))
+                    .run_if(resource_exists::<db::LevelDB>)
+                    .run_if(not(in_state(NetworkType::Client))),
+            )
             .add_event::<SaveChunkEvent>()
             .add_event::<db::DataFromDBEvent>()
+            .add_event::<db::ChunkQuarantinedEvent>()
             .insert_resource(SaveTimer(Timer::from_seconds(0.1, TimerMode::Repeating)))
-            .init_resource::<LevelCreationInput>();
+            .init_resource::<LevelCreationInput>()
+            .insert_resource(migrations::MigrationRegistry::with_game_steps())
+            .init_resource::<QuarantinedChunks>()
+            .init_resource::<entities::ChunkEntities>()
+            .init_resource::<entities::PendingRestores>()
+            .register_type::<SaveWithChunk>();
     }
 }
 
+fn close_level_db(
+    mut commands: Commands,
+    mut quarantined: ResMut<QuarantinedChunks>,
+    mut tracked: ResMut<entities::ChunkEntities>,
+    mut pending: ResMut<entities::PendingRestores>,
+) {
+    commands.remove_resource::<db::LevelDB>();
+    commands.remove_resource::<backup::ScheduledBackups>();
+    commands.remove_resource::<LevelSaveVersion>();
+    quarantined.0.clear();
+    //the entities they track were saved, and the next level's have new ids
+    *tracked = entities::ChunkEntities::default();
+    pending.clear();
+}
+
+fn record_quarantined_chunks(
+    mut events: EventReader<db::ChunkQuarantinedEvent>,
+    mut quarantined: ResMut<QuarantinedChunks>,
+) {
+    for event in events.read() {
+        warn!(
+            "Quarantined corrupt {:?} row for chunk {:?} in level {:?}: {}",
+            event.table, event.coord, event.level, event.reason
+        );
+        quarantined.0.push(event.clone());
+    }
+}
+
+//the file the level named `name` is saved in
+pub fn level_file_path(env_path: &str, name: &str) -> PathBuf {
+    std::path::Path::new(env_path).join(name.to_owned() + setup::LEVEL_FILE_EXTENSION)
+}
+
 #[derive(Resource)]
 pub struct SavedLevels(pub Vec<SavedLevelInfo>);
 
@@ -70,6 +175,23 @@ impl Default for LevelCreationInput {
     }
 }
 
+//why the last level couldn't be opened. shown on the world select screen, and cleared when a level is opened
+#[derive(Resource, Debug)]
+pub struct LevelOpenError {
+    pub level: &'static str,
+    pub message: String,
+}
+
+//the SAVE_VERSION the open level had before it was opened
+//rows that can't be read in a level saved by a newer version may be in a layout this version doesn't know,
+// so they're kept instead of being quarantined
+#[derive(Resource, Debug)]
+pub struct LevelSaveVersion(pub Version);
+
+//rows quarantined since the level was opened, shown in the debug ui
+#[derive(Resource, Default)]
+pub struct QuarantinedChunks(pub Vec<db::ChunkQuarantinedEvent>);
+
 #[derive(Resource)]
 pub struct BlockTextureMap(pub HashMap<PathBuf, u32>);
 
@@ -123,6 +245,12 @@ impl<T: Into<Id> + From<Id> + std::hash::Hash + Clone + Eq + PartialEq> LoadedTo
 #[derive(Component)]
 pub struct NeedsSaving;
 
+//opts an entity into being saved with the chunk it's in. it's despawned when the chunk unloads and respawned when it loads
+//the entity needs an ActorName so it can be respawned through the ActorRegistry. its reflected components are restored on top of what the actor spawns with
+#[derive(Component, Clone, Copy, Reflect, Default)]
+#[reflect(Component, FromWorld)]
+pub struct SaveWithChunk;
+
 #[derive(Component)]
 pub struct NeedsLoading;
 
@@ -135,15 +263,46 @@ pub struct LoadingItems;
 #[derive(Resource)]
 pub struct SaveTimer(Timer);
 
+fn save_timer_finished(timer: Res<SaveTimer>) -> bool {
+    timer.0.just_finished()
+}
+
+//systems that write level-wide state (calendar, assault, etc) to the world_info table
+//runs whenever chunks are saved and when the game is closed
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SaveWorldStateSet;
+
 #[derive(Event)]
 pub struct SaveChunkEvent(ChunkCoord);
 
 //run length encoded format for chunks
-//TODO: figure out how to do entities
+//entities are saved separately, in ChunkTable::Entities
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChunkSaveFormat {
     pub position: ChunkCoord,
     pub data: Vec<(BlockId, u16)>,
+    //(index, state) for every block without the default state
+    pub states: Vec<(u16, BlockState)>,
+    //(index, level) for every fluid block that isn't a source
+    pub fluid_levels: Vec<(u16, u8)>,
+}
+
+//the layout terrain and buffer rows were saved with before chunks had block states and fluid levels
+#[derive(Deserialize)]
+struct LegacyChunkSaveFormat {
+    position: ChunkCoord,
+    data: Vec<(BlockId, u16)>,
+}
+
+impl From<LegacyChunkSaveFormat> for ChunkSaveFormat {
+    fn from(value: LegacyChunkSaveFormat) -> Self {
+        Self {
+            position: value.position,
+            data: value.data,
+            states: Vec::new(),
+            fluid_levels: Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -178,11 +337,25 @@ impl From<(ChunkCoord, &[BlockId; BLOCKS_PER_CHUNK])> for ChunkSaveFormat {
         Self {
             position: value.0,
             data,
+            states: Vec::new(),
+            fluid_levels: Vec::new(),
         }
     }
 }
 
 impl ChunkSaveFormat {
+    //reads a terrain or buffer row, including rows in the legacy layout
+    //the legacy layout has to use up the whole row, so that a damaged row isn't read as one. the error is from the current layout
+    pub fn decode(data: &[u8]) -> bincode::Result<Self> {
+        bincode::deserialize::<Self>(data).or_else(|e| {
+            bincode::DefaultOptions::new()
+                .with_fixint_encoding()
+                .reject_trailing_bytes()
+                .deserialize::<LegacyChunkSaveFormat>(data)
+                .map(ChunkSaveFormat::from)
+                .map_err(|_| e)
+        })
+    }
     //creates a save format by extracting the ids from the block array using the provided query
     //will replace with the empty block if the entities in the block array do not have a BlockId component
     pub fn ids_only(
@@ -209,6 +382,8 @@ impl ChunkSaveFormat {
         Self {
             position: value.0,
             data,
+            states: Vec::new(),
+            fluid_levels: Vec::new(),
         }
     }
     //creates a save format by extracting the ids from the block array using the provided query
@@ -228,6 +403,8 @@ impl ChunkSaveFormat {
         Self {
             position: value.0,
             data,
+            states: Vec::new(),
+            fluid_levels: Vec::new(),
         }
     }
     //creates a save format by extracting the ids from the block array using the provided query
@@ -246,8 +423,21 @@ impl ChunkSaveFormat {
         Self {
             position: value.0,
             data,
+            states: Vec::new(),
+            fluid_levels: Vec::new(),
         }
     }
+    pub fn with_states(mut self, states: &BlockStates) -> Self {
+        self.states = states
+            .iter()
+            .map(|(idx, state)| (idx as u16, state))
+            .collect();
+        self
+    }
+    pub fn with_fluid_levels(mut self, fluid_levels: Vec<(u16, u8)>) -> Self {
+        self.fluid_levels = fluid_levels;
+        self
+    }
     pub fn into_chunk(
         self,
         chunk_entity: Entity,
@@ -262,6 +452,9 @@ impl ChunkSaveFormat {
             }
             curr_idx += length as usize;
         }
+        for (idx, state) in self.states {
+            chunk.states.set(idx as usize, state);
+        }
         chunk
     }
     pub fn into_buffer(
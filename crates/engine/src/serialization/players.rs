@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actors::{abilities::stamina::Stamina, Combatant, Health, LocalPlayer, Player},
+    items::{
+        inventory::Inventory, loot::resolve_item, CreatorItem, ItemId, ItemName, ItemRegistry,
+        ItemStack,
+    },
+    net::{PlayerList, RemoteClient, HOST_USERNAME},
+    world::{levels::LevelId, BlockRegistry, Level},
+};
+
+use super::{
+    db::{LevelDB, LevelDBErr},
+    LoadedToSavedIdMap, SavedToLoadedIdMap,
+};
+
+//a player's state, saved in the players table under their username
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedPlayer {
+    //the level the player was in. the transform is only used if the player is spawned in the same level
+    pub level: LevelId,
+    pub transform: Transform,
+    pub health: Option<Health>,
+    pub stamina: Option<Stamina>,
+    //one entry per slot
+    pub inventory: Vec<Option<SavedItemStack>>,
+    pub selected_slot: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedItemStack {
+    pub item: SavedItem,
+    pub size: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum SavedItem {
+    //saved id from the item palette
+    Id(ItemId),
+    //items that aren't in the item registry, like the ones that place blocks
+    Name(ItemName),
+}
+
+impl SavedPlayer {
+    pub fn new(
+        level: LevelId,
+        transform: Transform,
+        combatant: Option<&Combatant>,
+        stamina: Option<&Stamina>,
+        inventory: Option<&Inventory>,
+        item_query: &Query<(Option<&ItemId>, &ItemName)>,
+        id_map: &LoadedToSavedIdMap<ItemId>,
+    ) -> Self {
+        let health = match combatant {
+            Some(Combatant::Root { health, .. }) => Some(*health),
+            _ => None,
+        };
+        let saved_inventory = inventory
+            .map(|inv| {
+                inv.iter()
+                    .map(|slot| {
+                        let (stack, _) = slot.as_ref()?;
+                        let (id, name) = item_query.get(stack.id).ok()?;
+                        let item = match id.and_then(|id| id_map.get(id)) {
+                            Some(saved_id) => SavedItem::Id(saved_id),
+                            None => SavedItem::Name(name.clone()),
+                        };
+                        Some(SavedItemStack {
+                            item,
+                            size: stack.size,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            level,
+            transform,
+            health,
+            stamina: stamina.copied(),
+            inventory: saved_inventory,
+            selected_slot: inventory.map(|inv| inv.selected_slot()).unwrap_or_default(),
+        }
+    }
+
+    //fills `inventory` with the saved items. items that no longer exist are dropped
+    pub fn restore_inventory(
+        &self,
+        inventory: &mut Inventory,
+        items: &ItemRegistry,
+        blocks: &BlockRegistry,
+        creator_query: &Query<&CreatorItem>,
+        id_map: &SavedToLoadedIdMap<ItemId>,
+        commands: &mut Commands,
+    ) {
+        for (slot, saved) in self.inventory.iter().enumerate().take(inventory.len()) {
+            let Some(saved) = saved else {
+                continue;
+            };
+            let item = match &saved.item {
+                SavedItem::Id(saved_id) => id_map
+                    .get(saved_id)
+                    .and_then(|id| items.get_entity(id, commands)),
+                SavedItem::Name(name) => resolve_item(name, items, blocks, creator_query),
+            };
+            match item {
+                Some(item) => inventory.set_slot_no_events(slot, ItemStack::new(item, saved.size)),
+                None => warn!("Couldn't restore saved item {:?}", saved.item),
+            }
+        }
+        inventory.select_slot(self.selected_slot as i32);
+    }
+}
+
+pub fn load_player(db: &mut LevelDB, username: &str) -> Result<Option<SavedPlayer>, LevelDBErr> {
+    match db.storage().load_player(username)? {
+        Some(data) => bincode::deserialize(&data)
+            .map(Some)
+            .map_err(LevelDBErr::Bincode),
+        None => Ok(None),
+    }
+}
+
+pub fn save_player(db: &mut LevelDB, username: &str, player: &SavedPlayer) -> Option<LevelDBErr> {
+    let data = bincode::serialize(player).unwrap();
+    db.storage().save_player(username, data).err()
+}
+
+//saves every player on this machine or server. the local player is saved as the host
+//remote players only have the state the server knows about, their inventory lives on their client
+pub fn save_players(
+    player_query: Query<
+        (
+            &Transform,
+            Option<&Combatant>,
+            Option<&Stamina>,
+            Option<&Inventory>,
+            Option<&RemoteClient>,
+            Has<LocalPlayer>,
+        ),
+        With<Player>,
+    >,
+    item_query: Query<(Option<&ItemId>, &ItemName)>,
+    id_map: Res<LoadedToSavedIdMap<ItemId>>,
+    players: Res<PlayerList>,
+    level: Res<Level>,
+    mut db: ResMut<LevelDB>,
+) {
+    for (tf, combatant, stamina, inventory, remote, local) in player_query.iter() {
+        let username = if local {
+            HOST_USERNAME
+        } else if let Some(RemoteClient(Some(client_id))) = remote
+            && let Some(info) = players.infos.get(client_id)
+        {
+            info.username.as_str()
+        } else {
+            continue;
+        };
+        let saved = SavedPlayer::new(
+            level.id,
+            *tf,
+            combatant,
+            stamina,
+            inventory,
+            &item_query,
+            &id_map,
+        );
+        if let Some(e) = save_player(&mut db, username, &saved) {
+            error!("Error saving player {}: {:?}", username, e);
+        }
+    }
+}
@@ -5,8 +5,28 @@ pub const CREATE_CHUNK_TABLE: &str = "
             y INTEGER NOT NULL,
             z INTEGER NOT NULL,
             data BLOB NOT NULL,
+            codec INTEGER NOT NULL DEFAULT 0,
+            checksum INTEGER,
             PRIMARY KEY (tid,x,y,z)
         ) STRICT";
+//for chunk tables made before rows had a codec and checksum. old rows keep a null checksum and aren't checked
+pub const ADD_CHUNK_CODEC_COLUMN: &str = "
+        ALTER TABLE data ADD COLUMN codec INTEGER NOT NULL DEFAULT 0";
+pub const ADD_CHUNK_CHECKSUM_COLUMN: &str = "
+        ALTER TABLE data ADD COLUMN checksum INTEGER";
+//rows that couldn't be read are moved here, so they can be looked at later
+pub const CREATE_QUARANTINE_TABLE: &str = "
+        CREATE TABLE IF NOT EXISTS quarantine (
+            tid INTEGER NOT NULL,
+            x INTEGER NOT NULL,
+            y INTEGER NOT NULL,
+            z INTEGER NOT NULL,
+            data BLOB NOT NULL,
+            codec INTEGER NOT NULL,
+            checksum INTEGER,
+            reason TEXT NOT NULL,
+            time INTEGER NOT NULL
+        ) STRICT";
 pub const CREATE_WORLD_INFO_TABLE: &str = "
         CREATE TABLE IF NOT EXISTS world_info (
             key TEXT NOT NULL,
@@ -14,14 +34,36 @@ pub const CREATE_WORLD_INFO_TABLE: &str = "
             PRIMARY KEY (key)
         ) STRICT";
 pub const SAVE_CHUNK_DATA: &str = "
-            INSERT OR REPLACE INTO data (tid,x, y, z, data)
-            VALUES (?1,?2,?3,?4,?5)";
+            INSERT OR REPLACE INTO data (tid,x, y, z, data, codec, checksum)
+            VALUES (?1,?2,?3,?4,?5,?6,?7)";
 pub const LOAD_CHUNK_DATA: &str = "
-            SELECT data FROM data
+            SELECT data, codec, checksum FROM data
+            WHERE tid = ?1 AND x = ?2 AND y = ?3 AND z = ?4";
+pub const DELETE_CHUNK_DATA: &str = "
+            DELETE FROM data
             WHERE tid = ?1 AND x = ?2 AND y = ?3 AND z = ?4";
+pub const QUARANTINE_CHUNK_DATA: &str = "
+            INSERT INTO quarantine (tid, x, y, z, data, codec, checksum, reason, time)
+            VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9)";
+//only deletes the row if it wasn't saved over since it was read
+pub const DELETE_QUARANTINED_CHUNK_DATA: &str = "
+            DELETE FROM data
+            WHERE tid = ?1 AND x = ?2 AND y = ?3 AND z = ?4 AND data = ?5";
 pub const INSERT_WORLD_INFO: &str = "
             INSERT OR REPLACE INTO world_info (key,value)
             VALUES (?1,?2)";
 pub const LOAD_WORLD_INFO: &str = "
             SELECT value FROM world_info
-            WHERE key = ?1";
\ No newline at end of file
+            WHERE key = ?1";
+pub const CREATE_PLAYER_TABLE: &str = "
+        CREATE TABLE IF NOT EXISTS players (
+            username TEXT NOT NULL,
+            data BLOB NOT NULL,
+            PRIMARY KEY (username)
+        ) STRICT";
+pub const SAVE_PLAYER_DATA: &str = "
+            INSERT OR REPLACE INTO players (username,data)
+            VALUES (?1,?2)";
+pub const LOAD_PLAYER_DATA: &str = "
+            SELECT data FROM players
+            WHERE username = ?1";
@@ -1,16 +1,25 @@
 use ahash::HashSet;
-use bevy::prelude::*;
+use bevy::{ecs::component::Components, prelude::*};
+use serde::Serialize;
 
 use crate::{
+    actors::ActorName,
     world::{
-        chunk::{ChunkCoord, ChunkType},
-        BlockId, Level,
+        chunk::{ArrayChunk, ChunkCoord, ChunkType, BLOCKS_PER_CHUNK},
+        levels::{ActiveLevelChangedEvent, LevelId, LevelMember, Levels},
+        settings::Settings,
+        util::BlockPalette,
+        BlockId, BlockStates, Level, LevelData,
     },
-    worldgen::GeneratedChunk,
+    worldgen::{DecorationResources, GeneratedChunk, UsedShaperResources},
 };
 
 use super::db::*;
-use super::{ChunkSaveFormat, LoadedToSavedIdMap, NeedsSaving, SaveChunkEvent, SaveTimer};
+use super::diff::{TerrainBaseline, TerrainDiff};
+use super::entities::{save_all_entities, ChunkEntities};
+use super::{
+    ChunkSaveFormat, LoadedToSavedIdMap, NeedsSaving, SaveChunkEvent, SaveTimer, SaveWithChunk,
+};
 
 pub fn save_all(
     mut save_writer: EventWriter<SaveChunkEvent>,
@@ -39,26 +48,156 @@ pub fn do_saving(
     mut commands: Commands,
     block_query: Query<&BlockId>,
     id_map: Res<LoadedToSavedIdMap<BlockId>>,
+    settings: Res<Settings>,
+    shaper: Option<Res<UsedShaperResources>>,
+    decoration: Option<Res<DecorationResources>>,
 ) {
-    let mut saved = 0;
     //get unique coordinates
     let to_save = HashSet::from_iter(save_events.read().map(|x| x.0));
+    let baseline = terrain_baseline(&settings, &level, shaper.as_deref(), decoration.as_deref());
+    let (save_data, diffs, saved) = chunk_save_commands(
+        &level,
+        to_save,
+        baseline.is_some(),
+        &mut commands,
+        &block_query,
+        &id_map,
+    );
+    if saved > 0 {
+        queue_chunk_saves(&mut db, save_data, diffs, baseline);
+        debug!("Queued saving for {} chunks.", saved);
+    }
+}
+
+//saves and unloads the active level when the local player leaves it, then makes their new level active
+pub fn switch_active_level(
+    mut levels: ResMut<Levels>,
+    level: Res<Level>,
+    mut db: ResMut<LevelDB>,
+    save_query: Query<&ChunkCoord, (With<NeedsSaving>, With<GeneratedChunk>)>,
+    member_query: Query<(Entity, &LevelMember)>,
+    entity_query: Query<(EntityRef, &ActorName, &Transform), With<SaveWithChunk>>,
+    block_query: Query<&BlockId>,
+    id_map: Res<LoadedToSavedIdMap<BlockId>>,
+    mut tracked: ResMut<ChunkEntities>,
+    registry: Res<AppTypeRegistry>,
+    components: &Components,
+    settings: Res<Settings>,
+    shaper: Option<Res<UsedShaperResources>>,
+    decoration: Option<Res<DecorationResources>>,
+    mut changed_writer: EventWriter<ActiveLevelChangedEvent>,
+    mut commands: Commands,
+) {
+    let Some(new) = levels.take_pending_switch() else {
+        return;
+    };
+    let old = level.id;
+    info!("switching from level {:?} to {:?}", old, new);
+    let to_save = save_query
+        .iter()
+        .copied()
+        .chain(level.buffer_iter().map(|buf_ref| *buf_ref.key()))
+        .collect::<HashSet<_>>();
+    //the worldgen resources are still the old level's, they're remade after the switch
+    let baseline = terrain_baseline(&settings, &level, shaper.as_deref(), decoration.as_deref());
+    let (save_data, diffs, _) = chunk_save_commands(
+        &level,
+        to_save,
+        baseline.is_some(),
+        &mut commands,
+        &block_query,
+        &id_map,
+    );
+    queue_chunk_saves(&mut db, save_data, diffs, baseline);
+    let (save_data, saved_entities) = save_all_entities(
+        &level,
+        &entity_query,
+        &mut tracked,
+        components,
+        &registry.read(),
+    );
+    db.save_chunk_data(save_data);
+    for entity in saved_entities {
+        commands.entity(entity).despawn_recursive();
+    }
+    for entity in level.remove_all_chunks() {
+        if let Some(ec) = commands.get_entity(entity) {
+            ec.despawn_recursive();
+        }
+    }
+    for (entity, member) in member_query.iter() {
+        if member.0 == old {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    levels.set_active(new, &mut commands);
+    changed_writer.send(ActiveLevelChangedEvent { old, new });
+}
+
+//what terrain is diffed against, if the level saves diffs
+fn terrain_baseline(
+    settings: &Settings,
+    level: &LevelData,
+    shaper: Option<&UsedShaperResources>,
+    decoration: Option<&DecorationResources>,
+) -> Option<TerrainBaseline> {
+    if !settings.save_terrain_diffs {
+        return None;
+    }
+    match (shaper, decoration) {
+        (Some(shaper), Some(decoration)) => {
+            Some(TerrainBaseline::new(shaper, decoration, level.seed))
+        }
+        //the generator isn't set up yet, so chunks are saved whole
+        _ => None,
+    }
+}
+
+//returns the commands to save the terrain, ticks and buffers at each coord, the terrain to diff if `diffing`,
+// and how many chunks were saved
+fn chunk_save_commands(
+    level: &LevelData,
+    to_save: HashSet<ChunkCoord>,
+    diffing: bool,
+    commands: &mut Commands,
+    block_query: &Query<&BlockId>,
+    id_map: &LoadedToSavedIdMap<BlockId>,
+) -> (Vec<SaveCommand>, Vec<TerrainToDiff>, usize) {
+    let mut saved = 0;
     let mut save_data = Vec::new();
+    let mut diffs = Vec::new();
     for coord in to_save {
         if let Some(chunk_ref) = level.get_chunk(coord) {
             match chunk_ref.value() {
                 ChunkType::Full(chunk) => {
                     if let Some(mut ec) = commands.get_entity(chunk.entity) {
-                        save_data.push(SaveCommand(
-                            ChunkTable::Terrain,
+                        push_terrain(
+                            &mut save_data,
+                            diffing.then_some(&mut diffs),
+                            level,
                             coord,
-                            bincode::serialize(&ChunkSaveFormat::palette_ids_only(
-                                (chunk.position, &chunk.blocks),
-                                &block_query,
-                                &id_map,
-                            ))
-                            .unwrap(),
-                        ));
+                            chunk,
+                            block_query,
+                            id_map,
+                        );
+                        //most chunks have no ticks, so they don't get a row
+                        let ticks = level.get_saved_block_ticks(coord);
+                        if ticks.is_empty() {
+                            save_data.push(SaveCommand(
+                                level.id,
+                                ChunkTable::BlockTicks,
+                                coord,
+                                Vec::new(),
+                            ));
+                        } else {
+                            push_row(
+                                &mut save_data,
+                                level.id,
+                                ChunkTable::BlockTicks,
+                                coord,
+                                &ticks,
+                            );
+                        }
                         saved += 1;
                         ec.remove::<NeedsSaving>();
                     }
@@ -76,20 +215,125 @@ pub fn do_saving(
             }
         }
         if let Some(buffer) = level.get_buffer(&coord) {
-            save_data.push(SaveCommand(
+            push_row(
+                &mut save_data,
+                level.id,
                 ChunkTable::Buffers,
                 coord,
-                bincode::serialize(&ChunkSaveFormat::ids_only(
-                    (coord, buffer.value()),
-                    &block_query,
-                    &id_map,
-                ))
-                .unwrap(),
-            ));
+                &ChunkSaveFormat::ids_only((coord, buffer.value()), block_query, id_map),
+            );
         }
     }
-    if saved > 0 {
-        db.save_chunk_data(save_data);
-        debug!("Queued saving for {} chunks.", saved);
+    (save_data, diffs, saved)
+}
+
+//the chunk's terrain goes in `diffs` when they're being made, otherwise it's saved whole
+fn push_terrain(
+    save_data: &mut Vec<SaveCommand>,
+    diffs: Option<&mut Vec<TerrainToDiff>>,
+    level: &LevelData,
+    coord: ChunkCoord,
+    chunk: &ArrayChunk,
+    block_query: &Query<&BlockId>,
+    id_map: &LoadedToSavedIdMap<BlockId>,
+) {
+    let fluid_levels = level.get_saved_fluid_levels(coord);
+    let whole =
+        ChunkSaveFormat::palette_ids_only((chunk.position, &chunk.blocks), block_query, id_map)
+            .with_states(&chunk.states)
+            .with_fluid_levels(fluid_levels.clone());
+    match diffs {
+        Some(diffs) => {
+            let blocks = chunk.blocks.get_components(block_query);
+            let mut chunk_ids = LoadedToSavedIdMap::default();
+            for (_, id, _) in blocks.palette.iter() {
+                if let Some(saved) = id_map.get(id) {
+                    chunk_ids.insert(*id, saved);
+                }
+            }
+            diffs.push(TerrainToDiff {
+                level: level.id,
+                coord,
+                whole,
+                blocks,
+                states: chunk.states.clone(),
+                fluid_levels,
+                id_map: chunk_ids,
+            });
+        }
+        None => push_row(save_data, level.id, ChunkTable::Terrain, coord, &whole),
+    }
+}
+
+//a chunk's terrain, copied out so it can be diffed against the generator in the db task
+struct TerrainToDiff {
+    level: LevelId,
+    coord: ChunkCoord,
+    whole: ChunkSaveFormat,
+    blocks: BlockPalette<BlockId, BLOCKS_PER_CHUNK>,
+    states: BlockStates,
+    fluid_levels: Vec<(u16, u8)>,
+    //only the ids in `blocks`
+    id_map: LoadedToSavedIdMap<BlockId>,
+}
+
+impl TerrainToDiff {
+    //the diff, unless it would be longer than the whole chunk
+    fn encode(self, baseline: &TerrainBaseline) -> Result<SaveCommand, LevelDBErr> {
+        let diff = TerrainDiff::new(
+            &baseline.generate(self.coord),
+            &self.blocks,
+            &self.states,
+            &self.id_map,
+        )
+        .with_fluid_levels(self.fluid_levels);
+        if diff.changes.len() < self.whole.data.len() {
+            SaveCommand::encode(self.level, ChunkTable::TerrainDiff, self.coord, &diff)
+        } else {
+            SaveCommand::encode(self.level, ChunkTable::Terrain, self.coord, &self.whole)
+        }
+    }
+}
+
+//generating the baselines is slow, so the diffs are made in the db task, after the rest of the rows
+fn queue_chunk_saves(
+    db: &mut LevelDB,
+    save_data: Vec<SaveCommand>,
+    diffs: Vec<TerrainToDiff>,
+    baseline: Option<TerrainBaseline>,
+) {
+    db.save_chunk_data(save_data);
+    let Some(baseline) = baseline else {
+        return;
+    };
+    if !diffs.is_empty() {
+        db.save_chunk_data_with(move || {
+            diffs
+                .into_iter()
+                .filter_map(|terrain| {
+                    let coord = terrain.coord;
+                    terrain
+                        .encode(&baseline)
+                        .inspect_err(|e| {
+                            error!("Error serializing terrain at {:?}: {:?}", coord, e)
+                        })
+                        .ok()
+                })
+                .collect()
+        });
+    }
+}
+
+//rows that can't be serialized are logged and skipped, so the rest still get saved
+fn push_row<T: Serialize>(
+    save_data: &mut Vec<SaveCommand>,
+    level: LevelId,
+    table: ChunkTable,
+    coord: ChunkCoord,
+    value: &T,
+) {
+    match SaveCommand::encode(level, table, coord, value) {
+        Ok(row) => save_data.push(row),
+        Err(e) => error!("Error serializing {:?} at {:?}: {:?}", table, coord, e),
     }
 }
@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use bincode::ErrorKind;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use util::iterators::Volume;
+
+use crate::world::{
+    BlockBuffer, BlockChange, BlockCoord, BlockId, BlockName, BlockRegistry, BlockState, BlockType,
+    Id, LevelData,
+};
+
+//bump when the layout of Schematic changes, and handle the old versions in `from_bytes`
+//version 2 added block states
+pub const SCHEMATIC_VERSION: u32 = 2;
+
+//a region of blocks that can be saved to a file and placed in any level
+//uses block names instead of ids, so it doesn't depend on the registry or chunk boundaries it was made with
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Schematic {
+    pub version: u32,
+    pub size: IVec3,
+    //names of the blocks used in this schematic. data index 0 is empty, and index i is palette[i-1]
+    pub palette: Vec<BlockName>,
+    //run length encoded (data index, run), in the same order as Volume::iter (x, then y, then z)
+    pub data: Vec<(u16, u32)>,
+    //(index, state) for every block without the default state, indexed in the same order as `data`
+    pub states: Vec<(u32, BlockState)>,
+}
+
+//the layout before block states were saved
+#[derive(Deserialize)]
+struct SchematicV1 {
+    version: u32,
+    size: IVec3,
+    palette: Vec<BlockName>,
+    data: Vec<(u16, u32)>,
+}
+
+impl From<SchematicV1> for Schematic {
+    fn from(old: SchematicV1) -> Self {
+        Self {
+            version: SCHEMATIC_VERSION,
+            size: old.size,
+            palette: old.palette,
+            data: old.data,
+            states: Vec::new(),
+        }
+    }
+}
+
+//what `import` couldn't put in the buffer
+#[derive(Debug, Default)]
+pub struct SchematicImport {
+    //names that aren't in the registry. those blocks are skipped, but the rest are still added
+    pub missing: Vec<BlockName>,
+    //the states of the added blocks, where they go in the level. buffers only hold blocks, and placing a block
+    // resets its state, so these are set once the buffer is applied, the same way world edits set them
+    pub states: Vec<(BlockCoord, BlockState)>,
+}
+
+#[derive(Debug)]
+pub enum SchematicError {
+    Bincode(Box<ErrorKind>),
+    UnsupportedVersion(u32),
+    //runs don't add up to the size, refer to palette entries that don't exist, or states are outside the region
+    InvalidData,
+}
+
+impl SchematicImport {
+    //sets the states once the buffer has been applied. blocks in chunks that aren't loaded keep the default state
+    pub fn set_states(&self, level: &LevelData) {
+        for (pos, state) in self.states.iter() {
+            level.set_block_state_noupdate(*pos, *state);
+        }
+    }
+}
+
+impl std::fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchematicError::Bincode(e) => write!(f, "Couldn't read schematic: {}", e),
+            SchematicError::UnsupportedVersion(v) => {
+                write!(f, "Unsupported schematic version: {}", v)
+            }
+            SchematicError::InvalidData => write!(f, "Invalid schematic data"),
+        }
+    }
+}
+
+impl std::error::Error for SchematicError {}
+
+impl Schematic {
+    //blocks without a name and unloaded positions are saved as empty
+    pub fn export(level: &LevelData, volume: Volume, name_query: &Query<&BlockName>) -> Self {
+        let mut palette: Vec<BlockName> = Vec::new();
+        let mut states = Vec::new();
+        let data = volume
+            .iter()
+            .enumerate()
+            .map(|(i, pos)| match level.get_block(BlockCoord::from(pos)) {
+                Some(BlockType::Filled(e)) => match name_query.get(e) {
+                    Ok(name) => {
+                        let state = level.get_block_state(BlockCoord::from(pos));
+                        if !state.is_default() {
+                            states.push((i as u32, state));
+                        }
+                        match palette.iter().position(|n| n == name) {
+                            Some(idx) => idx as u16 + 1,
+                            None => {
+                                palette.push(name.clone());
+                                palette.len() as u16
+                            }
+                        }
+                    }
+                    Err(_) => 0,
+                },
+                _ => 0,
+            })
+            .dedup_with_count()
+            .map(|(run, idx)| (idx, run as u32))
+            .collect();
+        Self {
+            version: SCHEMATIC_VERSION,
+            size: volume.size(),
+            palette,
+            data,
+            states,
+        }
+    }
+
+    pub fn volume_at(&self, offset: BlockCoord) -> Volume {
+        let min = IVec3::from(offset);
+        Volume::new(min, min + self.size)
+    }
+
+    //adds the schematic's blocks to `buffer` with its min corner at `offset`
+    //empty blocks are included, so the schematic replaces whatever was there
+    //the states are returned to set after the buffer is applied, see SchematicImport
+    pub fn import(
+        &self,
+        offset: BlockCoord,
+        registry: &BlockRegistry,
+        buffer: &mut BlockBuffer<BlockId>,
+    ) -> Result<SchematicImport, SchematicError> {
+        self.validate()?;
+        let mut missing = Vec::new();
+        let ids: Vec<Option<BlockId>> = std::iter::once(Some(BlockId(Id::Empty)))
+            .chain(self.palette.iter().map(|name| {
+                let id = registry.id_map.get(name).copied();
+                if id.is_none() {
+                    missing.push(name.clone());
+                }
+                id
+            }))
+            .collect();
+        let mut positions = self.volume_at(offset).iter();
+        //where each block was added, so skipped and empty blocks don't get states
+        let mut added = Vec::new();
+        for (idx, run) in self.data.iter() {
+            for pos in positions.by_ref().take(*run as usize) {
+                let pos = BlockCoord::from(pos);
+                let id = ids[*idx as usize];
+                if let Some(id) = id {
+                    buffer.set(pos, BlockChange::Set(id));
+                }
+                added.push(id.is_some_and(|id| id != BlockId(Id::Empty)).then_some(pos));
+            }
+        }
+        let states = self
+            .states
+            .iter()
+            .filter_map(|(i, state)| Some((added[*i as usize]?, *state)))
+            .collect();
+        Ok(SchematicImport { missing, states })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SchematicError> {
+        bincode::serialize(self).map_err(SchematicError::Bincode)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SchematicError> {
+        //the version is the first field in every version, so it can be read without knowing the rest of the layout
+        let version: u32 = bincode::deserialize(bytes).map_err(SchematicError::Bincode)?;
+        let schematic = match version {
+            1 => bincode::deserialize::<SchematicV1>(bytes).map(Schematic::from),
+            SCHEMATIC_VERSION => bincode::deserialize::<Schematic>(bytes),
+            _ => return Err(SchematicError::UnsupportedVersion(version)),
+        }
+        .map_err(SchematicError::Bincode)?;
+        schematic.validate()?;
+        Ok(schematic)
+    }
+
+    fn validate(&self) -> Result<(), SchematicError> {
+        if self.size.min_element() < 0 {
+            return Err(SchematicError::InvalidData);
+        }
+        let expected = self.size.x as u64 * self.size.y as u64 * self.size.z as u64;
+        let total: u64 = self.data.iter().map(|(_, run)| *run as u64).sum();
+        if total != expected
+            || self
+                .data
+                .iter()
+                .any(|(idx, _)| *idx as usize > self.palette.len())
+            || self.states.iter().any(|(i, _)| *i as u64 >= expected)
+        {
+            return Err(SchematicError::InvalidData);
+        }
+        Ok(())
+    }
+}
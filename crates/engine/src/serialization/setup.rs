@@ -14,25 +14,36 @@ use crate::items::{
 };
 use crate::mesher::item_mesher::GenerateItemMeshEvent;
 use crate::mesher::{mesh_single_block, TerrainTexture};
+use crate::serialization::backup::{self, BackupReason, ScheduledBackups};
 use crate::serialization::db::{LevelDB, LevelDBErr};
-use crate::serialization::queries::{
-    CREATE_CHUNK_TABLE, CREATE_WORLD_INFO_TABLE, INSERT_WORLD_INFO, LOAD_WORLD_INFO,
+use crate::serialization::migrations::{self, MigrationRegistry};
+use crate::serialization::storage::StorageKind;
+use crate::serialization::{
+    LevelCreationInput, LevelOpenError, LoadingBlocks, LoadingItems, SavedLevelInfo,
 };
-use crate::serialization::{LevelCreationInput, LoadingBlocks, LoadingItems, SavedLevelInfo};
 use crate::util::string::Version;
+use crate::world::levels::{LevelId, Levels};
 use crate::world::settings::GraphicsSettings;
 use crate::world::{settings::Settings, Level};
 use crate::world::{
-    BlockId, BlockName, BlockNameIdMap, BlockRegistry, BlockResources, Id, LevelData,
+    BlockId, BlockName, BlockNameIdMap, BlockRegistry, BlockResources, BlockTags, Id, LevelData,
     LevelLoadState, NamedBlockMesh,
 };
+use crate::worldgen::{GeneratorSettings, GENERATOR_VERSION};
 use crate::GameState;
 
 use super::{
-    state, BlockTextureMap, ItemTextureMap, LoadedToSavedIdMap, SavedLevels, SavedToLoadedIdMap,
+    level_file_path, state, BlockTextureMap, ItemTextureMap, LevelSaveVersion, LoadedToSavedIdMap,
+    SavedLevels, SavedToLoadedIdMap, SAVE_VERSION,
 };
 
-const LEVEL_FILE_EXTENSION: &str = ".db";
+pub(super) const LEVEL_FILE_EXTENSION: &str = ".db";
+//the levels in the save besides the surface, which is made from the save's seed
+const LEVELS_KEY: &str = "levels";
+//the generator of each level in LEVELS_KEY, kept apart so saves from before levels had their own generators still load
+const GENERATORS_KEY: &str = "level_generators";
+//the generator version terrain diffs were made against, see check_generator_version
+const GENERATOR_VERSION_KEY: &str = "generator_version";
 
 pub struct SetupPlugin;
 
@@ -256,6 +267,7 @@ pub fn load_block_registry(
     texture_map: Res<BlockTextureMap>,
     loading_blocks: Query<(Entity, Option<&Children>), With<LoadingBlocks>>,
     block_name_query: Query<&BlockName>,
+    block_tags_query: Query<&BlockTags>,
     name_resolution_query: Query<&NamedBlockMesh>,
     block_resources: Option<Res<BlockResources>>,
 ) {
@@ -287,13 +299,22 @@ pub fn load_block_registry(
                     .remove::<NamedBlockMesh>();
             }
             match block_name_query.get(*child) {
-                Ok(name) => registry.add_basic(name.clone(), single_mesh, *child, &mut commands),
+                Ok(name) => {
+                    registry.add_basic(name.clone(), single_mesh, *child, &mut commands);
+                    if let Ok(BlockTags(tags)) = block_tags_query.get(*child) {
+                        registry.add_tags(registry.get_id(name), tags);
+                    }
+                }
                 Err(e) => warn!("Block doesn't have a name! Error {:?}", e),
             }
         }
     }
 
-    info!("Finished loading {} block types", registry.id_map.len());
+    info!(
+        "Finished loading {} block types with {} tags",
+        registry.id_map.len(),
+        registry.tag_ids.len()
+    );
     commands.insert_resource(BlockResources {
         registry: std::sync::Arc::new(registry),
     });
@@ -358,35 +379,43 @@ pub fn on_level_created(
     settings: Res<Settings>,
     block_resources: Res<BlockResources>,
     item_resources: Res<ItemResources>,
+    migrations: Res<MigrationRegistry>,
     mut next_state: ResMut<NextState<LevelLoadState>>,
     mut commands: Commands,
     mut next_game_state: ResMut<NextState<GameState>>,
 ) {
     info!("creating level...");
+    commands.remove_resource::<LevelOpenError>();
 
     fs::create_dir_all(settings.env_path).unwrap();
-    let db = LevelDB::new(
-        std::path::Path::new(settings.env_path)
-            .join(input.name.to_owned() + LEVEL_FILE_EXTENSION)
-            .as_path(),
-    );
+    let (kind, path) = level_storage(&settings, input.name);
+    let db = kind
+        .open(&path)
+        .map(|storage| LevelDB::with_storage(&path, storage));
     match db {
         Ok(mut db) => {
-            if let Some(err) = db.execute_command_sync(|sql| sql.execute(CREATE_CHUNK_TABLE, [])) {
-                error!("Error creating chunk table: {:?}", err);
-                next_game_state.set(GameState::Menu);
-                return;
-            }
-            if let Some(err) =
-                db.execute_command_sync(|sql| sql.execute(CREATE_WORLD_INFO_TABLE, []))
-            {
-                error!("Error creating world info table: {:?}", err);
-                next_game_state.set(GameState::Menu);
-                return;
+            db.set_codec(settings.chunk_codec);
+            match check_level_version(&mut db, &migrations, settings.backups_kept) {
+                Ok(saved_version) => commands.insert_resource(LevelSaveVersion(saved_version)),
+                Err(err) => {
+                    error!("Error checking level version: {:?}", err);
+                    fail_level_open(
+                        input.name,
+                        err.to_string(),
+                        &mut commands,
+                        &mut next_game_state,
+                    );
+                    return;
+                }
             }
-            if let Err(err) = check_level_version(&mut db) {
-                error!("Error checking level version: {:?}", err);
-                next_game_state.set(GameState::Menu);
+            if let Err(err) = check_generator_version(&mut db, settings.save_terrain_diffs) {
+                error!("Error checking generator version: {:?}", err);
+                fail_level_open(
+                    input.name,
+                    err.to_string(),
+                    &mut commands,
+                    &mut next_game_state,
+                );
                 return;
             }
             load_block_palette(&mut db, &mut commands, &block_resources.registry);
@@ -394,104 +423,203 @@ pub fn on_level_created(
             let default_seed = input.seed.unwrap_or(rand::thread_rng().next_u64());
             match load_or_set_level_seed(&mut db, default_seed) {
                 Ok(seed) => {
-                    commands.insert_resource(Level(Arc::new(LevelData::new(input.name, seed))));
+                    let level = Level(Arc::new(LevelData::new(input.name, LevelId::SURFACE, seed)));
+                    let mut levels = Levels::new(&level);
+                    load_saved_levels(&mut db, &mut levels);
+                    commands.insert_resource(levels);
+                    commands.insert_resource(level);
                 }
                 Err(err) => {
                     error!("Error reading level seed: {:?}", err);
-                    next_game_state.set(GameState::Menu);
+                    fail_level_open(
+                        input.name,
+                        err.to_string(),
+                        &mut commands,
+                        &mut next_game_state,
+                    );
                     return;
                 }
             }
 
             commands.insert_resource(db);
+            commands.insert_resource(ScheduledBackups::default());
             next_state.set(LevelLoadState::Loading);
             info!("in state loading!");
         }
         Err(e) => {
             error!("couldn't open db {}", e);
-            next_game_state.set(GameState::Menu);
+            fail_level_open(
+                input.name,
+                e.to_string(),
+                &mut commands,
+                &mut next_game_state,
+            );
         }
     }
 }
-fn check_level_version(db: &mut LevelDB) -> Result<(), LevelDBErr> {
-    match db.execute_query_sync(LOAD_WORLD_INFO, rusqlite::params!["version"], |row| {
-        row.get::<_, Vec<u8>>(0)
-    }) {
-        Ok(data) => match bincode::deserialize::<&str>(&data) {
-            Ok(version) => {
-                let my_version = Version::game_version();
-                let saved_version = Version::from(version);
-                info!(
-                    "saved version of level is {:?}, my version is {:?}",
-                    saved_version, my_version
+//levels that already exist are opened the way they were saved, new ones are made with Settings::level_storage
+fn level_storage(settings: &Settings, name: &str) -> (StorageKind, PathBuf) {
+    let path = |kind: StorageKind| {
+        level_file_path(settings.env_path, name).with_extension(kind.extension())
+    };
+    StorageKind::ALL
+        .into_iter()
+        .filter(|kind| *kind != StorageKind::Memory)
+        .find(|kind| path(*kind).exists())
+        .map_or(
+            (settings.level_storage, path(settings.level_storage)),
+            |kind| (kind, path(kind)),
+        )
+}
+
+//goes back to the world select screen, which shows the error
+fn fail_level_open(
+    level: &'static str,
+    message: String,
+    commands: &mut Commands,
+    next_game_state: &mut NextState<GameState>,
+) {
+    commands.insert_resource(LevelOpenError { level, message });
+    next_game_state.set(GameState::Menu);
+}
+
+//upgrades older saves with the registered migrations and stamps the save with the current version
+//the save is backed up before it's migrated. returns the version the save had, new saves have the current one
+fn check_level_version(
+    db: &mut LevelDB,
+    migrations: &MigrationRegistry,
+    backups_kept: usize,
+) -> Result<Version, LevelDBErr> {
+    let my_version = Version::from(SAVE_VERSION);
+    let saved_version = match db.load_world_info::<String>("version") {
+        Ok(Some(version)) => {
+            let saved_version = Version::from(version.as_str());
+            info!(
+                "saved version of level is {:?}, my version is {:?}",
+                saved_version, my_version
+            );
+            if saved_version > my_version && !my_version.game_compatible(&saved_version) {
+                error!(
+                    "Opening a newer world version than I can handle: {:?}",
+                    version
                 );
-                if saved_version > my_version && !my_version.game_compatible(&saved_version) {
-                    error!(
-                        "Opening a newer world version than I can handle: {:?}",
-                        version
-                    );
-                    return Err(LevelDBErr::NewWorldVersion);
-                }
+                return Err(LevelDBErr::NewWorldVersion);
             }
-            Err(e) => {
-                error!("Corrupt world version: {:?}", e);
-                return Err(LevelDBErr::Bincode(e));
+            let steps = migrations.plan(&saved_version, &my_version)?;
+            if !steps.is_empty() {
+                //migrations are written in sql, so levels that aren't saved in sqlite can't be upgraded.
+                // they're turned away before anything is backed up or changed
+                let mut conn = match db.connection() {
+                    Err(LevelDBErr::Unsupported(_)) => {
+                        return Err(LevelDBErr::Unsupported("upgrading from an older version"))
+                    }
+                    conn => conn?,
+                };
+                let dir = backup::backup_dir(db.path());
+                let backup = backup::snapshot(&conn, &dir, BackupReason::Migration)?;
+                info!("Backed up level to {} before migrating", backup.display());
+                migrations::run_migrations(&mut conn, &steps)?;
+                backup::rotate_backups(&dir, backups_kept);
             }
-        },
-        Err(LevelDBErr::Sqlite(rusqlite::Error::QueryReturnedNoRows)) => {} //this is fine - new worlds have no version
+            saved_version
+        }
+        Ok(None) => my_version, //this is fine - new worlds have no version
         Err(e) => {
             error!("Error getting world version from db: {:?}", e);
             return Err(e);
         }
+    };
+    db.storage().save_world_info(vec![(
+        "version".to_string(),
+        bincode::serialize(SAVE_VERSION).unwrap(),
+    )])?;
+    Ok(saved_version)
+}
+
+//levels with terrain diffs can only be opened with the generator the diffs were made against
+//levels that have never saved diffs aren't stamped, so they open with any generator
+fn check_generator_version(db: &mut LevelDB, save_terrain_diffs: bool) -> Result<(), LevelDBErr> {
+    match db.load_world_info::<u32>(GENERATOR_VERSION_KEY)? {
+        Some(version) if version != GENERATOR_VERSION => Err(LevelDBErr::GeneratorChanged(version)),
+        Some(_) => Ok(()),
+        None if save_terrain_diffs => db.storage().save_world_info(vec![(
+            GENERATOR_VERSION_KEY.to_string(),
+            bincode::serialize(&GENERATOR_VERSION).unwrap(),
+        )]),
+        None => Ok(()),
+    }
+}
+
+//recreates the levels that were made in this save. their seeds come from the surface's, so only their names and
+// generators are saved. levels saved before they had their own generators get the surface's
+fn load_saved_levels(db: &mut LevelDB, levels: &mut Levels) {
+    let generators = db.load_world_info::<Vec<(LevelId, GeneratorSettings)>>(GENERATORS_KEY);
+    let generators = match generators {
+        Ok(generators) => generators
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<HashMap<_, _>>(),
+        Err(e) => {
+            error!("Error loading the save's level generators: {:?}", e);
+            HashMap::new()
+        }
+    };
+    match db.load_world_info::<Vec<(LevelId, String)>>(LEVELS_KEY) {
+        Ok(saved) => {
+            for (id, name) in saved.unwrap_or_default() {
+                let generator = generators.get(&id).cloned().unwrap_or_default();
+                levels.create(id, name.leak(), generator);
+            }
+        }
+        Err(e) => error!("Error loading the save's levels: {:?}", e),
     }
-    if let Some(err) = db.execute_command_sync(|sql| {
-        sql.execute(
-            INSERT_WORLD_INFO,
-            rusqlite::params![
-                "version",
-                bincode::serialize(env!("CARGO_PKG_VERSION")).unwrap()
-            ],
-        )
-    }) {
-        return Err(err);
+}
+
+//writes the levels in the save whenever one is added
+pub(super) fn save_levels(levels: Res<Levels>, mut db: ResMut<LevelDB>) {
+    let saved = levels
+        .iter()
+        .filter(|level| level.id != LevelId::SURFACE)
+        .sorted_by_key(|level| level.id)
+        .collect::<Vec<_>>();
+    let names = saved
+        .iter()
+        .map(|level| (level.id, level.name.to_string()))
+        .collect::<Vec<_>>();
+    let generators = saved
+        .iter()
+        .map(|level| (level.id, level.generator.clone()))
+        .collect::<Vec<_>>();
+    match bincode::serialize(&generators) {
+        Ok(data) => db.save_world_info(GENERATORS_KEY, data),
+        Err(e) => error!("Error saving the save's level generators: {:?}", e),
+    }
+    match bincode::serialize(&names) {
+        Ok(data) => db.save_world_info(LEVELS_KEY, data),
+        Err(e) => error!("Error saving the save's levels: {:?}", e),
     }
-    Ok(())
 }
 
 // returns the active the seed of the level.
 // this will seed in the world info table if present, otherwise, default seed.
 fn load_or_set_level_seed(db: &mut LevelDB, default_seed: u64) -> Result<u64, LevelDBErr> {
     const SEED_KEY: &str = "seed";
-    match db.execute_query_sync(LOAD_WORLD_INFO, rusqlite::params![SEED_KEY], |row| {
-        row.get::<_, Vec<u8>>(0)
-    }) {
-        Ok(data) => match bincode::deserialize::<u64>(&data) {
-            Ok(seed) => {
-                info!("loaded saved seed: {}", seed);
-                Ok(seed)
-            }
-            Err(e) => {
-                error!("Corrupt world seed: {:?}", e);
-                Err(LevelDBErr::Bincode(e))
-            }
-        },
-        Err(LevelDBErr::Sqlite(rusqlite::Error::QueryReturnedNoRows)) => {
+    match db.load_world_info::<u64>(SEED_KEY) {
+        Ok(Some(seed)) => {
+            info!("loaded saved seed: {}", seed);
+            Ok(seed)
+        }
+        Ok(None) => {
             //world does not have a set seed, we need to set it to the default seed.
-            match db.execute_command_sync(|sql| {
-                sql.execute(
-                    INSERT_WORLD_INFO,
-                    rusqlite::params![SEED_KEY, bincode::serialize(&default_seed).unwrap()],
-                )
-            }) {
-                None => {
-                    info!(
-                        "level doesn't contain a saved seed, set it to {}",
-                        default_seed
-                    );
-                    Ok(default_seed)
-                }
-                Some(e) => Err(e),
-            }
+            db.storage().save_world_info(vec![(
+                SEED_KEY.to_string(),
+                bincode::serialize(&default_seed).unwrap(),
+            )])?;
+            info!(
+                "level doesn't contain a saved seed, set it to {}",
+                default_seed
+            );
+            Ok(default_seed)
         }
         Err(e) => {
             error!("Error getting seed from db: {:?}", e);
@@ -501,10 +629,8 @@ fn load_or_set_level_seed(db: &mut LevelDB, default_seed: u64) -> Result<u64, Le
 }
 
 fn load_block_palette(db: &mut LevelDB, commands: &mut Commands, registry: &BlockRegistry) {
-    match db.execute_query_sync(LOAD_WORLD_INFO, rusqlite::params!["block_palette"], |row| {
-        row.get(0)
-    }) {
-        Ok(data) => {
+    match db.storage().load_world_info("block_palette") {
+        Ok(Some(data)) => {
             match create_block_id_maps_from_palette(&data, registry) {
                 Some((mut saved_to_loaded, mut loaded_to_saved)) => {
                     //if we have new blocks that were not in the palette before, add them
@@ -513,12 +639,10 @@ fn load_block_palette(db: &mut LevelDB, commands: &mut Commands, registry: &Bloc
                         &mut saved_to_loaded,
                         &mut loaded_to_saved,
                     );
-                    if let Some(err) = db.execute_command_sync(|sql| {
-                        sql.execute(
-                            INSERT_WORLD_INFO,
-                            rusqlite::params!["block_palette", palette],
-                        )
-                    }) {
+                    if let Err(err) = db
+                        .storage()
+                        .save_world_info(vec![("block_palette".to_string(), palette)])
+                    {
                         error!("Error updating block palette! {:?}", err);
                         return;
                     }
@@ -531,7 +655,7 @@ fn load_block_palette(db: &mut LevelDB, commands: &mut Commands, registry: &Bloc
                 }
             }
         }
-        Err(LevelDBErr::Sqlite(rusqlite::Error::QueryReturnedNoRows)) => {
+        Ok(None) => {
             //there is no palette saved, so we create one using only our current map.
             //This happens when a new world is created
             let mut saved_to_loaded = SavedToLoadedIdMap::default();
@@ -541,12 +665,10 @@ fn load_block_palette(db: &mut LevelDB, commands: &mut Commands, registry: &Bloc
                 &mut saved_to_loaded,
                 &mut loaded_to_saved,
             );
-            if let Some(err) = db.execute_command_sync(|sql| {
-                sql.execute(
-                    INSERT_WORLD_INFO,
-                    rusqlite::params!["block_palette", palette],
-                )
-            }) {
+            if let Err(err) = db
+                .storage()
+                .save_world_info(vec![("block_palette".to_string(), palette)])
+            {
                 error!("Error creating block palette! {:?}", err);
                 return;
             }
@@ -559,9 +681,8 @@ fn load_block_palette(db: &mut LevelDB, commands: &mut Commands, registry: &Bloc
     }
 }
 
-#[allow(clippy::ptr_arg)] //must be vec, sql cannot retrieve [u8]
 fn create_block_id_maps_from_palette(
-    data: &Vec<u8>,
+    data: &[u8],
     registry: &BlockRegistry,
 ) -> Option<(SavedToLoadedIdMap<BlockId>, LoadedToSavedIdMap<BlockId>)> {
     match bincode::deserialize::<BlockNameIdMap>(data) {
@@ -617,10 +738,8 @@ fn create_palette_from_block_id_map(
 }
 
 fn load_item_palette(db: &mut LevelDB, commands: &mut Commands, registry: &ItemRegistry) {
-    match db.execute_query_sync(LOAD_WORLD_INFO, rusqlite::params!["item_palette"], |row| {
-        row.get(0)
-    }) {
-        Ok(data) => {
+    match db.storage().load_world_info("item_palette") {
+        Ok(Some(data)) => {
             match create_item_id_maps_from_palette(&data, registry) {
                 Some((mut saved_to_loaded, mut loaded_to_saved)) => {
                     //if we have new blocks that were not in the palette before, add them
@@ -629,12 +748,10 @@ fn load_item_palette(db: &mut LevelDB, commands: &mut Commands, registry: &ItemR
                         &mut saved_to_loaded,
                         &mut loaded_to_saved,
                     );
-                    if let Some(err) = db.execute_command_sync(|sql| {
-                        sql.execute(
-                            INSERT_WORLD_INFO,
-                            rusqlite::params!["item_palette", palette],
-                        )
-                    }) {
+                    if let Err(err) = db
+                        .storage()
+                        .save_world_info(vec![("item_palette".to_string(), palette)])
+                    {
                         error!("Error updating item palette! {:?}", err);
                         return;
                     }
@@ -647,7 +764,7 @@ fn load_item_palette(db: &mut LevelDB, commands: &mut Commands, registry: &ItemR
                 }
             }
         }
-        Err(LevelDBErr::Sqlite(rusqlite::Error::QueryReturnedNoRows)) => {
+        Ok(None) => {
             //there is no palette saved, so we create one using only our current map.
             //This happens when a new world is created
             let mut saved_to_loaded = SavedToLoadedIdMap::default();
@@ -657,12 +774,10 @@ fn load_item_palette(db: &mut LevelDB, commands: &mut Commands, registry: &ItemR
                 &mut saved_to_loaded,
                 &mut loaded_to_saved,
             );
-            if let Some(err) = db.execute_command_sync(|sql| {
-                sql.execute(
-                    INSERT_WORLD_INFO,
-                    rusqlite::params!["item_palette", palette],
-                )
-            }) {
+            if let Err(err) = db
+                .storage()
+                .save_world_info(vec![("item_palette".to_string(), palette)])
+            {
                 error!("Error creating item palette! {:?}", err);
                 return;
             }
@@ -675,9 +790,8 @@ fn load_item_palette(db: &mut LevelDB, commands: &mut Commands, registry: &ItemR
     }
 }
 
-#[allow(clippy::ptr_arg)] //must be vec, sql cannot retrieve [u8]
 fn create_item_id_maps_from_palette(
-    data: &Vec<u8>,
+    data: &[u8],
     registry: &ItemRegistry,
 ) -> Option<(SavedToLoadedIdMap<ItemId>, LoadedToSavedIdMap<ItemId>)> {
     match bincode::deserialize::<ItemNameIdMap>(data) {
@@ -733,7 +847,8 @@ fn create_palette_from_item_id_map(
 }
 
 fn load_saved_level_list(settings: Res<Settings>, mut commands: Commands) {
-    let level_name_regex = regex::Regex::new("(.+)\\.db").unwrap();
+    //sqlite levels are files, region levels are folders
+    let level_name_regex = regex::Regex::new("^(.+)\\.(db|regions)$").unwrap();
 
     let levels = match std::fs::read_dir(settings.env_path) {
         Ok(paths) => SavedLevels(
@@ -0,0 +1,101 @@
+use std::sync::Mutex;
+
+use dashmap::DashMap;
+
+use super::{ChunkWrite, LevelStorage, StoredRow};
+use crate::{
+    serialization::db::{ChunkQuarantinedEvent, ChunkTable, LevelDBErr},
+    world::{chunk::ChunkCoord, levels::LevelId},
+};
+
+//keeps the level in memory, so nothing is left behind when it's closed. for tests, and servers that don't keep their world
+#[derive(Default)]
+pub struct MemoryStorage {
+    //keyed by tid and coord, like the sqlite chunk table
+    chunks: DashMap<(i32, ChunkCoord), StoredRow, ahash::RandomState>,
+    world_info: DashMap<String, Vec<u8>, ahash::RandomState>,
+    players: DashMap<String, Vec<u8>, ahash::RandomState>,
+    quarantined: Mutex<Vec<(ChunkQuarantinedEvent, StoredRow)>>,
+}
+
+impl MemoryStorage {
+    //every row that's been quarantined, oldest first
+    pub fn quarantined(&self) -> Vec<(ChunkQuarantinedEvent, StoredRow)> {
+        self.quarantined.lock().unwrap().clone()
+    }
+}
+
+impl LevelStorage for MemoryStorage {
+    fn write_chunks(&self, writes: Vec<ChunkWrite>) -> Result<(), LevelDBErr> {
+        for ChunkWrite {
+            level,
+            table,
+            coord,
+            row,
+        } in writes
+        {
+            let key = (table.tid(level), coord);
+            match row {
+                Some(row) => {
+                    self.chunks.insert(key, row);
+                }
+                None => {
+                    self.chunks.remove(&key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_chunk(
+        &self,
+        level: LevelId,
+        table: ChunkTable,
+        coord: ChunkCoord,
+    ) -> Result<Option<StoredRow>, LevelDBErr> {
+        Ok(self
+            .chunks
+            .get(&(table.tid(level), coord))
+            .map(|row| row.clone()))
+    }
+
+    fn quarantine_chunk(
+        &self,
+        chunk: &ChunkQuarantinedEvent,
+        row: &StoredRow,
+    ) -> Result<(), LevelDBErr> {
+        self.quarantined
+            .lock()
+            .unwrap()
+            .push((chunk.clone(), row.clone()));
+        self.chunks
+            .remove_if(&(chunk.table.tid(chunk.level), chunk.coord), |_, stored| {
+                stored.data == row.data
+            });
+        Ok(())
+    }
+
+    fn load_world_info(&self, key: &str) -> Result<Option<Vec<u8>>, LevelDBErr> {
+        Ok(self.world_info.get(key).map(|value| value.clone()))
+    }
+
+    fn save_world_info(&self, rows: Vec<(String, Vec<u8>)>) -> Result<(), LevelDBErr> {
+        for (key, value) in rows {
+            self.world_info.insert(key, value);
+        }
+        Ok(())
+    }
+
+    fn load_player(&self, username: &str) -> Result<Option<Vec<u8>>, LevelDBErr> {
+        Ok(self.players.get(username).map(|data| data.clone()))
+    }
+
+    fn save_player(&self, username: &str, data: Vec<u8>) -> Result<(), LevelDBErr> {
+        self.players.insert(username.to_string(), data);
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), LevelDBErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use std::{path::Path, sync::Arc};
+
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+
+use super::db::{ChunkQuarantinedEvent, ChunkTable, LevelDBErr};
+use crate::world::{chunk::ChunkCoord, levels::LevelId};
+
+pub mod memory;
+pub mod region;
+pub mod sqlite;
+
+pub use memory::MemoryStorage;
+pub use region::RegionStorage;
+pub use sqlite::SqliteStorage;
+
+//where a level's chunks, world_info and players are kept. LevelDB queues up work and runs it against one of these
+//the methods are called from the db's task, one at a time, so they can block
+pub trait LevelStorage {
+    //writes the rows in order. a write without a row deletes the chunk's row in that table
+    fn write_chunks(&self, writes: Vec<ChunkWrite>) -> Result<(), LevelDBErr>;
+    fn read_chunk(
+        &self,
+        level: LevelId,
+        table: ChunkTable,
+        coord: ChunkCoord,
+    ) -> Result<Option<StoredRow>, LevelDBErr>;
+    //keeps `row` somewhere it won't be loaded from, then deletes the chunk's row if it still holds `row.data`
+    fn quarantine_chunk(
+        &self,
+        chunk: &ChunkQuarantinedEvent,
+        row: &StoredRow,
+    ) -> Result<(), LevelDBErr>;
+    fn load_world_info(&self, key: &str) -> Result<Option<Vec<u8>>, LevelDBErr>;
+    fn save_world_info(&self, rows: Vec<(String, Vec<u8>)>) -> Result<(), LevelDBErr>;
+    fn load_player(&self, username: &str) -> Result<Option<Vec<u8>>, LevelDBErr>;
+    fn save_player(&self, username: &str, data: Vec<u8>) -> Result<(), LevelDBErr>;
+    //makes sure everything written so far is on disk
+    fn flush(&self) -> Result<(), LevelDBErr>;
+    //for work that's written in sql, like migrations, backups and worldtool. only sqlite levels have it
+    fn connection(&self) -> Result<PooledConnection<SqliteConnectionManager>, LevelDBErr> {
+        Err(LevelDBErr::Unsupported("sql"))
+    }
+}
+
+pub type SharedStorage = Arc<dyn LevelStorage + Send + Sync>;
+
+//a chunk row the way it's stored: the encoded blob, the id of the codec it was encoded with and the blob's checksum
+//rows saved before there were checksums don't have one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoredRow {
+    pub data: Vec<u8>,
+    pub codec: i64,
+    pub checksum: Option<u32>,
+}
+
+pub struct ChunkWrite {
+    pub level: LevelId,
+    pub table: ChunkTable,
+    pub coord: ChunkCoord,
+    pub row: Option<StoredRow>,
+}
+
+//which storage new levels are made with, see Settings::level_storage
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum StorageKind {
+    //one sqlite file
+    #[default]
+    Sqlite,
+    //a folder of region files, see RegionStorage
+    Region,
+    //nothing is written to disk, the level is gone when it's closed
+    Memory,
+}
+
+impl StorageKind {
+    pub const ALL: [StorageKind; 3] = [
+        StorageKind::Sqlite,
+        StorageKind::Region,
+        StorageKind::Memory,
+    ];
+
+    //added to the level's name to get the file or folder it's saved in
+    pub fn extension(self) -> &'static str {
+        match self {
+            StorageKind::Sqlite => "db",
+            StorageKind::Region => "regions",
+            StorageKind::Memory => "",
+        }
+    }
+
+    //opens the level saved at `path`, making it if it doesn't exist
+    pub fn open(self, path: &Path) -> Result<SharedStorage, LevelDBErr> {
+        Ok(match self {
+            StorageKind::Sqlite => Arc::new(SqliteStorage::open(path)?),
+            StorageKind::Region => Arc::new(RegionStorage::open(path)?),
+            StorageKind::Memory => Arc::new(MemoryStorage::default()),
+        })
+    }
+}
+
+//when a row was quarantined, in seconds since the unix epoch
+fn unix_time() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
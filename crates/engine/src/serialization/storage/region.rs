@@ -0,0 +1,442 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use bevy::utils::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+use super::{unix_time, ChunkWrite, LevelStorage, StoredRow};
+use crate::{
+    serialization::db::{ChunkQuarantinedEvent, ChunkTable, LevelDBErr},
+    world::{chunk::ChunkCoord, levels::LevelId},
+};
+
+//chunks are grouped into cubes this many chunks across, with a file for each cube in each table
+const REGION_SIZE: i32 = 8;
+const REGION_CHUNKS: usize = (REGION_SIZE * REGION_SIZE * REGION_SIZE) as usize;
+const SLOT_LEN: usize = 32;
+const HEADER_LEN: u64 = (REGION_CHUNKS * SLOT_LEN) as u64;
+//set in a slot's flags if the row has a checksum
+const HAS_CHECKSUM: u32 = 1;
+//regions are only compacted once there's at least this much to reclaim
+const MIN_COMPACT_GARBAGE: u64 = 1024 * 1024;
+//regions kept open at once. each one holds a file handle and its header
+const MAX_OPEN_REGIONS: usize = 64;
+
+const WORLD_INFO_FILE: &str = "world_info";
+const PLAYERS_FILE: &str = "players";
+const QUARANTINE_FILE: &str = "quarantine";
+
+//the level is a folder of flat files:
+// <tid>/<x>.<y>.<z>.region  the chunks in a region, see Region
+// world_info, players       every row, rewritten whole when one changes
+// quarantine                quarantined rows, one after another
+pub struct RegionStorage {
+    dir: PathBuf,
+    //locked while files are read or changed, so only one call uses them at a time
+    files: Mutex<Files>,
+}
+
+#[derive(Default)]
+struct Files {
+    //files written since the last flush
+    unsynced: HashSet<PathBuf>,
+    //regions that have been opened, so their headers aren't read again for every chunk
+    open: HashMap<PathBuf, Region>,
+}
+
+impl Files {
+    //the region at `path`, opening it if it isn't already. None if nothing has been saved in it and `create` is false
+    fn region(&mut self, path: &Path, create: bool) -> io::Result<Option<&mut Region>> {
+        if !self.open.contains_key(path) {
+            let region = match create {
+                true => Region::open_or_create(path)?,
+                false => match Region::open(path)? {
+                    Some(region) => region,
+                    None => return Ok(None),
+                },
+            };
+            if self.open.len() >= MAX_OPEN_REGIONS {
+                //chunks are loaded all over the place, so any region is as good to close as another
+                if let Some(closed) = self.open.keys().next().cloned() {
+                    self.open.remove(&closed);
+                }
+            }
+            self.open.insert(path.to_path_buf(), region);
+        }
+        Ok(self.open.get_mut(path))
+    }
+}
+
+//a quarantined row in the quarantine file, with what the sqlite quarantine table keeps
+#[derive(Serialize, Deserialize)]
+struct QuarantinedRow {
+    tid: i32,
+    coord: ChunkCoord,
+    data: Vec<u8>,
+    codec: i64,
+    checksum: Option<u32>,
+    reason: String,
+    time: i64,
+}
+
+impl RegionStorage {
+    //opens the level folder at `dir`, making it if it doesn't exist
+    pub fn open(dir: &Path) -> Result<Self, LevelDBErr> {
+        fs::create_dir_all(dir).map_err(LevelDBErr::Io)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            files: Mutex::new(Files::default()),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Files> {
+        self.files.lock().unwrap()
+    }
+
+    //the region file the chunk is in, and the chunk's slot in it
+    fn locate(&self, level: LevelId, table: ChunkTable, coord: ChunkCoord) -> (PathBuf, usize) {
+        let size = REGION_SIZE as usize;
+        let local = |c: i32| c.rem_euclid(REGION_SIZE) as usize;
+        let path = self.dir.join(table.tid(level).to_string()).join(format!(
+            "{}.{}.{}.region",
+            coord.x.div_euclid(REGION_SIZE),
+            coord.y.div_euclid(REGION_SIZE),
+            coord.z.div_euclid(REGION_SIZE)
+        ));
+        (
+            path,
+            (local(coord.x) * size + local(coord.y)) * size + local(coord.z),
+        )
+    }
+
+    fn write_regions(&self, writes: Vec<ChunkWrite>, files: &mut Files) -> io::Result<()> {
+        let mut written = HashSet::new();
+        for ChunkWrite {
+            level,
+            table,
+            coord,
+            row,
+        } in writes
+        {
+            let (path, idx) = self.locate(level, table, coord);
+            //there's nothing to delete in a region that was never made
+            let Some(region) = files.region(&path, row.is_some())? else {
+                continue;
+            };
+            region.write(idx, row.as_ref())?;
+            written.insert(path);
+        }
+        for path in written {
+            if let Some(region) = files.open.remove(&path) {
+                files.open.insert(path.clone(), region.compact(&path)?);
+            }
+            files.unsynced.insert(path);
+        }
+        Ok(())
+    }
+
+    fn quarantine_region_row(
+        &self,
+        chunk: &ChunkQuarantinedEvent,
+        row: &StoredRow,
+        record: &[u8],
+        files: &mut Files,
+    ) -> io::Result<()> {
+        let quarantine = self.dir.join(QUARANTINE_FILE);
+        File::options()
+            .append(true)
+            .create(true)
+            .open(&quarantine)?
+            .write_all(record)?;
+        files.unsynced.insert(quarantine);
+        let (path, idx) = self.locate(chunk.level, chunk.table, chunk.coord);
+        if let Some(region) = files.region(&path, false)? {
+            if region
+                .read(idx)?
+                .is_some_and(|stored| stored.data == row.data)
+            {
+                region.write(idx, None)?;
+                files.unsynced.insert(path);
+            }
+        }
+        Ok(())
+    }
+
+    fn load_map(&self, name: &str) -> Result<BTreeMap<String, Vec<u8>>, LevelDBErr> {
+        match fs::read(self.dir.join(name)) {
+            Ok(data) => bincode::deserialize(&data).map_err(LevelDBErr::Bincode),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(LevelDBErr::Io(e)),
+        }
+    }
+
+    fn update_map(
+        &self,
+        name: &str,
+        f: impl FnOnce(&mut BTreeMap<String, Vec<u8>>),
+    ) -> Result<(), LevelDBErr> {
+        let _lock = self.lock();
+        let mut map = self.load_map(name)?;
+        f(&mut map);
+        let data = bincode::serialize(&map).map_err(LevelDBErr::Bincode)?;
+        replace_file(&self.dir.join(name), &data).map_err(LevelDBErr::Io)
+    }
+}
+
+impl LevelStorage for RegionStorage {
+    fn write_chunks(&self, writes: Vec<ChunkWrite>) -> Result<(), LevelDBErr> {
+        let mut files = self.lock();
+        self.write_regions(writes, &mut files)
+            .map_err(LevelDBErr::Io)
+    }
+
+    fn read_chunk(
+        &self,
+        level: LevelId,
+        table: ChunkTable,
+        coord: ChunkCoord,
+    ) -> Result<Option<StoredRow>, LevelDBErr> {
+        let (path, idx) = self.locate(level, table, coord);
+        let mut files = self.lock();
+        match files.region(&path, false) {
+            Ok(Some(region)) => region.read(idx),
+            Ok(None) => Ok(None),
+            Err(e) => Err(e),
+        }
+        .map_err(LevelDBErr::Io)
+    }
+
+    //the row is added to the quarantine file
+    fn quarantine_chunk(
+        &self,
+        chunk: &ChunkQuarantinedEvent,
+        row: &StoredRow,
+    ) -> Result<(), LevelDBErr> {
+        let record = bincode::serialize(&QuarantinedRow {
+            tid: chunk.table.tid(chunk.level),
+            coord: chunk.coord,
+            data: row.data.clone(),
+            codec: row.codec,
+            checksum: row.checksum,
+            reason: chunk.reason.clone(),
+            time: unix_time(),
+        })
+        .map_err(LevelDBErr::Bincode)?;
+        let mut files = self.lock();
+        self.quarantine_region_row(chunk, row, &record, &mut files)
+            .map_err(LevelDBErr::Io)
+    }
+
+    fn load_world_info(&self, key: &str) -> Result<Option<Vec<u8>>, LevelDBErr> {
+        Ok(self.load_map(WORLD_INFO_FILE)?.remove(key))
+    }
+
+    fn save_world_info(&self, rows: Vec<(String, Vec<u8>)>) -> Result<(), LevelDBErr> {
+        self.update_map(WORLD_INFO_FILE, |map| map.extend(rows))
+    }
+
+    fn load_player(&self, username: &str) -> Result<Option<Vec<u8>>, LevelDBErr> {
+        Ok(self.load_map(PLAYERS_FILE)?.remove(username))
+    }
+
+    fn save_player(&self, username: &str, data: Vec<u8>) -> Result<(), LevelDBErr> {
+        self.update_map(PLAYERS_FILE, |map| {
+            map.insert(username.to_string(), data);
+        })
+    }
+
+    fn flush(&self) -> Result<(), LevelDBErr> {
+        let mut files = self.lock();
+        for path in std::mem::take(&mut files.unsynced) {
+            match files.open.get(&path) {
+                Some(region) => region.file.sync_all(),
+                None => File::options()
+                    .write(true)
+                    .open(&path)
+                    .and_then(|file| file.sync_all()),
+            }
+            .map_err(LevelDBErr::Io)?;
+        }
+        Ok(())
+    }
+}
+
+//where a chunk's row is in its region file. a slot with a zero offset is empty, rows always come after the header
+#[derive(Clone, Copy, Default)]
+struct Slot {
+    offset: u64,
+    codec: i64,
+    len: u32,
+    checksum: u32,
+    flags: u32,
+}
+
+impl Slot {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        Self {
+            offset: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            codec: i64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            len: u32_at(16),
+            checksum: u32_at(20),
+            flags: u32_at(24),
+        }
+    }
+
+    //the last 4 bytes are unused
+    fn to_bytes(self) -> [u8; SLOT_LEN] {
+        let mut bytes = [0; SLOT_LEN];
+        bytes[0..8].copy_from_slice(&self.offset.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.codec.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.len.to_le_bytes());
+        bytes[20..24].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes[24..28].copy_from_slice(&self.flags.to_le_bytes());
+        bytes
+    }
+}
+
+//a region file is a header with a slot for each chunk in the region, then the rows in the order they were written
+//saving a chunk appends its row and then points its slot at it, so a save that's cut short leaves the old row.
+// the rows left behind are cleared out by compact
+struct Region {
+    file: File,
+    slots: Vec<Slot>,
+}
+
+impl Region {
+    //None if nothing has been saved in the region
+    fn open(path: &Path) -> io::Result<Option<Self>> {
+        match File::options().read(true).write(true).open(path) {
+            Ok(file) => Self::read_header(file).map(Some),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn open_or_create(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        Self::read_header(file)
+    }
+
+    //the part of a header past the end of the file is empty slots
+    fn read_header(mut file: File) -> io::Result<Self> {
+        let mut header = Vec::new();
+        Read::by_ref(&mut file)
+            .take(HEADER_LEN)
+            .read_to_end(&mut header)?;
+        header.resize(HEADER_LEN as usize, 0);
+        Ok(Self {
+            file,
+            slots: header
+                .chunks_exact(SLOT_LEN)
+                .map(Slot::from_bytes)
+                .collect(),
+        })
+    }
+
+    //a row that runs past the end of the file comes back cut short, so it fails its checksum and is quarantined
+    fn read(&mut self, idx: usize) -> io::Result<Option<StoredRow>> {
+        let slot = self.slots[idx];
+        if slot.offset == 0 {
+            return Ok(None);
+        }
+        self.file.seek(SeekFrom::Start(slot.offset))?;
+        let mut data = Vec::new();
+        Read::by_ref(&mut self.file)
+            .take(slot.len as u64)
+            .read_to_end(&mut data)?;
+        Ok(Some(StoredRow {
+            data,
+            codec: slot.codec,
+            checksum: (slot.flags & HAS_CHECKSUM != 0).then_some(slot.checksum),
+        }))
+    }
+
+    //None empties the slot
+    fn write(&mut self, idx: usize, row: Option<&StoredRow>) -> io::Result<()> {
+        let slot = match row {
+            Some(row) => {
+                let len = u32::try_from(row.data.len()).map_err(|_| {
+                    io::Error::new(ErrorKind::InvalidInput, "chunk row is too big for a region")
+                })?;
+                //a new file is empty, the header is filled in as slots are written
+                let offset = self.file.seek(SeekFrom::End(0))?.max(HEADER_LEN);
+                self.file.seek(SeekFrom::Start(offset))?;
+                self.file.write_all(&row.data)?;
+                Slot {
+                    offset,
+                    codec: row.codec,
+                    len,
+                    checksum: row.checksum.unwrap_or_default(),
+                    flags: if row.checksum.is_some() {
+                        HAS_CHECKSUM
+                    } else {
+                        0
+                    },
+                }
+            }
+            None if self.slots[idx].offset == 0 => return Ok(()),
+            None => Slot::default(),
+        };
+        self.file.seek(SeekFrom::Start((idx * SLOT_LEN) as u64))?;
+        self.file.write_all(&slot.to_bytes())?;
+        self.slots[idx] = slot;
+        Ok(())
+    }
+
+    //rewrites the region without the rows that were saved over, once they take up more room than the rows in use
+    //returns the region to keep using, which is reopened if it was compacted
+    fn compact(mut self, path: &Path) -> io::Result<Self> {
+        let live: u64 = self.slots.iter().map(|slot| slot.len as u64).sum();
+        let garbage = self
+            .file
+            .metadata()?
+            .len()
+            .saturating_sub(HEADER_LEN + live);
+        if garbage < MIN_COMPACT_GARBAGE || garbage < live {
+            return Ok(self);
+        }
+        let mut rows = Vec::new();
+        for idx in 0..REGION_CHUNKS {
+            if let Some(row) = self.read(idx)? {
+                rows.push((idx, row));
+            }
+        }
+        drop(self);
+        //written next to the region and moved over it, so the region is never half compacted
+        let tmp = path.with_extension("tmp");
+        if tmp.exists() {
+            fs::remove_file(&tmp)?;
+        }
+        let mut compacted = Region::open_or_create(&tmp)?;
+        for (idx, row) in rows {
+            compacted.write(idx, Some(&row))?;
+        }
+        compacted.file.sync_all()?;
+        drop(compacted);
+        fs::rename(&tmp, path)?;
+        Self::open_or_create(path)
+    }
+}
+
+//writes `data` next to `path` and moves it over, so `path` always has either the old or the new contents
+fn replace_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
@@ -0,0 +1,235 @@
+use std::path::Path;
+
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{unix_time, ChunkWrite, LevelStorage, StoredRow};
+use crate::{
+    serialization::{
+        db::{ChunkQuarantinedEvent, ChunkTable, LevelDBErr},
+        queries::*,
+    },
+    world::{chunk::ChunkCoord, levels::LevelId},
+};
+
+//the level is one sqlite file, with tables for chunks, world_info and players
+pub struct SqliteStorage {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl SqliteStorage {
+    //opens the level file at `path` and makes any tables it's missing
+    pub fn open(path: &Path) -> Result<Self, LevelDBErr> {
+        let manager = SqliteConnectionManager::file(path).with_init(|conn| {
+            conn.execute_batch(
+                "PRAGMA journal_mode=WAL;
+             PRAGMA synchronous=NORMAL;",
+            )
+        });
+        let pool = Pool::new(manager).map_err(LevelDBErr::R2D2)?;
+        let conn = pool.get().map_err(LevelDBErr::R2D2)?;
+        create_chunk_tables(&conn)?;
+        conn.execute(CREATE_WORLD_INFO_TABLE, [])
+            .and_then(|_| conn.execute(CREATE_PLAYER_TABLE, []))
+            .map_err(LevelDBErr::Sqlite)?;
+        drop(conn);
+        Ok(Self { pool })
+    }
+}
+
+impl LevelStorage for SqliteStorage {
+    fn write_chunks(&self, writes: Vec<ChunkWrite>) -> Result<(), LevelDBErr> {
+        self.connection()?.write_chunks(writes)
+    }
+
+    fn read_chunk(
+        &self,
+        level: LevelId,
+        table: ChunkTable,
+        coord: ChunkCoord,
+    ) -> Result<Option<StoredRow>, LevelDBErr> {
+        self.connection()?.read_chunk(level, table, coord)
+    }
+
+    fn quarantine_chunk(
+        &self,
+        chunk: &ChunkQuarantinedEvent,
+        row: &StoredRow,
+    ) -> Result<(), LevelDBErr> {
+        self.connection()?.quarantine_chunk(chunk, row)
+    }
+
+    fn load_world_info(&self, key: &str) -> Result<Option<Vec<u8>>, LevelDBErr> {
+        self.connection()?.load_world_info(key)
+    }
+
+    fn save_world_info(&self, rows: Vec<(String, Vec<u8>)>) -> Result<(), LevelDBErr> {
+        self.connection()?.save_world_info(rows)
+    }
+
+    fn load_player(&self, username: &str) -> Result<Option<Vec<u8>>, LevelDBErr> {
+        self.connection()?.load_player(username)
+    }
+
+    fn save_player(&self, username: &str, data: Vec<u8>) -> Result<(), LevelDBErr> {
+        self.connection()?.save_player(username, data)
+    }
+
+    fn flush(&self) -> Result<(), LevelDBErr> {
+        self.connection()?.flush()
+    }
+
+    //don't hold on to it, the pool only has so many
+    fn connection(&self) -> Result<PooledConnection<SqliteConnectionManager>, LevelDBErr> {
+        self.pool.get().map_err(LevelDBErr::R2D2)
+    }
+}
+
+//a single connection with the level's tables in it. SqliteStorage runs everything through one of these from its pool
+impl LevelStorage for Connection {
+    fn write_chunks(&self, writes: Vec<ChunkWrite>) -> Result<(), LevelDBErr> {
+        let mut save = self
+            .prepare_cached(SAVE_CHUNK_DATA)
+            .map_err(LevelDBErr::Sqlite)?;
+        let mut delete = self
+            .prepare_cached(DELETE_CHUNK_DATA)
+            .map_err(LevelDBErr::Sqlite)?;
+        for ChunkWrite {
+            level,
+            table,
+            coord,
+            row,
+        } in writes
+        {
+            let tid = table.tid(level);
+            match row {
+                Some(row) => save.execute(params![
+                    tid,
+                    coord.x,
+                    coord.y,
+                    coord.z,
+                    row.data,
+                    row.codec,
+                    row.checksum
+                ]),
+                None => delete.execute(params![tid, coord.x, coord.y, coord.z]),
+            }
+            .map_err(LevelDBErr::Sqlite)?;
+        }
+        Ok(())
+    }
+
+    fn read_chunk(
+        &self,
+        level: LevelId,
+        table: ChunkTable,
+        coord: ChunkCoord,
+    ) -> Result<Option<StoredRow>, LevelDBErr> {
+        self.prepare_cached(LOAD_CHUNK_DATA)
+            .and_then(|mut stmt| {
+                stmt.query_row(
+                    params![table.tid(level), coord.x, coord.y, coord.z],
+                    |row| {
+                        Ok(StoredRow {
+                            data: row.get(0)?,
+                            codec: row.get(1)?,
+                            checksum: row.get(2)?,
+                        })
+                    },
+                )
+                .optional()
+            })
+            .map_err(LevelDBErr::Sqlite)
+    }
+
+    //copies the row to the quarantine table, so it can be looked at later
+    fn quarantine_chunk(
+        &self,
+        chunk: &ChunkQuarantinedEvent,
+        row: &StoredRow,
+    ) -> Result<(), LevelDBErr> {
+        let tid = chunk.table.tid(chunk.level);
+        self.execute(
+            QUARANTINE_CHUNK_DATA,
+            params![
+                tid,
+                chunk.coord.x,
+                chunk.coord.y,
+                chunk.coord.z,
+                row.data,
+                row.codec,
+                row.checksum,
+                chunk.reason,
+                unix_time()
+            ],
+        )
+        .and_then(|_| {
+            self.execute(
+                DELETE_QUARANTINED_CHUNK_DATA,
+                params![tid, chunk.coord.x, chunk.coord.y, chunk.coord.z, row.data],
+            )
+        })
+        .map(|_| ())
+        .map_err(LevelDBErr::Sqlite)
+    }
+
+    fn load_world_info(&self, key: &str) -> Result<Option<Vec<u8>>, LevelDBErr> {
+        self.query_row(LOAD_WORLD_INFO, params![key], |row| row.get(0))
+            .optional()
+            .map_err(LevelDBErr::Sqlite)
+    }
+
+    fn save_world_info(&self, rows: Vec<(String, Vec<u8>)>) -> Result<(), LevelDBErr> {
+        let mut stmt = self
+            .prepare_cached(INSERT_WORLD_INFO)
+            .map_err(LevelDBErr::Sqlite)?;
+        for (key, value) in rows {
+            stmt.execute(params![key, value])
+                .map_err(LevelDBErr::Sqlite)?;
+        }
+        Ok(())
+    }
+
+    fn load_player(&self, username: &str) -> Result<Option<Vec<u8>>, LevelDBErr> {
+        self.query_row(LOAD_PLAYER_DATA, params![username], |row| row.get(0))
+            .optional()
+            .map_err(LevelDBErr::Sqlite)
+    }
+
+    fn save_player(&self, username: &str, data: Vec<u8>) -> Result<(), LevelDBErr> {
+        self.execute(SAVE_PLAYER_DATA, params![username, data])
+            .map(|_| ())
+            .map_err(LevelDBErr::Sqlite)
+    }
+
+    //moves what it can from the write ahead log into the level file
+    fn flush(&self) -> Result<(), LevelDBErr> {
+        self.query_row("PRAGMA wal_checkpoint(PASSIVE)", [], |_| Ok(()))
+            .map_err(LevelDBErr::Sqlite)
+    }
+}
+
+//makes the chunk and quarantine tables, and adds the columns older chunk tables are missing
+pub fn create_chunk_tables(conn: &Connection) -> Result<(), LevelDBErr> {
+    conn.execute(CREATE_CHUNK_TABLE, [])
+        .map_err(LevelDBErr::Sqlite)?;
+    let columns = conn
+        .prepare("SELECT name FROM pragma_table_info('data')")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(LevelDBErr::Sqlite)?;
+    if !columns.iter().any(|column| column == "codec") {
+        conn.execute(ADD_CHUNK_CODEC_COLUMN, [])
+            .map_err(LevelDBErr::Sqlite)?;
+    }
+    if !columns.iter().any(|column| column == "checksum") {
+        conn.execute(ADD_CHUNK_CHECKSUM_COLUMN, [])
+            .map_err(LevelDBErr::Sqlite)?;
+    }
+    conn.execute(CREATE_QUARANTINE_TABLE, [])
+        .map(|_| ())
+        .map_err(LevelDBErr::Sqlite)
+}
@@ -0,0 +1,34 @@
+use rusqlite::Connection;
+
+use crate::serialization::backup::{list_backups, rotate_backups, snapshot, BackupReason};
+
+#[test]
+fn test_backup_rotation() {
+    let dir = std::env::temp_dir().join(format!("backups-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let conn = Connection::open_in_memory().unwrap();
+
+    //taken in the same second, so they need names that don't collide
+    let migrations = (0..2)
+        .map(|_| snapshot(&conn, &dir, BackupReason::Migration).unwrap())
+        .collect::<Vec<_>>();
+    let scheduled = (0..3)
+        .map(|_| snapshot(&conn, &dir, BackupReason::Scheduled).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(list_backups(&dir).len(), 5);
+
+    //the newest of each reason is kept, so scheduled backups don't push out the migration backup
+    rotate_backups(&dir, 1);
+    let kept = list_backups(&dir);
+    assert_eq!(kept.len(), 2);
+    let newest = |reason| {
+        kept.iter()
+            .find(|backup| backup.reason == reason)
+            .unwrap()
+            .path
+            .clone()
+    };
+    assert!(migrations.contains(&newest(BackupReason::Migration)));
+    assert!(scheduled.contains(&newest(BackupReason::Scheduled)));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,30 @@
+use std::assert_matches::assert_matches;
+
+use crate::serialization::{codec::ChunkCodec, db::LevelDBErr};
+
+#[test]
+fn test_codec_round_trip() {
+    let data = (0..5000).map(|i| (i / 100) as u8).collect::<Vec<_>>();
+    for codec in ChunkCodec::ALL {
+        assert_eq!(Some(codec), ChunkCodec::from_id(codec.id()));
+        let encoded = codec.encode(data.clone());
+        assert_eq!(data, codec.decode(&encoded).unwrap());
+    }
+    assert!(ChunkCodec::Lz4.encode(data.clone()).len() < data.len());
+    assert_eq!(None, ChunkCodec::from_id(-1));
+}
+
+#[test]
+fn test_lz4_rejects_bad_length() {
+    assert_matches!(
+        ChunkCodec::Lz4.decode(&[1, 2]),
+        Err(LevelDBErr::Codec(ChunkCodec::Lz4, _))
+    );
+    //says it's 4gb
+    let mut encoded = ChunkCodec::Lz4.encode(vec![1; 100]);
+    encoded[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_matches!(
+        ChunkCodec::Lz4.decode(&encoded),
+        Err(LevelDBErr::Codec(ChunkCodec::Lz4, _))
+    );
+}
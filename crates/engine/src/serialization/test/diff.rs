@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use crate::{
+    serialization::{diff::TerrainDiff, LoadedToSavedIdMap, SavedToLoadedIdMap},
+    world::{
+        chunk::{ChunkCoord, ChunkTrait, GeneratingChunk, BLOCKS_PER_CHUNK},
+        BlockId, BlockName, BlockRegistry, BlockState, BlockStates, Id,
+    },
+    worldgen::{decoration_settings, generate_chunk, shaper_settings, GeneratorSettings},
+};
+
+//every block the generator places
+const BLOCKS: [&str; 12] = [
+    "stone",
+    "dirt",
+    "grass",
+    "sand",
+    "snow",
+    "snow_sheet",
+    "ruby_ore",
+    "log",
+    "leaves",
+    "lily",
+    "cactus",
+    "cactus_flower",
+];
+
+fn registry() -> BlockRegistry {
+    let mut registry = BlockRegistry::default();
+    for (id, name) in BLOCKS.into_iter().enumerate() {
+        registry
+            .id_map
+            .insert(BlockName::core(name), BlockId(Id::Basic(id as u32)));
+    }
+    registry
+}
+
+//settings are made from scratch each time, like they are when a level is opened
+fn generate(seed: u64, coord: ChunkCoord) -> GeneratingChunk {
+    generate_chunk(
+        coord,
+        Arc::new(shaper_settings(seed, &GeneratorSettings::default())),
+        &decoration_settings(&registry(), seed, &GeneratorSettings::default()),
+        seed,
+    )
+}
+
+fn blocks(chunk: &GeneratingChunk) -> Vec<BlockId> {
+    chunk.blocks.iter().copied().collect()
+}
+
+//a column down through the surface, so there's terrain, decoration and structures to compare
+fn column() -> impl Iterator<Item = ChunkCoord> {
+    (-2..3).map(|y| ChunkCoord::new(3, y, -5))
+}
+
+#[test]
+fn test_generation_is_deterministic() {
+    let mut filled = 0;
+    for coord in column() {
+        let chunk = blocks(&generate(1234, coord));
+        assert_eq!(chunk, blocks(&generate(1234, coord)), "at {:?}", coord);
+        filled += chunk.iter().filter(|id| id.0 != Id::Empty).count();
+    }
+    //a column of air would pass without testing anything
+    assert!(filled > 0);
+}
+
+#[test]
+fn test_terrain_diff_round_trip() {
+    let coord = ChunkCoord::new(3, 0, -5);
+    let generated = generate(1234, coord);
+    let mut edited = generated.clone();
+    let placed = BlockId(Id::Basic(BLOCKS.len() as u32));
+    for idx in 100..110 {
+        edited.set_block(idx, placed);
+    }
+    edited.set_block(BLOCKS_PER_CHUNK - 1, BlockId(Id::Empty));
+    let mut states = BlockStates::default();
+    states.set(105, BlockState(3));
+
+    //saved ids are offset from loaded ones, like a palette from another run
+    let mut to_saved = LoadedToSavedIdMap::default();
+    let mut to_loaded = SavedToLoadedIdMap::default();
+    for id in 0..=BLOCKS.len() as u32 {
+        to_saved.insert(BlockId(Id::Basic(id)), BlockId(Id::Basic(id + 10)));
+        to_loaded.insert(BlockId(Id::Basic(id + 10)), BlockId(Id::Basic(id)));
+    }
+    let mut diff = TerrainDiff::new(&generated, &edited.blocks, &states, &to_saved)
+        .with_fluid_levels(vec![(101, 4)]);
+    assert_eq!(
+        diff.changes[0],
+        (100, 10, BlockId(Id::Basic(BLOCKS.len() as u32 + 10)))
+    );
+    diff.map_to_loaded(&to_loaded);
+
+    let loaded = diff.apply(&generate(1234, coord));
+    let mut expected = blocks(&edited).into_iter();
+    for (id, run) in loaded.data {
+        for _ in 0..run {
+            assert_eq!(Some(id), expected.next());
+        }
+    }
+    assert_eq!(expected.next(), None);
+    assert_eq!(loaded.states, vec![(105, BlockState(3))]);
+    assert_eq!(loaded.fluid_levels, vec![(101, 4)]);
+}
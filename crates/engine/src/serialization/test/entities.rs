@@ -0,0 +1,157 @@
+use std::{assert_matches::assert_matches, path::Path, sync::Arc};
+
+use bevy::{
+    ecs::{system::RunSystemOnce, world::CommandQueue},
+    prelude::*,
+};
+
+use crate::{
+    actors::{ActorName, ActorRegistry, ActorResources},
+    items::{loot::DroppedItem, ItemName},
+    serialization::{
+        db::{load_chunk_row, ChunkTable, LevelDB, LoadedRow},
+        entities::{
+            restore_saved_components, save_into_level, spawn_saved, PendingRestores, SavedEntity,
+        },
+        storage::MemoryStorage,
+        SaveWithChunk,
+    },
+    world::{chunk::ChunkCoord, levels::LevelId},
+};
+
+#[test]
+fn test_saved_entity_roundtrip() {
+    let mut world = World::new();
+    let registry = AppTypeRegistry::default();
+    {
+        let mut registry = registry.write();
+        registry.register::<DroppedItem>();
+        registry.register::<SaveWithChunk>();
+        registry.register::<Transform>();
+        registry.register::<Name>();
+    }
+    world.insert_resource(registry.clone());
+    let tf = Transform::from_xyz(1.0, 2.0, 3.0);
+    let entity = world
+        .spawn((
+            DroppedItem {
+                item: ItemName::core("ruby"),
+                size: 5,
+                age: 1.5,
+            },
+            SaveWithChunk,
+            tf,
+            Name::new("dropped item"),
+        ))
+        .id();
+    let saved = SavedEntity::new(
+        world.entity(entity),
+        &ActorName::core("dropped_item"),
+        tf,
+        world.components(),
+        &registry.read(),
+    );
+    //bevy's components are left to the spawner
+    assert_eq!(saved.components.len(), 2);
+    let saved: SavedEntity = bincode::deserialize(&bincode::serialize(&saved).unwrap()).unwrap();
+    assert_eq!(saved.transform, tf);
+
+    let restored = world.spawn(tf).id();
+    let mut queue = CommandQueue::default();
+    saved.restore(
+        restored,
+        &registry.read(),
+        &mut Commands::new(&mut queue, &world),
+    );
+    queue.apply(&mut world);
+    let dropped = world.get::<DroppedItem>(restored).unwrap();
+    assert_eq!(dropped.item, ItemName::core("ruby"));
+    assert_eq!(dropped.size, 5);
+    assert_eq!(dropped.age, 1.5);
+    assert!(world.get::<SaveWithChunk>(restored).is_some());
+    assert!(world.get::<Name>(restored).is_none());
+}
+
+#[test]
+fn test_save_into_level_keeps_saved_entities() {
+    let storage = Arc::new(MemoryStorage::default());
+    let mut db = LevelDB::with_storage(Path::new("test"), storage.clone());
+    let cave = LevelId(1);
+    let coord = ChunkCoord::new(0, -1, 0);
+    let saved = |x: f32| SavedEntity {
+        actor: ActorName::core("dropped_item"),
+        transform: Transform::from_xyz(x, -5.0, 0.0),
+        components: Vec::new(),
+    };
+    //two entities sent to the same chunk of a level that isn't loaded, at different times
+    save_into_level(&mut db, cave, coord, vec![saved(1.0)]);
+    save_into_level(&mut db, cave, coord, vec![saved(2.0)]);
+    drop(db);
+    assert_matches!(
+        load_chunk_row(storage.as_ref(), cave, ChunkTable::Entities, coord).unwrap(),
+        LoadedRow::Found(data) if bincode::deserialize::<Vec<SavedEntity>>(&data)
+            .unwrap()
+            .iter()
+            .map(|entity| entity.transform.translation.x)
+            .collect::<Vec<_>>() == vec![1.0, 2.0]
+    );
+}
+
+#[test]
+fn test_restore_onto_spawned_actor() {
+    let mut app = App::new();
+    let registry = AppTypeRegistry::default();
+    registry.write().register::<DroppedItem>();
+    let mut actors = ActorRegistry::default();
+    actors.add_dynamic(
+        ActorName::core("dropped_item"),
+        Box::new(|commands, tf| {
+            commands.spawn((tf, ActorName::core("dropped_item")));
+        }),
+    );
+    app.insert_resource(registry.clone())
+        .insert_resource(ActorResources { registry: actors })
+        .init_resource::<PendingRestores>()
+        .add_observer(restore_saved_components);
+
+    //two stacks in the same spot, which can't be told apart by where they were
+    let tf = Transform::from_xyz(1.0, 2.0, 3.0);
+    let saved = [3, 5].map(|size| {
+        let entity = app
+            .world_mut()
+            .spawn(DroppedItem {
+                item: ItemName::core("ruby"),
+                size,
+                age: 0.0,
+            })
+            .id();
+        let saved = SavedEntity::new(
+            app.world().entity(entity),
+            &ActorName::core("dropped_item"),
+            tf,
+            app.world().components(),
+            &registry.read(),
+        );
+        app.world_mut().despawn(entity);
+        saved
+    });
+    app.world_mut()
+        .run_system_once(
+            move |resources: Res<ActorResources>,
+                  mut pending: ResMut<PendingRestores>,
+                  mut commands: Commands| {
+                for saved in saved.clone() {
+                    spawn_saved(saved, &resources, &mut pending, &mut commands);
+                }
+            },
+        )
+        .unwrap();
+    let mut sizes = app
+        .world_mut()
+        .query_filtered::<&DroppedItem, With<ActorName>>()
+        .iter(app.world())
+        .map(|dropped| dropped.size)
+        .collect::<Vec<_>>();
+    sizes.sort();
+    assert_eq!(sizes, vec![3, 5]);
+}
@@ -0,0 +1,35 @@
+use crate::{
+    serialization::ChunkSaveFormat,
+    world::{chunk::ChunkCoord, levels::LevelId, BlockCoord, LevelData},
+};
+
+#[test]
+fn test_fluid_levels_round_trip() {
+    let coord = ChunkCoord::new(1, -2, 0);
+    let origin = BlockCoord::from(coord);
+    let flowing = origin + BlockCoord::new(3, 4, 5);
+    let shallow = origin + BlockCoord::new(3, 3, 5);
+    let source = origin + BlockCoord::new(2, 4, 5);
+    let level = LevelData::new("test", LevelId::SURFACE, 0);
+    level.set_fluid_level(flowing, Some(6));
+    level.set_fluid_level(shallow, Some(1));
+
+    let saved = ChunkSaveFormat {
+        position: coord,
+        data: Vec::new(),
+        states: Vec::new(),
+        fluid_levels: Vec::new(),
+    }
+    .with_fluid_levels(level.get_saved_fluid_levels(coord));
+    let mut loaded_format: ChunkSaveFormat =
+        bincode::deserialize(&bincode::serialize(&saved).unwrap()).unwrap();
+
+    //a level with nothing saved would read every fluid as a source
+    let loaded = LevelData::new("test", LevelId::SURFACE, 0);
+    loaded.set_fluid_level(source, Some(2));
+    loaded.load_saved_fluid_levels(coord, std::mem::take(&mut loaded_format.fluid_levels));
+    assert_eq!(loaded.get_fluid_level(flowing), Some(6));
+    assert_eq!(loaded.get_fluid_level(shallow), Some(1));
+    //levels left over from before the chunk loaded don't stick around
+    assert_eq!(loaded.get_fluid_level(source), None);
+}
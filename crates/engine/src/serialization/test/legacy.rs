@@ -0,0 +1,43 @@
+use serde::Serialize;
+
+use crate::{
+    serialization::ChunkSaveFormat,
+    world::{chunk::ChunkCoord, BlockId, BlockState, Id},
+};
+
+//how terrain was saved before chunks had block states
+#[derive(Serialize)]
+struct BaselineChunk {
+    position: ChunkCoord,
+    data: Vec<(BlockId, u16)>,
+}
+
+#[test]
+fn test_decode_legacy_chunk() {
+    let position = ChunkCoord::new(4, -1, 9);
+    let data = vec![(BlockId(Id::Basic(2)), 100), (BlockId(Id::Empty), 3996)];
+    let old = bincode::serialize(&BaselineChunk {
+        position,
+        data: data.clone(),
+    })
+    .unwrap();
+    let chunk = ChunkSaveFormat::decode(&old).unwrap();
+    assert_eq!(chunk.position, position);
+    assert_eq!(chunk.data, data);
+    assert!(chunk.states.is_empty());
+    assert!(chunk.fluid_levels.is_empty());
+
+    let current = bincode::serialize(&ChunkSaveFormat {
+        position,
+        data,
+        states: vec![(7, BlockState(1))],
+        fluid_levels: vec![(8, 3)],
+    })
+    .unwrap();
+    assert_eq!(
+        ChunkSaveFormat::decode(&current).unwrap().states,
+        vec![(7, BlockState(1))]
+    );
+    //a cut off row isn't mistaken for the legacy layout
+    assert!(ChunkSaveFormat::decode(&current[..current.len() - 1]).is_err());
+}
@@ -0,0 +1,167 @@
+use std::assert_matches::assert_matches;
+
+use rusqlite::{params, Connection};
+
+use serde::Serialize;
+
+use crate::{
+    serialization::{
+        db::{ChunkTable, LevelDBErr},
+        migrations::{self, Migration, MigrationRegistry},
+        queries::{
+            CREATE_CHUNK_TABLE, CREATE_WORLD_INFO_TABLE, INSERT_WORLD_INFO, LOAD_WORLD_INFO,
+        },
+        ChunkSaveFormat, SAVE_VERSION,
+    },
+    util::string::Version,
+    world::{chunk::ChunkCoord, levels::LevelId, BlockId, Id},
+};
+
+fn rename_seed(conn: &Connection) -> Result<(), LevelDBErr> {
+    migrations::rename_world_info(conn, "old_seed", "seed")
+}
+
+fn fail(_: &Connection) -> Result<(), LevelDBErr> {
+    Err(LevelDBErr::InvalidWorldVersion)
+}
+
+fn get_info(conn: &Connection, key: &str) -> Option<Vec<u8>> {
+    conn.query_row(LOAD_WORLD_INFO, params![key], |row| row.get(0))
+        .ok()
+}
+
+//0.1 -> 0.2 -> 0.3, added out of order
+fn test_registry(
+    first: fn(&Connection) -> Result<(), LevelDBErr>,
+    second: fn(&Connection) -> Result<(), LevelDBErr>,
+) -> MigrationRegistry {
+    let mut registry = MigrationRegistry::default();
+    registry
+        .add(Migration {
+            name: "second",
+            versions: Version::from("0.2.0")..Version::from("0.3.0"),
+            run: second,
+        })
+        .add(Migration {
+            name: "first",
+            versions: Version::from("0.1.0")..Version::from("0.2.0"),
+            run: first,
+        });
+    registry
+}
+
+fn do_nothing(_: &Connection) -> Result<(), LevelDBErr> {
+    Ok(())
+}
+
+#[test]
+fn test_migration_plan() {
+    let registry = test_registry(rename_seed, do_nothing);
+    let names = |steps: Vec<&Migration>| steps.iter().map(|step| step.name).collect::<Vec<_>>();
+    assert_eq!(
+        names(
+            registry
+                .plan(&Version::from("0.1.4"), &Version::from("0.3.1"))
+                .unwrap()
+        ),
+        vec!["first", "second"]
+    );
+    //steps that go past the game's version aren't used
+    assert_eq!(
+        names(
+            registry
+                .plan(&Version::from("0.1.0"), &Version::from("0.2.5"))
+                .unwrap()
+        ),
+        vec!["first"]
+    );
+    assert!(registry
+        .plan(&Version::from("0.3.0"), &Version::from("0.3.2"))
+        .unwrap()
+        .is_empty());
+    assert_matches!(
+        registry.plan(&Version::from("0.0.9"), &Version::from("0.3.0")),
+        Err(LevelDBErr::MissingMigration(_))
+    );
+}
+
+#[test]
+fn test_run_migrations() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    conn.execute(CREATE_WORLD_INFO_TABLE, []).unwrap();
+    conn.execute(INSERT_WORLD_INFO, params!["old_seed", vec![1u8, 2, 3]])
+        .unwrap();
+
+    let registry = test_registry(rename_seed, do_nothing);
+    let steps = registry
+        .plan(&Version::from("0.1.0"), &Version::from("0.3.0"))
+        .unwrap();
+    migrations::run_migrations(&mut conn, &steps).unwrap();
+    assert_eq!(get_info(&conn, "seed"), Some(vec![1, 2, 3]));
+    assert_eq!(get_info(&conn, "old_seed"), None);
+    let version = get_info(&conn, "version").unwrap();
+    assert_eq!(bincode::deserialize::<&str>(&version).unwrap(), "0.3.0");
+
+    //a failed step rolls back the steps before it
+    conn.execute(INSERT_WORLD_INFO, params!["old_seed", vec![4u8]])
+        .unwrap();
+    let registry = test_registry(rename_seed, fail);
+    let steps = registry
+        .plan(&Version::from("0.1.0"), &Version::from("0.3.0"))
+        .unwrap();
+    assert_matches!(
+        migrations::run_migrations(&mut conn, &steps),
+        Err(LevelDBErr::MigrationFailed("second", _))
+    );
+    assert_eq!(get_info(&conn, "old_seed"), Some(vec![4]));
+}
+
+//how terrain was saved before chunks had block states
+#[derive(Serialize)]
+struct BaselineChunk {
+    position: ChunkCoord,
+    data: Vec<(BlockId, u16)>,
+}
+
+#[test]
+fn test_migrate_baseline_terrain() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    conn.execute(CREATE_WORLD_INFO_TABLE, []).unwrap();
+    conn.execute(CREATE_CHUNK_TABLE, []).unwrap();
+    let position = ChunkCoord::new(2, 0, -3);
+    let data = vec![(BlockId(Id::Basic(1)), 4096)];
+    let old = bincode::serialize(&BaselineChunk {
+        position,
+        data: data.clone(),
+    })
+    .unwrap();
+    //saved without a checksum, like rows from before there were any
+    conn.execute(
+        "INSERT INTO data (tid, x, y, z, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            ChunkTable::Terrain.tid(LevelId::SURFACE),
+            position.x,
+            position.y,
+            position.z,
+            old
+        ],
+    )
+    .unwrap();
+
+    let registry = MigrationRegistry::with_game_steps();
+    let steps = registry
+        .plan(&Version::from("0.1.0"), &Version::from(SAVE_VERSION))
+        .unwrap();
+    assert!(!steps.is_empty());
+    migrations::run_migrations(&mut conn, &steps).unwrap();
+    let migrated: Vec<u8> = conn
+        .query_row("SELECT data FROM data", [], |row| row.get(0))
+        .unwrap();
+    //the current layout reads it without falling back to the old one
+    let chunk: ChunkSaveFormat = bincode::deserialize(&migrated).unwrap();
+    assert_eq!(chunk.position, position);
+    assert_eq!(chunk.data, data);
+    assert!(chunk.states.is_empty());
+    assert!(chunk.fluid_levels.is_empty());
+    assert_eq!(migrated.len(), old.len() + 16);
+}
@@ -0,0 +1,9 @@
+mod backup;
+mod codec;
+mod diff;
+mod entities;
+mod fluids;
+mod legacy;
+mod migrations;
+mod quarantine;
+mod storage;
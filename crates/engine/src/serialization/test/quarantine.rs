@@ -0,0 +1,211 @@
+use std::{assert_matches::assert_matches, path::Path, sync::Arc};
+
+use bevy::prelude::*;
+use rusqlite::{params, Connection};
+
+use crate::{
+    serialization::{
+        codec::ChunkCodec,
+        db::{load_chunk_row, ChunkTable, DataFromDBEvent, LevelDB, LoadedRow},
+        loading::load_chunk_terrain,
+        queries::SAVE_CHUNK_DATA,
+        storage::{
+            sqlite::create_chunk_tables, ChunkWrite, LevelStorage, MemoryStorage, StoredRow,
+        },
+        ChunkSaveFormat, LevelOpenError, SavedToLoadedIdMap,
+    },
+    util::checksum::crc32,
+    world::{
+        chunk::{ChunkCoord, ChunkType},
+        events::ChunkUpdatedEvent,
+        levels::LevelId,
+        BlockId, BlockRegistry, BlockResources, Id, Level, LevelData,
+    },
+    worldgen::ChunkNeedsGenerated,
+    GameState,
+};
+
+const COORD: ChunkCoord = ChunkCoord { x: 1, y: -2, z: 3 };
+
+fn save_row(conn: &Connection, data: &[u8]) {
+    save_encoded_row(conn, ChunkCodec::Bincode, data.to_vec());
+}
+
+fn save_encoded_row(conn: &Connection, codec: ChunkCodec, data: Vec<u8>) {
+    let data = codec.encode(data);
+    conn.execute(
+        SAVE_CHUNK_DATA,
+        params![
+            ChunkTable::Terrain.tid(LevelId::SURFACE),
+            COORD.x,
+            COORD.y,
+            COORD.z,
+            data,
+            codec.id(),
+            crc32(&data)
+        ],
+    )
+    .unwrap();
+}
+
+fn load_row(conn: &Connection) -> LoadedRow {
+    load_chunk_row(conn, LevelId::SURFACE, ChunkTable::Terrain, COORD).unwrap()
+}
+
+fn count(conn: &Connection, table: &str) -> i64 {
+    conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+        row.get(0)
+    })
+    .unwrap()
+}
+
+#[test]
+fn test_quarantine_corrupt_row() {
+    let conn = Connection::open_in_memory().unwrap();
+    create_chunk_tables(&conn).unwrap();
+    assert_matches!(load_row(&conn), LoadedRow::Missing);
+
+    save_row(&conn, &[1, 2, 3, 4]);
+    assert_matches!(load_row(&conn), LoadedRow::Found(data) if data == [1, 2, 3, 4]);
+
+    //truncated
+    conn.execute("UPDATE data SET data = X'010203'", [])
+        .unwrap();
+    assert_matches!(
+        load_row(&conn),
+        LoadedRow::Quarantined(chunk) if chunk.coord == COORD && chunk.table == ChunkTable::Terrain
+    );
+    assert_eq!(count(&conn, "data"), 0);
+    assert_eq!(count(&conn, "quarantine"), 1);
+    //the chunk loads like it was never saved
+    assert_matches!(load_row(&conn), LoadedRow::Missing);
+
+    save_row(&conn, &[5]);
+    conn.execute("UPDATE data SET codec = 99", []).unwrap();
+    assert_matches!(load_row(&conn), LoadedRow::Quarantined(_));
+    assert_eq!(count(&conn, "quarantine"), 2);
+
+    //rows come back decoded
+    save_encoded_row(&conn, ChunkCodec::Lz4, vec![6; 100]);
+    assert_matches!(load_row(&conn), LoadedRow::Found(data) if data == [6; 100]);
+    //a blob that passes its checksum but can't be decompressed
+    let mut bad = ChunkCodec::Lz4.encode(vec![6; 100]);
+    bad.truncate(6);
+    conn.execute(
+        "UPDATE data SET data = ?1, checksum = ?2",
+        params![bad, crc32(&bad)],
+    )
+    .unwrap();
+    assert_matches!(load_row(&conn), LoadedRow::Quarantined(_));
+    assert_eq!(count(&conn, "quarantine"), 3);
+}
+
+#[test]
+fn test_upgrade_old_chunk_table() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute(
+        "CREATE TABLE data (
+            tid INTEGER NOT NULL,
+            x INTEGER NOT NULL,
+            y INTEGER NOT NULL,
+            z INTEGER NOT NULL,
+            data BLOB NOT NULL,
+            PRIMARY KEY (tid,x,y,z)
+        ) STRICT",
+        [],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO data (tid,x,y,z,data) VALUES (?1,?2,?3,?4,?5)",
+        params![0, COORD.x, COORD.y, COORD.z, vec![7u8]],
+    )
+    .unwrap();
+    create_chunk_tables(&conn).unwrap();
+    //running it again on an upgraded table does nothing
+    create_chunk_tables(&conn).unwrap();
+    //rows from before there were checksums aren't checked
+    assert_matches!(load_row(&conn), LoadedRow::Found(data) if data == [7]);
+    save_row(&conn, &[8]);
+    assert_matches!(load_row(&conn), LoadedRow::Found(data) if data == [8]);
+}
+
+#[test]
+fn test_quarantine_undecodable_terrain() {
+    let storage = Arc::new(MemoryStorage::default());
+    let terrain = bincode::serialize(&ChunkSaveFormat {
+        position: COORD,
+        data: vec![(BlockId(Id::Basic(1)), 100), (BlockId(Id::Empty), 100)],
+        states: Vec::new(),
+        fluid_levels: Vec::new(),
+    })
+    .unwrap();
+    //cut short, and saved before rows had checksums, so the db can't tell it's corrupt
+    storage
+        .write_chunks(vec![ChunkWrite {
+            level: LevelId::SURFACE,
+            table: ChunkTable::Terrain,
+            coord: COORD,
+            row: Some(StoredRow {
+                data: terrain[..terrain.len() - 3].to_vec(),
+                codec: ChunkCodec::Bincode.id(),
+                checksum: None,
+            }),
+        }])
+        .unwrap();
+    let LoadedRow::Found(data) = load_chunk_row(
+        storage.as_ref(),
+        LevelId::SURFACE,
+        ChunkTable::Terrain,
+        COORD,
+    )
+    .unwrap() else {
+        panic!("the row should load without a checksum");
+    };
+
+    let mut app = App::new();
+    let entity = app.world_mut().spawn(COORD).id();
+    let level = LevelData::new("test", LevelId::SURFACE, 0);
+    level.add_chunk(COORD, ChunkType::Ungenerated(entity));
+    app.insert_resource(Level(Arc::new(level)))
+        .insert_resource(LevelDB::with_storage(Path::new("test"), storage.clone()))
+        .insert_resource(BlockResources {
+            registry: Arc::new(BlockRegistry::default()),
+        })
+        .init_resource::<SavedToLoadedIdMap<BlockId>>()
+        .init_resource::<NextState<GameState>>()
+        .add_event::<DataFromDBEvent>()
+        .add_event::<ChunkUpdatedEvent>()
+        .add_systems(Update, load_chunk_terrain);
+    app.world_mut().send_event(DataFromDBEvent(
+        LevelId::SURFACE,
+        COORD,
+        vec![
+            (ChunkTable::Terrain, data),
+            (ChunkTable::Buffers, Vec::new()),
+            (ChunkTable::BlockTicks, Vec::new()),
+            (ChunkTable::Entities, Vec::new()),
+            (ChunkTable::TerrainDiff, Vec::new()),
+        ],
+    ));
+    app.update();
+
+    //the chunk is generated again instead of the level being closed
+    assert!(matches!(
+        app.world().get::<ChunkNeedsGenerated>(entity),
+        Some(ChunkNeedsGenerated::Full)
+    ));
+    assert!(app.world().get_resource::<LevelOpenError>().is_none());
+    //closing the level runs the queued quarantine
+    app.world_mut().remove_resource::<LevelDB>();
+    assert_matches!(
+        load_chunk_row(
+            storage.as_ref(),
+            LevelId::SURFACE,
+            ChunkTable::Terrain,
+            COORD
+        )
+        .unwrap(),
+        LoadedRow::Missing
+    );
+    assert_eq!(storage.quarantined().len(), 1);
+}
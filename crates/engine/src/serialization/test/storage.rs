@@ -0,0 +1,257 @@
+use std::{
+    assert_matches::assert_matches,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    serialization::{
+        codec::ChunkCodec,
+        db::{load_chunk_row, ChunkQuarantinedEvent, ChunkTable, LevelDB, LoadedRow, SaveCommand},
+        storage::{
+            ChunkWrite, LevelStorage, MemoryStorage, RegionStorage, SqliteStorage, StoredRow,
+        },
+    },
+    util::checksum::crc32,
+    world::{chunk::ChunkCoord, levels::LevelId},
+};
+
+//in region (-2, 0, 2), with room for a neighbour in the same region
+const COORD: ChunkCoord = ChunkCoord {
+    x: -11,
+    y: 0,
+    z: 17,
+};
+
+fn row(data: &[u8]) -> StoredRow {
+    StoredRow {
+        data: data.to_vec(),
+        codec: ChunkCodec::Bincode.id(),
+        checksum: Some(crc32(data)),
+    }
+}
+
+fn write(storage: &dyn LevelStorage, table: ChunkTable, coord: ChunkCoord, row: Option<StoredRow>) {
+    storage
+        .write_chunks(vec![ChunkWrite {
+            level: LevelId::SURFACE,
+            table,
+            coord,
+            row,
+        }])
+        .unwrap();
+}
+
+fn read(storage: &dyn LevelStorage, table: ChunkTable, coord: ChunkCoord) -> Option<StoredRow> {
+    storage.read_chunk(LevelId::SURFACE, table, coord).unwrap()
+}
+
+//a folder for the test to save in. emptied first, in case a run that failed left it behind
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("storage-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+//what every storage has to do the same way
+fn check_storage(storage: &dyn LevelStorage) {
+    use ChunkTable::*;
+    assert_eq!(read(storage, Terrain, COORD), None);
+
+    write(storage, Terrain, COORD, Some(row(&[1, 2, 3])));
+    //other tables, levels and chunks in the same region are kept apart
+    let next = ChunkCoord::new(COORD.x + 1, COORD.y, COORD.z);
+    write(storage, Buffers, COORD, Some(row(&[4])));
+    write(storage, Terrain, next, Some(row(&[5])));
+    assert_eq!(read(storage, Terrain, COORD), Some(row(&[1, 2, 3])));
+    assert_eq!(read(storage, Buffers, COORD), Some(row(&[4])));
+    assert_eq!(read(storage, Terrain, next), Some(row(&[5])));
+    assert_eq!(
+        storage.read_chunk(LevelId(1), Terrain, COORD).unwrap(),
+        None
+    );
+
+    write(storage, Terrain, COORD, Some(row(&[6; 100])));
+    assert_eq!(read(storage, Terrain, COORD), Some(row(&[6; 100])));
+    write(storage, Terrain, COORD, None);
+    assert_eq!(read(storage, Terrain, COORD), None);
+    //deleting a row that isn't there does nothing
+    write(storage, TerrainDiff, COORD, None);
+    assert_eq!(read(storage, Buffers, COORD), Some(row(&[4])));
+
+    //like a row saved before there were checksums
+    let old = StoredRow {
+        data: vec![7],
+        codec: ChunkCodec::Bincode.id(),
+        checksum: None,
+    };
+    write(storage, Terrain, COORD, Some(old.clone()));
+    assert_eq!(read(storage, Terrain, COORD), Some(old.clone()));
+
+    //the row is only deleted if it wasn't saved over since it was read
+    let chunk = ChunkQuarantinedEvent {
+        level: LevelId::SURFACE,
+        table: Terrain,
+        coord: COORD,
+        reason: "test".to_string(),
+    };
+    storage.quarantine_chunk(&chunk, &row(&[8])).unwrap();
+    assert_eq!(read(storage, Terrain, COORD), Some(old.clone()));
+    storage.quarantine_chunk(&chunk, &old).unwrap();
+    assert_eq!(read(storage, Terrain, COORD), None);
+
+    let mut corrupt = row(&[9, 9]);
+    corrupt.data.push(0);
+    write(storage, Terrain, COORD, Some(corrupt));
+    assert_matches!(
+        load_chunk_row(storage, LevelId::SURFACE, Terrain, COORD).unwrap(),
+        LoadedRow::Quarantined(_)
+    );
+    assert_eq!(read(storage, Terrain, COORD), None);
+
+    assert_eq!(storage.load_world_info("seed").unwrap(), None);
+    storage
+        .save_world_info(vec![
+            ("seed".to_string(), vec![1]),
+            ("version".to_string(), vec![2]),
+        ])
+        .unwrap();
+    storage
+        .save_world_info(vec![("seed".to_string(), vec![3])])
+        .unwrap();
+    assert_eq!(storage.load_world_info("seed").unwrap(), Some(vec![3]));
+    assert_eq!(storage.load_world_info("version").unwrap(), Some(vec![2]));
+
+    assert_eq!(storage.load_player("host").unwrap(), None);
+    storage.save_player("host", vec![10]).unwrap();
+    assert_eq!(storage.load_player("host").unwrap(), Some(vec![10]));
+
+    storage.flush().unwrap();
+}
+
+#[test]
+fn test_sqlite_storage() {
+    let dir = test_dir("sqlite");
+    check_storage(&SqliteStorage::open(&dir.join("level.db")).unwrap());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_memory_storage() {
+    let storage = MemoryStorage::default();
+    check_storage(&storage);
+    assert_eq!(storage.quarantined().len(), 3);
+}
+
+#[test]
+fn test_region_storage() {
+    let dir = test_dir("region");
+    check_storage(&RegionStorage::open(&dir).unwrap());
+
+    //everything is still there when the level is opened again
+    let storage = RegionStorage::open(&dir).unwrap();
+    assert_eq!(read(&storage, ChunkTable::Buffers, COORD), Some(row(&[4])));
+    assert_eq!(storage.load_world_info("seed").unwrap(), Some(vec![3]));
+    assert_eq!(storage.load_player("host").unwrap(), Some(vec![10]));
+
+    //rows that are saved over are cleared out once they take up enough room
+    let region = dir.join("0").join("-2.0.2.region");
+    for i in 0..20 {
+        write(
+            &storage,
+            ChunkTable::Terrain,
+            COORD,
+            Some(row(&[i; 200 * 1024])),
+        );
+    }
+    assert_eq!(
+        read(&storage, ChunkTable::Terrain, COORD),
+        Some(row(&[19; 200 * 1024]))
+    );
+    assert!(std::fs::metadata(&region).unwrap().len() < 2 * 1024 * 1024);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_level_db_with_storage() {
+    let storage = Arc::new(MemoryStorage::default());
+    let mut db = LevelDB::with_storage(Path::new("test"), storage.clone());
+    db.set_codec(ChunkCodec::Lz4);
+    let terrain = vec![1u8; 100];
+    db.save_chunk_data(vec![SaveCommand::encode(
+        LevelId::SURFACE,
+        ChunkTable::Terrain,
+        COORD,
+        &terrain,
+    )
+    .unwrap()]);
+    db.save_world_info("seed", vec![5]);
+    //whatever is still queued is saved when the level is closed
+    drop(db);
+    assert_matches!(
+        load_chunk_row(storage.as_ref(), LevelId::SURFACE, ChunkTable::Terrain, COORD).unwrap(),
+        LoadedRow::Found(data) if bincode::deserialize::<Vec<u8>>(&data).unwrap() == terrain
+    );
+    assert_eq!(
+        read(storage.as_ref(), ChunkTable::Terrain, COORD).map(|row| row.codec),
+        Some(ChunkCodec::Lz4.id())
+    );
+    assert_eq!(storage.load_world_info("seed").unwrap(), Some(vec![5]));
+}
+
+//rows made in the db task are saved in order with the ones queued around them
+#[test]
+fn test_level_db_deferred_save() {
+    let storage = Arc::new(MemoryStorage::default());
+    let mut db = LevelDB::with_storage(Path::new("test"), storage.clone());
+    db.save_chunk_data(vec![SaveCommand::encode(
+        LevelId::SURFACE,
+        ChunkTable::Terrain,
+        COORD,
+        &vec![1u8; 100],
+    )
+    .unwrap()]);
+    db.save_chunk_data_with(|| {
+        vec![
+            SaveCommand::encode(LevelId::SURFACE, ChunkTable::TerrainDiff, COORD, &vec![2u8])
+                .unwrap(),
+        ]
+    });
+    drop(db);
+    assert_matches!(
+        load_chunk_row(
+            storage.as_ref(),
+            LevelId::SURFACE,
+            ChunkTable::Terrain,
+            COORD
+        )
+        .unwrap(),
+        LoadedRow::Missing
+    );
+    assert_matches!(
+        load_chunk_row(storage.as_ref(), LevelId::SURFACE, ChunkTable::TerrainDiff, COORD).unwrap(),
+        LoadedRow::Found(data) if bincode::deserialize::<Vec<u8>>(&data).unwrap() == vec![2u8]
+    );
+}
+
+//a save without data deletes the row, like chunks that no longer have block ticks
+#[test]
+fn test_level_db_empty_save_deletes() {
+    let storage = Arc::new(MemoryStorage::default());
+    write(
+        storage.as_ref(),
+        ChunkTable::BlockTicks,
+        COORD,
+        Some(row(&[1])),
+    );
+    let mut db = LevelDB::with_storage(Path::new("test"), storage.clone());
+    db.save_chunk_data(vec![SaveCommand(
+        LevelId::SURFACE,
+        ChunkTable::BlockTicks,
+        COORD,
+        Vec::new(),
+    )]);
+    drop(db);
+    assert_eq!(read(storage.as_ref(), ChunkTable::BlockTicks, COORD), None);
+}
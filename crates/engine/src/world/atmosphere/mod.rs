@@ -7,7 +7,17 @@ use bevy::{
     render::render_resource::{TextureViewDescriptor, TextureViewDimension},
 };
 
-use crate::{actors::world_anchor::ActiveWorldAnchor, GameState};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actors::world_anchor::ActiveWorldAnchor,
+    serialization::{db::LevelDB, SaveWorldStateSet},
+    world::LevelLoadState,
+    GameState,
+};
+
+//world_info key the calendar is saved under
+const CALENDAR_KEY: &str = "calendar";
 
 #[derive(Component, Reflect)]
 struct Sun {
@@ -23,7 +33,7 @@ pub struct Calendar {
     pub time: GameTime,
 }
 
-#[derive(Default, PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(Default, PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct GameTime {
     pub day: u64,
     pub time: Duration,
@@ -186,6 +196,8 @@ impl Plugin for AtmospherePlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, (setup_environment, setup_calendar))
             .add_systems(OnEnter(GameState::Game), setup_calendar)
+            .add_systems(OnEnter(LevelLoadState::Loading), load_calendar)
+            .add_systems(Last, save_calendar.in_set(SaveWorldStateSet))
             .add_systems(OnEnter(GameState::Menu), spawn_sun)
             .add_systems(OnEnter(GameState::Game), spawn_sun)
             .add_systems(
@@ -288,6 +300,30 @@ fn setup_calendar(mut commands: Commands) {
     });
 }
 
+//restores the time of day saved with the level. new levels keep the time from setup_calendar
+fn load_calendar(
+    db: Option<ResMut<LevelDB>>,
+    mut calendar: ResMut<Calendar>,
+    mut speed: ResMut<CalendarSpeed>,
+) {
+    let Some(mut db) = db else {
+        return;
+    };
+    match db.load_world_info::<GameTime>(CALENDAR_KEY) {
+        Ok(Some(time)) => {
+            info!("Loaded calendar: {}", time);
+            calendar.time = time;
+            speed.target = time;
+        }
+        Ok(None) => {}
+        Err(e) => error!("Error loading calendar: {:?}", e),
+    }
+}
+
+fn save_calendar(calendar: Res<Calendar>, mut db: ResMut<LevelDB>) {
+    db.save_world_info(CALENDAR_KEY, bincode::serialize(&calendar.time).unwrap());
+}
+
 fn update_calendar(
     time: Res<Time>,
     mut calendar: ResMut<Calendar>,
@@ -16,8 +16,9 @@ use serde::{Deserialize, Serialize};
 use util::direction::Direction;
 
 use super::{
+    blocks::fluid::SOURCE_HEIGHT,
     chunk::{ChunkCoord, ChunkIdx, CHUNK_SIZE_I32},
-    Id,
+    BlockTag, BlockTagSet, Id, TagId,
 };
 
 #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -141,6 +142,8 @@ pub enum NamedBlockMeshShape {
     //Slab with height from bottom (1.0) is the same as uniform, (0.0) is empty
     BottomSlab(f32, [PathBuf; 6]),
     Cross([PathBuf; 2]),
+    //fluid with all sides being the same texture. height comes from the fluid level
+    Fluid(PathBuf),
 }
 
 impl NamedBlockMeshShape {
@@ -159,6 +162,9 @@ impl NamedBlockMeshShape {
             NamedBlockMeshShape::Cross(names) => {
                 BlockMeshShape::Cross(names.map(|name| *map.0.get(&name).unwrap()))
             }
+            NamedBlockMeshShape::Fluid(name) => {
+                BlockMeshShape::Fluid(SOURCE_HEIGHT, [*map.0.get(&name).unwrap(); 6])
+            }
         }
     }
 }
@@ -185,6 +191,8 @@ pub enum BlockMeshShape {
     //x-shaped criss-cross (like minecraft flower). each face is a unit square at a 45 degree angle centered in the block
     //technically 4 faces, two for each direction (forward and backwards face) so we don't have to have a special two-sided material
     Cross([u32; 2]),
+    //like a bottom slab, but the height is set by the fluid level when meshing
+    Fluid(f32, [u32; 6]),
 }
 
 impl BlockMeshShape {
@@ -197,6 +205,7 @@ impl BlockMeshShape {
             BlockMeshShape::MultiTexture(_) => false,
             BlockMeshShape::BottomSlab(_, _) => true,
             BlockMeshShape::Cross(_) => true,
+            BlockMeshShape::Fluid(_, _) => true,
         }
     }
 }
@@ -221,6 +230,19 @@ impl BlockPhysics {
         }
     }
 }
+
+//how much an explosion ray loses when it passes through this block. a ray only breaks the block if it has more than this left
+//blocks without this use BlastResistance::default()
+#[derive(Component, Clone, Copy, Reflect, Debug, PartialEq)]
+#[reflect(Component, FromWorld)]
+pub struct BlastResistance(pub f32);
+
+impl Default for BlastResistance {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
 //0 = healthy, 1 = broken
 #[derive(Clone, Copy)]
 pub struct BlockDamage {
@@ -267,6 +289,10 @@ pub struct BlockRegistry {
     pub dynamic_generators: Vec<Box<dyn BlockGenerator>>,
     //block ids may not be stable across program runs
     pub id_map: BlockNameIdMap,
+    pub tag_ids: HashMap<BlockTag, TagId>,
+    //indexed by basic/dynamic id
+    basic_tags: Vec<BlockTagSet>,
+    dynamic_tags: Vec<BlockTagSet>,
 }
 
 impl BlockRegistry {
@@ -379,6 +405,44 @@ impl BlockRegistry {
             None => BlockType::Empty,
         }
     }
+    //adds `tags` to the block type, registering any tags that haven't been seen yet
+    pub fn add_tags(&mut self, block_id: BlockId, tags: &[BlockTag]) {
+        let (list, idx) = match block_id {
+            BlockId(Id::Empty) => return,
+            BlockId(Id::Basic(id)) => (&mut self.basic_tags, id as usize),
+            BlockId(Id::Dynamic(id)) => (&mut self.dynamic_tags, id as usize),
+        };
+        if idx >= list.len() {
+            list.resize(idx + 1, BlockTagSet::default());
+        }
+        for tag in tags {
+            let next_id = TagId(self.tag_ids.len() as u16);
+            let tag_id = *self.tag_ids.entry(tag.clone()).or_insert(next_id);
+            list[idx].insert(tag_id);
+        }
+    }
+    pub fn get_tag(&self, tag: &BlockTag) -> Option<TagId> {
+        self.tag_ids.get(tag).copied()
+    }
+    //tags that no block has are left out, since nothing can match them
+    pub fn tag_set<'a>(&self, tags: impl IntoIterator<Item = &'a BlockTag>) -> BlockTagSet {
+        tags.into_iter().filter_map(|tag| self.get_tag(tag)).collect()
+    }
+    pub fn get_tags(&self, block_id: BlockId) -> Option<&BlockTagSet> {
+        match block_id {
+            BlockId(Id::Empty) => None,
+            BlockId(Id::Basic(id)) => self.basic_tags.get(id as usize),
+            BlockId(Id::Dynamic(id)) => self.dynamic_tags.get(id as usize),
+        }
+    }
+    pub fn has_tag(&self, block_id: BlockId, tag: TagId) -> bool {
+        self.get_tags(block_id).is_some_and(|tags| tags.contains(tag))
+    }
+    //true if the block has any of `tags`
+    pub fn has_any_tag(&self, block_id: BlockId, tags: &BlockTagSet) -> bool {
+        self.get_tags(block_id)
+            .is_some_and(|block_tags| block_tags.intersects(tags))
+    }
     pub fn remove_entity(id_query: &Query<&BlockId>, b: BlockType, commands: &mut Commands) {
         match b {
             BlockType::Filled(entity) => match id_query.get(entity) {
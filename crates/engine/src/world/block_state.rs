@@ -0,0 +1,250 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use util::direction::Direction;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Reflect)]
+pub enum BlockAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl BlockAxis {
+    pub fn from_direction(dir: Direction) -> Self {
+        match dir {
+            Direction::PosX | Direction::NegX => BlockAxis::X,
+            Direction::PosY | Direction::NegY => BlockAxis::Y,
+            Direction::PosZ | Direction::NegZ => BlockAxis::Z,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Reflect)]
+pub enum BlockHalf {
+    Bottom,
+    Top,
+}
+
+//per-position block properties, packed into 16 bits so blocks don't need their own entity to be rotated, opened, etc.
+//bits 0-2: facing (0 = none, otherwise Direction::to_idx() + 1)
+//bits 3-4: axis (0 = none, 1 = x, 2 = y, 3 = z)
+//bits 5-6: half (0 = none, 1 = bottom, 2 = top)
+//bit 7: open
+//the default state (all zeros) is what every block has unless something sets it
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug, Serialize, Deserialize)]
+pub struct BlockState(pub u16);
+
+impl BlockState {
+    const FACING_SHIFT: u16 = 0;
+    const FACING_MASK: u16 = 0b111;
+    const AXIS_SHIFT: u16 = 3;
+    const AXIS_MASK: u16 = 0b11;
+    const HALF_SHIFT: u16 = 5;
+    const HALF_MASK: u16 = 0b11;
+    const OPEN_SHIFT: u16 = 7;
+
+    fn get_bits(self, shift: u16, mask: u16) -> u16 {
+        (self.0 >> shift) & mask
+    }
+
+    fn with_bits(self, shift: u16, mask: u16, bits: u16) -> Self {
+        Self((self.0 & !(mask << shift)) | ((bits & mask) << shift))
+    }
+
+    pub fn is_default(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn facing(self) -> Option<Direction> {
+        match self.get_bits(Self::FACING_SHIFT, Self::FACING_MASK) {
+            0 => None,
+            bits => Some(Direction::from((bits - 1) as usize)),
+        }
+    }
+
+    pub fn with_facing(self, facing: Option<Direction>) -> Self {
+        let bits = facing.map(|dir| dir.to_idx() as u16 + 1).unwrap_or(0);
+        self.with_bits(Self::FACING_SHIFT, Self::FACING_MASK, bits)
+    }
+
+    pub fn axis(self) -> Option<BlockAxis> {
+        match self.get_bits(Self::AXIS_SHIFT, Self::AXIS_MASK) {
+            1 => Some(BlockAxis::X),
+            2 => Some(BlockAxis::Y),
+            3 => Some(BlockAxis::Z),
+            _ => None,
+        }
+    }
+
+    pub fn with_axis(self, axis: Option<BlockAxis>) -> Self {
+        let bits = match axis {
+            None => 0,
+            Some(BlockAxis::X) => 1,
+            Some(BlockAxis::Y) => 2,
+            Some(BlockAxis::Z) => 3,
+        };
+        self.with_bits(Self::AXIS_SHIFT, Self::AXIS_MASK, bits)
+    }
+
+    pub fn half(self) -> Option<BlockHalf> {
+        match self.get_bits(Self::HALF_SHIFT, Self::HALF_MASK) {
+            1 => Some(BlockHalf::Bottom),
+            2 => Some(BlockHalf::Top),
+            _ => None,
+        }
+    }
+
+    pub fn with_half(self, half: Option<BlockHalf>) -> Self {
+        let bits = match half {
+            None => 0,
+            Some(BlockHalf::Bottom) => 1,
+            Some(BlockHalf::Top) => 2,
+        };
+        self.with_bits(Self::HALF_SHIFT, Self::HALF_MASK, bits)
+    }
+
+    pub fn open(self) -> bool {
+        self.get_bits(Self::OPEN_SHIFT, 1) == 1
+    }
+
+    pub fn with_open(self, open: bool) -> Self {
+        self.with_bits(Self::OPEN_SHIFT, 1, open as u16)
+    }
+
+    //returns the face of the unrotated block that ends up pointing towards `world_face`
+    //facing rotates the block's PosZ face to point in that direction, axis rotates the PosY face onto that axis
+    //facing takes priority if both are set
+    pub fn model_face(self, world_face: Direction) -> Direction {
+        match (self.facing(), self.axis()) {
+            (Some(facing), _) => unrotate_face(world_face, Direction::PosZ, facing),
+            (None, Some(BlockAxis::X)) => {
+                unrotate_face(world_face, Direction::PosY, Direction::PosX)
+            }
+            (None, Some(BlockAxis::Z)) => {
+                unrotate_face(world_face, Direction::PosY, Direction::PosZ)
+            }
+            (None, Some(BlockAxis::Y)) | (None, None) => world_face,
+        }
+    }
+
+    //reorders per-face data (indexed by Direction::to_idx()) so each face gets the data of the model face now pointing that way
+    pub fn rotate_faces<T: Copy>(self, faces: [T; 6]) -> [T; 6] {
+        std::array::from_fn(|i| faces[self.model_face(Direction::from(i)).to_idx()])
+    }
+}
+
+//inverse of the smallest rotation that takes `from` to `to`
+fn unrotate_face(face: Direction, from: Direction, to: Direction) -> Direction {
+    if from == to {
+        face
+    } else if from == to.opposite() {
+        //half turn, around y unless we're flipping vertically
+        let around = if matches!(from, Direction::PosY | Direction::NegY) {
+            BlockAxis::X
+        } else {
+            BlockAxis::Y
+        };
+        if BlockAxis::from_direction(face) == around {
+            face
+        } else {
+            face.opposite()
+        }
+    } else if face == to {
+        from
+    } else if face == to.opposite() {
+        from.opposite()
+    } else if face == from {
+        to.opposite()
+    } else if face == from.opposite() {
+        to
+    } else {
+        face
+    }
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Default, Reflect, Debug)]
+#[reflect(Component, FromWorld)]
+//declares which block state properties a block uses, and how they're chosen when the block is placed
+pub struct BlockStateProperties {
+    //front of the block faces the player who placed it
+    pub facing: bool,
+    //only face horizontal directions
+    pub horizontal_only: bool,
+    //aligns with the face the block was placed against (like logs)
+    pub axis: bool,
+    //top or bottom half of the block, based on where it was placed against
+    pub half: bool,
+    //can be opened and closed. always placed closed
+    pub open: bool,
+}
+
+impl BlockStateProperties {
+    //`normal` is the face of the block that was clicked, `look` is the direction the placer is looking
+    //`hit_offset_y` is how far above the center of the clicked face the hit was
+    pub fn placement_state(
+        &self,
+        normal: Direction,
+        look: Vec3,
+        hit_offset_y: f32,
+    ) -> BlockState {
+        let mut state = BlockState::default();
+        if self.facing {
+            let look = if self.horizontal_only {
+                Vec3::new(look.x, 0.0, look.z)
+            } else {
+                look
+            };
+            if look != Vec3::ZERO {
+                state = state.with_facing(Some(Direction::from(-look)));
+            }
+        }
+        if self.axis {
+            state = state.with_axis(Some(BlockAxis::from_direction(normal)));
+        }
+        if self.half {
+            let half = match normal {
+                Direction::NegY => BlockHalf::Top,
+                Direction::PosY => BlockHalf::Bottom,
+                _ if hit_offset_y > 0.0 => BlockHalf::Top,
+                _ => BlockHalf::Bottom,
+            };
+            state = state.with_half(Some(half));
+        }
+        state
+    }
+}
+
+//sparse per-chunk storage for block states. most blocks have the default state, so only the rest are stored
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BlockStates {
+    //sorted by index
+    states: Vec<(u16, BlockState)>,
+}
+
+impl BlockStates {
+    pub fn get(&self, idx: usize) -> BlockState {
+        match self.states.binary_search_by_key(&(idx as u16), |(i, _)| *i) {
+            Ok(pos) => self.states[pos].1,
+            Err(_) => BlockState::default(),
+        }
+    }
+
+    pub fn set(&mut self, idx: usize, state: BlockState) {
+        match self.states.binary_search_by_key(&(idx as u16), |(i, _)| *i) {
+            Ok(pos) if state.is_default() => {
+                self.states.remove(pos);
+            }
+            Ok(pos) => self.states[pos].1 = state,
+            Err(_) if state.is_default() => {}
+            Err(pos) => self.states.insert(pos, (idx as u16, state)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, BlockState)> + '_ {
+        self.states.iter().map(|(i, s)| (*i as usize, *s))
+    }
+}
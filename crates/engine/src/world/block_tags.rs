@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+//a named group of blocks, like core:logs or core:soil
+//blocks declare their tags in their scene files, and the registry turns them into bitsets so checking a block's tags doesn't need its components
+#[derive(Default, Clone, Debug, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub struct BlockTag {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl BlockTag {
+    pub fn new(namespace: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+            name: name.into(),
+        }
+    }
+    //creates a tag in the core namespace
+    pub fn core(name: impl Into<String>) -> Self {
+        Self {
+            namespace: "core".into(),
+            name: name.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for BlockTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.name)
+    }
+}
+
+//the tags a block type has. only read when the registry is built, use BlockRegistry to check tags after that
+#[derive(Component, Clone, Reflect, Default, Debug)]
+#[reflect(Component, FromWorld)]
+pub struct BlockTags(pub Vec<BlockTag>);
+
+//index of a tag in the registry. ids are assigned in the order tags are first seen, so they aren't stable across runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TagId(pub u16);
+
+//set of tags, one bit per TagId
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct BlockTagSet {
+    words: Vec<u64>,
+}
+
+impl BlockTagSet {
+    pub fn insert(&mut self, tag: TagId) {
+        let (word, bit) = Self::locate(tag);
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= 1 << bit;
+    }
+
+    pub fn contains(&self, tag: TagId) -> bool {
+        let (word, bit) = Self::locate(tag);
+        self.words.get(word).is_some_and(|w| w & (1 << bit) != 0)
+    }
+
+    //true if any tag is in both sets
+    pub fn intersects(&self, other: &BlockTagSet) -> bool {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .any(|(a, b)| a & b != 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|w| *w == 0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = TagId> + '_ {
+        self.words.iter().enumerate().flat_map(|(word, bits)| {
+            (0..u64::BITS)
+                .filter(move |bit| bits & (1 << bit) != 0)
+                .map(move |bit| TagId((word as u32 * u64::BITS + bit) as u16))
+        })
+    }
+
+    fn locate(tag: TagId) -> (usize, u32) {
+        (
+            tag.0 as usize / u64::BITS as usize,
+            tag.0 as u32 % u64::BITS,
+        )
+    }
+}
+
+impl FromIterator<TagId> for BlockTagSet {
+    fn from_iter<T: IntoIterator<Item = TagId>>(iter: T) -> Self {
+        let mut set = BlockTagSet::default();
+        for tag in iter {
+            set.insert(tag);
+        }
+        set
+    }
+}
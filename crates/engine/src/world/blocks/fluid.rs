@@ -0,0 +1,250 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use util::direction::Direction;
+
+use crate::{
+    mesher::NeedsMesh,
+    serialization::NeedsSaving,
+    world::{
+        chunk::ChunkCoord, events::ChunkUpdatedEvent, BlockCoord, BlockId, BlockType, Level,
+        LevelSystemSet,
+    },
+};
+
+pub struct FluidPlugin;
+
+impl Plugin for FluidPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, tick_fluids.in_set(LevelSystemSet::PostTick))
+            .register_type::<Fluid>();
+    }
+}
+
+//fixed ticks between fluid updates
+const FLUID_TICK_INTERVAL: u32 = 8;
+
+//fluid levels are stored on the level. a fluid block without a stored level is a source
+//flowing fluid goes from 1 (almost gone) to MAX_FLOWING_LEVEL (next to a source)
+pub const MAX_FLOWING_LEVEL: u8 = 7;
+//fluid that is flowing down from above. spreads sideways like a source, but dries up without one
+pub const FALLING_LEVEL: u8 = MAX_FLOWING_LEVEL + 1;
+//height of a source block's surface
+pub const SOURCE_HEIGHT: f32 = 0.875;
+
+#[derive(Component, Clone, Copy, Reflect, Default)]
+#[reflect(Component, FromWorld)]
+pub struct Fluid {
+    //flowing fluid between two sources on solid ground becomes a new source
+    pub infinite_sources: bool,
+}
+
+//None for a source block
+fn effective_level(level: Option<u8>) -> u8 {
+    level.unwrap_or(FALLING_LEVEL)
+}
+
+//height of the fluid surface inside the block, from 0-1
+pub fn fluid_height(level: Option<u8>) -> f32 {
+    effective_level(level).min(FALLING_LEVEL) as f32 / FALLING_LEVEL as f32 * SOURCE_HEIGHT
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum FluidChange {
+    Remove,
+    //None for a source
+    Set(Option<u8>),
+}
+
+fn tick_fluids(
+    mut tick: Local<u32>,
+    level: Res<Level>,
+    fluid_query: Query<&Fluid>,
+    id_query: Query<&BlockId>,
+    mut update_writer: EventWriter<ChunkUpdatedEvent>,
+    mut commands: Commands,
+) {
+    *tick += 1;
+    if *tick < FLUID_TICK_INTERVAL {
+        return;
+    }
+    *tick = 0;
+    let _my_span = info_span!("tick_fluids", name = "tick_fluids").entered();
+
+    let is_fluid = |block: Option<BlockType>, fluid_block: Entity| {
+        matches!(block, Some(BlockType::Filled(e)) if e == fluid_block)
+    };
+    //all changes are computed from the state at the start of the tick, so update order doesn't matter
+    let mut changes: HashMap<BlockCoord, (Entity, FluidChange)> = HashMap::new();
+    let mut propose = |pos: BlockCoord, fluid_block: Entity, change: FluidChange| {
+        match changes.get(&pos) {
+            //removing always loses to adding fluid
+            Some((_, FluidChange::Set(old))) => {
+                if let FluidChange::Set(new) = change
+                    && new.is_none_or(|new| old.is_some_and(|old| new > old))
+                {
+                    changes.insert(pos, (fluid_block, change));
+                }
+            }
+            _ => {
+                changes.insert(pos, (fluid_block, change));
+            }
+        }
+    };
+    for pos in level.take_fluid_updates() {
+        let Some(BlockType::Filled(fluid_block)) = level.get_block(pos) else {
+            continue;
+        };
+        let Ok(fluid) = fluid_query.get(fluid_block) else {
+            continue;
+        };
+        let current = level.get_fluid_level(pos);
+        let below = level.get_block(pos.offset(Direction::NegY));
+
+        //find what this block's level should be
+        let target = if current.is_none() {
+            FluidChange::Set(None)
+        } else if is_fluid(level.get_block(pos.offset(Direction::PosY)), fluid_block) {
+            FluidChange::Set(Some(FALLING_LEVEL))
+        } else {
+            let mut max_level = 0;
+            let mut sources = 0;
+            for dir in [
+                Direction::PosX,
+                Direction::NegX,
+                Direction::PosZ,
+                Direction::NegZ,
+            ] {
+                let neighbor = pos.offset(dir);
+                if !is_fluid(level.get_block(neighbor), fluid_block) {
+                    continue;
+                }
+                let neighbor_level = level.get_fluid_level(neighbor);
+                if neighbor_level.is_none() {
+                    sources += 1;
+                }
+                max_level = max_level.max(effective_level(neighbor_level) - 1);
+            }
+            let below_supports = match below {
+                Some(BlockType::Filled(e)) if e == fluid_block => {
+                    level.get_fluid_level(pos.offset(Direction::NegY)).is_none()
+                }
+                Some(BlockType::Filled(_)) => true,
+                _ => false,
+            };
+            if fluid.infinite_sources && sources >= 2 && below_supports {
+                FluidChange::Set(None)
+            } else if max_level > 0 {
+                FluidChange::Set(Some(max_level))
+            } else {
+                FluidChange::Remove
+            }
+        };
+        if target != FluidChange::Set(current) {
+            propose(pos, fluid_block, target);
+        }
+        let FluidChange::Set(new_level) = target else {
+            continue;
+        };
+
+        //spread down if we can, otherwise out to the sides
+        let below_pos = pos.offset(Direction::NegY);
+        match below {
+            Some(BlockType::Empty) => {
+                propose(below_pos, fluid_block, FluidChange::Set(Some(FALLING_LEVEL)))
+            }
+            Some(BlockType::Filled(e)) if e == fluid_block => {
+                if level
+                    .get_fluid_level(below_pos)
+                    .is_some_and(|l| l < FALLING_LEVEL)
+                {
+                    propose(below_pos, fluid_block, FluidChange::Set(Some(FALLING_LEVEL)));
+                }
+            }
+            None => {}
+            Some(BlockType::Filled(_)) => {
+                let spread_level = effective_level(new_level) - 1;
+                if spread_level == 0 {
+                    continue;
+                }
+                for dir in [
+                    Direction::PosX,
+                    Direction::NegX,
+                    Direction::PosZ,
+                    Direction::NegZ,
+                ] {
+                    let neighbor = pos.offset(dir);
+                    match level.get_block(neighbor) {
+                        Some(BlockType::Empty) => {
+                            propose(neighbor, fluid_block, FluidChange::Set(Some(spread_level)))
+                        }
+                        Some(BlockType::Filled(e))
+                            if e == fluid_block
+                                && level
+                                    .get_fluid_level(neighbor)
+                                    .is_some_and(|l| l < spread_level) =>
+                        {
+                            propose(neighbor, fluid_block, FluidChange::Set(Some(spread_level)))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    //blocks that are placed or removed go through the normal path, which also queues the next fluid update
+    let mut placed = Vec::new();
+    let mut to_remesh = Vec::new();
+    for (pos, (fluid_block, change)) in changes.iter() {
+        match change {
+            FluidChange::Remove => placed.push((*pos, BlockType::Empty)),
+            FluidChange::Set(_) => {
+                if level.get_block(*pos) == Some(BlockType::Filled(*fluid_block)) {
+                    to_remesh.push(*pos);
+                } else {
+                    placed.push((*pos, BlockType::Filled(*fluid_block)));
+                }
+            }
+        }
+    }
+    level.batch_set_block_entities(
+        placed.into_iter(),
+        &id_query,
+        &mut update_writer,
+        &mut commands,
+    );
+    for (pos, (_, change)) in changes {
+        if let FluidChange::Set(fluid_level) = change {
+            level.set_fluid_level(pos, fluid_level);
+        }
+    }
+    //level-only changes don't touch the block, so we have to remesh, save and queue updates ourselves
+    //fluid levels are saved with the chunk's terrain, so only the chunk the block is in needs saving
+    let mut remesh_chunks = HashSet::new();
+    let mut save_chunks = HashSet::new();
+    for pos in to_remesh {
+        level.queue_fluid_update(pos);
+        save_chunks.insert(ChunkCoord::from(pos));
+        remesh_chunks.insert(ChunkCoord::from(pos));
+        for dir in Direction::iter() {
+            remesh_chunks.insert(ChunkCoord::from(pos.offset(dir)));
+        }
+    }
+    for coord in remesh_chunks {
+        if let Some(entity) = level.get_chunk_entity(coord)
+            && let Some(mut ec) = commands.get_entity(entity)
+        {
+            ec.try_insert(NeedsMesh::default());
+        }
+    }
+    for coord in save_chunks {
+        if let Some(entity) = level.get_chunk_entity(coord)
+            && let Some(mut ec) = commands.get_entity(entity)
+        {
+            ec.try_insert(NeedsSaving);
+        }
+    }
+}
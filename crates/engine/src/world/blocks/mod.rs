@@ -8,11 +8,22 @@ pub struct BlocksPlugin;
 
 pub mod tnt;
 pub mod fall;
+pub mod fluid;
+pub mod ticks;
+pub mod signal;
+pub mod support;
 
 impl Plugin for BlocksPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_plugins((tnt::TNTPlugin, fall::FallPlugin))
+            .add_plugins((
+                tnt::TNTPlugin,
+                fall::FallPlugin,
+                fluid::FluidPlugin,
+                ticks::BlockTickPlugin,
+                signal::SignalPlugin,
+                support::SupportPlugin,
+            ))
             .add_systems(Update, heal_block_damages.in_set(LevelSystemSet::Main))
         ;
     }
@@ -0,0 +1,284 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use util::direction::Direction;
+
+use crate::world::{
+    events::{send_neighbor_changed_events, BlockNeighborChangedEvent, ChunkUpdatedEvent},
+    BlockCoord, BlockId, BlockName, BlockResources, BlockType, Level, LevelSystemSet,
+};
+
+use super::ticks::{BlockTickEvent, BlockTickKind};
+
+pub struct SignalPlugin;
+
+impl Plugin for SignalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SignalChangedEvent>()
+            .add_event::<AlarmRungEvent>()
+            .add_systems(
+                Update,
+                //reads the neighbor events sent this frame, so a broken wire stops carrying signal right away
+                (
+                    update_signals.after(send_neighbor_changed_events),
+                    (swap_on_signal, ring_alarms),
+                )
+                    .chain()
+                    .in_set(LevelSystemSet::Main),
+            )
+            .register_type::<SignalSource>()
+            .register_type::<SignalWire>()
+            .register_type::<SignalConsumer>()
+            .register_type::<BreakSensor>()
+            .register_type::<SignalSwap>()
+            .register_type::<AlarmBell>();
+    }
+}
+
+pub const MAX_SIGNAL: u8 = 15;
+//wires past this in one network are ignored, so a huge network can't stall a frame
+const MAX_NETWORK_SIZE: usize = 4096;
+
+//always powers neighboring wires and consumers
+#[derive(Component, Clone, Copy, Reflect, Default)]
+#[reflect(Component, FromWorld)]
+pub struct SignalSource {
+    pub strength: u8,
+}
+
+//carries signal, losing one strength per block
+#[derive(Component, Clone, Copy, Reflect, Default)]
+#[reflect(Component, FromWorld)]
+pub struct SignalWire;
+
+//gets a SignalChangedEvent when it becomes powered or unpowered
+#[derive(Component, Clone, Copy, Reflect, Default)]
+#[reflect(Component, FromWorld)]
+pub struct SignalConsumer;
+
+//acts as a source for `pulse_ticks` block ticks after one of its neighbors is broken
+#[derive(Component, Clone, Copy, Reflect, Default)]
+#[reflect(Component, FromWorld)]
+pub struct BreakSensor {
+    pub strength: u8,
+    pub pulse_ticks: u32,
+}
+
+//consumer that turns into `to` when its powered state becomes `when_powered` (opening and closing gates)
+#[derive(Component, Clone, Reflect, Default)]
+#[reflect(Component, FromWorld)]
+pub struct SignalSwap {
+    pub to: BlockName,
+    pub when_powered: bool,
+}
+
+//consumer that sends an AlarmRungEvent when it becomes powered
+#[derive(Component, Clone, Copy, Reflect, Default)]
+#[reflect(Component, FromWorld)]
+pub struct AlarmBell;
+
+#[derive(Event)]
+pub struct SignalChangedEvent {
+    pub block_position: BlockCoord,
+    pub block: Entity,
+    pub powered: bool,
+}
+
+#[derive(Event)]
+pub struct AlarmRungEvent {
+    pub block_position: BlockCoord,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SignalRole {
+    None,
+    Wire,
+    Source(u8),
+    Consumer,
+}
+
+fn update_signals(
+    mut neighbor_reader: EventReader<BlockNeighborChangedEvent>,
+    mut tick_reader: EventReader<BlockTickEvent>,
+    mut signal_writer: EventWriter<SignalChangedEvent>,
+    level: Res<Level>,
+    role_query: Query<(
+        Option<&SignalWire>,
+        Option<&SignalSource>,
+        Option<&BreakSensor>,
+        Option<&SignalConsumer>,
+    )>,
+    mut commands: Commands,
+) {
+    let mut seeds = HashSet::new();
+    for event in neighbor_reader.read() {
+        seeds.insert(event.changed_position);
+        //a neighbor was broken, so trip the sensor
+        if event.changed_to == BlockType::Empty
+            && let Ok((_, _, Some(sensor), _)) = role_query.get(event.block)
+        {
+            level.set_signal_level(event.block_position, sensor.strength.min(MAX_SIGNAL));
+            level.schedule_block_tick(event.block_position, sensor.pulse_ticks, &mut commands);
+            seeds.insert(event.block_position);
+        }
+    }
+    for tick in tick_reader.read() {
+        if tick.kind == BlockTickKind::Scheduled
+            && let Ok((_, _, Some(_), _)) = role_query.get(tick.block)
+        {
+            level.set_signal_level(tick.position, 0);
+            seeds.insert(tick.position);
+        }
+    }
+    if seeds.is_empty() {
+        return;
+    }
+    let _my_span = info_span!("update_signals", name = "update_signals").entered();
+
+    let role = |pos: BlockCoord| -> SignalRole {
+        let Some(BlockType::Filled(block)) = level.get_block(pos) else {
+            return SignalRole::None;
+        };
+        match role_query.get(block) {
+            Ok((Some(_), _, _, _)) => SignalRole::Wire,
+            Ok((_, Some(source), _, _)) => SignalRole::Source(source.strength.min(MAX_SIGNAL)),
+            Ok((_, _, Some(_), _)) => SignalRole::Source(level.get_signal_level(pos)),
+            Ok((_, _, _, Some(_))) => SignalRole::Consumer,
+            _ => SignalRole::None,
+        }
+    };
+
+    //find every wire and consumer connected to the changed blocks
+    let mut roles = HashMap::new();
+    let mut wires = Vec::new();
+    let mut consumers = HashSet::new();
+    let mut to_visit: Vec<BlockCoord> = seeds.iter().copied().collect();
+    while let Some(pos) = to_visit.pop() {
+        if roles.contains_key(&pos) {
+            continue;
+        }
+        let pos_role = role(pos);
+        roles.insert(pos, pos_role);
+        match pos_role {
+            SignalRole::Wire if wires.len() < MAX_NETWORK_SIZE => wires.push(pos),
+            SignalRole::Wire => continue,
+            SignalRole::Consumer => {
+                consumers.insert(pos);
+                continue;
+            }
+            //the changed blocks might be anything (like a wire that was just broken), so always search around them
+            SignalRole::None | SignalRole::Source(_) if seeds.contains(&pos) => {}
+            SignalRole::None | SignalRole::Source(_) => continue,
+        }
+        for dir in Direction::iter() {
+            let neighbor = pos.offset(dir);
+            if !roles.contains_key(&neighbor) {
+                to_visit.push(neighbor);
+            }
+        }
+    }
+    let mut neighbor_role = |pos: BlockCoord| -> SignalRole {
+        *roles.entry(pos).or_insert_with(|| role(pos))
+    };
+
+    //spread power from the sources through the wires
+    let mut levels: HashMap<BlockCoord, u8> = wires.iter().map(|pos| (*pos, 0)).collect();
+    let mut queue = Vec::new();
+    for wire in wires.iter() {
+        let mut strength = 0;
+        for dir in Direction::iter() {
+            if let SignalRole::Source(s) = neighbor_role(wire.offset(dir)) {
+                strength = strength.max(s);
+            }
+        }
+        if strength > 0 {
+            levels.insert(*wire, strength);
+            queue.push(*wire);
+        }
+    }
+    while let Some(pos) = queue.pop() {
+        let spread = levels[&pos].saturating_sub(1);
+        if spread == 0 {
+            continue;
+        }
+        for dir in Direction::iter() {
+            let neighbor = pos.offset(dir);
+            if let Some(neighbor_level) = levels.get_mut(&neighbor)
+                && *neighbor_level < spread
+            {
+                *neighbor_level = spread;
+                queue.push(neighbor);
+            }
+        }
+    }
+    for (pos, strength) in levels.iter() {
+        level.set_signal_level(*pos, *strength);
+    }
+
+    //consumers are powered by any neighboring source or powered wire
+    for consumer in consumers {
+        let mut strength = 0;
+        for dir in Direction::iter() {
+            let neighbor = consumer.offset(dir);
+            match neighbor_role(neighbor) {
+                SignalRole::Source(s) => strength = strength.max(s),
+                SignalRole::Wire => {
+                    strength = strength.max(levels.get(&neighbor).copied().unwrap_or(0))
+                }
+                _ => {}
+            }
+        }
+        let was_powered = level.get_signal_level(consumer) > 0;
+        level.set_signal_level(consumer, strength);
+        if was_powered != (strength > 0)
+            && let Some(BlockType::Filled(block)) = level.get_block(consumer)
+        {
+            signal_writer.send(SignalChangedEvent {
+                block_position: consumer,
+                block,
+                powered: strength > 0,
+            });
+        }
+    }
+}
+
+fn swap_on_signal(
+    mut reader: EventReader<SignalChangedEvent>,
+    swap_query: Query<&SignalSwap>,
+    level: Res<Level>,
+    resources: Res<BlockResources>,
+    id_query: Query<&BlockId>,
+    mut update_writer: EventWriter<ChunkUpdatedEvent>,
+    mut commands: Commands,
+) {
+    for event in reader.read() {
+        if let Ok(swap) = swap_query.get(event.block)
+            && swap.when_powered == event.powered
+            && let Some(to) = resources.registry.get_basic(&swap.to)
+        {
+            level.set_block_entity(
+                event.block_position,
+                BlockType::Filled(to),
+                &id_query,
+                &mut update_writer,
+                &mut commands,
+            );
+        }
+    }
+}
+
+fn ring_alarms(
+    mut reader: EventReader<SignalChangedEvent>,
+    mut writer: EventWriter<AlarmRungEvent>,
+    alarm_query: Query<&AlarmBell>,
+) {
+    for event in reader.read() {
+        if event.powered && alarm_query.contains(event.block) {
+            writer.send(AlarmRungEvent {
+                block_position: event.block_position,
+            });
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use util::direction::{Direction, DirectionFlags};
+
+use crate::{
+    actors::block_actors::{FallingBlock, SpawnFallingBlockEvent},
+    world::{
+        events::ChunkUpdatedEvent, BlockCoord, BlockId, BlockType, Level, LevelSystemSet,
+    },
+};
+
+pub struct SupportPlugin;
+
+impl Plugin for SupportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, process_support.in_set(LevelSystemSet::Main))
+            .register_type::<StructuralSupport>();
+    }
+}
+
+//structures bigger than this are assumed to be supported, so one check can't stall a frame
+const MAX_STRUCTURE_SIZE: usize = 4096;
+
+//opts a block in to falling when it loses support
+//resting on a block without this component (or an unloaded chunk) fully supports it, and so does resting on a supported block
+//support drops by one for each block it has to reach sideways or down, and can't be more than `strength`
+//so strength 1 only stands on something, and strength 5 can hang up to 4 blocks away from a support
+//blocks without this component never fall and always count as ground
+#[derive(Component, Clone, Copy, Reflect, Default)]
+#[reflect(Component, FromWorld)]
+pub struct StructuralSupport {
+    pub strength: u8,
+}
+
+fn process_support(
+    level: Res<Level>,
+    support_query: Query<&StructuralSupport>,
+    id_query: Query<&BlockId>,
+    mut fall_writer: EventWriter<SpawnFallingBlockEvent>,
+    mut update_writer: EventWriter<ChunkUpdatedEvent>,
+    mut commands: Commands,
+) {
+    let removed = level.take_support_checks();
+    if removed.is_empty() {
+        return;
+    }
+    let _my_span = info_span!("process_support", name = "process_support").entered();
+    let strength = |block: Option<BlockType>| match block {
+        Some(BlockType::Filled(e)) => support_query.get(e).ok().map(|s| s.strength),
+        _ => None,
+    };
+
+    let mut checked = HashSet::new();
+    let mut to_fall = Vec::new();
+    for start in removed
+        .iter()
+        .flat_map(|pos| Direction::iter().map(move |dir| pos.offset(dir)))
+    {
+        if checked.contains(&start) || strength(level.get_block(start)).is_none() {
+            continue;
+        }
+        //find the structure connected to the removed block
+        let mut structure: HashMap<BlockCoord, (Entity, u8)> = HashMap::new();
+        let mut to_visit = vec![start];
+        let mut too_big = false;
+        while let Some(pos) = to_visit.pop() {
+            if structure.contains_key(&pos) {
+                continue;
+            }
+            let block = level.get_block(pos);
+            let (Some(BlockType::Filled(entity)), Some(s)) = (block, strength(block)) else {
+                continue;
+            };
+            structure.insert(pos, (entity, s));
+            if structure.len() > MAX_STRUCTURE_SIZE {
+                too_big = true;
+                break;
+            }
+            for dir in Direction::iter() {
+                to_visit.push(pos.offset(dir));
+            }
+        }
+        checked.extend(structure.keys().copied());
+        if too_big {
+            continue;
+        }
+
+        //blocks resting on ground are fully supported, then support spreads through the structure
+        let mut support: HashMap<BlockCoord, u8> = HashMap::new();
+        let mut queue = Vec::new();
+        for (pos, (_, s)) in structure.iter() {
+            let below = pos.offset(Direction::NegY);
+            let grounded = match level.get_block(below) {
+                None => true,
+                Some(BlockType::Empty) => false,
+                Some(BlockType::Filled(_)) => !structure.contains_key(&below),
+            };
+            if grounded && *s > 0 {
+                support.insert(*pos, *s);
+                queue.push(*pos);
+            }
+        }
+        while let Some(pos) = queue.pop() {
+            let pos_support = support[&pos];
+            for dir in Direction::iter() {
+                let neighbor = pos.offset(dir);
+                let Some((_, neighbor_strength)) = structure.get(&neighbor) else {
+                    continue;
+                };
+                let carried = if dir == Direction::PosY {
+                    pos_support
+                } else {
+                    pos_support - 1
+                };
+                let new_support = carried.min(*neighbor_strength);
+                if new_support > support.get(&neighbor).copied().unwrap_or(0) {
+                    support.insert(neighbor, new_support);
+                    queue.push(neighbor);
+                }
+            }
+        }
+        for (pos, (entity, _)) in structure {
+            if !support.contains_key(&pos) {
+                to_fall.push((pos, entity));
+            }
+        }
+    }
+    if to_fall.is_empty() {
+        return;
+    }
+    level.batch_set_block_entities(
+        to_fall.iter().map(|(pos, _)| (*pos, BlockType::Empty)),
+        &id_query,
+        &mut update_writer,
+        &mut commands,
+    );
+    fall_writer.send_batch(to_fall.into_iter().map(|(pos, block)| SpawnFallingBlockEvent {
+        position: pos.to_vec3(),
+        initial_velocity: Vec3::ZERO,
+        falling_block: FallingBlock {
+            block,
+            place_on_landing: true,
+            impact_direcitons: DirectionFlags::NegY,
+        },
+    }));
+}
@@ -0,0 +1,256 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use util::direction::Direction;
+
+use crate::{
+    serialization::NeedsSaving,
+    world::{
+        chunk::{ChunkIdx, ChunkType, BLOCKS_PER_CHUNK},
+        events::ChunkUpdatedEvent,
+        BlockCoord, BlockId, BlockName, BlockResources, BlockType, Level, LevelSystemSet,
+    },
+};
+
+pub struct BlockTickPlugin;
+
+impl Plugin for BlockTickPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BlockTickEvent>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    (send_scheduled_ticks, send_random_ticks).in_set(LevelSystemSet::PreTick),
+                    (spread_on_tick, melt_on_tick, grow_on_tick).in_set(LevelSystemSet::Tick),
+                ),
+            )
+            .register_type::<RandomTicking>()
+            .register_type::<SpreadOnRandomTick>()
+            .register_type::<MeltOnRandomTick>()
+            .register_type::<GrowOnRandomTick>();
+    }
+}
+
+//positions sampled in each loaded chunk every fixed update
+const RANDOM_TICKS_PER_CHUNK: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockTickKind {
+    //requested with `LevelData::schedule_block_tick`
+    Scheduled,
+    //only sent to blocks with `RandomTicking`
+    Random,
+}
+
+#[derive(Event)]
+pub struct BlockTickEvent {
+    pub position: BlockCoord,
+    pub block: Entity,
+    pub kind: BlockTickKind,
+}
+
+//block opts in to random ticks
+#[derive(Component, Clone, Copy, Reflect, Default)]
+#[reflect(Component, FromWorld)]
+pub struct RandomTicking;
+
+//on random ticks, turns a nearby `target` block with nothing above it into this block (grass onto dirt)
+#[derive(Component, Clone, Reflect, Default)]
+#[reflect(Component, FromWorld)]
+pub struct SpreadOnRandomTick {
+    pub target: BlockName,
+}
+
+//removed on random ticks once the block light here reaches `min_block_light` (snow near a light)
+#[derive(Component, Clone, Copy, Reflect, Default)]
+#[reflect(Component, FromWorld)]
+pub struct MeltOnRandomTick {
+    pub min_block_light: u8,
+}
+
+//on random ticks, grows upwards until its column is `max_height` blocks tall
+//if `stem` is set, the block moves up and leaves the stem behind it (cactus flower on top of cactus)
+#[derive(Component, Clone, Reflect, Default)]
+#[reflect(Component, FromWorld)]
+pub struct GrowOnRandomTick {
+    pub max_height: u8,
+    pub stem: Option<BlockName>,
+}
+
+fn send_scheduled_ticks(
+    level: Res<Level>,
+    mut writer: EventWriter<BlockTickEvent>,
+    mut commands: Commands,
+) {
+    for position in level.advance_block_ticks() {
+        //the chunk's saved ticks are now out of date
+        if let Some(entity) = level.get_chunk_entity(position.into())
+            && let Some(mut ec) = commands.get_entity(entity)
+        {
+            ec.try_insert(NeedsSaving);
+        }
+        if let Some(BlockType::Filled(block)) = level.get_block(position) {
+            writer.send(BlockTickEvent {
+                position,
+                block,
+                kind: BlockTickKind::Scheduled,
+            });
+        }
+    }
+}
+
+fn send_random_ticks(
+    level: Res<Level>,
+    random_query: Query<(), With<RandomTicking>>,
+    mut writer: EventWriter<BlockTickEvent>,
+) {
+    let _my_span = info_span!("send_random_ticks", name = "send_random_ticks").entered();
+    let mut rng = thread_rng();
+    let mut ticks = Vec::new();
+    for chunk_ref in level.chunks_iter() {
+        let ChunkType::Full(chunk) = chunk_ref.value() else {
+            continue;
+        };
+        //most chunks won't have any blocks that care
+        if !chunk.blocks.palette.iter().any(|(_, block, count)| {
+            *count > 0 && matches!(block, BlockType::Filled(e) if random_query.contains(*e))
+        }) {
+            continue;
+        }
+        let origin = BlockCoord::from(chunk.position);
+        for _ in 0..RANDOM_TICKS_PER_CHUNK {
+            let idx = rng.gen_range(0..BLOCKS_PER_CHUNK);
+            if let BlockType::Filled(block) = chunk[idx]
+                && random_query.contains(block)
+            {
+                ticks.push(BlockTickEvent {
+                    position: origin + BlockCoord::from(ChunkIdx::from_usize(idx)),
+                    block,
+                    kind: BlockTickKind::Random,
+                });
+            }
+        }
+    }
+    writer.send_batch(ticks);
+}
+
+fn spread_on_tick(
+    mut reader: EventReader<BlockTickEvent>,
+    spread_query: Query<&SpreadOnRandomTick>,
+    level: Res<Level>,
+    resources: Res<BlockResources>,
+    id_query: Query<&BlockId>,
+    mut update_writer: EventWriter<ChunkUpdatedEvent>,
+    mut commands: Commands,
+) {
+    let mut rng = thread_rng();
+    for tick in reader.read() {
+        if tick.kind != BlockTickKind::Random {
+            continue;
+        }
+        let Ok(spread) = spread_query.get(tick.block) else {
+            continue;
+        };
+        let Some(target) = resources.registry.get_basic(&spread.target) else {
+            continue;
+        };
+        let offset = BlockCoord::new(
+            rng.gen_range(-1..=1),
+            rng.gen_range(-1..=1),
+            rng.gen_range(-1..=1),
+        );
+        let pos = tick.position + offset;
+        if level.get_block(pos) == Some(BlockType::Filled(target))
+            && level.get_block(pos.offset(Direction::PosY)) == Some(BlockType::Empty)
+        {
+            level.set_block_entity(
+                pos,
+                BlockType::Filled(tick.block),
+                &id_query,
+                &mut update_writer,
+                &mut commands,
+            );
+        }
+    }
+}
+
+fn melt_on_tick(
+    mut reader: EventReader<BlockTickEvent>,
+    melt_query: Query<&MeltOnRandomTick>,
+    level: Res<Level>,
+    id_query: Query<&BlockId>,
+    mut update_writer: EventWriter<ChunkUpdatedEvent>,
+    mut commands: Commands,
+) {
+    for tick in reader.read() {
+        if tick.kind != BlockTickKind::Random {
+            continue;
+        }
+        let Ok(melt) = melt_query.get(tick.block) else {
+            continue;
+        };
+        let light = level
+            .get_light(tick.position)
+            .map(|light| light.block())
+            .unwrap_or(0);
+        if light >= melt.min_block_light {
+            level.set_block_entity(
+                tick.position,
+                BlockType::Empty,
+                &id_query,
+                &mut update_writer,
+                &mut commands,
+            );
+        }
+    }
+}
+
+fn grow_on_tick(
+    mut reader: EventReader<BlockTickEvent>,
+    grow_query: Query<&GrowOnRandomTick>,
+    level: Res<Level>,
+    resources: Res<BlockResources>,
+    id_query: Query<&BlockId>,
+    mut update_writer: EventWriter<ChunkUpdatedEvent>,
+    mut commands: Commands,
+) {
+    for tick in reader.read() {
+        if tick.kind != BlockTickKind::Random {
+            continue;
+        }
+        let Ok(grow) = grow_query.get(tick.block) else {
+            continue;
+        };
+        let above = tick.position.offset(Direction::PosY);
+        if level.get_block(above) != Some(BlockType::Empty) {
+            continue;
+        }
+        let stem = grow
+            .stem
+            .as_ref()
+            .and_then(|name| resources.registry.get_basic(name));
+        let in_column = |block: Option<BlockType>| match block {
+            Some(BlockType::Filled(e)) => e == tick.block || Some(e) == stem,
+            _ => false,
+        };
+        let mut height = 1;
+        let mut below = tick.position.offset(Direction::NegY);
+        while height < grow.max_height && in_column(level.get_block(below)) {
+            height += 1;
+            below = below.offset(Direction::NegY);
+        }
+        if height >= grow.max_height {
+            continue;
+        }
+        let mut changes = vec![(above, BlockType::Filled(tick.block))];
+        if let Some(stem) = stem {
+            changes.push((tick.position, BlockType::Filled(stem)));
+        }
+        level.batch_set_block_entities(
+            changes.into_iter(),
+            &id_query,
+            &mut update_writer,
+            &mut commands,
+        );
+    }
+}
@@ -1,10 +1,17 @@
+use std::marker::PhantomData;
+
 use bevy::prelude::*;
 
 use util::direction::DirectionFlags;
 
 use crate::{
-    actors::block_actors::{FallingBlock, LandedFallingBlockEvent, SpawnFallingBlockEvent},
+    actors::{
+        block_actors::{FallingBlock, LandedFallingBlockEvent, SpawnFallingBlockEvent},
+        team::{FreeForAllTeam, PlayerTeam},
+        Damage,
+    },
     world::{
+        blocks::signal::SignalChangedEvent,
         events::{BlockUsedEvent, ChunkUpdatedEvent, ExplosionEvent},
         BlockId, BlockType, Level, LevelSystemSet,
     },
@@ -26,31 +33,45 @@ impl Plugin for TNTPlugin {
 #[reflect(Component, FromWorld)]
 pub struct TNTBlock {
     pub explosion_strength: f32,
+    //damage and knockback to combatants at the center of the explosion
+    pub damage: f32,
+    pub knockback: f32,
+    //friendly tnt doesn't hurt players or the world anchor
+    pub friendly: bool,
 }
 
+//tnt is lit by using it or powering it
 pub fn process_tnt(
     mut explosions: EventWriter<SpawnFallingBlockEvent>,
     mut uses: EventReader<BlockUsedEvent>,
+    mut signals: EventReader<SignalChangedEvent>,
     tnt_query: Query<&TNTBlock>,
     level: Res<Level>,
     id_query: Query<&BlockId>,
     mut update_writer: EventWriter<ChunkUpdatedEvent>,
     mut commands: Commands,
 ) {
-    for used in uses.read() {
-        if tnt_query.get(used.block_used).is_ok() {
+    let used = uses
+        .read()
+        .map(|used| (used.block_position, used.block_used));
+    let powered = signals
+        .read()
+        .filter(|signal| signal.powered)
+        .map(|signal| (signal.block_position, signal.block));
+    for (block_position, block) in used.chain(powered) {
+        if tnt_query.get(block).is_ok() {
             level.set_block_entity(
-                used.block_position,
+                block_position,
                 BlockType::Empty,
                 &id_query,
                 &mut update_writer,
                 &mut commands,
             );
             explosions.send(SpawnFallingBlockEvent {
-                position: used.block_position.center(),
+                position: block_position.center(),
                 initial_velocity: Vec3::ZERO,
                 falling_block: FallingBlock {
-                    block: used.block_used,
+                    block,
                     place_on_landing: false,
                     impact_direcitons: DirectionFlags::all(),
                 },
@@ -60,16 +81,34 @@ pub fn process_tnt(
 }
 
 pub fn tnt_landed(
-    mut explosions: EventWriter<ExplosionEvent>,
+    mut friendly_explosions: EventWriter<ExplosionEvent<PlayerTeam>>,
+    mut explosions: EventWriter<ExplosionEvent<FreeForAllTeam>>,
     tnt_query: Query<&TNTBlock>,
     mut reader: EventReader<LandedFallingBlockEvent>,
 ) {
     for event in reader.read() {
         if let Ok(tnt) = tnt_query.get(event.falling_block.block) {
-            explosions.send(ExplosionEvent {
-                radius: tnt.explosion_strength,
-                origin: event.position,
-            });
+            let origin = event.position.center();
+            let damage = Damage::new(tnt.damage);
+            if tnt.friendly {
+                friendly_explosions.send(ExplosionEvent {
+                    origin,
+                    radius: tnt.explosion_strength,
+                    damage,
+                    knockback: tnt.knockback,
+                    owner: None,
+                    team: PhantomData,
+                });
+            } else {
+                explosions.send(ExplosionEvent {
+                    origin,
+                    radius: tnt.explosion_strength,
+                    damage,
+                    knockback: tnt.knockback,
+                    owner: None,
+                    team: PhantomData,
+                });
+            }
         }
     }
 }
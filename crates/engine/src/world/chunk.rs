@@ -8,7 +8,9 @@ use serde::{Deserialize, Serialize};
 
 use util::direction::Direction;
 
-use super::{util::BlockPalette, BlockCoord, BlockId, BlockRegistry, BlockType, Id};
+use super::{
+    util::BlockPalette, BlockCoord, BlockId, BlockRegistry, BlockState, BlockStates, BlockType, Id,
+};
 
 pub const CHUNK_SIZE: usize = 16;
 pub const FAT_CHUNK_SIZE: usize = CHUNK_SIZE + 2;
@@ -310,6 +312,7 @@ impl<T> ChunkBlock for T where T: Clone + Send + Sync + PartialEq {}
 pub trait ChunkTrait<Block: PartialEq>: Index<ChunkIdx> + Index<usize> {
     fn scale(&self) -> i32;
     fn get_block_pos(&self, pos: ChunkIdx) -> Vec3;
+    //also resets the block state at `idx`
     fn set_block(&mut self, idx: usize, block: Block);
 }
 
@@ -320,6 +323,8 @@ where
     Block: ChunkBlock,
 {
     pub blocks: Box<Storage>,
+    //state of each block, only used for full-resolution chunks
+    pub states: BlockStates,
     pub position: ChunkCoord,
     pub entity: Entity,
     //lod level, scale of chunk is 2^level
@@ -335,6 +340,7 @@ impl<Storage: ChunkStorage<Block>, Block: ChunkBlock> Chunk<Storage, Block> {
     ) -> Chunk<NewStorage, NewBlock> {
         Chunk {
             blocks: storage,
+            states: self.states.clone(),
             position: self.position,
             entity: self.entity,
             level: self.level,
@@ -344,6 +350,7 @@ impl<Storage: ChunkStorage<Block>, Block: ChunkBlock> Chunk<Storage, Block> {
     //writes all the data in `with` into `self` except for the entity
     pub fn overwrite(&mut self, with: Self) {
         self.blocks = with.blocks;
+        self.states = with.states;
         self.position = with.position;
         self.level = with.level;
     }
@@ -373,6 +380,7 @@ impl<Storage: ChunkStorage<Block>, Block: ChunkBlock> ChunkTrait<Block> for Chun
 
     fn set_block(&mut self, idx: usize, block: Block) {
         self.blocks.set_block(idx, block);
+        self.states.set(idx, BlockState::default());
     }
 }
 
@@ -412,6 +420,7 @@ impl ArrayChunk {
     pub fn new(position: ChunkCoord, entity: Entity) -> ArrayChunk {
         Chunk {
             blocks: Box::new(BlockPalette::new(BlockType::Empty)),
+            states: BlockStates::default(),
             entity,
             position,
             level: 1,
@@ -424,6 +433,7 @@ impl GeneratingChunk {
     pub fn new(position: ChunkCoord, entity: Entity) -> GeneratingChunk {
         Chunk {
             blocks: Box::new(BlockPalette::new(BlockId::default())),
+            states: BlockStates::default(),
             entity,
             position,
             level: 1,
@@ -446,7 +456,7 @@ impl GeneratingChunk {
             mapped_palette.push((*key, block, *r));
         }
         self.with_storage(Box::new(BlockPalette {
-            data: self.blocks.data,
+            data: self.blocks.data.clone(),
             palette: mapped_palette,
         }))
     }
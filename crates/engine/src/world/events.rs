@@ -1,18 +1,36 @@
+use std::marker::PhantomData;
+
 use super::{
-    chunk::ChunkCoord, BlockCoord, BlockDamage, BlockId, BlockResources, Id, Level, LevelSystemSet,
+    chunk::ChunkCoord, BlastResistance, BlockCoord, BlockDamage, BlockId, BlockResources,
+    BlockType, Id, Level, LevelData, LevelSystemSet,
+};
+use crate::{
+    actors::{team::*, AttackEvent, Combatant, Damage, LevelEntity},
+    all_teams_function, all_teams_system,
+    physics::{query::VoxelTraversal, spatial::SpatialIndex},
 };
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashSet};
+use rand::prelude::*;
+use util::direction::Direction;
 
 pub struct WorldEventsPlugin;
 
 impl Plugin for WorldEventsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<ExplosionEvent>()
-            .add_event::<BlockUsedEvent>()
+        app.add_event::<BlockUsedEvent>()
             .add_event::<BlockDamageSetEvent>()
             .add_event::<BlockHitEvent>()
             .add_event::<ChunkUpdatedEvent>()
-            .add_systems(Update, process_explosions.in_set(LevelSystemSet::Main));
+            .add_event::<BlockNeighborChangedEvent>()
+            .add_systems(
+                Update,
+                (
+                    all_teams_system!(process_explosions),
+                    send_neighbor_changed_events,
+                )
+                    .in_set(LevelSystemSet::Main),
+            );
+        all_teams_function!(app, add_event, ExplosionEvent);
     }
 }
 
@@ -40,10 +58,28 @@ pub struct BlockDamageSetEvent {
     pub damager: Option<Entity>,
 }
 
+//`radius` is how far the explosion reaches through open air. blocks in the way use up some of it, see BlastResistance
+//combatants that `T` targets take `damage` and `knockback`, scaled down by distance and by the blocks between them and `origin`
 #[derive(Event)]
-pub struct ExplosionEvent {
+pub struct ExplosionEvent<T: Team> {
+    pub origin: Vec3,
     pub radius: f32,
-    pub origin: BlockCoord,
+    pub damage: Damage,
+    pub knockback: f32,
+    //sent as the attacker. explosions without an owner are attributed to the level
+    pub owner: Option<Entity>,
+    pub team: PhantomData<T>,
+}
+
+//sent to each non-empty neighbor of a block that was set
+#[derive(Event)]
+pub struct BlockNeighborChangedEvent {
+    pub block_position: BlockCoord,
+    pub block: Entity,
+    pub changed_position: BlockCoord,
+    //direction from `block_position` to `changed_position`
+    pub direction: Direction,
+    pub changed_to: BlockType,
 }
 
 //triggered when a chunk is spawned in or a block is changed
@@ -52,32 +88,164 @@ pub struct ChunkUpdatedEvent {
     pub coord: ChunkCoord,
 }
 
-fn process_explosions(
-    mut reader: EventReader<ExplosionEvent>,
+pub fn send_neighbor_changed_events(
+    level: Res<Level>,
+    mut writer: EventWriter<BlockNeighborChangedEvent>,
+) {
+    let mut events = Vec::new();
+    for changed_position in level.take_changed_blocks() {
+        let Some(changed_to) = level.get_block(changed_position) else {
+            continue;
+        };
+        for dir in Direction::iter() {
+            let block_position = changed_position.offset(dir);
+            if let Some(BlockType::Filled(block)) = level.get_block(block_position) {
+                events.push(BlockNeighborChangedEvent {
+                    block_position,
+                    block,
+                    changed_position,
+                    direction: dir.opposite(),
+                    changed_to,
+                });
+            }
+        }
+    }
+    writer.send_batch(events);
+}
+
+fn process_explosions<T: Team>(
+    mut reader: EventReader<ExplosionEvent<T>>,
     level: Res<Level>,
     mut commands: Commands,
     id_query: Query<&BlockId>,
+    resistance_query: Query<&BlastResistance>,
+    target_query: Query<&GlobalTransform, (With<Combatant>, T::Targets)>,
+    spatial_index: Res<SpatialIndex>,
+    level_entity: Res<LevelEntity>,
     resources: Res<BlockResources>,
+    mut attack_writer: EventWriter<AttackEvent>,
     mut update_writer: EventWriter<ChunkUpdatedEvent>,
 ) {
+    let resistance = |block: Entity| resistance_query.get(block).copied().unwrap_or_default().0;
+    let mut rng = thread_rng();
     for event in reader.read() {
-        let size = event.radius.ceil() as i32;
-        let mut changes = Vec::with_capacity((size * size * size) as usize);
-        for x in -size..size + 1 {
-            for y in -size..size + 1 {
-                for z in -size..size + 1 {
-                    if x * x + y * y + z * z <= size * size {
-                        changes.push((event.origin + BlockCoord::new(x, y, z), BlockId(Id::Empty)));
-                    }
-                }
+        //combatants are checked before any blocks are removed, so the blocks that protect them count even if they break
+        for target in spatial_index.query_range(event.origin, event.radius) {
+            let Ok(gtf) = target_query.get(target) else {
+                continue;
+            };
+            let exposure = explosion_exposure(
+                &level,
+                event.origin,
+                gtf.translation(),
+                event.radius,
+                resistance,
+            );
+            if exposure <= 0.0 {
+                continue;
             }
+            attack_writer.send(AttackEvent {
+                attacker: event.owner.unwrap_or(level_entity.0),
+                target,
+                damage: Damage {
+                    amount: event.damage.amount * exposure,
+                    ..event.damage
+                },
+                knockback: (gtf.translation() - event.origin).normalize_or(Vec3::Y)
+                    * event.knockback
+                    * exposure,
+            });
         }
+        let removed = explosion_blocks(&level, event.origin, event.radius, resistance, &mut rng);
         level.batch_set_block(
-            changes.into_iter(),
+            removed.iter().map(|pos| (*pos, BlockId(Id::Empty))),
             &resources.registry,
             &id_query,
             &mut update_writer,
             &mut commands,
         );
+        for pos in removed {
+            level.queue_support_check(pos);
+        }
+    }
+}
+
+//directions spread evenly over a sphere (a fibonacci lattice)
+fn sphere_directions(count: usize) -> impl Iterator<Item = Vec3> {
+    let golden_angle = std::f32::consts::PI * (3.0 - 5.0_f32.sqrt());
+    (0..count).map(move |i| {
+        let y = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+        let r = (1.0 - y * y).sqrt();
+        let theta = golden_angle * i as f32;
+        Vec3::new(r * theta.cos(), y, r * theta.sin())
+    })
+}
+
+//returns the blocks an explosion breaks
+//rays go out from `origin` with a strength of about `radius`, losing 1 per block travelled. a ray breaks each block it reaches with more strength
+//left than the block's resistance, and loses that much. it stops at the first block it can't break or at unloaded blocks
+pub fn explosion_blocks(
+    level: &LevelData,
+    origin: Vec3,
+    radius: f32,
+    resistance: impl Fn(Entity) -> f32,
+    rng: &mut impl Rng,
+) -> HashSet<BlockCoord> {
+    const MIN_RAYS: usize = 64;
+    const MAX_RAYS: usize = 8192;
+    //enough rays that they're less than a block apart at the edge of the explosion
+    let ray_count = ((4.0 * std::f32::consts::PI * radius * radius).ceil() as usize * 2)
+        .clamp(MIN_RAYS, MAX_RAYS);
+    let mut removed = HashSet::new();
+    for direction in sphere_directions(ray_count) {
+        //vary the strength a bit so the edges aren't a perfect sphere
+        let strength = radius * rng.gen_range(0.7..=1.3);
+        let mut spent = 0.0;
+        for step in VoxelTraversal::new(origin, direction, strength) {
+            let left = strength - step.distance - spent;
+            match level.get_block(step.coord) {
+                None => break,
+                Some(BlockType::Empty) => {}
+                Some(BlockType::Filled(block)) => {
+                    let block_resistance = resistance(block);
+                    if left <= block_resistance {
+                        break;
+                    }
+                    spent += block_resistance;
+                    removed.insert(step.coord);
+                }
+            }
+        }
+    }
+    removed
+}
+
+//how much of an explosion reaches `target`, from 0 at `radius` or behind enough blocks to 1 at `origin`
+//blocks between `origin` and `target` count as if the explosion had to travel their resistance further
+pub fn explosion_exposure(
+    level: &LevelData,
+    origin: Vec3,
+    target: Vec3,
+    radius: f32,
+    resistance: impl Fn(Entity) -> f32,
+) -> f32 {
+    let offset = target - origin;
+    let distance = offset.length();
+    if radius <= 0.0 || distance >= radius {
+        return 0.0;
+    }
+    let mut left = radius - distance;
+    for step in VoxelTraversal::new(origin, offset.normalize_or_zero(), distance) {
+        //the target's own block doesn't protect it
+        if step.coord == BlockCoord::from(target) {
+            break;
+        }
+        if let Some(BlockType::Filled(block)) = level.get_block(step.coord) {
+            left -= resistance(block);
+            if left <= 0.0 {
+                return 0.0;
+            }
+        }
     }
+    left / radius
 }
@@ -1,4 +1,10 @@
-use std::{ops::Deref, sync::Arc};
+use std::{
+    ops::Deref,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 use crate::{
     mesher::NeedsMesh,
@@ -6,24 +12,38 @@ use crate::{
     util::{
         direction::Direction,
         iterators::{Volume, VolumeContainer},
-        max_component_norm,
     },
-    world::BlockcastHit,
-    worldgen::{ChunkNeedsGenerated, GeneratedChunk, GenerationPhase},
+    worldgen::{ChunkNeedsGenerated, GeneratedChunk, GenerationPhase, GeneratorSettings},
     GameState,
 };
-use bevy::{prelude::*, utils::hashbrown::HashSet};
-use dashmap::DashMap;
+use bevy::{
+    prelude::*,
+    utils::{hashbrown::HashSet, HashMap},
+};
+use dashmap::{DashMap, DashSet};
 
 use super::{
     chunk::*,
     events::{BlockDamageSetEvent, BlockUsedEvent, ChunkUpdatedEvent},
-    BlockBuffer, BlockCoord, BlockDamage, BlockId, BlockRegistry, BlockType, Id, UsableBlock,
+    levels::LevelId,
+    light::{ChunkLight, FatChunkLight, LightLevel},
+    BlockBuffer, BlockCoord, BlockDamage, BlockId, BlockRegistry, BlockState, BlockTagSet,
+    BlockType, Id, TagId, UsableBlock,
 };
 
 #[derive(Resource)]
 pub struct Level(pub Arc<LevelData>);
 
+//a block that was destroyed by `LevelData::damage_block`
+//dynamic blocks are despawned when they're removed, so `block` may not exist anymore
+#[derive(Clone, Copy, Debug)]
+pub struct BrokenBlock {
+    pub position: BlockCoord,
+    pub block: Entity,
+    //the item, block, or other entity that broke the block
+    pub damager: Option<Entity>,
+}
+
 impl AsRef<LevelData> for Level {
     fn as_ref(&self) -> &LevelData {
         &self.0
@@ -43,27 +63,70 @@ struct DamagedBlock(BlockDamage, Entity);
 
 pub struct LevelData {
     pub name: &'static str,
+    //which of the levels in `Levels` this is. also namespaces its tables in the db
+    pub id: LevelId,
     pub seed: u64,
+    //how the level's terrain is generated from its seed
+    pub generator: GeneratorSettings,
     chunks: DashMap<ChunkCoord, ChunkType, ahash::RandomState>,
     buffers: DashMap<ChunkCoord, Box<[BlockType; BLOCKS_PER_CHUNK]>, ahash::RandomState>,
     block_damages: DashMap<BlockCoord, BlockDamage, ahash::RandomState>,
     lod_chunks:
         DashMap<usize, DashMap<ChunkCoord, LODChunkType, ahash::RandomState>, ahash::RandomState>,
+    //sky and block light for each full chunk, kept in sync by the light systems
+    light: DashMap<ChunkCoord, ChunkLight, ahash::RandomState>,
+    //blocks that changed since the last light update
+    light_updates: DashSet<BlockCoord, ahash::RandomState>,
+    //chunks that need a full lighting pass
+    chunk_light_queue: DashSet<ChunkCoord, ahash::RandomState>,
+    //levels of flowing fluid blocks, keyed by chunk then index. fluid blocks without a level are sources
+    fluid_levels: DashMap<ChunkCoord, HashMap<usize, u8>, ahash::RandomState>,
+    //blocks that may need a fluid update next fluid tick
+    fluid_updates: DashSet<BlockCoord, ahash::RandomState>,
+    //signal strength of powered wires, consumers, and active sensors, keyed by chunk then index
+    signal_levels: DashMap<ChunkCoord, HashMap<usize, u8>, ahash::RandomState>,
+    //blocks that were set since the last time neighbors were notified
+    changed_blocks: DashSet<BlockCoord, ahash::RandomState>,
+    //removed blocks whose neighbors might have lost their structural support
+    support_checks: DashSet<BlockCoord, ahash::RandomState>,
+    //blocks destroyed by damage since their drops were last spawned
+    broken_blocks: DashMap<BlockCoord, BrokenBlock, ahash::RandomState>,
+    //number of block ticks that have happened since the level was loaded
+    block_tick: AtomicU64,
+    //(position, tick it happens on) for scheduled block ticks, stored by chunk so they can be saved and unloaded with it
+    scheduled_ticks: DashMap<ChunkCoord, Vec<(BlockCoord, u64)>, ahash::RandomState>,
     spawn_point: Vec3,
 }
 
 impl LevelData {
-    pub fn new(name: &'static str, seed: u64) -> LevelData {
+    pub fn new(name: &'static str, id: LevelId, seed: u64) -> LevelData {
         LevelData {
             name,
+            id,
             seed,
+            generator: GeneratorSettings::default(),
             chunks: DashMap::with_hasher(ahash::RandomState::new()),
             buffers: DashMap::with_hasher(ahash::RandomState::new()),
             block_damages: DashMap::with_hasher(ahash::RandomState::new()),
             lod_chunks: DashMap::with_hasher(ahash::RandomState::new()),
+            light: DashMap::with_hasher(ahash::RandomState::new()),
+            light_updates: DashSet::with_hasher(ahash::RandomState::new()),
+            chunk_light_queue: DashSet::with_hasher(ahash::RandomState::new()),
+            fluid_levels: DashMap::with_hasher(ahash::RandomState::new()),
+            fluid_updates: DashSet::with_hasher(ahash::RandomState::new()),
+            signal_levels: DashMap::with_hasher(ahash::RandomState::new()),
+            changed_blocks: DashSet::with_hasher(ahash::RandomState::new()),
+            support_checks: DashSet::with_hasher(ahash::RandomState::new()),
+            broken_blocks: DashMap::with_hasher(ahash::RandomState::new()),
+            block_tick: AtomicU64::new(0),
+            scheduled_ticks: DashMap::with_hasher(ahash::RandomState::new()),
             spawn_point: Vec3::new(0.0, 0.0, 10.0),
         }
     }
+    pub fn with_generator(mut self, generator: GeneratorSettings) -> Self {
+        self.generator = generator;
+        self
+    }
     pub fn get_block(&self, key: BlockCoord) -> Option<BlockType> {
         if let Some(r) = self.get_chunk(ChunkCoord::from(key)) {
             if let ChunkType::Full(chunk) = r.value() {
@@ -72,6 +135,15 @@ impl LevelData {
         }
         None
     }
+    //default state if the chunk isn't loaded
+    pub fn get_block_state(&self, key: BlockCoord) -> BlockState {
+        if let Some(r) = self.get_chunk(ChunkCoord::from(key)) {
+            if let ChunkType::Full(chunk) = r.value() {
+                return chunk.states.get(ChunkIdx::from(key).into());
+            }
+        }
+        BlockState::default()
+    }
     pub fn get_block_entity(&self, key: BlockCoord) -> Option<Entity> {
         match self.get_block(key) {
             Some(block_type) => match block_type {
@@ -86,6 +158,47 @@ impl LevelData {
         self.fill_volume_container(&mut container);
         container
     }
+    //false for empty and unloaded blocks
+    pub fn block_has_tag(
+        &self,
+        key: BlockCoord,
+        tag: TagId,
+        registry: &BlockRegistry,
+        id_query: &Query<&BlockId>,
+    ) -> bool {
+        self.get_block_entity(key)
+            .and_then(|entity| id_query.get(entity).ok())
+            .is_some_and(|id| registry.has_tag(*id, tag))
+    }
+    //true if the block has any of `tags`. false for empty and unloaded blocks
+    pub fn block_has_any_tag(
+        &self,
+        key: BlockCoord,
+        tags: &BlockTagSet,
+        registry: &BlockRegistry,
+        id_query: &Query<&BlockId>,
+    ) -> bool {
+        self.get_block_entity(key)
+            .and_then(|entity| id_query.get(entity).ok())
+            .is_some_and(|id| registry.has_any_tag(*id, tags))
+    }
+    //positions in `volume` with a block that has any of `tags`
+    pub fn find_tagged_blocks(
+        &self,
+        volume: Volume,
+        tags: &BlockTagSet,
+        registry: &BlockRegistry,
+        id_query: &Query<&BlockId>,
+    ) -> Vec<BlockCoord> {
+        if tags.is_empty() {
+            return Vec::new();
+        }
+        volume
+            .iter()
+            .map(BlockCoord::from)
+            .filter(|pos| self.block_has_any_tag(*pos, tags, registry, id_query))
+            .collect()
+    }
     pub fn fill_volume_container(&self, container: &mut VolumeContainer<BlockType>) {
         //todo - optimize to get needed chunks all at once
         for pos in container.volume().iter() {
@@ -153,6 +266,17 @@ impl LevelData {
         }
         if remove_block {
             self.set_block_entity(key, BlockType::Empty, id_query, update_writer, commands);
+            self.queue_support_check(key);
+            if let Some(block) = entity {
+                self.broken_blocks.insert(
+                    key,
+                    BrokenBlock {
+                        position: key,
+                        block,
+                        damager,
+                    },
+                );
+            }
             return entity;
         }
         None
@@ -219,6 +343,11 @@ impl LevelData {
                 };
                 BlockRegistry::remove_entity(id_query, chunk[ChunkIdx::from(key)], commands);
                 ChunkTrait::set_block(chunk, ChunkIdx::from(key).into(), block);
+                self.light_updates.insert(key);
+                self.set_fluid_level(key, None);
+                self.queue_fluid_update(key);
+                self.set_signal_level(key, 0);
+                self.changed_blocks.insert(key);
                 return Some(chunk.entity);
             }
         }
@@ -236,11 +365,37 @@ impl LevelData {
             if let ChunkType::Full(ref mut chunk) = r.value_mut() {
                 BlockRegistry::remove_entity(id_query, chunk[ChunkIdx::from(key)], commands);
                 ChunkTrait::set_block(chunk, ChunkIdx::from(key).into(), val);
+                self.light_updates.insert(key);
+                self.set_fluid_level(key, None);
+                self.queue_fluid_update(key);
+                self.set_signal_level(key, 0);
+                self.changed_blocks.insert(key);
                 return Some(chunk.entity);
             }
         }
         None
     }
+    //doesn't mesh or save
+    pub fn set_block_state_noupdate(&self, key: BlockCoord, state: BlockState) -> Option<Entity> {
+        if let Some(mut r) = self.get_chunk_mut(ChunkCoord::from(key)) {
+            if let ChunkType::Full(ref mut chunk) = r.value_mut() {
+                chunk.states.set(ChunkIdx::from(key).into(), state);
+                return Some(chunk.entity);
+            }
+        }
+        None
+    }
+    pub fn set_block_state(
+        &self,
+        key: BlockCoord,
+        state: BlockState,
+        commands: &mut Commands,
+        update_writer: &mut EventWriter<ChunkUpdatedEvent>,
+    ) {
+        if let Some(chunk_entity) = self.set_block_state_noupdate(key, state) {
+            Self::update_chunk_only::<true>(chunk_entity, key.into(), commands, update_writer);
+        }
+    }
     pub fn update_chunk_only<const SAVE: bool>(
         chunk_entity: Entity,
         coord: ChunkCoord,
@@ -382,10 +537,11 @@ impl LevelData {
     pub fn overwrite_or_spawn_chunk(
         &self,
         coord: ChunkCoord,
-        chunk: ChunkSaveFormat,
+        mut chunk: ChunkSaveFormat,
         commands: &mut Commands,
         registry: &BlockRegistry,
     ) -> Entity {
+        self.load_saved_fluid_levels(coord, std::mem::take(&mut chunk.fluid_levels));
         //overwrite old chunk
         if let Some(mut r) = self.get_chunk_mut(coord) {
             let v = r.value_mut();
@@ -406,6 +562,7 @@ impl LevelData {
                     id
                 }
             };
+            self.chunk_light_queue.insert(coord);
             return id;
         }
         //spawn new chunk
@@ -485,6 +642,7 @@ impl LevelData {
             if let Some(mut chunk_ref) = self.get_chunk_mut(coord) {
                 if let ChunkType::Full(ref mut c) = chunk_ref.value_mut() {
                     buf.apply_to(c.blocks.as_mut());
+                    self.chunk_light_queue.insert(c.position);
                     Self::update_chunk_only::<true>(c.entity, c.position, commands, update_writer);
                     //self.update_chunk_neighbors_only(c.position, commands);
                     continue;
@@ -520,6 +678,7 @@ impl LevelData {
                     }
                     start += *run as usize;
                 }
+                self.chunk_light_queue.insert(c.position);
                 Self::update_chunk_only::<true>(c.entity, c.position, commands, update_writer);
                 //self.update_chunk_neighbors_only(c.position, commands);
                 //we've already spawned in the buffer, so we shouldn't store it
@@ -564,8 +723,36 @@ impl LevelData {
         self.chunks.iter()
     }
     pub fn remove_chunk(&self, key: ChunkCoord) -> Option<(ChunkCoord, ChunkType)> {
+        self.light.remove(&key);
+        self.chunk_light_queue.remove(&key);
+        self.fluid_levels.remove(&key);
+        self.scheduled_ticks.remove(&key);
+        self.signal_levels.remove(&key);
         self.chunks.remove(&key)
     }
+    //removes every chunk and lod chunk, returning their entities so they can be despawned
+    //used when this stops being the active level. anything that needs saving has to be saved first
+    pub fn remove_all_chunks(&self) -> Vec<Entity> {
+        let coords: Vec<ChunkCoord> = self.chunks.iter().map(|c| *c.key()).collect();
+        let mut entities: Vec<Entity> = coords
+            .into_iter()
+            .filter_map(|coord| self.remove_chunk(coord))
+            .map(|(_, chunk)| match chunk {
+                ChunkType::Ungenerated(id) => id,
+                ChunkType::Generating(_, chunk) => chunk.entity,
+                ChunkType::Full(chunk) => chunk.entity,
+            })
+            .collect();
+        for lods in self.lod_chunks.iter() {
+            entities.extend(lods.iter().map(|c| match c.value() {
+                LODChunkType::Ungenerated(id, _) => *id,
+                LODChunkType::Full(chunk) => chunk.entity,
+            }));
+        }
+        self.lod_chunks.clear();
+        self.buffers.clear();
+        entities
+    }
     pub fn get_chunk(
         &self,
         key: ChunkCoord,
@@ -612,7 +799,8 @@ impl LevelData {
         if let Some(mut c) = self.get_chunk_mut(key) {
             if let ChunkType::Generating(_, chunk) = c.value_mut() {
                 *c = ChunkType::Full(chunk.to_array_chunk(registry, commands));
-                self.apply_buffer(c.value_mut())
+                self.apply_buffer(c.value_mut());
+                self.chunk_light_queue.insert(key);
             }
         }
     }
@@ -622,6 +810,9 @@ impl LevelData {
         let _my_span = info_span!("add_chunk", name = "add_chunk").entered();
         //copy contents of buffer into chunk if necessary
         self.apply_buffer(&mut chunk);
+        if matches!(chunk, ChunkType::Full(_)) {
+            self.chunk_light_queue.insert(key);
+        }
         self.chunks.insert(key, chunk);
     }
     pub fn apply_buffer(&self, chunk: &mut ChunkType) {
@@ -684,49 +875,253 @@ impl LevelData {
         }
     }
 
-    //todo improve this (bresehams -> sweep_ray)
-    //only hits blocks
-    pub fn blockcast(
-        &self,
-        origin: Vec3,
-        end_offset: Vec3,
-        mut checker: impl FnMut(Option<BlockType>) -> bool,
-    ) -> Option<BlockcastHit> {
-        let _my_span = info_span!("blockcast", name = "blockcast").entered();
-        const STEP_SIZE: f32 = 1.0 / 32.0;
-        let line_len = end_offset.length();
-        let line_norm = end_offset / line_len;
-        let mut old_coords = BlockCoord::from(origin);
-        let block = self.get_block(old_coords);
-        if checker(block) {
-            return Some(BlockcastHit {
-                hit_pos: origin,
-                block_pos: old_coords,
-                block,
-                normal: BlockCoord::new(0, 0, 0),
-            });
-        }
-        let mut t = 0.0;
-        while t < line_len {
-            t += STEP_SIZE;
-            let test_point = origin + t * line_norm;
-            let test_block = BlockCoord::from(test_point);
-            if test_block == old_coords {
+    pub fn contains_full_chunk(&self, key: ChunkCoord) -> bool {
+        self.get_chunk(key)
+            .is_some_and(|c| matches!(c.value(), ChunkType::Full(_)))
+    }
+    //returns None if the chunk hasn't been lit yet
+    pub fn get_light(&self, key: BlockCoord) -> Option<LightLevel> {
+        self.light
+            .get(&ChunkCoord::from(key))
+            .map(|light| light[ChunkIdx::from(key)])
+    }
+    //returns true if the light at `key` changed
+    pub fn set_light(&self, key: BlockCoord, val: LightLevel) -> bool {
+        if let Some(mut light) = self.light.get_mut(&ChunkCoord::from(key)) {
+            let old = std::mem::replace(&mut light[ChunkIdx::from(key)], val);
+            return old != val;
+        }
+        false
+    }
+    pub fn has_chunk_light(&self, key: ChunkCoord) -> bool {
+        self.light.contains_key(&key)
+    }
+    pub fn insert_chunk_light(&self, key: ChunkCoord, light: ChunkLight) {
+        self.light.insert(key, light);
+    }
+    pub fn queue_chunk_lighting(&self, key: ChunkCoord) {
+        self.chunk_light_queue.insert(key);
+    }
+    pub fn take_chunk_lighting_queue(&self, max_count: usize) -> Vec<ChunkCoord> {
+        let taken: Vec<ChunkCoord> = self
+            .chunk_light_queue
+            .iter()
+            .take(max_count)
+            .map(|c| *c)
+            .collect();
+        for coord in taken.iter() {
+            self.chunk_light_queue.remove(coord);
+        }
+        taken
+    }
+    pub fn take_light_updates(&self) -> Vec<BlockCoord> {
+        let taken: Vec<BlockCoord> = self.light_updates.iter().map(|c| *c).collect();
+        for coord in taken.iter() {
+            self.light_updates.remove(coord);
+        }
+        taken
+    }
+    //None if the block is a fluid source, or not a fluid at all
+    pub fn get_fluid_level(&self, key: BlockCoord) -> Option<u8> {
+        self.fluid_levels
+            .get(&ChunkCoord::from(key))
+            .and_then(|levels| levels.get(&ChunkIdx::from(key).to_usize()).copied())
+    }
+    //None makes the block a source
+    pub fn set_fluid_level(&self, key: BlockCoord, level: Option<u8>) {
+        let coord = ChunkCoord::from(key);
+        let idx = ChunkIdx::from(key).to_usize();
+        match level {
+            Some(level) => {
+                self.fluid_levels.entry(coord).or_default().insert(idx, level);
+            }
+            None => {
+                if let Some(mut levels) = self.fluid_levels.get_mut(&coord) {
+                    levels.remove(&idx);
+                }
+            }
+        }
+    }
+    //queues the block and its neighbors for the next fluid tick
+    pub fn queue_fluid_update(&self, key: BlockCoord) {
+        self.fluid_updates.insert(key);
+        for dir in Direction::iter() {
+            self.fluid_updates.insert(key.offset(dir));
+        }
+    }
+    pub fn take_fluid_updates(&self) -> Vec<BlockCoord> {
+        let taken: Vec<BlockCoord> = self.fluid_updates.iter().map(|c| *c).collect();
+        for coord in taken.iter() {
+            self.fluid_updates.remove(coord);
+        }
+        taken
+    }
+    //0 if the block isn't powered
+    pub fn get_signal_level(&self, key: BlockCoord) -> u8 {
+        self.signal_levels
+            .get(&ChunkCoord::from(key))
+            .and_then(|levels| levels.get(&ChunkIdx::from(key).to_usize()).copied())
+            .unwrap_or(0)
+    }
+    pub fn set_signal_level(&self, key: BlockCoord, level: u8) {
+        let coord = ChunkCoord::from(key);
+        let idx = ChunkIdx::from(key).to_usize();
+        if level > 0 {
+            self.signal_levels.entry(coord).or_default().insert(idx, level);
+        } else if let Some(mut levels) = self.signal_levels.get_mut(&coord) {
+            levels.remove(&idx);
+        }
+    }
+    //blocks that were set since this was last called
+    pub fn take_changed_blocks(&self) -> Vec<BlockCoord> {
+        let taken: Vec<BlockCoord> = self.changed_blocks.iter().map(|c| *c).collect();
+        for coord in taken.iter() {
+            self.changed_blocks.remove(coord);
+        }
+        taken
+    }
+    //blocks around `key` will be checked for structural support, after `key` was removed
+    pub fn queue_support_check(&self, key: BlockCoord) {
+        self.support_checks.insert(key);
+    }
+    pub fn take_support_checks(&self) -> Vec<BlockCoord> {
+        let taken: Vec<BlockCoord> = self.support_checks.iter().map(|c| *c).collect();
+        for coord in taken.iter() {
+            self.support_checks.remove(coord);
+        }
+        taken
+    }
+    //blocks destroyed with `damage_block`, so their drops can be spawned
+    pub fn take_broken_blocks(&self) -> Vec<BrokenBlock> {
+        let taken: Vec<BrokenBlock> = self.broken_blocks.iter().map(|b| *b.value()).collect();
+        for broken in taken.iter() {
+            self.broken_blocks.remove(&broken.position);
+        }
+        taken
+    }
+    //`key` will get a scheduled block tick `delay` ticks from now. if it already has one, the earlier one is kept
+    pub fn schedule_block_tick(&self, key: BlockCoord, delay: u32, commands: &mut Commands) {
+        let coord = ChunkCoord::from(key);
+        let due = self.block_tick.load(Ordering::Relaxed) + delay.max(1) as u64;
+        {
+            let mut ticks = self.scheduled_ticks.entry(coord).or_default();
+            match ticks.iter_mut().find(|(pos, _)| *pos == key) {
+                Some((_, old_due)) => *old_due = (*old_due).min(due),
+                None => ticks.push((key, due)),
+            }
+        }
+        //pending ticks are saved with the chunk
+        if let Some(entity) = self.get_chunk_entity(coord)
+            && let Some(mut ec) = commands.get_entity(entity)
+        {
+            ec.try_insert(NeedsSaving);
+        }
+    }
+    //advances the block tick and returns all the blocks with a scheduled tick that's due
+    //ticks in chunks that aren't full yet wait until they are
+    pub fn advance_block_ticks(&self) -> Vec<BlockCoord> {
+        let now = self.block_tick.fetch_add(1, Ordering::Relaxed) + 1;
+        let due_chunks: Vec<ChunkCoord> = self
+            .scheduled_ticks
+            .iter()
+            .filter(|ticks| ticks.iter().any(|(_, due)| *due <= now))
+            .map(|ticks| *ticks.key())
+            .collect();
+        let mut due = Vec::new();
+        for coord in due_chunks {
+            if !self.contains_full_chunk(coord) {
                 continue;
             }
-
-            old_coords = test_block;
-            let b = self.get_block(test_block);
-            if checker(b) {
-                return Some(BlockcastHit {
-                    hit_pos: test_point,
-                    block_pos: test_block,
-                    block: b,
-                    normal: max_component_norm(test_point - old_coords.center()).into(),
+            if let Some(mut ticks) = self.scheduled_ticks.get_mut(&coord) {
+                ticks.retain(|(pos, tick)| {
+                    if *tick <= now {
+                        due.push(*pos);
+                        false
+                    } else {
+                        true
+                    }
                 });
             }
+            self.scheduled_ticks.remove_if(&coord, |_, ticks| ticks.is_empty());
         }
-        None
+        due
+    }
+    //pending scheduled ticks in the chunk as (chunk index, ticks remaining), for saving
+    pub fn get_saved_block_ticks(&self, coord: ChunkCoord) -> Vec<(u16, u32)> {
+        let now = self.block_tick.load(Ordering::Relaxed);
+        self.scheduled_ticks
+            .get(&coord)
+            .map(|ticks| {
+                ticks
+                    .iter()
+                    .map(|(pos, due)| {
+                        (
+                            ChunkIdx::from(*pos).to_usize() as u16,
+                            due.saturating_sub(now).min(u32::MAX as u64) as u32,
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    //inverse of `get_saved_block_ticks`
+    pub fn load_saved_block_ticks(&self, coord: ChunkCoord, saved: Vec<(u16, u32)>) {
+        if saved.is_empty() {
+            return;
+        }
+        let now = self.block_tick.load(Ordering::Relaxed);
+        let origin = BlockCoord::from(coord);
+        let mut ticks = self.scheduled_ticks.entry(coord).or_default();
+        for (idx, remaining) in saved {
+            let pos = origin + BlockCoord::from(ChunkIdx::from_usize(idx as usize));
+            ticks.push((pos, now + remaining.max(1) as u64));
+        }
+    }
+    //(chunk index, level) for the fluids in the chunk that aren't sources, for saving
+    pub fn get_saved_fluid_levels(&self, coord: ChunkCoord) -> Vec<(u16, u8)> {
+        self.fluid_levels
+            .get(&coord)
+            .map(|levels| {
+                let mut saved: Vec<(u16, u8)> = levels
+                    .iter()
+                    .map(|(idx, level)| (*idx as u16, *level))
+                    .collect();
+                saved.sort_unstable();
+                saved
+            })
+            .unwrap_or_default()
+    }
+    //inverse of `get_saved_fluid_levels`. replaces whatever levels the chunk had
+    pub fn load_saved_fluid_levels(&self, coord: ChunkCoord, saved: Vec<(u16, u8)>) {
+        if saved.is_empty() {
+            self.fluid_levels.remove(&coord);
+            return;
+        }
+        self.fluid_levels.insert(
+            coord,
+            saved
+                .into_iter()
+                .map(|(idx, level)| (idx as usize, level))
+                .collect(),
+        );
+    }
+    //copies the light of the chunk and a one block border around it, for meshing
+    //unlit blocks are treated as fully lit so that unlit neighbors don't leave dark seams
+    pub fn get_fat_light(&self, key: ChunkCoord) -> Box<FatChunkLight> {
+        let mut fat_light = Box::new([LightLevel::FULL; BLOCKS_PER_FAT_CHUNK]);
+        let origin = BlockCoord::from(key);
+        for x in -1..CHUNK_SIZE_I8 + 1 {
+            for y in -1..CHUNK_SIZE_I8 + 1 {
+                for z in -1..CHUNK_SIZE_I8 + 1 {
+                    if let Some(light) = self.get_light(
+                        origin + BlockCoord::new(x as i32, y as i32, z as i32),
+                    ) {
+                        fat_light[Into::<usize>::into(FatChunkIdx::new(x, y, z))] = light;
+                    }
+                }
+            }
+        }
+        fat_light
     }
 
     pub fn get_spawn_point(&self) -> Vec3 {
@@ -0,0 +1,237 @@
+use std::sync::Arc;
+
+use bevy::{ecs::component::Components, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actors::{ActorName, LocalPlayer},
+    serialization::{
+        db::LevelDB,
+        entities::{self, SavedEntity},
+        SaveWithChunk,
+    },
+    worldgen::GeneratorSettings,
+};
+
+use super::{chunk::ChunkCoord, Level, LevelData, LevelSystemSet};
+
+pub struct LevelsPlugin;
+
+impl Plugin for LevelsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TeleportEvent>()
+            .add_event::<ActiveLevelChangedEvent>()
+            .add_systems(
+                Update,
+                process_teleports
+                    .in_set(LevelSystemSet::Main)
+                    .run_if(resource_exists::<Levels>),
+            )
+            .add_systems(
+                Update,
+                despawn_outside_active_level
+                    .in_set(LevelSystemSet::Despawn)
+                    .run_if(resource_exists::<Levels>),
+            )
+            .register_type::<LevelId>()
+            .register_type::<LevelMember>();
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Reflect, Serialize, Deserialize,
+)]
+pub struct LevelId(pub u32);
+
+impl LevelId {
+    //the level that's created with the save, and the one the player starts in
+    pub const SURFACE: LevelId = LevelId(0);
+}
+
+impl Default for LevelId {
+    fn default() -> Self {
+        Self::SURFACE
+    }
+}
+
+//which level an entity is in. entities without this are in whichever level is active
+//chunk loaders only load chunks for their own level, and entities are despawned along with their level's chunks when it stops being active
+#[derive(Component, Clone, Copy, Reflect, Default, Debug, PartialEq, Eq)]
+#[reflect(Component, FromWorld)]
+pub struct LevelMember(pub LevelId);
+
+//all the levels in the save. only the active one (the one the local player is in) has chunks loaded and is simulated,
+//the rest keep their LevelData so they can be switched back to, but nothing happens in them until then.
+//entities sent to them are saved into their chunks instead. the active one is also in the `Level` resource
+#[derive(Resource)]
+pub struct Levels {
+    levels: HashMap<LevelId, Arc<LevelData>>,
+    active: LevelId,
+    //set by teleporting the local player, and applied once the old level is saved and unloaded
+    pending_switch: Option<LevelId>,
+}
+
+impl Levels {
+    pub fn new(active: &Level) -> Self {
+        let mut levels = HashMap::new();
+        levels.insert(active.id, active.0.clone());
+        Self {
+            levels,
+            active: active.id,
+            pending_switch: None,
+        }
+    }
+
+    //adds a level that generates with `generator` and its own seed, derived from the surface's so every save gets different levels
+    //returns the existing level if there already is one with this id
+    pub fn create(
+        &mut self,
+        id: LevelId,
+        name: &'static str,
+        generator: GeneratorSettings,
+    ) -> Arc<LevelData> {
+        let base_seed = self
+            .levels
+            .get(&LevelId::SURFACE)
+            .or_else(|| self.levels.get(&self.active))
+            .map(|level| level.seed)
+            .unwrap_or_default();
+        let seed = base_seed ^ (id.0 as u64).wrapping_mul(0x9E3779B97F4A7C15);
+        self.levels
+            .entry(id)
+            .or_insert_with(|| Arc::new(LevelData::new(name, id, seed).with_generator(generator)))
+            .clone()
+    }
+
+    pub fn get(&self, id: LevelId) -> Option<Arc<LevelData>> {
+        self.levels.get(&id).cloned()
+    }
+
+    pub fn contains(&self, id: LevelId) -> bool {
+        self.levels.contains_key(&id)
+    }
+
+    pub fn active(&self) -> LevelId {
+        self.active
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = LevelId> + '_ {
+        self.levels.keys().copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<LevelData>> + '_ {
+        self.levels.values()
+    }
+
+    //entities in the active level, or the one it's about to switch to, stay spawned. the rest are saved into their level
+    pub fn keeps_entities(&self, id: LevelId) -> bool {
+        id == self.active || Some(id) == self.pending_switch
+    }
+
+    //makes `id` the active level at the end of this frame. does nothing if the level doesn't exist
+    pub fn request_switch(&mut self, id: LevelId) {
+        if id != self.active && self.levels.contains_key(&id) {
+            self.pending_switch = Some(id);
+        }
+    }
+
+    pub fn take_pending_switch(&mut self) -> Option<LevelId> {
+        self.pending_switch.take()
+    }
+
+    //only call after the old level is saved and unloaded. replaces the `Level` resource
+    pub fn set_active(&mut self, id: LevelId, commands: &mut Commands) -> Option<Arc<LevelData>> {
+        let level = self.levels.get(&id)?.clone();
+        self.active = id;
+        commands.insert_resource(Level(level.clone()));
+        Some(level)
+    }
+}
+
+//moves `entity` to `position` in `level`
+//if the local player changes levels, the active level switches with them. other entities sent to a level that isn't active are saved into it
+#[derive(Event)]
+pub struct TeleportEvent {
+    pub entity: Entity,
+    pub level: LevelId,
+    pub position: Vec3,
+}
+
+#[derive(Event)]
+pub struct ActiveLevelChangedEvent {
+    pub old: LevelId,
+    pub new: LevelId,
+}
+
+fn process_teleports(
+    mut reader: EventReader<TeleportEvent>,
+    mut levels: ResMut<Levels>,
+    mut tf_query: Query<(&mut Transform, Option<&LevelMember>, Has<LocalPlayer>)>,
+    mut commands: Commands,
+) {
+    for TeleportEvent {
+        entity,
+        level,
+        position,
+    } in reader.read()
+    {
+        let Ok((mut tf, member, is_player)) = tf_query.get_mut(*entity) else {
+            continue;
+        };
+        if !levels.contains(*level) {
+            warn!(
+                "Tried to teleport {:?} to missing level {:?}",
+                entity, level
+            );
+            continue;
+        }
+        tf.translation = *position;
+        let old_level = member.map(|m| m.0).unwrap_or(levels.active());
+        if old_level == *level {
+            continue;
+        }
+        commands.entity(*entity).insert(LevelMember(*level));
+        if is_player {
+            levels.request_switch(*level);
+        }
+    }
+}
+
+//entities that were moved to a level that isn't loaded are saved into it, and spawned when the chunk they're in loads there
+//ones that aren't saved with chunks go away, like they would have if it was loaded and then left
+fn despawn_outside_active_level(
+    query: Query<
+        (
+            EntityRef,
+            &LevelMember,
+            Option<&ActorName>,
+            Option<&Transform>,
+            Has<SaveWithChunk>,
+        ),
+        Changed<LevelMember>,
+    >,
+    levels: Res<Levels>,
+    db: Option<ResMut<LevelDB>>,
+    registry: Res<AppTypeRegistry>,
+    components: &Components,
+    mut commands: Commands,
+) {
+    let registry = registry.read();
+    let mut rows: HashMap<(LevelId, ChunkCoord), Vec<SavedEntity>> = HashMap::new();
+    for (entity, member, actor, tf, save) in query.iter() {
+        if levels.keeps_entities(member.0) {
+            continue;
+        }
+        if let (true, Some(actor), Some(tf), Some(_)) = (save, actor, tf, &db) {
+            rows.entry((member.0, ChunkCoord::from(tf.translation)))
+                .or_default()
+                .push(SavedEntity::new(entity, actor, *tf, components, &registry));
+        }
+        commands.entity(entity.id()).despawn_recursive();
+    }
+    if let Some(mut db) = db {
+        for ((level, coord), entities) in rows {
+            entities::save_into_level(&mut db, level, coord, entities);
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::hashbrown::HashSet};
+use util::direction::Direction;
+
+use crate::mesher::NeedsMesh;
+
+use super::{
+    chunk::{ChunkCoord, ChunkIdx, BLOCKS_PER_CHUNK, BLOCKS_PER_FAT_CHUNK, CHUNK_SIZE_I32},
+    BlockCoord, BlockMesh, BlockMeshShape, BlockType, Level, LevelData, LevelSystemSet,
+};
+
+pub const MAX_LIGHT: u8 = 15;
+//how many chunks get a full lighting pass each frame
+const CHUNK_LIGHTING_BUDGET: usize = 16;
+
+pub struct LightPlugin;
+
+impl Plugin for LightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            process_light_updates
+                .in_set(LevelSystemSet::LoadingAndMain)
+                .after(LevelSystemSet::Main),
+        )
+        .register_type::<BlockLight>();
+    }
+}
+
+//light emitted by a block, from 0 (none) to MAX_LIGHT
+#[derive(Component, Clone, Copy, Reflect, Default, Debug)]
+#[reflect(Component, FromWorld)]
+pub struct BlockLight {
+    pub emission: u8,
+}
+
+//sky light in the upper nibble, block light in the lower nibble
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct LightLevel(u8);
+
+impl LightLevel {
+    pub const FULL: LightLevel = LightLevel::new(MAX_LIGHT, MAX_LIGHT);
+
+    pub const fn new(sky: u8, block: u8) -> Self {
+        Self((sky << 4) | (block & 0xf))
+    }
+    pub fn sky(self) -> u8 {
+        self.0 >> 4
+    }
+    pub fn block(self) -> u8 {
+        self.0 & 0xf
+    }
+    pub fn with_sky(self, sky: u8) -> Self {
+        Self::new(sky, self.block())
+    }
+    pub fn with_block(self, block: u8) -> Self {
+        Self::new(self.sky(), block)
+    }
+    //(sky, block) scaled to 0-1, used as the mesh vertex attribute
+    pub fn to_vec2(self) -> Vec2 {
+        Vec2::new(
+            self.sky() as f32 / MAX_LIGHT as f32,
+            self.block() as f32 / MAX_LIGHT as f32,
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChunkLight {
+    data: Box<[LightLevel; BLOCKS_PER_CHUNK]>,
+}
+
+impl Default for ChunkLight {
+    fn default() -> Self {
+        Self {
+            data: Box::new([LightLevel::default(); BLOCKS_PER_CHUNK]),
+        }
+    }
+}
+
+impl std::ops::Index<ChunkIdx> for ChunkLight {
+    type Output = LightLevel;
+
+    fn index(&self, index: ChunkIdx) -> &Self::Output {
+        &self.data[index.to_usize()]
+    }
+}
+
+impl std::ops::IndexMut<ChunkIdx> for ChunkLight {
+    fn index_mut(&mut self, index: ChunkIdx) -> &mut Self::Output {
+        &mut self.data[index.to_usize()]
+    }
+}
+
+//light for a chunk with one layer of neighbor information, indexed by FatChunkIdx
+pub type FatChunkLight = [LightLevel; BLOCKS_PER_FAT_CHUNK];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LightChannel {
+    Sky,
+    Block,
+}
+
+impl LightChannel {
+    fn get(self, light: LightLevel) -> u8 {
+        match self {
+            LightChannel::Sky => light.sky(),
+            LightChannel::Block => light.block(),
+        }
+    }
+    fn with(self, light: LightLevel, value: u8) -> LightLevel {
+        match self {
+            LightChannel::Sky => light.with_sky(value),
+            LightChannel::Block => light.with_block(value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LightProperties {
+    pub emission: u8,
+    //how much light is lost entering this block. MAX_LIGHT blocks light entirely
+    pub opacity: u8,
+}
+
+impl LightProperties {
+    pub const EMPTY: LightProperties = LightProperties {
+        emission: 0,
+        opacity: 1,
+    };
+}
+
+fn get_light_properties(
+    block: BlockType,
+    block_query: &Query<(Option<&BlockMesh>, Option<&BlockLight>)>,
+) -> LightProperties {
+    let BlockType::Filled(entity) = block else {
+        return LightProperties::EMPTY;
+    };
+    let Ok((mesh, light)) = block_query.get(entity) else {
+        return LightProperties::EMPTY;
+    };
+    let opacity = match mesh {
+        None => 1,
+        Some(mesh) => match mesh.shape {
+            BlockMeshShape::Empty | BlockMeshShape::Cross(_) | BlockMeshShape::BottomSlab(_, _) => 1,
+            _ if mesh.use_transparent_shader => 2,
+            _ => MAX_LIGHT,
+        },
+    };
+    LightProperties {
+        emission: light.map(|l| l.emission.min(MAX_LIGHT)).unwrap_or(0),
+        opacity,
+    }
+}
+
+//flood fill over the level's light storage, remembering which chunks need a remesh
+pub(crate) struct LightPropagator<'a, F: Fn(BlockType) -> LightProperties> {
+    level: &'a LevelData,
+    properties_of: F,
+    add_queue: VecDeque<BlockCoord>,
+    remove_queue: VecDeque<(BlockCoord, u8)>,
+    touched_chunks: HashSet<ChunkCoord>,
+}
+
+impl<'a, F: Fn(BlockType) -> LightProperties> LightPropagator<'a, F> {
+    pub fn new(level: &'a LevelData, properties_of: F) -> Self {
+        Self {
+            level,
+            properties_of,
+            add_queue: VecDeque::new(),
+            remove_queue: VecDeque::new(),
+            touched_chunks: HashSet::new(),
+        }
+    }
+
+    fn properties(&self, pos: BlockCoord) -> Option<LightProperties> {
+        self.level.get_block(pos).map(&self.properties_of)
+    }
+
+    fn set(&mut self, pos: BlockCoord, light: LightLevel) {
+        if self.level.set_light(pos, light) {
+            //faces in neighboring chunks use this block's light too
+            self.touched_chunks.insert(pos.into());
+            for dir in Direction::iter() {
+                self.touched_chunks.insert(pos.offset(dir).into());
+            }
+        }
+    }
+
+    fn set_channel(&mut self, pos: BlockCoord, channel: LightChannel, value: u8) {
+        if let Some(light) = self.level.get_light(pos) {
+            self.set(pos, channel.with(light, value));
+        }
+    }
+
+    //called after the block at `pos` changes
+    pub fn update_block(&mut self, pos: BlockCoord) {
+        let Some(props) = self.properties(pos) else {
+            return;
+        };
+        let Some(old) = self.level.get_light(pos) else {
+            return;
+        };
+        for channel in [LightChannel::Sky, LightChannel::Block] {
+            self.set_channel(pos, channel, 0);
+            self.remove_queue.push_back((pos, channel.get(old)));
+            self.propagate_removal(channel);
+            if channel == LightChannel::Block && props.emission > 0 {
+                self.set_channel(pos, channel, props.emission);
+                self.add_queue.push_back(pos);
+            }
+            //let the neighbors flood back in if the new block lets light through
+            for dir in Direction::iter() {
+                self.add_queue.push_back(pos.offset(dir));
+            }
+            self.propagate(channel);
+        }
+    }
+
+    //full lighting pass over a chunk that was just loaded or generated
+    pub fn light_chunk(&mut self, coord: ChunkCoord) {
+        self.level.insert_chunk_light(coord, ChunkLight::default());
+        self.touched_chunks.insert(coord);
+        let origin = BlockCoord::from(coord);
+        //block light from emitters
+        for i in 0..BLOCKS_PER_CHUNK {
+            let pos = origin + BlockCoord::from(ChunkIdx::from_usize(i));
+            if let Some(props) = self.properties(pos)
+                && props.emission > 0
+            {
+                self.set_channel(pos, LightChannel::Block, props.emission);
+                self.add_queue.push_back(pos);
+            }
+        }
+        self.pull_from_neighbors(coord, LightChannel::Block);
+        self.propagate(LightChannel::Block);
+
+        //sky light comes in through the top of the chunk.
+        //if the chunk above isn't lit yet we assume it's open sky, and fix it up when that chunk gets lit
+        for x in 0..CHUNK_SIZE_I32 {
+            for z in 0..CHUNK_SIZE_I32 {
+                let pos = origin + BlockCoord::new(x, CHUNK_SIZE_I32 - 1, z);
+                let above = self
+                    .level
+                    .get_light(pos.offset(Direction::PosY))
+                    .map(|l| l.sky())
+                    .unwrap_or(MAX_LIGHT);
+                let Some(props) = self.properties(pos) else {
+                    continue;
+                };
+                let sky = if above == MAX_LIGHT && props.opacity == 1 {
+                    MAX_LIGHT
+                } else {
+                    above.saturating_sub(props.opacity)
+                };
+                if sky > 0 {
+                    self.set_channel(pos, LightChannel::Sky, sky);
+                    self.add_queue.push_back(pos);
+                }
+            }
+        }
+        self.pull_from_neighbors(coord, LightChannel::Sky);
+        self.propagate(LightChannel::Sky);
+
+        //the chunk below may have assumed this chunk was open sky
+        let below = coord.offset(Direction::NegY);
+        if self.level.has_chunk_light(below) && !self.bottom_fully_skylit(origin) {
+            self.level.queue_chunk_lighting(below);
+        }
+    }
+
+    fn bottom_fully_skylit(&self, origin: BlockCoord) -> bool {
+        (0..CHUNK_SIZE_I32).all(|x| {
+            (0..CHUNK_SIZE_I32).all(|z| {
+                self.level
+                    .get_light(origin + BlockCoord::new(x, 0, z))
+                    .is_some_and(|l| l.sky() == MAX_LIGHT)
+            })
+        })
+    }
+
+    //queues the lit blocks bordering the chunk so their light spreads inwards
+    fn pull_from_neighbors(&mut self, coord: ChunkCoord, channel: LightChannel) {
+        let origin = BlockCoord::from(coord);
+        for a in 0..CHUNK_SIZE_I32 {
+            for b in 0..CHUNK_SIZE_I32 {
+                for border in [
+                    BlockCoord::new(-1, a, b),
+                    BlockCoord::new(CHUNK_SIZE_I32, a, b),
+                    BlockCoord::new(a, -1, b),
+                    BlockCoord::new(a, CHUNK_SIZE_I32, b),
+                    BlockCoord::new(a, b, -1),
+                    BlockCoord::new(a, b, CHUNK_SIZE_I32),
+                ] {
+                    let pos = origin + border;
+                    if self
+                        .level
+                        .get_light(pos)
+                        .is_some_and(|l| channel.get(l) > 1)
+                    {
+                        self.add_queue.push_back(pos);
+                    }
+                }
+            }
+        }
+    }
+
+    fn propagate(&mut self, channel: LightChannel) {
+        while let Some(pos) = self.add_queue.pop_front() {
+            let Some(light) = self.level.get_light(pos) else {
+                continue;
+            };
+            let value = channel.get(light);
+            if value <= 1 {
+                continue;
+            }
+            for dir in Direction::iter() {
+                let neighbor = pos.offset(dir);
+                let Some(neighbor_light) = self.level.get_light(neighbor) else {
+                    continue;
+                };
+                let Some(props) = self.properties(neighbor) else {
+                    continue;
+                };
+                if props.opacity >= MAX_LIGHT {
+                    continue;
+                }
+                //sky light travels straight down without dimming
+                let new_value = if channel == LightChannel::Sky
+                    && dir == Direction::NegY
+                    && value == MAX_LIGHT
+                    && props.opacity == 1
+                {
+                    MAX_LIGHT
+                } else {
+                    value.saturating_sub(props.opacity)
+                };
+                if new_value > channel.get(neighbor_light) {
+                    self.set(neighbor, channel.with(neighbor_light, new_value));
+                    self.add_queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+
+    //clears out light that came from the removed values, and queues up what's left to refill the gap
+    fn propagate_removal(&mut self, channel: LightChannel) {
+        while let Some((pos, value)) = self.remove_queue.pop_front() {
+            for dir in Direction::iter() {
+                let neighbor = pos.offset(dir);
+                let Some(neighbor_light) = self.level.get_light(neighbor) else {
+                    continue;
+                };
+                let neighbor_value = channel.get(neighbor_light);
+                if neighbor_value == 0 {
+                    continue;
+                }
+                let from_removed = neighbor_value < value
+                    || (channel == LightChannel::Sky
+                        && dir == Direction::NegY
+                        && value == MAX_LIGHT
+                        && neighbor_value == MAX_LIGHT);
+                if from_removed {
+                    self.set(neighbor, channel.with(neighbor_light, 0));
+                    self.remove_queue.push_back((neighbor, neighbor_value));
+                    //emitters keep their own light
+                    if channel == LightChannel::Block
+                        && let Some(props) = self.properties(neighbor)
+                        && props.emission > 0
+                    {
+                        self.set_channel(neighbor, channel, props.emission);
+                        self.add_queue.push_back(neighbor);
+                    }
+                } else {
+                    self.add_queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+}
+
+fn process_light_updates(
+    level: Res<Level>,
+    block_query: Query<(Option<&BlockMesh>, Option<&BlockLight>)>,
+    mut commands: Commands,
+) {
+    let _my_span = info_span!("process_light_updates", name = "process_light_updates").entered();
+    let mut propagator =
+        LightPropagator::new(&level, |block| get_light_properties(block, &block_query));
+    for coord in level.take_chunk_lighting_queue(CHUNK_LIGHTING_BUDGET) {
+        if level.contains_full_chunk(coord) {
+            propagator.light_chunk(coord);
+        }
+    }
+    for pos in level.take_light_updates() {
+        propagator.update_block(pos);
+    }
+    for coord in propagator.touched_chunks {
+        if let Some(entity) = level.get_chunk_entity(coord)
+            && let Some(mut ec) = commands.get_entity(entity)
+        {
+            ec.try_insert(NeedsMesh::default());
+        }
+    }
+}
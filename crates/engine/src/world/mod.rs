@@ -11,6 +11,12 @@ mod block;
 use ::util::plugin::UtilSystemSet;
 use bevy::prelude::*;
 pub use block::*;
+
+mod block_state;
+pub use block_state::*;
+
+mod block_tags;
+pub use block_tags::*;
 use serde::{Deserialize, Serialize};
 
 use crate::{physics::PhysicsSystemSet, GameState};
@@ -22,7 +28,10 @@ pub mod atmosphere;
 pub mod blocks;
 pub mod effects;
 pub mod events;
+pub mod levels;
+pub mod light;
 pub mod settings;
+pub mod world_edit;
 pub mod world_utils;
 
 #[cfg(test)]
@@ -100,7 +109,7 @@ impl Plugin for LevelPlugin {
             (
                 LevelSystemSet::PreTick
                     .before(PhysicsSystemSet::Main)
-                    .after(UtilSystemSet),
+                    .after(PhysicsSystemSet::UpdateSpatialIndex),
                 LevelSystemSet::Tick.in_set(PhysicsSystemSet::Main),
                 LevelSystemSet::PostTick.after(PhysicsSystemSet::UpdateDerivatives),
             )
@@ -122,6 +131,9 @@ impl Plugin for LevelPlugin {
             blocks::BlocksPlugin,
             util::LevelUtilsPlugin,
             atmosphere::AtmospherePlugin,
+            light::LightPlugin,
+            world_edit::WorldEditPlugin,
+            levels::LevelsPlugin,
         ))
         .add_sub_state::<LevelLoadState>()
         .enable_state_scoped_entities::<LevelLoadState>()
@@ -130,22 +142,19 @@ impl Plugin for LevelPlugin {
         .register_type::<[std::path::PathBuf; 6]>()
         .register_type::<[std::path::PathBuf; 2]>()
         .register_type::<BlockName>()
+        .register_type::<BlockTag>()
+        .register_type::<BlockTags>()
+        .register_type::<Vec<BlockTag>>()
         .register_type::<UsableBlock>()
         .register_type::<BlockCoord>()
         .register_type::<NamedBlockMesh>()
         .register_type::<NamedBlockMeshShape>()
-        .register_type::<BlockPhysics>();
+        .register_type::<BlockPhysics>()
+        .register_type::<BlastResistance>()
+        .register_type::<BlockStateProperties>();
     }
 }
 
-#[derive(Debug)]
-pub struct BlockcastHit {
-    pub hit_pos: Vec3,
-    pub block_pos: BlockCoord,
-    pub block: Option<BlockType>,
-    pub normal: BlockCoord,
-}
-
 //ids may not be stable across program runs. to get a specific id for an entity or name,
 // use the corresponding registry. DO NOT HARDCODE (unless the backing id dict is hardcoded)
 #[derive(Default, Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
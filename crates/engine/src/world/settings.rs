@@ -1,6 +1,11 @@
+use std::time::Duration;
+
 use bevy::{math::UVec2, prelude::*};
 
-use crate::chunk_loading::ChunkLoader;
+use crate::{
+    chunk_loading::ChunkLoader,
+    serialization::{codec::ChunkCodec, storage::StorageKind},
+};
 
 use super::chunk::ChunkCoord;
 
@@ -16,6 +21,19 @@ pub struct Settings {
     pub item_type_path: &'static str,
     pub block_tex_size: UVec2,
     pub mouse_sensitivity: f32,
+    //how often the open level is backed up
+    pub backup_interval: Duration,
+    //backups of each reason past this many are deleted, oldest first
+    pub backups_kept: usize,
+    //how chunks are stored in the level. chunks already saved with another codec can still be loaded
+    pub chunk_codec: ChunkCodec,
+    //saves only the blocks that differ from what the generator makes instead of whole chunks. saves are much smaller,
+    // but chunks are generated again to be saved or loaded. chunks saved either way can be loaded either way,
+    // but once a level has diffs it only opens with the generator they were made against
+    pub save_terrain_diffs: bool,
+    //what new levels are saved in. levels that already exist are opened with whatever they were saved in
+    //migrations only run on sqlite levels, so levels saved in anything else won't open after a breaking save change
+    pub level_storage: StorageKind,
 }
 
 impl Default for Settings {
@@ -46,6 +64,11 @@ impl Default for Settings {
             item_type_path: "items",
             block_tex_size: UVec2::new(16, 16),
             mouse_sensitivity: 0.005,
+            backup_interval: Duration::from_secs(10 * 60),
+            backups_kept: 5,
+            chunk_codec: ChunkCodec::Lz4,
+            save_terrain_diffs: false,
+            level_storage: StorageKind::Sqlite,
         }
     }
 }
@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+
+use crate::world::{
+    chunk::*,
+    levels::LevelId,
+    light::{LightPropagator, LightProperties, MAX_LIGHT},
+    BlockCoord, BlockType, LevelData,
+};
+
+const STONE: Entity = Entity::from_raw(1);
+const LAMP: Entity = Entity::from_raw(2);
+
+fn properties(block: BlockType) -> LightProperties {
+    match block {
+        BlockType::Filled(STONE) => LightProperties {
+            emission: 0,
+            opacity: MAX_LIGHT,
+        },
+        BlockType::Filled(LAMP) => LightProperties {
+            emission: 14,
+            opacity: 2,
+        },
+        _ => LightProperties::EMPTY,
+    }
+}
+
+//one chunk at the origin with nothing loaded around it
+fn level_with(blocks: &[(BlockCoord, Entity)]) -> LevelData {
+    let level = LevelData::new("test", LevelId::SURFACE, 0);
+    let mut chunk = ArrayChunk::new(ChunkCoord::new(0, 0, 0), Entity::PLACEHOLDER);
+    for (pos, block) in blocks {
+        ChunkTrait::set_block(
+            &mut chunk,
+            ChunkIdx::from(*pos).into(),
+            BlockType::Filled(*block),
+        );
+    }
+    level.add_chunk(ChunkCoord::new(0, 0, 0), ChunkType::Full(chunk));
+    level
+}
+
+fn clear_block(level: &LevelData, pos: BlockCoord) {
+    if let Some(mut chunk) = level.get_chunk_mut(ChunkCoord::from(pos)) {
+        if let ChunkType::Full(ref mut chunk) = chunk.value_mut() {
+            ChunkTrait::set_block(chunk, ChunkIdx::from(pos).into(), BlockType::Empty);
+        }
+    }
+}
+
+fn sky(level: &LevelData, x: i32, y: i32, z: i32) -> u8 {
+    level.get_light(BlockCoord::new(x, y, z)).unwrap().sky()
+}
+
+fn block(level: &LevelData, x: i32, y: i32, z: i32) -> u8 {
+    level.get_light(BlockCoord::new(x, y, z)).unwrap().block()
+}
+
+#[test]
+fn test_sky_light_column() {
+    let stone = BlockCoord::new(5, 10, 5);
+    let level = level_with(&[(stone, STONE)]);
+    let mut propagator = LightPropagator::new(&level, properties);
+    propagator.light_chunk(ChunkCoord::new(0, 0, 0));
+
+    //open columns are fully lit all the way down
+    assert_eq!(sky(&level, 0, 0, 0), MAX_LIGHT);
+    assert_eq!(sky(&level, 5, 11, 5), MAX_LIGHT);
+    //under the stone only light from the side gets in, one step dimmer
+    assert_eq!(sky(&level, 5, 10, 5), 0);
+    assert_eq!(sky(&level, 5, 9, 5), MAX_LIGHT - 1);
+    assert_eq!(sky(&level, 5, 0, 5), MAX_LIGHT - 1);
+
+    //taking the stone away lets the sky straight back down the column
+    clear_block(&level, stone);
+    propagator.update_block(stone);
+    assert_eq!(sky(&level, 5, 10, 5), MAX_LIGHT);
+    assert_eq!(sky(&level, 5, 0, 5), MAX_LIGHT);
+}
+
+#[test]
+fn test_block_light_falloff_and_removal() {
+    let lamp = BlockCoord::new(8, 8, 8);
+    let level = level_with(&[(lamp, LAMP)]);
+    let mut propagator = LightPropagator::new(&level, properties);
+    propagator.light_chunk(ChunkCoord::new(0, 0, 0));
+
+    //one level lost for every block of air
+    assert_eq!(block(&level, 8, 8, 8), 14);
+    assert_eq!(block(&level, 9, 8, 8), 13);
+    assert_eq!(block(&level, 12, 8, 8), 10);
+    assert_eq!(block(&level, 10, 9, 7), 10);
+    assert_eq!(block(&level, 8, 8, 1), 7);
+    //sky light is kept separately
+    assert_eq!(sky(&level, 9, 8, 8), MAX_LIGHT);
+
+    //removing the lamp takes all of its light with it
+    clear_block(&level, lamp);
+    propagator.update_block(lamp);
+    for pos in [(8, 8, 8), (9, 8, 8), (12, 8, 8), (10, 9, 7), (8, 8, 1)] {
+        assert_eq!(block(&level, pos.0, pos.1, pos.2), 0, "at {:?}", pos);
+    }
+    assert_eq!(sky(&level, 9, 8, 8), MAX_LIGHT);
+}
@@ -1,6 +1,12 @@
 use bevy::prelude::*;
 
 use crate::world::{block::*, chunk::*, util::BlockPalette};
+
+mod light;
+mod palette;
+mod signal;
+mod support;
+mod ticks;
 // use crate::serialization::ChunkSaveFormat;
 
 // use super::{chunk::*, *};
@@ -161,3 +167,326 @@ fn test_create_fat_palette() {
 
     app.update();
 }
+
+#[test]
+fn test_block_state_packing() {
+    use crate::world::{BlockAxis, BlockHalf, BlockState};
+    use ::util::direction::Direction;
+
+    let state = BlockState::default()
+        .with_facing(Some(Direction::NegZ))
+        .with_axis(Some(BlockAxis::X))
+        .with_half(Some(BlockHalf::Top))
+        .with_open(true);
+    assert_eq!(state.facing(), Some(Direction::NegZ));
+    assert_eq!(state.axis(), Some(BlockAxis::X));
+    assert_eq!(state.half(), Some(BlockHalf::Top));
+    assert!(state.open());
+
+    let state = state.with_facing(None).with_open(false);
+    assert_eq!(state.facing(), None);
+    assert_eq!(state.axis(), Some(BlockAxis::X));
+    assert_eq!(state.half(), Some(BlockHalf::Top));
+    assert!(!state.open());
+    assert!(BlockState::default().with_axis(None).is_default());
+}
+
+#[test]
+fn test_block_state_rotation() {
+    use crate::world::{BlockAxis, BlockState};
+    use ::util::direction::Direction;
+
+    //faces labeled by their own direction index
+    let faces: [usize; 6] = core::array::from_fn(|i| i);
+    assert_eq!(BlockState::default().rotate_faces(faces), faces);
+
+    //log lying along x shows its end on the x faces
+    let rotated = BlockState::default()
+        .with_axis(Some(BlockAxis::X))
+        .rotate_faces(faces);
+    assert_eq!(rotated[Direction::PosX.to_idx()], Direction::PosY.to_idx());
+    assert_eq!(rotated[Direction::NegX.to_idx()], Direction::NegY.to_idx());
+    assert_eq!(rotated[Direction::PosZ.to_idx()], Direction::PosZ.to_idx());
+
+    //every facing is a permutation that puts the front on the facing side
+    for dir in Direction::iter() {
+        let rotated = BlockState::default()
+            .with_facing(Some(dir))
+            .rotate_faces(faces);
+        assert_eq!(rotated[dir.to_idx()], Direction::PosZ.to_idx());
+        let mut sorted = rotated;
+        sorted.sort();
+        assert_eq!(sorted, faces);
+    }
+}
+
+#[test]
+fn test_paste_transform() {
+    use crate::world::{world_edit::PasteTransform, BlockAxis, BlockState};
+    use ::util::direction::Direction;
+
+    let size = IVec3::new(3, 2, 5);
+    for quarter_turns in 0..4 {
+        for mirror_x in [false, true] {
+            let transform = PasteTransform {
+                quarter_turns,
+                mirror_x,
+                mirror_z: false,
+            };
+            let new_size = transform.transform_size(size);
+            //every position lands somewhere different inside the new region
+            let mut seen = Vec::new();
+            for pos in ::util::iterators::Volume::new(IVec3::ZERO, size).iter() {
+                let moved = transform.transform_offset(pos, size);
+                assert!(moved.cmpge(IVec3::ZERO).all() && moved.cmplt(new_size).all());
+                assert!(!seen.contains(&moved));
+                seen.push(moved);
+            }
+        }
+    }
+    //one clockwise turn takes +x to +z
+    let turn = PasteTransform {
+        quarter_turns: 1,
+        ..default()
+    };
+    assert_eq!(turn.transform_size(size), IVec3::new(5, 2, 3));
+    assert_eq!(
+        turn.transform_offset(IVec3::new(2, 0, 0), size),
+        IVec3::new(4, 0, 2)
+    );
+    //and block states turn the same way
+    let state = BlockState::default()
+        .with_facing(Some(Direction::PosX))
+        .with_axis(Some(BlockAxis::X));
+    assert_eq!(turn.transform_state(state).facing(), Some(Direction::PosZ));
+    assert_eq!(turn.transform_state(state).axis(), Some(BlockAxis::Z));
+    let mirror = PasteTransform {
+        mirror_x: true,
+        ..default()
+    };
+    assert_eq!(mirror.transform_state(state).facing(), Some(Direction::NegX));
+    assert_eq!(mirror.transform_state(state).axis(), Some(BlockAxis::X));
+}
+
+#[test]
+fn test_schematic_bytes() {
+    use crate::serialization::schematic::{Schematic, SchematicError, SCHEMATIC_VERSION};
+    use crate::world::{BlockAxis, BlockState};
+
+    let schematic = Schematic {
+        version: SCHEMATIC_VERSION,
+        size: IVec3::new(2, 1, 2),
+        palette: vec![BlockName::core("stone")],
+        data: vec![(0, 1), (1, 3)],
+        states: vec![(2, BlockState::default().with_axis(Some(BlockAxis::X)))],
+    };
+    let bytes = schematic.to_bytes().unwrap();
+    assert_eq!(Schematic::from_bytes(&bytes).unwrap(), schematic);
+
+    //schematics from before block states load with every block in the default state
+    let v1 = bincode::serialize(&(
+        1u32,
+        schematic.size,
+        schematic.palette.clone(),
+        schematic.data.clone(),
+    ))
+    .unwrap();
+    assert_eq!(
+        Schematic::from_bytes(&v1).unwrap(),
+        Schematic {
+            states: Vec::new(),
+            ..schematic.clone()
+        }
+    );
+
+    let future = Schematic {
+        version: SCHEMATIC_VERSION + 1,
+        ..schematic.clone()
+    };
+    assert!(matches!(
+        Schematic::from_bytes(&future.to_bytes().unwrap()),
+        Err(SchematicError::UnsupportedVersion(_))
+    ));
+
+    //runs have to cover the whole region
+    let short = Schematic {
+        data: vec![(1, 3)],
+        ..schematic
+    };
+    assert!(matches!(
+        Schematic::from_bytes(&short.to_bytes().unwrap()),
+        Err(SchematicError::InvalidData)
+    ));
+}
+
+#[test]
+fn test_levels() {
+    use crate::serialization::db::ChunkTable;
+    use crate::world::{
+        levels::{LevelId, Levels},
+        Level, LevelData,
+    };
+    use crate::worldgen::GeneratorSettings;
+    use std::sync::Arc;
+
+    let surface = Level(Arc::new(LevelData::new("test", LevelId::SURFACE, 1234)));
+    let mut levels = Levels::new(&surface);
+    let generator = GeneratorSettings {
+        stone: BlockName::core("obsidian"),
+        ores: Vec::new(),
+        ..default()
+    };
+    let cave = levels.create(LevelId(1), "cave", generator.clone());
+    assert_eq!(cave.id, LevelId(1));
+    assert_ne!(cave.seed, surface.seed);
+    //each level keeps its own generator
+    assert_eq!(cave.generator, generator);
+    assert_eq!(surface.generator, GeneratorSettings::default());
+    //creating it again returns the same level
+    assert!(Arc::ptr_eq(
+        &cave,
+        &levels.create(LevelId(1), "cave", GeneratorSettings::default())
+    ));
+
+    levels.request_switch(LevelId(2));
+    assert_eq!(levels.take_pending_switch(), None);
+    levels.request_switch(LevelId(1));
+    assert_eq!(levels.take_pending_switch(), Some(LevelId(1)));
+
+    //the surface keeps the tids from before there were levels
+    assert_eq!(ChunkTable::BlockTicks.tid(LevelId::SURFACE), 2);
+    assert_ne!(
+        ChunkTable::Terrain.tid(LevelId(1)),
+        ChunkTable::Terrain.tid(LevelId::SURFACE)
+    );
+}
+
+#[test]
+fn test_block_tags() {
+    use crate::world::{BlockRegistry, BlockTag, BlockTagSet, TagId};
+
+    let mut registry = BlockRegistry::default();
+    let log = BlockId(Id::Basic(0));
+    let dirt = BlockId(Id::Basic(3));
+    registry.add_tags(log, &[BlockTag::core("logs")]);
+    registry.add_tags(dirt, &[BlockTag::core("soil"), BlockTag::new("mod", "diggable")]);
+    let logs = registry.get_tag(&BlockTag::core("logs")).unwrap();
+    let soil = registry.get_tag(&BlockTag::core("soil")).unwrap();
+    assert!(registry.has_tag(log, logs));
+    assert!(!registry.has_tag(log, soil));
+    assert!(registry.has_tag(dirt, soil));
+    //blocks without tags and empty never have one
+    assert!(!registry.has_tag(BlockId(Id::Basic(1)), logs));
+    assert!(!registry.has_tag(BlockId(Id::Empty), logs));
+
+    //unknown tags are left out of sets
+    let set = registry.tag_set(&[BlockTag::core("soil"), BlockTag::core("missing")]);
+    assert_eq!(set.iter().collect::<Vec<_>>(), vec![soil]);
+    assert!(registry.has_any_tag(dirt, &set));
+    assert!(!registry.has_any_tag(log, &set));
+
+    //sets grow past one word
+    let big: BlockTagSet = [TagId(1), TagId(70), TagId(200)].into_iter().collect();
+    assert!(big.contains(TagId(200)));
+    assert!(!big.contains(TagId(199)));
+    assert!(big.intersects(&[TagId(70)].into_iter().collect()));
+    assert!(!big.intersects(&[TagId(2)].into_iter().collect()));
+}
+
+#[test]
+fn test_loot_drop_roll() {
+    use crate::items::{
+        loot::{LootDrop, ToolKind, ToolRequirement},
+        ItemName,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let ore = LootDrop {
+        item: ItemName::core("ruby_ore"),
+        chance: 1.0,
+        min_count: 1,
+        max_count: 3,
+        tool: Some(ToolRequirement {
+            kind: ToolKind::Pickaxe,
+            level: 2,
+        }),
+    };
+    //the tool has to be the right kind and level
+    assert_eq!(ore.roll(&mut rng, |_| 0), 0);
+    assert_eq!(
+        ore.roll(&mut rng, |kind| if kind == ToolKind::Axe { 5 } else { 1 }),
+        0
+    );
+    for _ in 0..100 {
+        let count = ore.roll(&mut rng, |_| 2);
+        assert!((1..=3).contains(&count));
+    }
+
+    let never = LootDrop {
+        chance: 0.0,
+        tool: None,
+        ..ore.clone()
+    };
+    assert!((0..100).all(|_| never.roll(&mut rng, |_| 0) == 0));
+}
+
+#[test]
+fn test_explosion_resistance() {
+    use crate::world::{
+        events::{explosion_blocks, explosion_exposure},
+        levels::LevelId,
+        LevelData,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let dirt = Entity::from_raw(1);
+    let stone = Entity::from_raw(2);
+    let resistance = |block: Entity| if block == stone { 8.0 } else { 1.0 };
+    let level = LevelData::new("test", LevelId::SURFACE, 0);
+    let mut chunk = ArrayChunk::new(ChunkCoord::new(0, 0, 0), Entity::PLACEHOLDER);
+    for y in 0..CHUNK_SIZE as u8 {
+        for z in 0..CHUNK_SIZE as u8 {
+            for x in 2..=4 {
+                ChunkTrait::set_block(
+                    &mut chunk,
+                    ChunkIdx::new(x, y, z).into(),
+                    BlockType::Filled(dirt),
+                );
+            }
+            ChunkTrait::set_block(
+                &mut chunk,
+                ChunkIdx::new(8, y, z).into(),
+                BlockType::Filled(stone),
+            );
+        }
+    }
+    level.add_chunk(ChunkCoord::new(0, 0, 0), ChunkType::Full(chunk));
+
+    let origin = Vec3::new(5.5, 8.5, 8.5);
+    let mut rng = StdRng::seed_from_u64(0);
+    let removed = explosion_blocks(&level, origin, 8.0, resistance, &mut rng);
+    //dirt next to the explosion breaks, and the stone wall holds
+    assert!(removed.contains(&BlockCoord::new(4, 8, 8)));
+    assert!(removed.iter().all(|pos| (2..=4).contains(&pos.x)));
+
+    //the wall protects what's behind it, and open air only falls off with distance
+    let behind_wall = Vec3::new(10.5, 8.5, 8.5);
+    assert_eq!(
+        explosion_exposure(&level, origin, behind_wall, 8.0, resistance),
+        0.0
+    );
+    let in_the_open = Vec3::new(5.5, 8.5, 12.5);
+    assert_eq!(
+        explosion_exposure(&level, origin, in_the_open, 8.0, resistance),
+        0.5
+    );
+    assert_eq!(
+        explosion_exposure(&level, origin, in_the_open, 4.0, resistance),
+        0.0
+    );
+    //dirt only takes off a little
+    let behind_dirt = Vec3::new(1.5, 8.5, 8.5);
+    let exposure = explosion_exposure(&level, origin, behind_dirt, 8.0, resistance);
+    assert!(exposure > 0.0 && exposure < 0.5);
+}
@@ -0,0 +1,156 @@
+use std::ops::Index;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use util::palette::{Palette, PaletteIter, PaletteMap};
+
+use crate::world::{
+    chunk::BLOCKS_PER_CHUNK,
+    util::{BlockPalette, PackedKeys},
+};
+
+//the palette from before keys were packed, with a full u16 key per block
+struct ArrayPalette<V, const SIZE: usize> {
+    data: Box<[u16; SIZE]>,
+    palette: Vec<(u16, V, u16)>,
+}
+
+impl<V: Clone + PartialEq, const SIZE: usize> ArrayPalette<V, SIZE> {
+    fn new(default_val: V) -> Self {
+        Self {
+            data: Box::new([0; SIZE]),
+            palette: vec![(0, default_val, SIZE as u16)],
+        }
+    }
+}
+
+impl<V: Clone + PartialEq, const SIZE: usize> Index<usize> for ArrayPalette<V, SIZE> {
+    type Output = V;
+
+    fn index(&self, index: usize) -> &V {
+        PaletteMap::get_value(&self.palette, self.data[index]).unwrap()
+    }
+}
+
+impl<V: Clone + PartialEq, const SIZE: usize> Palette<u16, V, PaletteIter<u16, V>>
+    for ArrayPalette<V, SIZE>
+{
+    fn index_key(&self, index: usize) -> u16 {
+        self.data[index]
+    }
+
+    fn get_key(&self, value: &V) -> Option<u16> {
+        PaletteMap::get_key(&self.palette, value)
+    }
+
+    fn get_value(&self, key: u16) -> Option<&V> {
+        PaletteMap::get_value(&self.palette, key)
+    }
+
+    fn set(&mut self, index: usize, val: V) {
+        let new_key = match self.palette.get_entry_mut_value(&val) {
+            Some((k, _, r)) => {
+                *r += 1;
+                *k
+            }
+            None => match self.palette.iter().position(|(_, _, r)| *r == 0) {
+                Some(idx) => {
+                    self.palette[idx] = (idx as u16, val, 1);
+                    idx as u16
+                }
+                None => {
+                    let key = self.palette.len() as u16;
+                    self.palette.push((key, val, 1));
+                    key
+                }
+            },
+        };
+        self.palette.get_entry_mut(self.data[index]).unwrap().2 -= 1;
+        self.data[index] = new_key;
+    }
+
+    fn palette_iter(&self) -> PaletteIter<u16, V> {
+        PaletteIter {
+            data: self
+                .palette
+                .iter()
+                .filter(|(_, _, r)| *r > 0)
+                .map(|(k, v, _)| (*k, v.clone()))
+                .collect(),
+        }
+    }
+}
+
+//applies the same random writes to both palettes, drawing values from `0..distinct`
+//checks that every block and the set of used values match after each batch
+fn compare_random_writes(seed: u64, distinct: u32, writes: usize) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut packed: BlockPalette<u32, BLOCKS_PER_CHUNK> = BlockPalette::new(0);
+    let mut reference: ArrayPalette<u32, BLOCKS_PER_CHUNK> = ArrayPalette::new(0);
+    for batch in 0..writes / 256 {
+        //some batches only touch a small region, so counts drop to zero and entries get reused
+        let region = if batch % 3 == 0 { 64 } else { BLOCKS_PER_CHUNK };
+        for _ in 0..256 {
+            let idx = rng.gen_range(0..region);
+            let val = rng.gen_range(0..distinct);
+            packed.set(idx, val);
+            reference.set(idx, val);
+        }
+        for idx in 0..BLOCKS_PER_CHUNK {
+            assert_eq!(packed[idx], reference[idx], "seed {seed}, index {idx}");
+            let key = packed.index_key(idx);
+            assert_eq!(packed.get_value(key), Some(&packed[idx]));
+        }
+        let mut packed_values: Vec<u32> = packed.palette_iter().map(|(_, v)| v).collect();
+        let mut reference_values: Vec<u32> = reference.palette_iter().map(|(_, v)| v).collect();
+        packed_values.sort();
+        reference_values.sort();
+        assert_eq!(packed_values, reference_values, "seed {seed}");
+        for val in packed_values {
+            let key = packed.get_key(&val).unwrap();
+            assert_eq!(packed.get_value(key), Some(&val));
+        }
+        //the keys never take more bits than the palette needs
+        let max_key = packed.palette.iter().map(|(k, _, _)| *k).max().unwrap();
+        assert!(packed.data.bits() <= PackedKeys::bits_for(max_key));
+    }
+}
+
+#[test]
+fn test_packed_palette_matches_array_palette() {
+    for seed in 0..8 {
+        for distinct in [1, 2, 3, 5, 17, 300] {
+            compare_random_writes(seed, distinct, 4096);
+        }
+    }
+}
+
+#[test]
+fn test_packed_palette_shrinks() {
+    let mut palette: BlockPalette<u32, BLOCKS_PER_CHUNK> = BlockPalette::new(0);
+    assert_eq!(palette.data.bits(), 0);
+    for idx in 0..20 {
+        palette.set(idx, idx as u32 + 1);
+    }
+    assert_eq!(palette.data.bits(), 8);
+    for idx in 0..20 {
+        palette.set(idx, 0);
+    }
+    assert_eq!(palette.data.bits(), 0);
+    assert!(palette.iter().all(|v| *v == 0));
+}
+
+#[test]
+fn test_packed_keys() {
+    let mut keys = PackedKeys::new(100);
+    assert!(keys.iter().all(|k| k == 0));
+    for (i, key) in [1, 3, 15, 255, 1000].into_iter().enumerate() {
+        keys.set(i * 7, key);
+        assert_eq!(keys.bits(), PackedKeys::bits_for(key));
+    }
+    assert_eq!(keys.get(0), 1);
+    assert_eq!(keys.get(7), 3);
+    assert_eq!(keys.get(14), 15);
+    assert_eq!(keys.get(21), 255);
+    assert_eq!(keys.get(28), 1000);
+    assert_eq!(keys.iter().filter(|k| *k != 0).count(), 5);
+}
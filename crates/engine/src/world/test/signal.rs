@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+
+use crate::world::{
+    blocks::{
+        signal::{
+            AlarmBell, AlarmRungEvent, SignalChangedEvent, SignalConsumer, SignalPlugin,
+            SignalSource, SignalWire,
+        },
+        ticks::BlockTickEvent,
+    },
+    chunk::*,
+    events::{send_neighbor_changed_events, BlockNeighborChangedEvent, ChunkUpdatedEvent},
+    levels::LevelId,
+    BlockCoord, BlockId, BlockRegistry, BlockResources, BlockType, Level, LevelData,
+};
+
+fn set_block(app: &mut App, pos: BlockCoord, block: BlockType) {
+    app.world_mut()
+        .run_system_once(
+            move |level: Res<Level>, id_query: Query<&BlockId>, mut commands: Commands| {
+                level.set_block_entity_noupdate(pos, block, &id_query, &mut commands);
+            },
+        )
+        .unwrap();
+}
+
+fn drain<E: Event>(app: &mut App) -> Vec<E> {
+    app.world_mut()
+        .resource_mut::<Events<E>>()
+        .drain()
+        .collect()
+}
+
+#[test]
+fn test_signal_reaches_alarm() {
+    let mut app = App::new();
+    app.add_plugins(SignalPlugin)
+        .add_event::<BlockNeighborChangedEvent>()
+        .add_event::<BlockTickEvent>()
+        .add_event::<ChunkUpdatedEvent>()
+        .add_systems(Update, send_neighbor_changed_events)
+        .insert_resource(BlockResources {
+            registry: Arc::new(BlockRegistry::default()),
+        });
+    let source = app.world_mut().spawn(SignalSource { strength: 3 }).id();
+    let wire = app.world_mut().spawn(SignalWire).id();
+    let bell = app.world_mut().spawn((SignalConsumer, AlarmBell)).id();
+
+    //source, three wires and a bell in a row. the wires lose one strength per block, so the bell only just gets power
+    let at = |x: i32| BlockCoord::new(x, 5, 5);
+    let mut chunk = ArrayChunk::new(ChunkCoord::new(0, 0, 0), Entity::PLACEHOLDER);
+    for x in 2..=4 {
+        ChunkTrait::set_block(
+            &mut chunk,
+            ChunkIdx::from(at(x)).into(),
+            BlockType::Filled(wire),
+        );
+    }
+    ChunkTrait::set_block(
+        &mut chunk,
+        ChunkIdx::from(at(5)).into(),
+        BlockType::Filled(bell),
+    );
+    let level = LevelData::new("test", LevelId::SURFACE, 0);
+    level.add_chunk(ChunkCoord::new(0, 0, 0), ChunkType::Full(chunk));
+    let level = Arc::new(level);
+    app.insert_resource(Level(level.clone()));
+
+    //placing the source powers the network the same frame
+    set_block(&mut app, at(1), BlockType::Filled(source));
+    app.update();
+    assert_eq!(
+        (2..=5)
+            .map(|x| level.get_signal_level(at(x)))
+            .collect::<Vec<_>>(),
+        vec![3, 2, 1, 1]
+    );
+    let rung = drain::<AlarmRungEvent>(&mut app);
+    assert_eq!(rung.len(), 1);
+    assert_eq!(rung[0].block_position, at(5));
+    drain::<SignalChangedEvent>(&mut app);
+
+    //breaking a wire cuts the bell off
+    set_block(&mut app, at(3), BlockType::Empty);
+    app.update();
+    assert_eq!(level.get_signal_level(at(4)), 0);
+    assert_eq!(level.get_signal_level(at(5)), 0);
+    let changed = drain::<SignalChangedEvent>(&mut app);
+    assert_eq!(changed.len(), 1);
+    assert!(!changed[0].powered);
+    assert!(drain::<AlarmRungEvent>(&mut app).is_empty());
+}
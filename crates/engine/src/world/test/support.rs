@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::{
+    actors::block_actors::SpawnFallingBlockEvent,
+    world::{
+        blocks::support::{StructuralSupport, SupportPlugin},
+        chunk::*,
+        events::ChunkUpdatedEvent,
+        levels::LevelId,
+        BlockCoord, BlockType, Level, LevelData,
+    },
+};
+
+//the positions that were turned into falling blocks since the last call
+fn fallen(app: &mut App) -> Vec<IVec3> {
+    let mut fallen: Vec<IVec3> = app
+        .world_mut()
+        .resource_mut::<Events<SpawnFallingBlockEvent>>()
+        .drain()
+        .map(|event| event.position.as_ivec3())
+        .collect();
+    fallen.sort_by_key(|pos| pos.to_array());
+    fallen
+}
+
+#[test]
+fn test_unsupported_blocks_fall() {
+    let mut app = App::new();
+    app.add_plugins(SupportPlugin)
+        .add_event::<SpawnFallingBlockEvent>()
+        .add_event::<ChunkUpdatedEvent>();
+    let stone = app.world_mut().spawn_empty().id();
+    let beam = app
+        .world_mut()
+        .spawn(StructuralSupport { strength: 2 })
+        .id();
+
+    //a pillar standing on stone with an arm out to the side. strength 2 reaches one block sideways, so the end of the arm is too far out
+    let ground = BlockCoord::new(5, 2, 5);
+    let beams = [
+        BlockCoord::new(5, 3, 5),
+        BlockCoord::new(5, 4, 5),
+        BlockCoord::new(6, 4, 5),
+        BlockCoord::new(7, 4, 5),
+    ];
+    let mut chunk = ArrayChunk::new(ChunkCoord::new(0, 0, 0), Entity::PLACEHOLDER);
+    ChunkTrait::set_block(
+        &mut chunk,
+        ChunkIdx::from(ground).into(),
+        BlockType::Filled(stone),
+    );
+    for pos in beams {
+        ChunkTrait::set_block(
+            &mut chunk,
+            ChunkIdx::from(pos).into(),
+            BlockType::Filled(beam),
+        );
+    }
+    let level = LevelData::new("test", LevelId::SURFACE, 0);
+    level.add_chunk(ChunkCoord::new(0, 0, 0), ChunkType::Full(chunk));
+    let level = Arc::new(level);
+    app.insert_resource(Level(level.clone()));
+
+    //something next to the pillar was removed, so the structure is checked
+    level.queue_support_check(BlockCoord::new(4, 4, 5));
+    app.update();
+    assert_eq!(fallen(&mut app), vec![IVec3::new(7, 4, 5)]);
+    assert_eq!(level.get_block(beams[3]), Some(BlockType::Empty));
+    assert_eq!(level.get_block(beams[2]), Some(BlockType::Filled(beam)));
+
+    //without the stone nothing holds the rest up
+    if let ChunkType::Full(ref mut chunk) = level
+        .get_chunk_mut(ChunkCoord::new(0, 0, 0))
+        .unwrap()
+        .value_mut()
+    {
+        ChunkTrait::set_block(chunk, ChunkIdx::from(ground).into(), BlockType::Empty);
+    }
+    level.queue_support_check(ground);
+    app.update();
+    assert_eq!(
+        fallen(&mut app),
+        vec![
+            IVec3::new(5, 3, 5),
+            IVec3::new(5, 4, 5),
+            IVec3::new(6, 4, 5)
+        ]
+    );
+}
@@ -0,0 +1,54 @@
+use bevy::{ecs::world::CommandQueue, prelude::*};
+
+use crate::world::{chunk::*, levels::LevelId, BlockCoord, LevelData};
+
+fn level_with_chunk() -> LevelData {
+    let level = LevelData::new("test", LevelId::SURFACE, 0);
+    level.add_chunk(
+        ChunkCoord::new(0, 0, 0),
+        ChunkType::Full(ArrayChunk::new(
+            ChunkCoord::new(0, 0, 0),
+            Entity::PLACEHOLDER,
+        )),
+    );
+    level
+}
+
+//advances the level's ticks until something is due, returning how many ticks that took
+fn ticks_until_due(level: &LevelData, max: u32) -> Option<(u32, Vec<BlockCoord>)> {
+    (1..=max).find_map(|tick| {
+        let due = level.advance_block_ticks();
+        (!due.is_empty()).then_some((tick, due))
+    })
+}
+
+#[test]
+fn test_scheduled_block_ticks() {
+    let world = World::new();
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, &world);
+    let level = level_with_chunk();
+    let pos = BlockCoord::new(3, 4, 5);
+
+    //scheduling twice keeps the earlier tick
+    level.schedule_block_tick(pos, 5, &mut commands);
+    level.schedule_block_tick(pos, 3, &mut commands);
+    level.schedule_block_tick(pos, 8, &mut commands);
+    assert_eq!(ticks_until_due(&level, 10), Some((3, vec![pos])));
+    //it only happens once
+    assert_eq!(ticks_until_due(&level, 10), None);
+
+    //ticks in a chunk that isn't loaded wait for it
+    let unloaded = BlockCoord::new(-1, 4, 5);
+    level.schedule_block_tick(unloaded, 1, &mut commands);
+    assert_eq!(ticks_until_due(&level, 3), None);
+
+    //pending ticks are saved with the time they have left, and picked up again from there
+    level.schedule_block_tick(pos, 4, &mut commands);
+    level.advance_block_ticks();
+    let saved = level.get_saved_block_ticks(ChunkCoord::new(0, 0, 0));
+    assert_eq!(saved, vec![(ChunkIdx::from(pos).to_usize() as u16, 3)]);
+    let loaded = level_with_chunk();
+    loaded.load_saved_block_ticks(ChunkCoord::new(0, 0, 0), saved);
+    assert_eq!(ticks_until_due(&loaded, 10), Some((3, vec![pos])));
+}
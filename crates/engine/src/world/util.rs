@@ -2,7 +2,6 @@ use std::ops::Index;
 
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
-use serde_with::serde_as;
 use util::{
     bevy_utils,
     direction::{Direction, *},
@@ -194,12 +193,104 @@ impl From<Edge> for ChunkCoord {
     }
 }
 
-#[serde_as]
+//fixed length list of palette keys, packed with as few bits per key as the largest key needs
+//widths are always 0, 1, 2, 4, 8, or 16 bits so keys never cross a word boundary. with 0 bits every key is 0
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackedKeys {
+    len: usize,
+    bits: u32,
+    words: Vec<u64>,
+}
+
+impl PackedKeys {
+    pub fn new(len: usize) -> Self {
+        Self {
+            len,
+            bits: 0,
+            words: Vec::new(),
+        }
+    }
+
+    //smallest width that can hold `max_key`
+    pub fn bits_for(max_key: u16) -> u32 {
+        match max_key {
+            0 => 0,
+            1 => 1,
+            2..=3 => 2,
+            4..=15 => 4,
+            16..=255 => 8,
+            _ => 16,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn get(&self, index: usize) -> u16 {
+        if self.bits == 0 {
+            return 0;
+        }
+        let per_word = 64 / self.bits as usize;
+        let shift = (index % per_word) as u32 * self.bits;
+        ((self.words[index / per_word] >> shift) & self.mask()) as u16
+    }
+
+    //widens the keys first if `key` doesn't fit
+    pub fn set(&mut self, index: usize, key: u16) {
+        let needed = Self::bits_for(key);
+        if needed > self.bits {
+            self.remap(needed, |k| k);
+        }
+        self.write(index, key);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        (0..self.len).map(|i| self.get(i))
+    }
+
+    //replaces every key with map(key), repacked with `bits` per key. every mapped key has to fit
+    pub fn remap(&mut self, bits: u32, map: impl Fn(u16) -> u16) {
+        let keys: Vec<u16> = self.iter().map(map).collect();
+        self.bits = bits;
+        self.words = if bits == 0 {
+            Vec::new()
+        } else {
+            vec![0; self.len.div_ceil(64 / bits as usize)]
+        };
+        for (i, key) in keys.into_iter().enumerate() {
+            self.write(i, key);
+        }
+    }
+
+    fn mask(&self) -> u64 {
+        (1 << self.bits) - 1
+    }
+
+    fn write(&mut self, index: usize, key: u16) {
+        if self.bits == 0 {
+            return;
+        }
+        let per_word = 64 / self.bits as usize;
+        let shift = (index % per_word) as u32 * self.bits;
+        let mask = self.mask();
+        let word = &mut self.words[index / per_word];
+        *word = (*word & !(mask << shift)) | ((key as u64 & mask) << shift);
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BlockPalette<V, const SIZE: usize> {
-    #[serde_as(as = "[_; SIZE]")]
-    //serde doesn't support arrays with more than 32 elements by default
-    pub data: [u16; SIZE],
+    //key into the palette for each block. most chunks only have a few different blocks, so these are usually tiny
+    pub data: PackedKeys,
     //I think using a Vec will be faster than hashmap on average, since the number of blocks per chunk will usually be small
     pub palette: Vec<(u16, V, u16)>, //key, value, ref count
 }
@@ -207,7 +298,7 @@ pub struct BlockPalette<V, const SIZE: usize> {
 impl<V, const SIZE: usize> BlockPalette<V, SIZE> {
     pub fn new(default_val: V) -> Self {
         Self {
-            data: [0; SIZE],
+            data: PackedKeys::new(SIZE),
             palette: vec![(0, default_val, SIZE as u16)],
         }
     }
@@ -224,7 +315,31 @@ impl<V: Clone + PartialEq<V>, const SIZE: usize> BlockPalette<V, SIZE> {
         self.palette.iter_mut().find(|(_, v, r)| val == v && *r > 0)
     }
     pub fn iter(&self) -> impl Iterator<Item = &V> {
-        self.data.iter().map(|key| self.get_value(*key).unwrap())
+        self.data.iter().map(|key| self.get_value(key).unwrap())
+    }
+
+    //drops unused palette entries and renumbers the rest from 0, so the keys can be packed narrower
+    //only happens once the used entries would fit in half the keys of a narrower width, so a block being placed and broken over and over doesn't repack every time
+    fn compact_if_sparse(&mut self) {
+        let used = self.palette.iter().filter(|(_, _, r)| *r > 0).count();
+        let bits = self.data.bits();
+        if bits == 0 || (used > 1 && PackedKeys::bits_for((used * 2 - 1) as u16) >= bits) {
+            return;
+        }
+        let mut remap = vec![0; self.palette.len()];
+        let mut palette = Vec::with_capacity(used);
+        for (key, val, r) in self.palette.drain(..) {
+            if r > 0 {
+                let new_key = palette.len() as u16;
+                remap[key as usize] = new_key;
+                palette.push((new_key, val, r));
+            }
+        }
+        self.palette = palette;
+        self.data
+            .remap(PackedKeys::bits_for(used.saturating_sub(1) as u16), |key| {
+                remap[key as usize]
+            });
     }
 }
 
@@ -235,7 +350,7 @@ impl<const SIZE: usize> BlockPalette<BlockType, SIZE> {
     ) -> BlockPalette<T, SIZE> {
         let _span = info_span!("get_components", name = "get_components").entered();
         BlockPalette {
-            data: self.data,
+            data: self.data.clone(),
             palette: self.map_palette(query),
         }
     }
@@ -244,7 +359,7 @@ impl<const SIZE: usize> BlockPalette<BlockType, SIZE> {
         idx: usize,
         query: &Query<&T>,
     ) -> T {
-        self.get_value(self.data[idx])
+        self.get_value(self.data.get(idx))
             .map(|block| match block {
                 BlockType::Empty => T::default(),
                 BlockType::Filled(entity) => query.get(*entity).ok().cloned().unwrap_or_default(),
@@ -451,7 +566,7 @@ impl<V: Clone + PartialEq<V>, const SIZE: usize> Palette<u16, V, PaletteIter<u16
     for BlockPalette<V, SIZE>
 {
     fn index_key(&self, index: usize) -> u16 {
-        self.data[index]
+        self.data.get(index)
     }
 
     fn get_value(&self, key: u16) -> Option<&V> {
@@ -500,8 +615,12 @@ impl<V: Clone + PartialEq<V>, const SIZE: usize> Palette<u16, V, PaletteIter<u16
         //decrement old reference count
         let old_ref = self.get_entry_mut(self.index_key(index)).unwrap();
         old_ref.2 -= 1;
+        let old_unused = old_ref.2 == 0;
         //store new key in data
-        self.data[index] = new_key;
+        self.data.set(index, new_key);
+        if old_unused {
+            self.compact_if_sparse();
+        }
     }
 
     fn palette_iter(&self) -> PaletteIter<u16, V> {
@@ -0,0 +1,447 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use util::iterators::{Volume, VolumeContainer};
+
+use util::direction::Direction;
+
+use super::{
+    events::ChunkUpdatedEvent, BlockAxis, BlockCoord, BlockId, BlockName, BlockState, BlockType,
+    Id, Level,
+};
+
+pub struct WorldEditPlugin;
+
+impl Plugin for WorldEditPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldEdit>();
+    }
+}
+
+//edits that can be undone before the oldest is forgotten
+pub const DEFAULT_MAX_HISTORY: usize = 32;
+
+//a block and its state, which is what snapshots and the clipboard hold for each position
+pub type EditBlock = (BlockType, BlockState);
+
+//region edits with undo/redo
+//every edit snapshots the region it touches first, and goes through `batch_set_block_entities` so it's meshed and saved
+//unloaded positions and dynamic blocks are left alone, since their entities are despawned when replaced and couldn't be restored
+#[derive(Resource)]
+pub struct WorldEdit {
+    //oldest edits at the front
+    undo: VecDeque<VolumeContainer<EditBlock>>,
+    redo: Vec<VolumeContainer<EditBlock>>,
+    clipboard: Option<VolumeContainer<EditBlock>>,
+    pub max_history: usize,
+}
+
+impl Default for WorldEdit {
+    fn default() -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            clipboard: None,
+            max_history: DEFAULT_MAX_HISTORY,
+        }
+    }
+}
+
+//how the clipboard is placed when pasting. mirroring happens before rotating
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct PasteTransform {
+    //clockwise quarter turns around the y axis, looking down
+    pub quarter_turns: u8,
+    pub mirror_x: bool,
+    pub mirror_z: bool,
+}
+
+impl PasteTransform {
+    //size of a region of `size` after it's transformed
+    pub fn transform_size(self, size: IVec3) -> IVec3 {
+        if self.quarter_turns % 2 == 1 {
+            IVec3::new(size.z, size.y, size.x)
+        } else {
+            size
+        }
+    }
+
+    //maps `pos`, relative to the min corner of a region of `size`, to where it ends up relative to the transformed region's min corner
+    pub fn transform_offset(self, pos: IVec3, size: IVec3) -> IVec3 {
+        let mut pos = pos;
+        let mut size = size;
+        if self.mirror_x {
+            pos.x = size.x - 1 - pos.x;
+        }
+        if self.mirror_z {
+            pos.z = size.z - 1 - pos.z;
+        }
+        for _ in 0..self.quarter_turns % 4 {
+            pos = IVec3::new(size.z - 1 - pos.z, pos.y, pos.x);
+            size = IVec3::new(size.z, size.y, size.x);
+        }
+        pos
+    }
+
+    //turns the facing and axis of a pasted block the same way as its position
+    pub fn transform_state(self, state: BlockState) -> BlockState {
+        let mut facing = state.facing();
+        let mut axis = state.axis();
+        if self.mirror_x {
+            facing = facing.map(|dir| match dir {
+                Direction::PosX | Direction::NegX => dir.opposite(),
+                _ => dir,
+            });
+        }
+        if self.mirror_z {
+            facing = facing.map(|dir| match dir {
+                Direction::PosZ | Direction::NegZ => dir.opposite(),
+                _ => dir,
+            });
+        }
+        for _ in 0..self.quarter_turns % 4 {
+            //same turn as transform_offset, +x to +z
+            facing = facing.map(|dir| match dir {
+                Direction::PosX => Direction::PosZ,
+                Direction::PosZ => Direction::NegX,
+                Direction::NegX => Direction::NegZ,
+                Direction::NegZ => Direction::PosX,
+                _ => dir,
+            });
+            axis = axis.map(|axis| match axis {
+                BlockAxis::X => BlockAxis::Z,
+                BlockAxis::Z => BlockAxis::X,
+                BlockAxis::Y => BlockAxis::Y,
+            });
+        }
+        state.with_facing(facing).with_axis(axis)
+    }
+}
+
+impl WorldEdit {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear_history(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    //size of the copied region, if anything has been copied
+    pub fn clipboard_size(&self) -> Option<IVec3> {
+        self.clipboard.as_ref().map(|c| c.size())
+    }
+
+    //sets every block in `volume` to `block` in `state`
+    pub fn fill(
+        &mut self,
+        level: &Level,
+        volume: Volume,
+        block: BlockType,
+        state: BlockState,
+        id_query: &Query<&BlockId>,
+        update_writer: &mut EventWriter<ChunkUpdatedEvent>,
+        commands: &mut Commands,
+    ) {
+        self.edit(
+            level,
+            volume,
+            volume.iter().map(|pos| (pos, (block, state))),
+            id_query,
+            update_writer,
+            commands,
+        );
+    }
+
+    //sets every block named `from` in `volume` to `to` in `state`
+    pub fn replace(
+        &mut self,
+        level: &Level,
+        volume: Volume,
+        from: &BlockName,
+        to: BlockType,
+        state: BlockState,
+        name_query: &Query<&BlockName>,
+        id_query: &Query<&BlockId>,
+        update_writer: &mut EventWriter<ChunkUpdatedEvent>,
+        commands: &mut Commands,
+    ) {
+        let changes: Vec<(IVec3, EditBlock)> = volume
+            .iter()
+            .filter(|pos| match level.get_block(BlockCoord::from(*pos)) {
+                Some(BlockType::Filled(e)) => name_query.get(e).is_ok_and(|name| name == from),
+                _ => false,
+            })
+            .map(|pos| (pos, (to, state)))
+            .collect();
+        self.edit(
+            level,
+            volume,
+            changes.into_iter(),
+            id_query,
+            update_writer,
+            commands,
+        );
+    }
+
+    //sets the outside of `volume` to `block` in `state` and empties the inside
+    pub fn hollow(
+        &mut self,
+        level: &Level,
+        volume: Volume,
+        block: BlockType,
+        state: BlockState,
+        id_query: &Query<&BlockId>,
+        update_writer: &mut EventWriter<ChunkUpdatedEvent>,
+        commands: &mut Commands,
+    ) {
+        let max = volume.max_corner - IVec3::ONE;
+        let on_shell = |pos: IVec3| pos.cmpeq(volume.min_corner).any() || pos.cmpeq(max).any();
+        self.edit(
+            level,
+            volume,
+            volume.iter().map(|pos| {
+                if on_shell(pos) {
+                    (pos, (block, state))
+                } else {
+                    (pos, (BlockType::Empty, BlockState::default()))
+                }
+            }),
+            id_query,
+            update_writer,
+            commands,
+        );
+    }
+
+    //sets the four vertical sides of `volume` to `block` in `state`, leaving the floor, ceiling, and inside alone
+    pub fn walls(
+        &mut self,
+        level: &Level,
+        volume: Volume,
+        block: BlockType,
+        state: BlockState,
+        id_query: &Query<&BlockId>,
+        update_writer: &mut EventWriter<ChunkUpdatedEvent>,
+        commands: &mut Commands,
+    ) {
+        let max = volume.max_corner - IVec3::ONE;
+        let on_wall = |pos: IVec3| {
+            pos.x == volume.min_corner.x
+                || pos.x == max.x
+                || pos.z == volume.min_corner.z
+                || pos.z == max.z
+        };
+        self.edit(
+            level,
+            volume,
+            volume
+                .iter()
+                .filter(|pos| on_wall(*pos))
+                .map(|pos| (pos, (block, state))),
+            id_query,
+            update_writer,
+            commands,
+        );
+    }
+
+    //copies `volume` to the clipboard. unloaded positions and dynamic blocks aren't copied, and won't be pasted
+    pub fn copy(&mut self, level: &Level, volume: Volume, id_query: &Query<&BlockId>) {
+        let snapshot = snapshot(level, volume, id_query);
+        let mut clipboard = VolumeContainer::new(Volume::new(IVec3::ZERO, volume.size()));
+        for (pos, block) in snapshot.iter() {
+            clipboard.set(pos - volume.min_corner, block.copied());
+        }
+        self.clipboard = Some(clipboard);
+    }
+
+    //copies `volume` to the clipboard, then empties it
+    pub fn cut(
+        &mut self,
+        level: &Level,
+        volume: Volume,
+        id_query: &Query<&BlockId>,
+        update_writer: &mut EventWriter<ChunkUpdatedEvent>,
+        commands: &mut Commands,
+    ) {
+        self.copy(level, volume, id_query);
+        self.fill(
+            level,
+            volume,
+            BlockType::Empty,
+            BlockState::default(),
+            id_query,
+            update_writer,
+            commands,
+        );
+    }
+
+    //pastes the clipboard with its min corner at `origin`
+    //returns the volume that was pasted into, or None if the clipboard is empty
+    pub fn paste(
+        &mut self,
+        level: &Level,
+        origin: BlockCoord,
+        transform: PasteTransform,
+        id_query: &Query<&BlockId>,
+        update_writer: &mut EventWriter<ChunkUpdatedEvent>,
+        commands: &mut Commands,
+    ) -> Option<Volume> {
+        let clipboard = self.clipboard.take()?;
+        let origin = IVec3::from(origin);
+        let size = clipboard.size();
+        let volume = Volume::new(origin, origin + transform.transform_size(size));
+        self.edit(
+            level,
+            volume,
+            clipboard.iter().filter_map(|(pos, block)| {
+                block.map(|(b, state)| {
+                    (
+                        origin + transform.transform_offset(pos, size),
+                        (*b, transform.transform_state(*state)),
+                    )
+                })
+            }),
+            id_query,
+            update_writer,
+            commands,
+        );
+        self.clipboard = Some(clipboard);
+        Some(volume)
+    }
+
+    //returns false if there was nothing to undo
+    pub fn undo(
+        &mut self,
+        level: &Level,
+        id_query: &Query<&BlockId>,
+        update_writer: &mut EventWriter<ChunkUpdatedEvent>,
+        commands: &mut Commands,
+    ) -> bool {
+        let Some(before) = self.undo.pop_back() else {
+            return false;
+        };
+        let after = restore(level, before, id_query, update_writer, commands);
+        self.redo.push(after);
+        true
+    }
+
+    //returns false if there was nothing to redo
+    pub fn redo(
+        &mut self,
+        level: &Level,
+        id_query: &Query<&BlockId>,
+        update_writer: &mut EventWriter<ChunkUpdatedEvent>,
+        commands: &mut Commands,
+    ) -> bool {
+        let Some(after) = self.redo.pop() else {
+            return false;
+        };
+        let before = restore(level, after, id_query, update_writer, commands);
+        self.push_undo(before);
+        true
+    }
+
+    //snapshots `volume`, then applies the changes that are inside it
+    fn edit(
+        &mut self,
+        level: &Level,
+        volume: Volume,
+        changes: impl Iterator<Item = (IVec3, EditBlock)>,
+        id_query: &Query<&BlockId>,
+        update_writer: &mut EventWriter<ChunkUpdatedEvent>,
+        commands: &mut Commands,
+    ) {
+        let before = snapshot(level, volume, id_query);
+        set_blocks(
+            level,
+            changes.filter(|(pos, _)| before.get(*pos).is_some()),
+            id_query,
+            update_writer,
+            commands,
+        );
+        self.redo.clear();
+        self.push_undo(before);
+    }
+
+    fn push_undo(&mut self, snapshot: VolumeContainer<EditBlock>) {
+        self.undo.push_back(snapshot);
+        while self.undo.len() > self.max_history {
+            self.undo.pop_front();
+        }
+    }
+}
+
+//sets the blocks, then their states. setting a block resets its state, and already queues the remesh
+fn set_blocks(
+    level: &Level,
+    changes: impl Iterator<Item = (IVec3, EditBlock)>,
+    id_query: &Query<&BlockId>,
+    update_writer: &mut EventWriter<ChunkUpdatedEvent>,
+    commands: &mut Commands,
+) {
+    let changes: Vec<(BlockCoord, EditBlock)> = changes
+        .map(|(pos, block)| (BlockCoord::from(pos), block))
+        .collect();
+    level.batch_set_block_entities(
+        changes.iter().map(|(pos, (block, _))| (*pos, *block)),
+        id_query,
+        update_writer,
+        commands,
+    );
+    for (pos, (_, state)) in changes {
+        if !state.is_default() {
+            level.set_block_state_noupdate(pos, state);
+        }
+    }
+}
+
+//positions that are unloaded or have dynamic blocks are left as None
+fn snapshot(
+    level: &Level,
+    volume: Volume,
+    id_query: &Query<&BlockId>,
+) -> VolumeContainer<EditBlock> {
+    let mut container = VolumeContainer::new(volume);
+    for pos in volume.iter() {
+        let block = match level.get_block(BlockCoord::from(pos)) {
+            Some(BlockType::Filled(e))
+                if matches!(id_query.get(e), Ok(BlockId(Id::Dynamic(_)))) =>
+            {
+                None
+            }
+            block => block,
+        };
+        container.set(
+            pos,
+            block.map(|block| (block, level.get_block_state(BlockCoord::from(pos)))),
+        );
+    }
+    container
+}
+
+//sets the blocks in `snapshot`, and returns a snapshot of what they replaced
+fn restore(
+    level: &Level,
+    snapshot: VolumeContainer<EditBlock>,
+    id_query: &Query<&BlockId>,
+    update_writer: &mut EventWriter<ChunkUpdatedEvent>,
+    commands: &mut Commands,
+) -> VolumeContainer<EditBlock> {
+    let current = self::snapshot(level, snapshot.volume(), id_query);
+    set_blocks(
+        level,
+        snapshot
+            .iter()
+            .filter(|(pos, _)| current.get(*pos).is_some())
+            .filter_map(|(pos, block)| block.map(|b| (pos, *b))),
+        id_query,
+        update_writer,
+        commands,
+    );
+    current
+}
@@ -7,7 +7,10 @@ use crate::{
 };
 use bevy::prelude::*;
 
-use super::{pipeline::Heightmap, DecorationSettings, ShaperSettings};
+use super::{
+    pipeline::Heightmap, structures, DecorationSettings, GenerationPhase, ShaperSettings,
+    UsedShaperSettings,
+};
 
 #[allow(clippy::needless_range_loop)] //more readable with range
 pub fn shape_chunk<
@@ -170,3 +173,34 @@ pub fn gen_decoration(
     }
     biome_map
 }
+
+//bump when generate_chunk makes anything different for the same seed.
+//levels with terrain diffs are stamped with this, since their diffs only apply to the generator they were made against
+pub const GENERATOR_VERSION: u32 = 1;
+
+//what the generator makes at `coord` from the seed alone. the chunk above is only shaped, and structures
+//that spill in from neighboring chunks are left out. the pipeline can make something different,
+//since it decorates against whatever is loaded above, so this is what saved diffs are made against
+pub fn generate_chunk(
+    coord: ChunkCoord,
+    shaper: Arc<UsedShaperSettings>,
+    decoration: &DecorationSettings,
+    seed: u64,
+) -> GeneratingChunk {
+    let _my_span = info_span!("generate_chunk", name = "generate_chunk").entered();
+    let mut chunk = GeneratingChunk::new(coord, Entity::PLACEHOLDER);
+    let heightmap = shape_chunk(&mut chunk, shaper.clone(), decoration.stone);
+    let mut above = GeneratingChunk::new(coord + ChunkCoord::new(0, 1, 0), Entity::PLACEHOLDER);
+    shape_chunk(&mut above, shaper, decoration.stone);
+    let biomes = gen_decoration(
+        &mut chunk,
+        &ChunkType::Generating(GenerationPhase::Shaped, above),
+        &heightmap,
+        decoration,
+    );
+    let mut buffer = structures::gen_structures(&mut chunk, seed, biomes, &decoration.biomes);
+    if let Some(own) = buffer.buf.remove(&coord) {
+        own.apply_to(chunk.blocks.as_mut());
+    }
+    chunk
+}
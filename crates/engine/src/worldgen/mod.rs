@@ -2,14 +2,19 @@ use std::sync::Arc;
 
 use bevy::prelude::*;
 use bracket_noise::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     util::{noise::get_next_prng, noise::SplineNoise, spline::Spline},
-    world::{BlockId, BlockName, BlockResources, Level, LevelLoadState, LevelSystemSet},
+    world::{
+        levels::ActiveLevelChangedEvent, BlockId, BlockName, BlockRegistry, BlockResources, Level,
+        LevelLoadState, LevelSystemSet,
+    },
 };
 
 mod generator;
 mod pipeline;
+pub use generator::{generate_chunk, GENERATOR_VERSION};
 pub use pipeline::{ChunkNeedsGenerated, GeneratedChunk, ShaperSettings};
 
 use self::{biomes::UsedBiomeMap, pipeline::OreGenerator};
@@ -27,6 +32,7 @@ pub const SQUISH: usize = 7;
 
 pub type UsedShaperResources =
     ShaperResources<{ DENSITY }, { HEIGHTMAP }, { LANDMASS }, { SQUISH }>;
+pub type UsedShaperSettings = ShaperSettings<{ DENSITY }, { HEIGHTMAP }, { LANDMASS }, { SQUISH }>;
 
 pub struct WorldGenPlugin;
 
@@ -48,6 +54,13 @@ impl Plugin for WorldGenPlugin {
         .add_systems(
             OnEnter(LevelLoadState::Loading),
             (create_shaper_settings, create_decoration_settings),
+        )
+        //each level generates from its own seed and GeneratorSettings
+        .add_systems(
+            Update,
+            (create_shaper_settings, create_decoration_settings)
+                .in_set(LevelSystemSet::LoadingAndMain)
+                .run_if(on_event::<ActiveLevelChangedEvent>),
         );
     }
 }
@@ -75,22 +88,56 @@ pub struct DecorationSettings {
     pub ores: Vec<OreGenerator>,
 }
 
+//what makes one level's terrain different from another's, besides the seed. kept on LevelData and saved with the level
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeneratorSettings {
+    //see ShaperSettings. offsets from the heightmap of the highest and lowest control points of the density spline,
+    // with the density threshold at each
+    pub upper_density: Vec2,
+    pub mid_density: f32,
+    pub lower_density: Vec2,
+    //what the terrain is made of
+    pub stone: BlockName,
+    //ores that replace the stone in veins
+    pub ores: Vec<BlockName>,
+}
+
+//the surface's generator
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        Self {
+            upper_density: Vec2::new(25.0, 1.0),
+            mid_density: 0.0,
+            lower_density: Vec2::new(-100.0, -0.2),
+            stone: BlockName::core("stone"),
+            ores: vec![BlockName::core("ruby_ore")],
+        }
+    }
+}
+
 fn create_shaper_settings(mut commands: Commands, level: Res<Level>) {
-    let mut seed = level.seed ^ 0xABDFACDFAEDFA0DF;
-    let settings = ShaperSettings {
+    commands.insert_resource(ShaperResources(Arc::new(shaper_settings(
+        level.seed,
+        &level.generator,
+    ))));
+}
+
+//the same seed and settings always make the same shaper, so chunks can be generated again exactly
+pub fn shaper_settings(seed: u64, generator: &GeneratorSettings) -> UsedShaperSettings {
+    let mut seed = seed ^ 0xABDFACDFAEDFA0DF;
+    ShaperSettings {
         density_noise: create_density_noise(seed),
         landmass_noise: create_landmass_noise(get_next_seed(&mut seed)),
         squish_noise: create_squish_noise(get_next_seed(&mut seed)),
         //x = terrain height, y = density threshold to place a block
         //this is the maximum height, but an offset: heightmap_noise+upper_density.x = the highest control point on the spline
-        upper_density: Vec2::new(25.0, 1.0),
+        upper_density: generator.upper_density,
         //this is the middle height: which basically controls the
         heightmap_noise: create_heightmap_noise(get_next_seed(&mut seed)), //don't want the seeds to be the same
-        mid_density: 0.0,
+        mid_density: generator.mid_density,
         //this is the minimum height, but an offset: heightmap_noise+lower_density.x = the lowest control point on the spline
-        lower_density: Vec2::new(-100.0, -0.2),
-    };
-    commands.insert_resource(ShaperResources(Arc::new(settings)));
+        lower_density: generator.lower_density,
+    }
 }
 
 fn create_density_noise(seed: u64) -> SplineNoise<DENSITY> {
@@ -176,24 +223,40 @@ fn create_decoration_settings(
     mut commands: Commands,
     resources: Res<BlockResources>,
 ) {
-    let mut seed = level.seed ^ 0x6287192746;
+    commands.insert_resource(DecorationResources(Arc::new(decoration_settings(
+        &resources.registry,
+        level.seed,
+        &level.generator,
+    ))));
+}
+
+pub fn decoration_settings(
+    registry: &BlockRegistry,
+    seed: u64,
+    generator: &GeneratorSettings,
+) -> DecorationSettings {
+    let mut seed = seed ^ 0x6287192746;
 
     let mut ore_noise = FastNoise::seeded(get_next_seed(&mut seed));
     ore_noise.set_noise_type(NoiseType::Value);
     ore_noise.set_frequency(132671324.0);
 
-    commands.insert_resource(DecorationResources(Arc::new(DecorationSettings {
-        biomes: UsedBiomeMap::default(&resources.registry, seed),
+    DecorationSettings {
+        biomes: UsedBiomeMap::default(registry, seed),
         ore_noise,
-        stone: resources.registry.get_id(&BlockName::core("stone")),
-        ores: vec![OreGenerator {
-            ore_block: resources.registry.get_id(&BlockName::core("ruby_ore")),
-            can_replace: vec![resources.registry.get_id(&BlockName::core("stone"))],
-            rarity: (0, 1),
-            vein_min: 10,
-            vein_max: 20,
-        }],
-    })))
+        stone: registry.get_id(&generator.stone),
+        ores: generator
+            .ores
+            .iter()
+            .map(|ore| OreGenerator {
+                ore_block: registry.get_id(ore),
+                can_replace: vec![registry.get_id(&generator.stone)],
+                rarity: (0, 1),
+                vein_min: 10,
+                vein_max: 20,
+            })
+            .collect(),
+    }
 }
 
 fn get_next_seed(seed: &mut u64) -> u64 {
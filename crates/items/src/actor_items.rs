@@ -7,6 +7,7 @@ use engine::{
     physics::{
         collision::Aabb,
         query::{self, Raycast, RaycastHit},
+        spatial::SpatialIndex,
     },
     world::{BlockPhysics, Level},
     GameState,
@@ -129,6 +130,7 @@ fn do_spawn_actors(
     level: Res<Level>,
     block_physics_query: Query<&BlockPhysics>,
     object_query: Query<(Entity, &GlobalTransform, &Aabb)>,
+    spatial_index: Res<SpatialIndex>,
 ) {
     const REACH: f32 = 10.0;
     const BACKWARD_DIST: f32 = 1.0;
@@ -145,6 +147,7 @@ fn do_spawn_actors(
                 &level,
                 &block_physics_query,
                 &object_query,
+                &spatial_index,
                 &[*user],
             ) {
                 //jank to not spawn inside ground so easy
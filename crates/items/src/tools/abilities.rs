@@ -5,7 +5,8 @@ use util::direction::Direction;
 
 use engine::world::{
     events::{BlockDamageSetEvent, BlockHitEvent, ChunkUpdatedEvent},
-    BlockCoord, BlockId, Level, LevelSystemSet,
+    BlockCoord, BlockId, BlockRegistry, BlockResources, BlockTag, BlockTagSet, Level,
+    LevelSystemSet,
 };
 
 use super::{calc_block_damage, Tool, ToolResistance};
@@ -15,9 +16,7 @@ pub struct ToolAbilitiesPlugin;
 impl Plugin for ToolAbilitiesPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<AxeAbility>()
-            .register_type::<AxeAbilityTarget>()
             .register_type::<ShovelAbility>()
-            .register_type::<ShovelAbilityTarget>()
             .add_systems(
                 Update,
                 (axe_ability_system, shovel_ability_system).in_set(LevelSystemSet::Main),
@@ -25,28 +24,24 @@ impl Plugin for ToolAbilitiesPlugin {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Default, Serialize, Deserialize)]
-#[reflect(Component, FromWorld)]
-pub struct AxeAbilityTarget;
-
-#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Component, Reflect, Default, Serialize, Deserialize)]
 #[reflect(Component, FromWorld)]
 pub struct AxeAbility {
     pub max_blocks: usize,
     pub search_radius: i32,
     pub damage_mult: f32,
+    //blocks with any of these tags are felled together
+    pub targets: Vec<BlockTag>,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Default, Serialize, Deserialize)]
-#[reflect(Component, FromWorld)]
-pub struct ShovelAbilityTarget;
-
-#[derive(Copy, Clone, Debug, PartialEq, Component, Reflect, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Component, Reflect, Default, Serialize, Deserialize)]
 #[reflect(Component, FromWorld)]
 pub struct ShovelAbility {
     pub radius: usize,
     pub length: usize,
     pub damage_mult: f32,
+    //blocks with any of these tags are dug
+    pub targets: Vec<BlockTag>,
 }
 
 fn axe_ability_system(
@@ -55,7 +50,7 @@ fn axe_ability_system(
     axe_ability_query: Query<(&Tool, &AxeAbility)>,
     resistance_query: Query<&ToolResistance>,
     id_query: Query<&BlockId>,
-    target_query: Query<&AxeAbilityTarget>,
+    resources: Res<BlockResources>,
     mut commands: Commands,
     mut writer: EventWriter<BlockDamageSetEvent>,
     mut update_writer: EventWriter<ChunkUpdatedEvent>,
@@ -74,11 +69,18 @@ fn axe_ability_system(
                     max_blocks,
                     search_radius,
                     damage_mult,
+                    targets,
                 },
             )) = axe_ability_query.get(*item)
             {
+                let targets = resources.registry.tag_set(targets);
                 if let Some(block) = level.get_block_entity(*block_position) {
-                    if !target_query.contains(block) {
+                    if !level.block_has_any_tag(
+                        *block_position,
+                        &targets,
+                        &resources.registry,
+                        &id_query,
+                    ) {
                         continue;
                     }
                     let damage = calc_block_damage(
@@ -93,7 +95,8 @@ fn axe_ability_system(
                         *max_blocks,
                         *search_radius,
                         &id_query,
-                        &target_query,
+                        &resources.registry,
+                        &targets,
                         *item,
                         &mut HashSet::new(),
                         &mut commands,
@@ -115,7 +118,8 @@ fn do_axe_ability(
     max_blocks: usize,
     search_radius: i32,
     id_query: &Query<&BlockId>,
-    target_query: &Query<&AxeAbilityTarget>,
+    registry: &BlockRegistry,
+    targets: &BlockTagSet,
     tool: Entity,
     hits: &mut HashSet<BlockCoord>,
     commands: &mut Commands,
@@ -128,38 +132,37 @@ fn do_axe_ability(
             for x in -square_radius..square_radius + 1 {
                 for z in -square_radius..square_radius + 1 {
                     let pos = initial_pos + BlockCoord::new(x, y, z);
-                    if let Some(block) = level.get_block_entity(pos) {
-                        if target_query.contains(block) {
-                            if hits.insert(pos) {
-                                if pos != initial_pos {
-                                    level.damage_block(
-                                        pos,
-                                        damage,
-                                        Some(tool),
-                                        id_query,
-                                        writer,
-                                        update_writer,
-                                        commands,
-                                    );
-                                }
-                                do_axe_ability(
-                                    level,
-                                    damage,
+                    if level.block_has_any_tag(pos, targets, registry, id_query) {
+                        if hits.insert(pos) {
+                            if pos != initial_pos {
+                                level.damage_block(
                                     pos,
-                                    max_blocks,
-                                    search_radius,
+                                    damage,
+                                    Some(tool),
                                     id_query,
-                                    target_query,
-                                    tool,
-                                    hits,
-                                    commands,
                                     writer,
                                     update_writer,
+                                    commands,
                                 );
                             }
-                            if hits.len() >= max_blocks {
-                                return max_blocks;
-                            }
+                            do_axe_ability(
+                                level,
+                                damage,
+                                pos,
+                                max_blocks,
+                                search_radius,
+                                id_query,
+                                registry,
+                                targets,
+                                tool,
+                                hits,
+                                commands,
+                                writer,
+                                update_writer,
+                            );
+                        }
+                        if hits.len() >= max_blocks {
+                            return max_blocks;
                         }
                     }
                 }
@@ -172,38 +175,37 @@ fn do_axe_ability(
             for x in -square_radius..square_radius + 1 {
                 for z in -square_radius..square_radius + 1 {
                     let pos = initial_pos + BlockCoord::new(x, y, z);
-                    if let Some(block) = level.get_block_entity(pos) {
-                        if target_query.contains(block) {
-                            if hits.insert(pos) {
-                                if pos != initial_pos {
-                                    level.damage_block(
-                                        pos,
-                                        damage,
-                                        None,
-                                        id_query,
-                                        writer,
-                                        update_writer,
-                                        commands,
-                                    );
-                                }
-                                do_axe_ability(
-                                    level,
-                                    damage,
+                    if level.block_has_any_tag(pos, targets, registry, id_query) {
+                        if hits.insert(pos) {
+                            if pos != initial_pos {
+                                level.damage_block(
                                     pos,
-                                    max_blocks,
-                                    search_radius,
+                                    damage,
+                                    None,
                                     id_query,
-                                    target_query,
-                                    tool,
-                                    hits,
-                                    commands,
                                     writer,
                                     update_writer,
+                                    commands,
                                 );
                             }
-                            if hits.len() >= max_blocks {
-                                return max_blocks;
-                            }
+                            do_axe_ability(
+                                level,
+                                damage,
+                                pos,
+                                max_blocks,
+                                search_radius,
+                                id_query,
+                                registry,
+                                targets,
+                                tool,
+                                hits,
+                                commands,
+                                writer,
+                                update_writer,
+                            );
+                        }
+                        if hits.len() >= max_blocks {
+                            return max_blocks;
                         }
                     }
                 }
@@ -219,7 +221,7 @@ fn shovel_ability_system(
     shovel_ability_query: Query<(&Tool, &ShovelAbility)>,
     resistance_query: Query<&ToolResistance>,
     id_query: Query<&BlockId>,
-    target_query: Query<&ShovelAbilityTarget>,
+    resources: Res<BlockResources>,
     mut commands: Commands,
     mut writer: EventWriter<BlockDamageSetEvent>,
     mut update_writer: EventWriter<ChunkUpdatedEvent>,
@@ -238,9 +240,11 @@ fn shovel_ability_system(
                     radius,
                     length,
                     damage_mult,
+                    targets,
                 },
             )) = shovel_ability_query.get(*item)
             {
+                let targets = resources.registry.tag_set(targets);
                 let direction = Direction::from(*hit_forward);
                 let axis = BlockCoord::from(direction);
                 info!("axis: {:?}", axis);
@@ -248,7 +252,14 @@ fn shovel_ability_system(
                     direction.for_each_in_plane(*radius as i32, |offset| {
                         let coord = axis * len + offset.into() + *block_position;
                         if let Some(block) = level.get_block_entity(coord) {
-                            if coord == *block_position || !target_query.contains(block) {
+                            if coord == *block_position
+                                || !level.block_has_any_tag(
+                                    coord,
+                                    &targets,
+                                    &resources.registry,
+                                    &id_query,
+                                )
+                            {
                                 return;
                             }
                             let damage = calc_block_damage(
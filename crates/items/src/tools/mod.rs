@@ -1,18 +1,18 @@
 use bevy::prelude::*;
+use rand::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use engine::{
-    actors::Player,
-    physics::{collision::Aabb, query},
+    physics::{collision::Aabb, query, spatial::SpatialIndex},
     world::{
         events::{BlockDamageSetEvent, BlockHitEvent, ChunkUpdatedEvent},
-        BlockId, BlockPhysics, Level,
+        BlockId, BlockPhysics, BlockResources, Level,
     },
 };
 
 use engine::items::{
-    inventory::Inventory, CreatorItem, HitResult, ItemStack, ItemSystemSet, MaxStackSize,
-    PickupItemEvent, SwingEndEvent, SwingItemEvent,
+    loot::{resolve_item, BlockLoot, LootTable, SpawnDroppedItemEvent, ToolKind},
+    CreatorItem, HitResult, ItemResources, ItemStack, ItemSystemSet, SwingEndEvent, SwingItemEvent,
 };
 
 pub mod abilities;
@@ -28,7 +28,8 @@ impl Plugin for ToolsPlugin {
             .add_systems(
                 Update,
                 (on_swing, deal_block_damage).in_set(ItemSystemSet::UsageProcessing),
-            );
+            )
+            .add_systems(Update, drop_block_loot.in_set(ItemSystemSet::DropPickup));
     }
 }
 
@@ -59,6 +60,16 @@ pub struct Tool {
     pub shovel: u32,
 }
 
+impl Tool {
+    pub fn level(self, kind: ToolKind) -> u32 {
+        match kind {
+            ToolKind::Axe => self.axe,
+            ToolKind::Pickaxe => self.pickaxe,
+            ToolKind::Shovel => self.shovel,
+        }
+    }
+}
+
 #[derive(
     Copy, Clone, Hash, Eq, Debug, PartialEq, Component, Reflect, Default, Serialize, Deserialize,
 )]
@@ -72,6 +83,7 @@ pub fn on_swing(
     level: Res<Level>,
     block_physics_query: Query<&BlockPhysics>,
     object_query: Query<(Entity, &GlobalTransform, &Aabb)>,
+    spatial_index: Res<SpatialIndex>,
     item_query: Query<(), Without<DontHitBlocks>>,
 ) {
     for SwingItemEvent {
@@ -89,6 +101,7 @@ pub fn on_swing(
             &level,
             &block_physics_query,
             &object_query,
+            &spatial_index,
             &[*user],
         ) {
             writer.send(BlockHitEvent {
@@ -120,10 +133,6 @@ fn deal_block_damage(
     tool_query: Query<&Tool>,
     level: Res<Level>,
     id_query: Query<&BlockId>,
-    mut player_query: Query<&mut Inventory, With<Player>>,
-    block_query: Query<&CreatorItem>,
-    data_query: Query<&MaxStackSize>,
-    mut pickup_writer: EventWriter<PickupItemEvent>,
     mut writer: EventWriter<BlockDamageSetEvent>,
     mut update_writer: EventWriter<ChunkUpdatedEvent>,
     mut commands: Commands,
@@ -145,8 +154,7 @@ fn deal_block_damage(
                         .copied() //entity that hit the block had tool power
                         .unwrap_or_default(),
                 ); //...or nothing and use default tool
-                   //todo - remove this once item drops are entities
-            if let Some(broken) = level.damage_block(
+            level.damage_block(
                 *block_position,
                 calc_block_damage(resistance, tool),
                 *item,
@@ -154,13 +162,64 @@ fn deal_block_damage(
                 &mut writer,
                 &mut update_writer,
                 &mut commands,
-            ) {
-                if let Some(mut inv) = user.and_then(|e| player_query.get_mut(e).ok()) {
-                    if let Ok(CreatorItem(item)) = block_query.get(broken) {
-                        inv.pickup_item(ItemStack::new(*item, 1), &data_query, &mut pickup_writer);
+            );
+        }
+    }
+}
+
+//spawns the drops of blocks that were broken since last frame
+//blocks with a BlockLoot roll it with the tool that broke them, and other blocks drop their own item
+fn drop_block_loot(
+    level: Res<Level>,
+    loot_query: Query<(Option<&BlockLoot>, Option<&LootTable<Entity>>)>,
+    tool_query: Query<&Tool>,
+    creator_query: Query<&CreatorItem>,
+    block_resources: Res<BlockResources>,
+    item_resources: Res<ItemResources>,
+    mut writer: EventWriter<SpawnDroppedItemEvent>,
+) {
+    const DROP_SPEED: f32 = 2.0;
+    let mut rng = thread_rng();
+    for broken in level.take_broken_blocks() {
+        let Ok((block_loot, loot_table)) = loot_query.get(broken.block) else {
+            continue;
+        };
+        let tool = broken
+            .damager
+            .and_then(|damager| tool_query.get(damager).ok())
+            .copied()
+            .unwrap_or_default();
+        let mut drops = Vec::new();
+        match (block_loot, loot_table) {
+            (Some(block_loot), _) => {
+                for (name, count) in block_loot.roll(&mut rng, |kind| tool.level(kind)) {
+                    match resolve_item(
+                        name,
+                        &item_resources.registry,
+                        &block_resources.registry,
+                        &creator_query,
+                    ) {
+                        Some(item) => drops.push(ItemStack::new(item, count)),
+                        None => warn!("Couldn't find dropped item {:?}", name),
                     }
                 }
             }
+            (None, Some(loot_table)) => drops.extend(
+                loot_table
+                    .get_loot()
+                    .into_iter()
+                    .map(|item| ItemStack::new(item, 1)),
+            ),
+            (None, None) => {}
+        }
+        let position = broken.position.center();
+        for stack in drops {
+            writer.send(SpawnDroppedItemEvent {
+                stack,
+                position,
+                velocity: Vec3::new(rng.gen_range(-1.0..1.0), 1.0, rng.gen_range(-1.0..1.0))
+                    * DROP_SPEED,
+            });
         }
     }
 }
@@ -8,6 +8,7 @@ use engine::{
         collision::Aabb,
         movement::Velocity,
         query::{self, RaycastHit},
+        spatial::SpatialIndex,
     },
     world::{BlockPhysics, Level},
 };
@@ -70,6 +71,7 @@ pub fn attack_melee(
     physics_query: Query<&BlockPhysics>,
     weapon_query: Query<&MeleeWeaponItem>,
     object_query: Query<(Entity, &GlobalTransform, &Aabb)>,
+    spatial_index: Res<SpatialIndex>,
 ) {
     for SwingItemEvent {
         user,
@@ -84,6 +86,7 @@ pub fn attack_melee(
                 &level,
                 &physics_query,
                 &object_query,
+                &spatial_index,
                 &[*user],
             ) {
                 attack_writer.send(AttackEvent {
@@ -1,11 +1,15 @@
 use bevy::{app::AppExit, prelude::*};
-use std::time::Duration;
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
 
 use bevy_simple_text_input::{TextInput, TextInputTextColor, TextInputTextFont, TextInputValue};
 use engine::{
     actors::ghost::{GhostResources, Hand, HandState, Handed, OrbitParticle},
     effects::mesh_particles::MeshParticleEmitter,
-    serialization::{LevelCreationInput, SavedLevels},
+    serialization::{backup, level_file_path, LevelCreationInput, LevelOpenError, SavedLevels},
+    world::settings::Settings,
     GameState,
 };
 use util::{iterators::even_distribution_on_sphere, lerp, LocalRepeatingTimer};
@@ -37,7 +41,7 @@ impl Plugin for MainMenuPlugin {
                     exited: GameState::Game,
                     entered: GameState::Menu,
                 },
-                go_to_main_screen,
+                leave_game,
             )
             .add_systems(
                 OnTransition {
@@ -115,6 +119,27 @@ struct WorldSelectCreateText;
 #[component(storage = "SparseSet")]
 struct WorldSelectLoadLevelContainer;
 
+#[derive(Component, Clone, Copy)]
+#[component(storage = "SparseSet")]
+struct WorldSelectStatusText;
+
+#[derive(Component, Clone, Copy)]
+#[component(storage = "SparseSet")]
+struct WorldSelectBackupsButton(&'static str);
+
+//the backups of the level in the same row
+#[derive(Component, Clone, Copy)]
+#[component(storage = "SparseSet")]
+struct WorldSelectBackupList;
+
+#[derive(Component, Clone)]
+#[component(storage = "SparseSet")]
+struct WorldSelectRestoreButton {
+    level: &'static str,
+    path: PathBuf,
+    label: String,
+}
+
 #[derive(Component, Clone, Copy)]
 #[component(storage = "SparseSet")]
 struct WorldSelectLoadLevelButton(&'static str);
@@ -361,6 +386,15 @@ fn setup_world_select_screen(commands: &mut Commands, asset_server: &Res<AssetSe
                                         ));
                                     });
                             });
+                            //filled in when a level fails to open or a backup is restored
+                            let (_, font, picking) = get_text_style(asset_server);
+                            rows.spawn((
+                                Text::default(),
+                                TextColor(bevy::color::palettes::tailwind::RED_400.into()),
+                                font,
+                                picking,
+                                WorldSelectStatusText,
+                            ));
                         });
                 });
             //load world section
@@ -395,28 +429,6 @@ fn spawn_world_select_items(
         error!("world select container doesn't have commands");
         return;
     };
-    let button = (
-        ButtonColors::default(),
-        Node {
-            width: Val::Percent(100.),
-            height: Val::Px(48.0),
-            border: UiRect::all(Val::Px(2.0)),
-            // horizontally center child text
-            justify_content: JustifyContent::Center,
-            // vertically center child text
-            align_items: AlignItems::Center,
-            margin: UiRect::all(Val::Px(4.)),
-            ..default()
-        },
-        PickingBehavior {
-            should_block_lower: false,
-            ..default()
-        },
-        BorderColor(ButtonColors::default().default_border),
-        BackgroundColor(ButtonColors::default().default_background),
-        Button,
-    );
-
     for level in saved_worlds.0.iter() {
         root_ec.with_children(|container| {
             container
@@ -424,6 +436,8 @@ fn spawn_world_select_items(
                     Node {
                         width: Val::Percent(100.),
                         flex_direction: FlexDirection::Row,
+                        //the backup list wraps onto its own line
+                        flex_wrap: FlexWrap::Wrap,
                         justify_content: JustifyContent::Start,
                         ..default()
                     },
@@ -434,8 +448,11 @@ fn spawn_world_select_items(
                     },
                 ))
                 .with_children(|components| {
+                    let mut load_button = list_button();
+                    load_button.1.width = Val::Auto;
+                    load_button.1.flex_grow = 1.;
                     components
-                        .spawn((WorldSelectLoadLevelButton(level.name), button.clone()))
+                        .spawn((WorldSelectLoadLevelButton(level.name), load_button))
                         .observe(load_level_clicked)
                         .with_children(|text| {
                             text.spawn((
@@ -443,8 +460,166 @@ fn spawn_world_select_items(
                                 get_text_style(&asset_server).clone(),
                             ));
                         });
+                    let mut backups_button = list_button();
+                    backups_button.1.width = Val::Px(160.);
+                    components
+                        .spawn((WorldSelectBackupsButton(level.name), backups_button))
+                        .observe(backups_clicked)
+                        .with_children(|text| {
+                            text.spawn((
+                                Text("Backups".into()),
+                                get_text_style(&asset_server).clone(),
+                            ));
+                        });
+                });
+        });
+    }
+}
+
+fn list_button() -> (
+    ButtonColors,
+    Node,
+    PickingBehavior,
+    BorderColor,
+    BackgroundColor,
+    Button,
+) {
+    (
+        ButtonColors::default(),
+        Node {
+            width: Val::Percent(100.),
+            height: Val::Px(48.0),
+            border: UiRect::all(Val::Px(2.0)),
+            // horizontally center child text
+            justify_content: JustifyContent::Center,
+            // vertically center child text
+            align_items: AlignItems::Center,
+            margin: UiRect::all(Val::Px(4.)),
+            ..default()
+        },
+        PickingBehavior {
+            should_block_lower: false,
+            ..default()
+        },
+        BorderColor(ButtonColors::default().default_border),
+        BackgroundColor(ButtonColors::default().default_background),
+        Button,
+    )
+}
+
+//shows or hides the level's backups under its row
+fn backups_clicked(
+    mut click: Trigger<Pointer<Click>>,
+    button_query: Query<(&WorldSelectBackupsButton, &Parent)>,
+    list_query: Query<(Entity, &Parent), With<WorldSelectBackupList>>,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    click.propagate(false);
+    let Ok((&WorldSelectBackupsButton(level), row)) = button_query.get(click.entity()) else {
+        return;
+    };
+    if let Some((list, _)) = list_query
+        .iter()
+        .find(|(_, parent)| parent.get() == row.get())
+    {
+        commands.entity(list).despawn_recursive();
+        return;
+    }
+    let backups = backup::list_backups(&backup::backup_dir(&level_file_path(
+        settings.env_path,
+        level,
+    )));
+    commands.entity(row.get()).with_children(|row| {
+        row.spawn((
+            WorldSelectBackupList,
+            Node {
+                width: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::left(Val::Px(32.)),
+                ..default()
+            },
+            PickingBehavior {
+                should_block_lower: false,
+                ..default()
+            },
+        ))
+        .with_children(|list| {
+            if backups.is_empty() {
+                list.spawn((
+                    Text("No backups yet".into()),
+                    get_text_style(&asset_server).clone(),
+                ));
+            }
+            for backup in backups {
+                let label = format!("{} ({})", format_age(backup.time), backup.reason);
+                list.spawn((
+                    WorldSelectRestoreButton {
+                        level,
+                        path: backup.path,
+                        label: label.clone(),
+                    },
+                    list_button(),
+                ))
+                .observe(restore_clicked)
+                .with_children(|text| {
+                    text.spawn((Text(label), get_text_style(&asset_server).clone()));
                 });
+            }
         });
+    });
+}
+
+fn restore_clicked(
+    mut click: Trigger<Pointer<Click>>,
+    button_query: Query<&WorldSelectRestoreButton>,
+    list_query: Query<Entity, With<WorldSelectBackupList>>,
+    mut status_query: Query<(&mut Text, &mut TextColor), With<WorldSelectStatusText>>,
+    settings: Res<Settings>,
+    mut commands: Commands,
+) {
+    click.propagate(false);
+    let Ok(button) = button_query.get(click.entity()) else {
+        return;
+    };
+    let (message, color) = match backup::restore_backup(
+        &level_file_path(settings.env_path, button.level),
+        &button.path,
+        settings.backups_kept,
+    ) {
+        Ok(()) => (
+            format!("Restored {} from {}", button.level, button.label),
+            Color::WHITE,
+        ),
+        Err(e) => {
+            error!("Error restoring backup {}: {:?}", button.path.display(), e);
+            (
+                format!("Couldn't restore {}: {}", button.level, e),
+                bevy::color::palettes::tailwind::RED_400.into(),
+            )
+        }
+    };
+    for (mut text, mut text_color) in status_query.iter_mut() {
+        text.0 = message.clone();
+        text_color.0 = color;
+    }
+    for list in list_query.iter() {
+        commands.entity(list).despawn_recursive();
+    }
+}
+
+//there's no date formatting without pulling in another crate, and this is easier to read anyway
+fn format_age(time: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs();
+    match secs {
+        0..60 => "just now".into(),
+        60..3600 => format!("{} minutes ago", secs / 60),
+        3600..86400 => format!("{} hours ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86400),
     }
 }
 
@@ -488,6 +663,15 @@ fn hide_splash_screen(mut container_query: Query<&mut Visibility, With<SplashScr
     }
 }
 
+//levels that fail to open go back to the world select screen so the error can be shown
+fn leave_game(error: Option<Res<LevelOpenError>>, mut next_state: ResMut<NextState<MenuState>>) {
+    if error.is_some() {
+        next_state.set(MenuState::WorldSelect);
+    } else {
+        next_state.set(MenuState::Main);
+    }
+}
+
 fn go_to_main_screen(mut next_state: ResMut<NextState<MenuState>>) {
     next_state.set(MenuState::Main);
     info!("going to main screen");
@@ -520,10 +704,19 @@ fn hide_main_screen(mut container_query: Query<&mut Visibility, With<MainMenuCon
 
 fn show_world_select_screen(
     mut container_query: Query<&mut Visibility, With<WorldSelectContainer>>,
+    mut status_query: Query<(&mut Text, &mut TextColor), With<WorldSelectStatusText>>,
+    error: Option<Res<LevelOpenError>>,
 ) {
     for mut vis in container_query.iter_mut() {
         *vis = Visibility::Inherited;
     }
+    for (mut text, mut color) in status_query.iter_mut() {
+        text.0 = error
+            .as_ref()
+            .map(|error| format!("Couldn't open {}: {}", error.level, error.message))
+            .unwrap_or_default();
+        color.0 = bevy::color::palettes::tailwind::RED_400.into();
+    }
 }
 
 fn hide_world_select_screen(
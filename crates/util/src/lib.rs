@@ -7,6 +7,7 @@ pub mod third_party;
 pub use numerical_traits::*;
 
 pub mod bevy_utils;
+pub mod checksum;
 pub mod controls;
 pub mod direction;
 pub mod image;
@@ -57,3 +57,15 @@ impl From<&str> for Version {
         result
     }
 }
+
+//writes the version the way it was parsed, e.g. "0.2.0"
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts = [self.major, self.minor, self.patch]
+            .into_iter()
+            .map_while(|part| part.map(|part| part.to_string()))
+            .chain(self.cruft.clone())
+            .collect::<Vec<_>>();
+        write!(f, "{}", parts.join("."))
+    }
+}
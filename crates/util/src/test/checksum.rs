@@ -0,0 +1,12 @@
+use crate::checksum::crc32;
+
+#[test]
+fn test_crc32() {
+    //standard check values
+    assert_eq!(0, crc32(&[]));
+    assert_eq!(0xcbf43926, crc32(b"123456789"));
+    assert_eq!(
+        0x414fa339,
+        crc32(b"The quick brown fox jumps over the lazy dog")
+    );
+}
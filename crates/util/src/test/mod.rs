@@ -6,6 +6,8 @@ use crate::{max_component_norm, DEG_TO_RAD, RAD_TO_DEG};
 
 use super::direction::Direction;
 
+#[cfg(test)]
+mod checksum;
 #[cfg(test)]
 mod iterators;
 #[cfg(test)]
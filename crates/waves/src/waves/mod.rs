@@ -3,12 +3,14 @@ use std::{f32::consts::PI, sync::Arc, time::Duration};
 use bevy::prelude::*;
 use itertools::Itertools;
 use rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
 
 use engine::{
     actors::world_anchor::{ActiveWorldAnchor, WorldAnchor},
+    serialization::{db::LevelDB, SaveWorldStateSet},
     world::{
         atmosphere::{Calendar, NightStartedEvent},
-        BlockCoord, BlockType, Level,
+        BlockCoord, BlockType, Level, LevelLoadState,
     },
 };
 
@@ -22,6 +24,9 @@ use self::spawns::SkeletonPirateSpawn;
 
 pub mod spawns;
 
+//world_info key the assault is saved under
+const ASSAULT_KEY: &str = "assault";
+
 pub struct WavesPlugin;
 
 impl Plugin for WavesPlugin {
@@ -47,6 +52,8 @@ impl Plugin for WavesPlugin {
                     .run_if(resource_exists::<Assault>),
             ),
         )
+        .add_systems(OnEnter(LevelLoadState::Loading), load_assault)
+        .add_systems(Last, save_assault.in_set(SaveWorldStateSet))
         .insert_resource(Assault {
             to_spawn: Vec::new(),
             compiled: Vec::new(),
@@ -94,11 +101,20 @@ impl Assault {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 pub struct SpawnPoint {
     location: Vec3,
 }
 
-#[derive(Clone, Debug)]
+//the parts of Assault that change during a night. possible_spawns is set up by the plugin, so it isn't saved
+#[derive(Serialize, Deserialize, Debug)]
+struct SavedAssault {
+    to_spawn: Vec<WaveInfo>,
+    compiled: Vec<CompiledSpawn>,
+    spawn_points: Vec<SpawnPoint>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WaveInfo {
     pub strength_mult: f32,
     pub start_time: Duration,
@@ -118,7 +134,7 @@ impl WaveInfo {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct WaveSpawn {
     start_offset: Duration,
     spawn: WaveSpawnType,
@@ -149,13 +165,13 @@ impl WaveSpawn {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 enum WaveSpawnType {
     Recursive(Box<WaveSpawn>),
     Strength(f32),
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 enum SpawnStrategy {
     Burst { count: u32 },
     Stream { count: u32, delay: Duration },
@@ -178,7 +194,7 @@ impl SpawnStrategy {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 struct CompiledSpawn {
     spawn_time: Duration,
     spawn_index: usize,
@@ -366,6 +382,51 @@ fn trigger_assault(
     assault_event.send(AssaultStartedEvent);
 }
 
+//picks up an assault that was in progress when the level was closed. otherwise starts with no assault
+fn load_assault(
+    db: Option<ResMut<LevelDB>>,
+    mut assault: ResMut<Assault>,
+    mut assault_event: EventWriter<AssaultStartedEvent>,
+) {
+    assault.to_spawn.clear();
+    assault.compiled.clear();
+    assault.spawn_points.clear();
+    let Some(mut db) = db else {
+        return;
+    };
+    match db.load_world_info::<SavedAssault>(ASSAULT_KEY) {
+        Ok(Some(saved)) => {
+            //spawn indices point into possible_spawns, drop any that don't exist anymore
+            let spawn_count = assault.possible_spawns.len();
+            assault.to_spawn = saved.to_spawn;
+            assault.compiled = saved
+                .compiled
+                .into_iter()
+                .filter(|spawn| spawn.spawn_index < spawn_count)
+                .collect();
+            assault.spawn_points = saved.spawn_points;
+            if !assault.to_spawn.is_empty() {
+                info!(
+                    "Resuming assault with {} spawns left",
+                    assault.compiled.len()
+                );
+                assault_event.send(AssaultStartedEvent);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => error!("Error loading assault: {:?}", e),
+    }
+}
+
+fn save_assault(assault: Res<Assault>, mut db: ResMut<LevelDB>) {
+    let saved = SavedAssault {
+        to_spawn: assault.to_spawn.clone(),
+        compiled: assault.compiled.clone(),
+        spawn_points: assault.spawn_points.clone(),
+    };
+    db.save_world_info(ASSAULT_KEY, bincode::serialize(&saved).unwrap());
+}
+
 fn search_for_spawn_volume(
     container: &mut VolumeContainer<BlockType>,
     search_origin: BlockCoord,
@@ -0,0 +1,314 @@
+use std::path::Path;
+
+use bevy::utils::HashMap;
+use engine::{
+    items::ItemNameIdMap,
+    serialization::{
+        backup::{backup_dir, rotate_backups, snapshot, BackupReason},
+        codec::ChunkCodec,
+        db::{ChunkTable, LevelDB},
+        diff::TerrainDiff,
+        queries::LOAD_CHUNK_DATA,
+        ChunkSaveFormat,
+    },
+    world::{
+        chunk::{ChunkCoord, ChunkIdx},
+        levels::LevelId,
+        settings::Settings,
+        BlockId, BlockName, BlockNameIdMap, BlockState, Id,
+    },
+};
+use rusqlite::{params, OptionalExtension};
+use serde::de::DeserializeOwned;
+use util::checksum::crc32;
+
+use crate::CmdResult;
+
+pub fn info(db: &mut LevelDB) -> CmdResult {
+    let conn = db.connection()?;
+    let mut select = conn.prepare("SELECT key, value FROM world_info ORDER BY key")?;
+    let rows = select
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (key, value) in rows {
+        match key.as_str() {
+            "version" => print_decoded::<String>(&key, &value, |version| println!("{}", version)),
+            "seed" => print_decoded::<u64>(&key, &value, |seed| println!("{}", seed)),
+            "block_palette" => print_decoded::<BlockNameIdMap>(&key, &value, |palette| {
+                println!("{} blocks", palette.len());
+                let mut entries = palette.iter().collect::<Vec<_>>();
+                entries.sort_by_key(|(_, id)| id_order(id.0));
+                for (name, id) in entries {
+                    println!("    {:?} {}", id.0, block_name(name));
+                }
+            }),
+            "item_palette" => print_decoded::<ItemNameIdMap>(&key, &value, |palette| {
+                println!("{} items", palette.len());
+                let mut entries = palette.iter().collect::<Vec<_>>();
+                entries.sort_by_key(|(_, id)| id_order(id.0));
+                for (name, id) in entries {
+                    println!("    {:?} {}:{}", id.0, name.namespace, name.name);
+                }
+            }),
+            _ => println!("{}: {} bytes", key, value.len()),
+        }
+    }
+    //levels saved before players were don't have a players table
+    let has_players: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'players')",
+        [],
+        |row| row.get(0),
+    )?;
+    let players: i64 = if has_players {
+        conn.query_row("SELECT COUNT(*) FROM players", [], |row| row.get(0))?
+    } else {
+        0
+    };
+    println!("players: {}", players);
+    Ok(())
+}
+
+//prints `key: ` then the decoded value, so one bad row doesn't hide the rest
+fn print_decoded<T: DeserializeOwned>(key: &str, value: &[u8], print: impl FnOnce(T)) {
+    print!("{}: ", key);
+    match bincode::deserialize(value) {
+        Ok(decoded) => print(decoded),
+        Err(e) => println!("couldn't decode {} bytes ({})", value.len(), e),
+    }
+}
+
+//sorts ids the way the palette hands them out
+fn id_order(id: Id) -> (u8, u32) {
+    match id {
+        Id::Empty => (0, 0),
+        Id::Basic(id) => (1, id),
+        Id::Dynamic(id) => (2, id),
+    }
+}
+
+fn block_name(name: &BlockName) -> String {
+    format!("{}:{}", name.namespace, name.name)
+}
+
+fn describe_tid(tid: i32) -> String {
+    match ChunkTable::from_tid(tid) {
+        Some((level, table)) => format!("level {} {:?}", level.0, table),
+        None => format!("unknown table {}", tid),
+    }
+}
+
+pub fn chunks(db: &mut LevelDB, level: Option<LevelId>, list: bool) -> CmdResult {
+    let conn = db.connection()?;
+    let tids = level.map_or(0..i32::MAX, ChunkTable::level_tids);
+    let mut select = conn.prepare(
+        "SELECT tid, COUNT(*), SUM(LENGTH(data)) FROM data
+        WHERE tid >= ?1 AND tid < ?2 GROUP BY tid ORDER BY tid",
+    )?;
+    let counts = select
+        .query_map(params![tids.start, tids.end], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    if counts.is_empty() {
+        println!("no chunks saved");
+    }
+    let mut list_coords =
+        conn.prepare("SELECT x, y, z, LENGTH(data) FROM data WHERE tid = ?1 ORDER BY x, y, z")?;
+    for (tid, count, bytes) in counts {
+        println!("{}: {} chunks, {} bytes", describe_tid(tid), count, bytes);
+        if !list {
+            continue;
+        }
+        let coords = list_coords.query_map(params![tid], |row| {
+            Ok((
+                ChunkCoord::new(row.get(0)?, row.get(1)?, row.get(2)?),
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
+        for coord in coords {
+            let (coord, bytes) = coord?;
+            println!("    {} {} {} ({} bytes)", coord.x, coord.y, coord.z, bytes);
+        }
+    }
+    //levels that haven't been opened since quarantining was added don't have the table
+    match conn.query_row("SELECT COUNT(*) FROM quarantine", [], |row| {
+        row.get::<_, i64>(0)
+    }) {
+        Ok(quarantined) if quarantined > 0 => {
+            println!("{} corrupt rows in quarantine", quarantined)
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub fn dump(db: &mut LevelDB, level: LevelId, coord: ChunkCoord) -> CmdResult {
+    let names = db
+        .load_world_info::<BlockNameIdMap>("block_palette")?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, id)| (id, name))
+        .collect::<HashMap<BlockId, BlockName>>();
+    let conn = db.connection()?;
+    let mut load = conn.prepare(LOAD_CHUNK_DATA)?;
+    let mut found = false;
+    for table in ChunkTable::ALL {
+        let Some((data, codec, checksum)) = load
+            .query_row(
+                params![table.tid(level), coord.x, coord.y, coord.z],
+                |row| {
+                    Ok((
+                        row.get::<_, Vec<u8>>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, Option<u32>>(2)?,
+                    ))
+                },
+            )
+            .optional()?
+        else {
+            continue;
+        };
+        found = true;
+        let codec_name = match ChunkCodec::from_id(codec) {
+            Some(codec) => format!("{:?}", codec),
+            None => format!("unknown ({})", codec),
+        };
+        let checksum = match checksum {
+            Some(checksum) if checksum == crc32(&data) => "ok",
+            Some(_) => "mismatch",
+            None => "none",
+        };
+        println!(
+            "{:?}: {} bytes, codec {}, checksum {}",
+            table,
+            data.len(),
+            codec_name,
+            checksum
+        );
+        let Some(codec) = ChunkCodec::from_id(codec) else {
+            continue;
+        };
+        match table {
+            ChunkTable::Terrain => print_terrain(
+                ChunkSaveFormat::decode(&codec.decode(&data)?)?,
+                coord,
+                &names,
+            ),
+            ChunkTable::TerrainDiff => {
+                print_diff(bincode::deserialize(&codec.decode(&data)?)?, coord, &names)
+            }
+            _ => {}
+        }
+    }
+    if !found {
+        println!("nothing saved at level {} chunk {:?}", level.0, coord);
+    }
+    Ok(())
+}
+
+fn print_terrain(chunk: ChunkSaveFormat, coord: ChunkCoord, names: &HashMap<BlockId, BlockName>) {
+    if chunk.position != coord {
+        println!("    saved with position {:?}", chunk.position);
+    }
+    let mut start = 0;
+    for (id, run) in chunk.data {
+        println!(
+            "    {}..{} {}",
+            start,
+            start + run as usize,
+            saved_block_name(id, names)
+        );
+        start += run as usize;
+    }
+    print_states(&chunk.states);
+}
+
+//only the blocks that differ from the generator are saved, so that's all that can be shown without generating the chunk
+fn print_diff(diff: TerrainDiff, coord: ChunkCoord, names: &HashMap<BlockId, BlockName>) {
+    if diff.position != coord {
+        println!("    saved with position {:?}", diff.position);
+    }
+    let changed: usize = diff.changes.iter().map(|(_, run, _)| *run as usize).sum();
+    println!("    {} blocks differ from the generator", changed);
+    for (start, run, id) in diff.changes {
+        println!(
+            "    {}..{} {}",
+            start,
+            start as usize + run as usize,
+            saved_block_name(id, names)
+        );
+    }
+    print_states(&diff.states);
+}
+
+fn saved_block_name(id: BlockId, names: &HashMap<BlockId, BlockName>) -> String {
+    match id.0 {
+        Id::Empty => "empty".to_string(),
+        _ => names
+            .get(&id)
+            .map(block_name)
+            .unwrap_or_else(|| format!("{:?} (not in palette)", id.0)),
+    }
+}
+
+fn print_states(states: &[(u16, BlockState)]) {
+    for (idx, state) in states {
+        let pos = ChunkIdx::from_usize(*idx as usize);
+        println!(
+            "    state at {} ({} {} {}): {:#06x}",
+            idx, pos.x, pos.y, pos.z, state.0
+        );
+    }
+}
+
+//deletes every table's rows for chunks outside the box, so entities and block ticks don't outlive their terrain
+pub fn prune(db: &mut LevelDB, level: LevelId, center: ChunkCoord, radius: u32) -> CmdResult {
+    let conn = db.connection()?;
+    let dir = backup_dir(db.path());
+    let backup = snapshot(&conn, &dir, BackupReason::Maintenance)?;
+    rotate_backups(&dir, Settings::default().backups_kept);
+    println!("backed up level to {}", backup.display());
+    let radius = radius.min(i32::MAX as u32) as i32;
+    let tids = ChunkTable::level_tids(level);
+    let deleted = conn.execute(
+        "DELETE FROM data WHERE tid >= ?1 AND tid < ?2
+        AND (ABS(x - ?3) > ?6 OR ABS(y - ?4) > ?6 OR ABS(z - ?5) > ?6)",
+        params![tids.start, tids.end, center.x, center.y, center.z, radius],
+    )?;
+    println!(
+        "deleted {} rows more than {} chunks from {:?}. run vacuum to shrink the file",
+        deleted, radius, center
+    );
+    Ok(())
+}
+
+pub fn vacuum(db: &mut LevelDB) -> CmdResult {
+    let before = level_size(db.path());
+    let conn = db.connection()?;
+    conn.execute_batch("VACUUM")?;
+    //vacuum goes through the write ahead log, so it has to be emptied for the file to shrink on disk
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    println!(
+        "{} bytes before, {} bytes after",
+        before,
+        level_size(db.path())
+    );
+    Ok(())
+}
+
+//the level file and its write ahead log
+fn level_size(path: &Path) -> u64 {
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    [path, Path::new(&wal)]
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
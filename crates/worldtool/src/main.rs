@@ -0,0 +1,153 @@
+//headless tool for inspecting and fixing level saves without starting the game
+//lints created using dylint will give a warning
+#![allow(unknown_lints)]
+
+use std::{env, path::Path, process::ExitCode, str::FromStr};
+
+use engine::{
+    serialization::db::LevelDB,
+    world::{chunk::ChunkCoord, levels::LevelId},
+};
+
+mod commands;
+
+const USAGE: &str = "usage: worldtool <command> <level.db> [args]
+
+commands:
+    info                          print the version, seed, palettes and other world info
+    chunks [--level N] [--list]   count the saved chunks in each table, or list their coordinates
+    dump <x> <y> <z> [--level N]  print the blocks saved in a chunk, by name
+    prune <radius> [--center <x> <y> <z>] [--level N]
+                                  delete chunks more than radius chunks from center (default 0 0 0) on any axis
+                                  the level is backed up first
+    vacuum                        rebuild the database to reclaim the space left by deleted chunks
+
+--level defaults to the surface. close the level in game before changing it";
+
+pub type CmdResult = Result<(), Box<dyn std::error::Error>>;
+
+fn main() -> ExitCode {
+    let mut args = Args(env::args().skip(1).collect());
+    let (Some(command), Some(path)) = (args.positional(), args.positional()) else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+    let path = Path::new(&path);
+    //LevelDB makes a new empty level if there isn't one
+    if !path.is_file() {
+        eprintln!("no level at {}", path.display());
+        return ExitCode::FAILURE;
+    }
+    //saves from before the chunk table had checksums are upgraded when they're opened, the same way the game does it
+    let mut db = match LevelDB::new(path) {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("couldn't open {}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(&command, &mut args, &mut db) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: &str, args: &mut Args, db: &mut LevelDB) -> CmdResult {
+    match command {
+        "info" => {
+            args.finish()?;
+            commands::info(db)
+        }
+        "chunks" => {
+            let list = args.flag("--list");
+            let level = args.level()?;
+            args.finish()?;
+            commands::chunks(db, level, list)
+        }
+        "dump" => {
+            let level = args.level()?.unwrap_or(LevelId::SURFACE);
+            let coord = args.coord()?;
+            args.finish()?;
+            commands::dump(db, level, coord)
+        }
+        "prune" => {
+            let level = args.level()?.unwrap_or(LevelId::SURFACE);
+            let center = match args.option("--center", 3)? {
+                Some(mut center) => center.coord()?,
+                None => ChunkCoord::new(0, 0, 0),
+            };
+            let radius = args.parse::<u32>("radius")?;
+            args.finish()?;
+            commands::prune(db, level, center, radius)
+        }
+        "vacuum" => {
+            args.finish()?;
+            commands::vacuum(db)
+        }
+        _ => Err(format!("unknown command {}\n\n{}", command, USAGE).into()),
+    }
+}
+
+//what's left of the command line. options are taken out first, then positional arguments in order
+struct Args(Vec<String>);
+
+impl Args {
+    fn positional(&mut self) -> Option<String> {
+        (!self.0.is_empty()).then(|| self.0.remove(0))
+    }
+
+    fn parse<T: FromStr>(&mut self, name: &str) -> Result<T, String> {
+        let arg = self
+            .positional()
+            .ok_or_else(|| format!("missing {}\n\n{}", name, USAGE))?;
+        arg.parse()
+            .map_err(|_| format!("{} isn't a valid {}", arg, name))
+    }
+
+    fn flag(&mut self, name: &str) -> bool {
+        match self.0.iter().position(|arg| arg == name) {
+            Some(idx) => {
+                self.0.remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    //takes out `name` and the `count` values after it
+    fn option(&mut self, name: &str, count: usize) -> Result<Option<Args>, String> {
+        let Some(idx) = self.0.iter().position(|arg| arg == name) else {
+            return Ok(None);
+        };
+        if idx + count >= self.0.len() {
+            return Err(format!("{} needs {} values", name, count));
+        }
+        let values = self.0.drain(idx..=idx + count).skip(1).collect();
+        Ok(Some(Args(values)))
+    }
+
+    fn level(&mut self) -> Result<Option<LevelId>, String> {
+        match self.option("--level", 1)? {
+            Some(mut level) => Ok(Some(LevelId(level.parse("level")?))),
+            None => Ok(None),
+        }
+    }
+
+    fn coord(&mut self) -> Result<ChunkCoord, String> {
+        Ok(ChunkCoord::new(
+            self.parse("x")?,
+            self.parse("y")?,
+            self.parse("z")?,
+        ))
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        match self.0.first() {
+            Some(arg) => Err(format!("unexpected argument {}\n\n{}", arg, USAGE)),
+            None => Ok(()),
+        }
+    }
+}